@@ -0,0 +1,455 @@
+//! Verification of the rounds of a drand randomness beacon, and derivation of the tickets and
+//! seeds of the seals from them.
+//!
+//! The tickets and seeds are drawn from verified beacon entries instead of being picked by the
+//! provers: the seed from a recent round, so that an old proof can not be replayed, and the ticket
+//! from a round at least `seed_lookback` rounds before the seed, so that the prover commits to the
+//! replica before knowing the seed.
+
+use anyhow::{anyhow, ensure, Result};
+use ark_bls12_381::G1Affine;
+use ark_ff::Zero;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::bls;
+
+/// The domain separation tag of the signatures of drand.
+pub const BEACON_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// The tag of the randomness drawn for the tickets.
+pub const TICKET_TAG: u64 = 5;
+/// The tag of the randomness drawn for the interactive seeds.
+pub const SEED_TAG: u64 = 6;
+
+/// How the beacon chains its rounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BeaconScheme {
+    /// Each round signs the signature of the previous round and its own number.
+    Chained,
+    /// Each round signs only its own number.
+    Unchained,
+}
+
+/// The chain of a beacon, and the lookback rules of the randomness drawn from it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BeaconConfig {
+    /// The compressed public key of the beacon, in G1.
+    pub public_key: Vec<u8>,
+    pub scheme: BeaconScheme,
+    /// The time of the first round, in seconds.
+    pub genesis_time: u64,
+    /// The time between two rounds, in seconds.
+    pub period: u64,
+    /// The minimum number of rounds between the ticket and the seed of a seal.
+    pub seed_lookback: u64,
+    /// The maximum number of rounds between the ticket and the seed of the first proof of a
+    /// sector, which bounds the time taken to seal it.
+    pub ticket_max_age: u64,
+    /// The maximum number of rounds between the seed of a proof and its submission.
+    pub seed_max_age: u64,
+}
+
+/// A round of the beacon.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BeaconEntry {
+    pub round: u64,
+    /// The compressed signature of the round, in G2.
+    pub signature: Vec<u8>,
+    /// The signature of the previous round, for the chained beacons.
+    pub previous_signature: Option<Vec<u8>>,
+}
+
+impl BeaconEntry {
+    /// The message signed by the round.
+    pub fn message(&self, scheme: BeaconScheme) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        if scheme == BeaconScheme::Chained {
+            let previous = self
+                .previous_signature
+                .as_ref()
+                .ok_or_else(|| anyhow!("missing previous signature of round {}", self.round))?;
+            hasher.update(previous);
+        }
+        hasher.update(self.round.to_be_bytes());
+        Ok(hasher.finalize().into())
+    }
+
+    /// The randomness of the round, as published by drand.
+    pub fn randomness(&self) -> [u8; 32] {
+        Sha256::digest(&self.signature).into()
+    }
+}
+
+/// The beacon entries a seal draws its ticket and seed from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SealRandomness {
+    pub ticket: BeaconEntry,
+    pub seed: BeaconEntry,
+}
+
+/// Draws 32 bytes of randomness for `tag` from a beacon entry, personalized with `entropy`.
+pub fn draw_randomness(tag: u64, entry: &BeaconEntry, entropy: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(tag.to_be_bytes())
+        .chain_update(entry.randomness())
+        .chain_update(entry.round.to_be_bytes())
+        .chain_update(entropy)
+        .finalize()
+        .into()
+}
+
+/// A beacon with a decoded public key.
+#[derive(Clone, Debug)]
+pub struct Beacon {
+    config: BeaconConfig,
+    public_key: G1Affine,
+}
+
+impl Beacon {
+    pub fn new(config: &BeaconConfig) -> Result<Self> {
+        ensure!(config.period > 0, "beacon period must be positive");
+        let public_key = bls::decode_g1_compressed(&config.public_key)?;
+        ensure!(!public_key.is_zero(), "beacon public key is the identity");
+        Ok(Beacon {
+            config: config.clone(),
+            public_key,
+        })
+    }
+
+    pub fn config(&self) -> &BeaconConfig {
+        &self.config
+    }
+
+    /// The latest round at `time`, or 0 before the genesis.
+    pub fn round_at(&self, time: u64) -> u64 {
+        match time.checked_sub(self.config.genesis_time) {
+            Some(elapsed) => elapsed / self.config.period + 1,
+            None => 0,
+        }
+    }
+
+    /// Checks the signature of `entry`.
+    pub fn verify(&self, entry: &BeaconEntry) -> Result<()> {
+        ensure!(entry.round > 0, "invalid beacon round 0");
+        let signature = bls::decode_g2_compressed(&entry.signature)?;
+        let message = entry.message(self.config.scheme)?;
+        ensure!(
+            bls::verify(&self.public_key, &message, BEACON_DST, &signature)?,
+            "invalid signature of beacon round {}",
+            entry.round
+        );
+        Ok(())
+    }
+
+    /// Verifies the entries of `randomness` and their lookback at time `now`, and returns the
+    /// ticket and the seed of the sector `sector_id` of `prover_id`.
+    ///
+    /// `sealed_ticket_round` is the ticket round the sector was first proven with, if any. A
+    /// sector proven again with the same ticket is not held to `ticket_max_age`, it was sealed
+    /// long ago.
+    pub fn seal_randomness(
+        &self,
+        randomness: &SealRandomness,
+        prover_id: &[u8],
+        sector_id: u64,
+        now: u64,
+        sealed_ticket_round: Option<u64>,
+    ) -> Result<([u8; 32], [u8; 32])> {
+        let SealRandomness { ticket, seed } = randomness;
+        let current_round = self.round_at(now);
+        ensure!(
+            seed.round <= current_round,
+            "seed round {} is after the current round {}",
+            seed.round,
+            current_round
+        );
+        ensure!(
+            current_round <= seed.round.saturating_add(self.config.seed_max_age),
+            "seed round {} is older than {} rounds",
+            seed.round,
+            self.config.seed_max_age
+        );
+        ensure!(
+            ticket.round.saturating_add(self.config.seed_lookback) <= seed.round,
+            "seed round {} is less than {} rounds after the ticket round {}",
+            seed.round,
+            self.config.seed_lookback,
+            ticket.round
+        );
+        ensure!(
+            sealed_ticket_round == Some(ticket.round)
+                || seed.round <= ticket.round.saturating_add(self.config.ticket_max_age),
+            "ticket round {} is more than {} rounds before the seed round {}",
+            ticket.round,
+            self.config.ticket_max_age,
+            seed.round
+        );
+        self.verify(ticket)?;
+        self.verify(seed)?;
+
+        let entropy = [prover_id, &sector_id.to_be_bytes()].concat();
+        Ok((
+            draw_randomness(TICKET_TAG, ticket, &entropy),
+            draw_randomness(SEED_TAG, seed, &entropy),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use group::{Curve, Group};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use serde_json::{json, Value};
+
+    const TEST_SEED: [u8; 16] = [
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ];
+
+    /// Rounds of the League of Entropy mainnet, as served by the HTTP API of drand.
+    const RECORDED: &str = include_str!("../tests/fixtures/beacon.json");
+    /// Rounds of a chained and of an unchained local beacon, in the same format, for the cases
+    /// the recorded rounds can not cover: rounds of the same chain some rounds apart, and rounds
+    /// signed with another key.
+    const GENERATED: &str = include_str!("../tests/fixtures/beacon_generated.json");
+
+    fn decode_hex(value: &Value) -> Vec<u8> {
+        hex::decode(value.as_str().expect("hex string")).expect("hex failure")
+    }
+
+    /// The beacon and the entries of the chain `name` of `fixtures`.
+    fn fixture(fixtures: &str, name: &str, seed_lookback: u64) -> (Beacon, Vec<BeaconEntry>) {
+        let fixtures: Value = serde_json::from_str(fixtures).expect("fixtures failure");
+        let info = &fixtures[name]["info"];
+        let scheme = match info["schemeID"].as_str() {
+            Some("pedersen-bls-chained") => BeaconScheme::Chained,
+            Some("pedersen-bls-unchained") => BeaconScheme::Unchained,
+            scheme => panic!("unknown scheme {:?}", scheme),
+        };
+        let config = BeaconConfig {
+            public_key: decode_hex(&info["public_key"]),
+            scheme,
+            genesis_time: info["genesis_time"].as_u64().unwrap(),
+            period: info["period"].as_u64().unwrap(),
+            seed_lookback,
+            ticket_max_age: 4,
+            seed_max_age: 1,
+        };
+        let entries = fixtures[name]["rounds"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|round| {
+                let entry = BeaconEntry {
+                    round: round["round"].as_u64().unwrap(),
+                    signature: decode_hex(&round["signature"]),
+                    previous_signature: round.get("previous_signature").map(decode_hex),
+                };
+                assert_eq!(
+                    entry.randomness().to_vec(),
+                    decode_hex(&round["randomness"])
+                );
+                entry
+            })
+            .collect();
+        (Beacon::new(&config).expect("beacon failure"), entries)
+    }
+
+    #[test]
+    fn test_verify_recorded() {
+        let (beacon, entries) = fixture(RECORDED, "mainnet", 0);
+        for entry in &entries {
+            beacon.verify(entry).expect("verify failure");
+        }
+
+        let mut entry = entries[0].clone();
+        entry.round += 1;
+        assert!(beacon.verify(&entry).is_err());
+        let mut entry = entries[0].clone();
+        entry.previous_signature = Some(entries[0].signature.clone());
+        assert!(beacon.verify(&entry).is_err());
+        let mut entry = entries[0].clone();
+        entry.previous_signature = None;
+        assert!(beacon.verify(&entry).is_err());
+    }
+
+    #[test]
+    fn test_verify_generated() {
+        for name in ["chained", "unchained"] {
+            let (beacon, entries) = fixture(GENERATED, name, 2);
+            for entry in &entries {
+                beacon.verify(entry).expect("verify failure");
+            }
+
+            let mut entry = entries[1].clone();
+            entry.signature = entries[2].signature.clone();
+            assert!(beacon.verify(&entry).is_err());
+        }
+
+        // The rounds of one chain do not verify with the key of another.
+        let (mainnet, recorded) = fixture(RECORDED, "mainnet", 0);
+        let (chained, _) = fixture(GENERATED, "chained", 2);
+        let (_, entries) = fixture(GENERATED, "unchained", 2);
+        assert!(chained.verify(&entries[0]).is_err());
+        assert!(chained.verify(&recorded[0]).is_err());
+        assert!(mainnet.verify(&entries[0]).is_err());
+    }
+
+    #[test]
+    fn test_seal_randomness() {
+        let (beacon, entries) = fixture(RECORDED, "mainnet", 0);
+        let genesis_time = beacon.config().genesis_time;
+        let period = beacon.config().period;
+        let time_of = |round: u64| genesis_time + (round - 1) * period;
+        assert_eq!(beacon.round_at(genesis_time - 1), 0);
+        assert_eq!(beacon.round_at(time_of(3)), 3);
+        assert_eq!(beacon.round_at(time_of(3) + period - 1), 3);
+
+        let prover_id = [7u8; 32];
+        let round = entries[0].round;
+        let randomness = SealRandomness {
+            ticket: entries[0].clone(),
+            seed: entries[0].clone(),
+        };
+        let (ticket, seed) = beacon
+            .seal_randomness(&randomness, &prover_id, 1, time_of(round), None)
+            .expect("seal randomness failure");
+        assert_ne!(ticket, seed);
+        let (other_ticket, other_seed) = beacon
+            .seal_randomness(&randomness, &prover_id, 2, time_of(round), None)
+            .expect("seal randomness failure");
+        assert_ne!(ticket, other_ticket);
+        assert_ne!(seed, other_seed);
+        // Forged entries are rejected.
+        let mut forged = randomness.clone();
+        forged.seed.previous_signature = Some(entries[0].signature.clone());
+        assert!(beacon
+            .seal_randomness(&forged, &prover_id, 1, time_of(round), None)
+            .is_err());
+
+        // The seed must be drawn after the lookback, before the submission.
+        let (beacon, entries) = fixture(GENERATED, "chained", 2);
+        let time_of = |round: u64| beacon.config().genesis_time + (round - 1) * period;
+        let randomness = SealRandomness {
+            ticket: entries[0].clone(),
+            seed: entries[2].clone(),
+        };
+        beacon
+            .seal_randomness(&randomness, &prover_id, 1, time_of(4), None)
+            .expect("seal randomness failure");
+        let early_seed = SealRandomness {
+            ticket: entries[0].clone(),
+            seed: entries[1].clone(),
+        };
+        assert!(beacon
+            .seal_randomness(&early_seed, &prover_id, 1, time_of(4), None)
+            .is_err());
+        assert!(beacon
+            .seal_randomness(&randomness, &prover_id, 1, time_of(2), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_seal_randomness_age() {
+        let (beacon, entries) = fixture(GENERATED, "chained", 2);
+        let genesis_time = beacon.config().genesis_time;
+        let period = beacon.config().period;
+        let time_of = |round: u64| genesis_time + (round - 1) * period;
+        let prover_id = [7u8; 32];
+        let randomness = |ticket: usize, seed: usize| SealRandomness {
+            ticket: entries[ticket - 1].clone(),
+            seed: entries[seed - 1].clone(),
+        };
+
+        // A long seal proven with a fresh seed, its ticket being as old as the seed allows.
+        beacon
+            .seal_randomness(&randomness(1, 5), &prover_id, 1, time_of(6), None)
+            .expect("seal randomness failure");
+        // A ticket older than that is rejected for a new seal, but not for a sector sealed with
+        // it and proven again.
+        assert!(beacon
+            .seal_randomness(&randomness(1, 6), &prover_id, 1, time_of(6), None)
+            .is_err());
+        assert!(beacon
+            .seal_randomness(&randomness(1, 6), &prover_id, 1, time_of(6), Some(2))
+            .is_err());
+        beacon
+            .seal_randomness(&randomness(1, 6), &prover_id, 1, time_of(6), Some(1))
+            .expect("seal randomness failure");
+
+        // An old seed, and so an old proof, can not be replayed.
+        assert!(beacon
+            .seal_randomness(&randomness(1, 4), &prover_id, 1, time_of(6), Some(1))
+            .is_err());
+        assert!(beacon
+            .seal_randomness(&randomness(1, 5), &prover_id, 1, time_of(7), None)
+            .is_err());
+    }
+
+    /// Regenerates the rounds of the local beacons, signing with blstrs.
+    #[test]
+    #[ignore]
+    fn generate_fixtures() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let mut fixtures = serde_json::Map::new();
+        for (name, scheme) in [
+            ("chained", BeaconScheme::Chained),
+            ("unchained", BeaconScheme::Unchained),
+        ] {
+            let secret_key = blstrs::Scalar::random(&mut rng);
+            let public_key = (blstrs::G1Projective::generator() * secret_key).to_affine();
+            let mut previous_signature = Sha256::digest(name).to_vec();
+            let mut rounds = vec![];
+            for round in 1..=6 {
+                let mut entry = BeaconEntry {
+                    round,
+                    signature: vec![],
+                    previous_signature: Some(previous_signature.clone()),
+                };
+                if scheme == BeaconScheme::Unchained {
+                    entry.previous_signature = None;
+                }
+                let message = entry.message(scheme).unwrap();
+                let signature =
+                    blstrs::G2Projective::hash_to_curve(&message, BEACON_DST, &[]) * secret_key;
+                entry.signature = signature.to_affine().to_compressed().to_vec();
+
+                let mut round = json!({
+                    "round": round,
+                    "randomness": hex::encode(entry.randomness()),
+                    "signature": hex::encode(&entry.signature),
+                });
+                if let Some(previous) = &entry.previous_signature {
+                    round["previous_signature"] = json!(hex::encode(previous));
+                }
+                rounds.push(round);
+                previous_signature = entry.signature;
+            }
+            let info = json!({
+                "public_key": hex::encode(public_key.to_compressed()),
+                "period": 30,
+                "genesis_time": 1_692_803_367u64,
+                "schemeID": match scheme {
+                    BeaconScheme::Chained => "pedersen-bls-chained",
+                    BeaconScheme::Unchained => "pedersen-bls-unchained",
+                },
+            });
+            fixtures.insert(name.to_string(), json!({ "info": info, "rounds": rounds }));
+        }
+        std::fs::write(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/beacon_generated.json"
+            ),
+            serde_json::to_string_pretty(&fixtures).unwrap() + "\n",
+        )
+        .expect("write failure");
+    }
+}
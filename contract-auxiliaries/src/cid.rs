@@ -0,0 +1,80 @@
+//! Decoding of the CIDs of commitments, mirroring `seal::types::cid`: `comm_p` and `comm_d` use
+//! the `fil-commitment-unsealed` codec with a `sha2-256-trunc254-padded` multihash, `comm_r` the
+//! `fil-commitment-sealed` codec with a `poseidon-bls12_381-a2-fc1` multihash.
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::domain::{poseidon::PoseidonDomain, sha256::Sha256Domain};
+
+pub const FIL_COMMITMENT_UNSEALED: u64 = 0xf101;
+pub const FIL_COMMITMENT_SEALED: u64 = 0xf102;
+pub const SHA2_256_TRUNC254_PADDED: u64 = 0x1012;
+pub const POSEIDON_BLS12_381_A2_FC1: u64 = 0xb401;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Decodes the base32 CID of a `comm_p` or a `comm_d`.
+pub fn unsealed_from_cid(cid: &str) -> Result<Sha256Domain> {
+    decode(cid, FIL_COMMITMENT_UNSEALED, SHA2_256_TRUNC254_PADDED).map(Sha256Domain)
+}
+
+/// Decodes the base32 CID of a `comm_r`.
+pub fn sealed_from_cid(cid: &str) -> Result<PoseidonDomain> {
+    decode(cid, FIL_COMMITMENT_SEALED, POSEIDON_BLS12_381_A2_FC1).map(PoseidonDomain)
+}
+
+fn decode(cid: &str, codec: u64, multihash: u64) -> Result<[u8; 32]> {
+    let encoded = cid.strip_prefix('b').context("CID is not base32 encoded")?;
+    let bytes = base32_decode(encoded)?;
+    let mut bytes = bytes.as_slice();
+
+    ensure!(read_varint(&mut bytes)? == 1, "not a CIDv1");
+    ensure!(read_varint(&mut bytes)? == codec, "unexpected codec");
+    ensure!(
+        read_varint(&mut bytes)? == multihash,
+        "unexpected multihash"
+    );
+    ensure!(
+        read_varint(&mut bytes)? == 32 && bytes.len() == 32,
+        "commitment is not 32 bytes"
+    );
+
+    let mut commitment = [0; 32];
+    commitment.copy_from_slice(bytes);
+    Ok(commitment)
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0;
+    for (i, byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Ok(value);
+        }
+    }
+    bail!("truncated varint")
+}
+
+/// RFC 4648 base32, lowercase and without padding.
+fn base32_decode(encoded: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .context("invalid base32 character")?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    ensure!(
+        bits < 5 && buffer & ((1 << bits) - 1) == 0,
+        "invalid base32 padding"
+    );
+    Ok(bytes)
+}
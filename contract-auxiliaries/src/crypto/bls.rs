@@ -0,0 +1,403 @@
+//! BLS signatures over BLS12-381 with the public keys in G1 and the signatures in G2, as signed by
+//! drand: the `BLS12381G2_XMD:SHA-256_SSWU_RO_` hash to curve of RFC 9380 and the compressed point
+//! encoding of zcash.
+
+use anyhow::{anyhow, ensure, Result};
+use ark_bls12_381::{Bls12_381, Fq, Fq2, G1Affine, G2Affine, G2Projective};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{field_new, BigInteger, BigInteger384, Field, One, PrimeField, SquareRootField, Zero};
+use sha2::{Digest, Sha256};
+
+/// The size of a compressed point of G1, the public keys.
+pub const G1_COMPRESSED_SIZE: usize = 48;
+/// The size of a compressed point of G2, the signatures.
+pub const G2_COMPRESSED_SIZE: usize = 96;
+
+const COMPRESSION_FLAG: u8 = 0x80;
+const INFINITY_FLAG: u8 = 0x40;
+const SIGN_FLAG: u8 = 0x20;
+
+/// The isogenous curve `y^2 = x^3 + A' x + B'` of the simplified SWU map, with `A' = 240 i` and
+/// `B' = 1012 (1 + i)`, and its non-square `Z = -(2 + i)`.
+const SSWU_A: Fq2 = field_new!(Fq2, field_new!(Fq, "0"), field_new!(Fq, "240"));
+const SSWU_B: Fq2 = field_new!(Fq2, field_new!(Fq, "1012"), field_new!(Fq, "1012"));
+const SSWU_Z: Fq2 = field_new!(Fq2, field_new!(Fq, "-2"), field_new!(Fq, "-1"));
+
+/// The effective cofactor `h_eff` of G2, in little-endian limbs.
+const H_EFF: [u64; 10] = [
+    0xe8020005aaa95551,
+    0x59894c0adebbf6b4,
+    0xe954cbc06689f6a3,
+    0x2ec0ec69d7477c1a,
+    0x6d82bf015d1212b0,
+    0x329c2f178731db95,
+    0x9986ff031508ffe1,
+    0x88e2a8e9145ad768,
+    0x584c6a0ea91b3528,
+    0x0bc69f08f2ee75b3,
+];
+
+// The coefficients of the 3-isogeny map from the SWU curve to E2, constant term first (RFC 9380,
+// appendix E.3). The leading coefficients of the denominators are one.
+const ISO_X_NUM: [Fq2; 4] = [
+    field_new!(
+        Fq2,
+        field_new!(Fq, "889424345604814976315064405719089812568196182208668418962679585805340366775741747653930584250892369786198727235542"),
+        field_new!(Fq, "889424345604814976315064405719089812568196182208668418962679585805340366775741747653930584250892369786198727235542"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "0"),
+        field_new!(Fq, "2668273036814444928945193217157269437704588546626005256888038757416021100327225242961791752752677109358596181706522"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "2668273036814444928945193217157269437704588546626005256888038757416021100327225242961791752752677109358596181706526"),
+        field_new!(Fq, "1334136518407222464472596608578634718852294273313002628444019378708010550163612621480895876376338554679298090853261"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "3557697382419259905260257622876359250272784728834673675850718343221361467102966990615722337003569479144794908942033"),
+        field_new!(Fq, "0"),
+    ),
+];
+
+const ISO_X_DEN: [Fq2; 2] = [
+    field_new!(
+        Fq2,
+        field_new!(Fq, "0"),
+        field_new!(Fq, "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559715"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "12"),
+        field_new!(Fq, "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559775"),
+    ),
+];
+
+const ISO_Y_NUM: [Fq2; 4] = [
+    field_new!(
+        Fq2,
+        field_new!(Fq, "3261222600550988246488569487636662646083386001431784202863158481286248011511053074731078808919938689216061999863558"),
+        field_new!(Fq, "3261222600550988246488569487636662646083386001431784202863158481286248011511053074731078808919938689216061999863558"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "0"),
+        field_new!(Fq, "889424345604814976315064405719089812568196182208668418962679585805340366775741747653930584250892369786198727235518"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "2668273036814444928945193217157269437704588546626005256888038757416021100327225242961791752752677109358596181706524"),
+        field_new!(Fq, "1334136518407222464472596608578634718852294273313002628444019378708010550163612621480895876376338554679298090853263"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "2816510427748580758331037284777117739799287910327449993381818688383577828123182200904113516794492504322962636245776"),
+        field_new!(Fq, "0"),
+    ),
+];
+
+const ISO_Y_DEN: [Fq2; 3] = [
+    field_new!(
+        Fq2,
+        field_new!(Fq, "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559355"),
+        field_new!(Fq, "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559355"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "0"),
+        field_new!(Fq, "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559571"),
+    ),
+    field_new!(
+        Fq2,
+        field_new!(Fq, "18"),
+        field_new!(Fq, "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559769"),
+    ),
+];
+/// Expands `msg` into `len` pseudo-random bytes with `expand_message_xmd` and SHA-256.
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Result<Vec<u8>> {
+    const B_IN_BYTES: usize = 32;
+    const R_IN_BYTES: usize = 64;
+
+    let ell = len.div_ceil(B_IN_BYTES);
+    ensure!(
+        ell <= 255 && len <= u16::MAX as usize,
+        "too many bytes requested"
+    );
+    ensure!(dst.len() <= 255, "domain separation tag too long");
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+
+    let b_0: [u8; 32] = Sha256::new()
+        .chain_update([0u8; R_IN_BYTES])
+        .chain_update(msg)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize()
+        .into();
+
+    let mut bytes = Vec::with_capacity(ell * B_IN_BYTES);
+    let mut b_i = [0u8; 32];
+    for i in 1..=ell {
+        let mut input = b_0;
+        input.iter_mut().zip(b_i).for_each(|(a, b)| *a ^= b);
+        b_i = Sha256::new()
+            .chain_update(input)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize()
+            .into();
+        bytes.extend_from_slice(&b_i);
+    }
+    bytes.truncate(len);
+    Ok(bytes)
+}
+
+/// Hashes `msg` to two elements of Fp2, with 64 bytes per element of Fp.
+fn hash_to_field(msg: &[u8], dst: &[u8]) -> Result<[Fq2; 2]> {
+    const L: usize = 64;
+
+    let bytes = expand_message_xmd(msg, dst, 4 * L)?;
+    let element = |i: usize| Fq::from_be_bytes_mod_order(&bytes[i * L..(i + 1) * L]);
+    Ok([
+        Fq2::new(element(0), element(1)),
+        Fq2::new(element(2), element(3)),
+    ])
+}
+
+/// The sign of an element of Fp2, as defined by RFC 9380.
+fn sgn0(x: &Fq2) -> bool {
+    let sign_0 = x.c0.into_repr().is_odd();
+    let sign_1 = x.c1.into_repr().is_odd();
+    sign_0 || (x.c0.is_zero() && sign_1)
+}
+
+/// Maps `u` to a point `(x', y')` of the isogenous curve with the simplified SWU map.
+fn map_to_curve_sswu(u: &Fq2) -> (Fq2, Fq2) {
+    let z_u2 = SSWU_Z * u.square();
+    let tv1 = (z_u2.square() + z_u2).inverse().unwrap_or_else(Fq2::zero);
+    let x1 = if tv1.is_zero() {
+        SSWU_B * (SSWU_Z * SSWU_A).inverse().expect("non-zero constant")
+    } else {
+        -SSWU_B * SSWU_A.inverse().expect("non-zero constant") * (Fq2::one() + tv1)
+    };
+    let g = |x: &Fq2| x.square() * x + SSWU_A * x + SSWU_B;
+
+    let (x, mut y) = match g(&x1).sqrt() {
+        Some(y) => (x1, y),
+        None => {
+            let x2 = z_u2 * x1;
+            let y = g(&x2).sqrt().expect("g(x2) is a square when g(x1) is not");
+            (x2, y)
+        }
+    };
+    if sgn0(u) != sgn0(&y) {
+        y = -y;
+    }
+    (x, y)
+}
+
+/// Evaluates the polynomial of `coefficients`, constant term first, at `x`.
+fn evaluate(coefficients: &[Fq2], x: &Fq2) -> Fq2 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Fq2::zero(), |acc, c| acc * x + c)
+}
+
+/// Maps a point of the isogenous curve to E2 with the 3-isogeny.
+fn iso_map((x, y): (Fq2, Fq2)) -> G2Projective {
+    let x_num = evaluate(&ISO_X_NUM, &x);
+    let x_den = evaluate(&ISO_X_DEN, &x) + x.square();
+    let y_num = evaluate(&ISO_Y_NUM, &x);
+    let y_den = evaluate(&ISO_Y_DEN, &x) + x.square() * x;
+
+    match (x_den.inverse(), y_den.inverse()) {
+        (Some(x_den), Some(y_den)) => {
+            G2Affine::new(x_num * x_den, y * y_num * y_den, false).into_projective()
+        }
+        _ => G2Projective::zero(),
+    }
+}
+
+/// Hashes `msg` to a point of G2 with the `BLS12381G2_XMD:SHA-256_SSWU_RO_` suite.
+pub fn hash_to_g2(msg: &[u8], dst: &[u8]) -> Result<G2Affine> {
+    let [u0, u1] = hash_to_field(msg, dst)?;
+    let q = iso_map(map_to_curve_sswu(&u0)) + iso_map(map_to_curve_sswu(&u1));
+    Ok(q.mul(H_EFF).into_affine())
+}
+
+/// Reads a big-endian element of Fp, rejecting the non-canonical ones.
+fn fq_from_be_bytes(bytes: &[u8]) -> Result<Fq> {
+    let mut limbs = [0u64; 6];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks(8)) {
+        *limb = u64::from_be_bytes(chunk.try_into().expect("chunk of 8 bytes"));
+    }
+    Fq::from_repr(BigInteger384(limbs)).ok_or_else(|| anyhow!("coordinate is not in the field"))
+}
+
+/// Reads the flags of a compressed point and the bytes of its x-coordinate without them. Returns
+/// `None` for the point at infinity.
+fn compressed_coordinate<const N: usize>(bytes: &[u8]) -> Result<Option<([u8; N], bool)>> {
+    let mut x: [u8; N] = bytes
+        .try_into()
+        .map_err(|_| anyhow!("invalid length {} of a compressed point", bytes.len()))?;
+    let flags = x[0];
+    ensure!(flags & COMPRESSION_FLAG != 0, "point is not compressed");
+    x[0] &= !(COMPRESSION_FLAG | INFINITY_FLAG | SIGN_FLAG);
+
+    if flags & INFINITY_FLAG != 0 {
+        ensure!(
+            flags & SIGN_FLAG == 0 && x.iter().all(|b| *b == 0),
+            "malformed point at infinity"
+        );
+        return Ok(None);
+    }
+    Ok(Some((x, flags & SIGN_FLAG != 0)))
+}
+
+/// Decodes a compressed point of G1, checking that it is in the subgroup.
+pub fn decode_g1_compressed(bytes: &[u8]) -> Result<G1Affine> {
+    let (x, greatest) = match compressed_coordinate::<G1_COMPRESSED_SIZE>(bytes)? {
+        Some(coordinate) => coordinate,
+        None => return Ok(G1Affine::zero()),
+    };
+    let point = G1Affine::get_point_from_x(fq_from_be_bytes(&x)?, greatest)
+        .ok_or_else(|| anyhow!("point is not on the curve"))?;
+    ensure!(
+        point.is_in_correct_subgroup_assuming_on_curve(),
+        "point is not in G1"
+    );
+    Ok(point)
+}
+
+/// Decodes a compressed point of G2, with the coordinates as `c1 || c0`, checking that it is in
+/// the subgroup.
+pub fn decode_g2_compressed(bytes: &[u8]) -> Result<G2Affine> {
+    let (x, greatest) = match compressed_coordinate::<G2_COMPRESSED_SIZE>(bytes)? {
+        Some(coordinate) => coordinate,
+        None => return Ok(G2Affine::zero()),
+    };
+    let x = Fq2::new(fq_from_be_bytes(&x[48..])?, fq_from_be_bytes(&x[..48])?);
+    let point = G2Affine::get_point_from_x(x, greatest)
+        .ok_or_else(|| anyhow!("point is not on the curve"))?;
+    ensure!(
+        point.is_in_correct_subgroup_assuming_on_curve(),
+        "point is not in G2"
+    );
+    Ok(point)
+}
+
+/// Returns whether `signature` is a signature of `msg` by `public_key`, that is whether
+/// `e(public_key, H(msg)) == e(g1, signature)`.
+pub fn verify(public_key: &G1Affine, msg: &[u8], dst: &[u8], signature: &G2Affine) -> Result<bool> {
+    if public_key.is_zero() || signature.is_zero() {
+        return Ok(false);
+    }
+    let hash = hash_to_g2(msg, dst)?;
+    let pairs = [
+        ((*public_key).into(), hash.into()),
+        (
+            (-G1Affine::prime_subgroup_generator()).into(),
+            (*signature).into(),
+        ),
+    ];
+    let qap = Bls12_381::miller_loop(pairs.iter());
+    Ok(Bls12_381::final_exponentiation(&qap) == Some(<Bls12_381 as PairingEngine>::Fqk::one()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::{G1Affine as BlstG1, G2Affine as BlstG2, G2Projective as BlstG2Projective};
+    use ff::Field as _;
+    use group::{prime::PrimeCurveAffine, Curve, Group};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    const TEST_SEED: [u8; 16] = [
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ];
+
+    const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+    #[test]
+    fn test_expand_message_xmd() {
+        // The test vectors of RFC 9380, appendix K.1.
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        let bytes = expand_message_xmd(b"", dst, 0x20).expect("expand failure");
+        assert_eq!(
+            hex::encode(bytes),
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"
+        );
+        let bytes = expand_message_xmd(b"abc", dst, 0x20).expect("expand failure");
+        assert_eq!(
+            hex::encode(bytes),
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"
+        );
+    }
+
+    #[test]
+    fn test_hash_to_g2() {
+        for msg in [&b""[..], b"abc", b"abcdef0123456789", &[0x61; 300]] {
+            let expected = BlstG2Projective::hash_to_curve(msg, DST, &[]).to_affine();
+            let point = hash_to_g2(msg, DST).expect("hash failure");
+            assert_eq!(
+                point,
+                decode_g2_compressed(&expected.to_compressed()).expect("decode failure")
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_compressed() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        for _ in 0..8 {
+            let g1 =
+                (blstrs::G1Projective::generator() * blstrs::Scalar::random(&mut rng)).to_affine();
+            let g2 = (BlstG2Projective::generator() * blstrs::Scalar::random(&mut rng)).to_affine();
+            let (x, y) = (g1.x().to_bytes_be(), g1.y().to_bytes_be());
+            let point = decode_g1_compressed(&g1.to_compressed()).expect("decode failure");
+            assert_eq!(point.x, fq_from_be_bytes(&x).unwrap());
+            assert_eq!(point.y, fq_from_be_bytes(&y).unwrap());
+            let point = decode_g2_compressed(&g2.to_compressed()).expect("decode failure");
+            let g2_y = g2.y();
+            assert_eq!(
+                point.y.c0,
+                fq_from_be_bytes(&g2_y.c0().to_bytes_be()).unwrap()
+            );
+            assert_eq!(
+                point.y.c1,
+                fq_from_be_bytes(&g2_y.c1().to_bytes_be()).unwrap()
+            );
+        }
+
+        assert!(decode_g1_compressed(&BlstG1::identity().to_compressed())
+            .expect("decode failure")
+            .is_zero());
+        assert!(decode_g2_compressed(&BlstG2::identity().to_compressed())
+            .expect("decode failure")
+            .is_zero());
+        // Uncompressed encodings and wrong lengths are rejected.
+        assert!(decode_g1_compressed(&BlstG1::generator().to_uncompressed()).is_err());
+        assert!(decode_g2_compressed(&BlstG1::generator().to_compressed()).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let secret_key = blstrs::Scalar::random(&mut rng);
+        let public_key = (blstrs::G1Projective::generator() * secret_key).to_affine();
+        let signature =
+            (BlstG2Projective::hash_to_curve(b"message", DST, &[]) * secret_key).to_affine();
+
+        let public_key = decode_g1_compressed(&public_key.to_compressed()).expect("decode failure");
+        let signature = decode_g2_compressed(&signature.to_compressed()).expect("decode failure");
+        assert!(verify(&public_key, b"message", DST, &signature).expect("verify failure"));
+        assert!(!verify(&public_key, b"other message", DST, &signature).expect("verify failure"));
+        assert!(!verify(&public_key, b"message", b"OTHER_DST", &signature).expect("verify failure"));
+    }
+}
@@ -1,6 +1,8 @@
 use sha2::{Digest, Sha256};
 
+pub mod bls;
 pub mod feistel;
+pub mod poseidon;
 
 pub struct DomainSeparationTag(&'static str);
 
@@ -0,0 +1,222 @@
+//! Poseidon over the BLS12-381 scalar field, as used by `hashers::poseidon`.
+//!
+//! Constants are derived the way neptune derives them for `Strength::Standard` and
+//! `HashType::MerkleTree`: round constants from the Grain LFSR and a Cauchy MDS matrix. The
+//! permutation is the plain, unoptimized one, which gives the same results as neptune's
+//! optimized implementation.
+
+use anyhow::{bail, ensure, Result};
+use ark_bls12_381::Fr;
+use ark_ff::{biginteger::BigInteger256, Field, PrimeField, Zero};
+use once_cell::sync::Lazy;
+
+/// Arities supported by `hashers::poseidon`.
+pub const POSEIDON_ARITIES: [usize; 4] = [2, 4, 8, 11];
+
+pub static POSEIDON_CONSTANTS_2: Lazy<PoseidonConstants> = Lazy::new(|| PoseidonConstants::new(2));
+pub static POSEIDON_CONSTANTS_4: Lazy<PoseidonConstants> = Lazy::new(|| PoseidonConstants::new(4));
+pub static POSEIDON_CONSTANTS_8: Lazy<PoseidonConstants> = Lazy::new(|| PoseidonConstants::new(8));
+pub static POSEIDON_CONSTANTS_11: Lazy<PoseidonConstants> =
+    Lazy::new(|| PoseidonConstants::new(11));
+
+const FULL_ROUNDS: usize = 8;
+const FIELD_BITS: u32 = 255;
+/// Gf(p)
+const FIELD: u32 = 1;
+/// x^5
+const SBOX: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoseidonConstants {
+    arity: usize,
+    domain_tag: Fr,
+    round_constants: Vec<Fr>,
+    mds_matrix: Vec<Vec<Fr>>,
+    partial_rounds: usize,
+}
+
+impl PoseidonConstants {
+    /// Panics if `arity` is not one of [`POSEIDON_ARITIES`].
+    pub fn new(arity: usize) -> Self {
+        let width = arity + 1;
+        let partial_rounds = partial_rounds(arity);
+
+        PoseidonConstants {
+            arity,
+            // 2^arity - 1
+            domain_tag: Fr::from((1u64 << arity) - 1),
+            round_constants: round_constants(width, partial_rounds),
+            mds_matrix: mds_matrix(width),
+            partial_rounds,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Hashes `arity` elements.
+    pub fn hash(&self, preimage: &[Fr]) -> Result<Fr> {
+        ensure!(
+            preimage.len() == self.arity,
+            "invalid preimage size {} for arity {}",
+            preimage.len(),
+            self.arity
+        );
+
+        let mut state = Vec::with_capacity(self.arity + 1);
+        state.push(self.domain_tag);
+        state.extend_from_slice(preimage);
+
+        let half_full_rounds = FULL_ROUNDS / 2;
+        let mut round_constants = self.round_constants.chunks(state.len());
+        for round in 0..FULL_ROUNDS + self.partial_rounds {
+            let constants = round_constants.next().expect("not enough round constants");
+            for (element, constant) in state.iter_mut().zip(constants) {
+                *element += constant;
+            }
+
+            let full = round < half_full_rounds || round >= half_full_rounds + self.partial_rounds;
+            if full {
+                state.iter_mut().for_each(quintic_s_box);
+            } else {
+                quintic_s_box(&mut state[0]);
+            }
+
+            state = self.product_mds(&state);
+        }
+
+        Ok(state[1])
+    }
+
+    fn product_mds(&self, state: &[Fr]) -> Vec<Fr> {
+        (0..state.len())
+            .map(|j| {
+                self.mds_matrix
+                    .iter()
+                    .zip(state)
+                    .fold(Fr::zero(), |acc, (row, element)| acc + row[j] * element)
+            })
+            .collect()
+    }
+}
+
+/// The constants of the given arity, if it is supported.
+pub fn poseidon_constants(arity: usize) -> Option<&'static PoseidonConstants> {
+    match arity {
+        2 => Some(&POSEIDON_CONSTANTS_2),
+        4 => Some(&POSEIDON_CONSTANTS_4),
+        8 => Some(&POSEIDON_CONSTANTS_8),
+        11 => Some(&POSEIDON_CONSTANTS_11),
+        _ => None,
+    }
+}
+
+/// Hashes the preimage with the constants of its length.
+pub fn poseidon_hash(preimage: &[Fr]) -> Result<Fr> {
+    match poseidon_constants(preimage.len()) {
+        Some(constants) => constants.hash(preimage),
+        None => bail!("unsupported arity for Poseidon hasher: {}", preimage.len()),
+    }
+}
+
+fn quintic_s_box(element: &mut Fr) {
+    let squared = element.square();
+    *element *= squared.square();
+}
+
+/// Number of partial rounds for 128 bits of security, see neptune's `round_numbers`.
+fn partial_rounds(arity: usize) -> usize {
+    match arity {
+        2 => 55,
+        4 => 56,
+        8 | 11 => 57,
+        _ => panic!("unsupported arity for Poseidon hasher: {}", arity),
+    }
+}
+
+/// The Cauchy matrix `1 / (x_i + y_j)` with `x = 0..t` and `y = t..2t`.
+fn mds_matrix(width: usize) -> Vec<Vec<Fr>> {
+    (0..width as u64)
+        .map(|x| {
+            (width as u64..2 * width as u64)
+                .map(|y| Fr::from(x + y).inverse().expect("mds entries are non zero"))
+                .collect()
+        })
+        .collect()
+}
+
+fn round_constants(width: usize, partial_rounds: usize) -> Vec<Fr> {
+    let mut grain = Grain::new(&[
+        (2, FIELD),
+        (4, SBOX),
+        (12, FIELD_BITS),
+        (12, width as u32),
+        (10, FULL_ROUNDS as u32),
+        (10, partial_rounds as u32),
+        (30, (1 << 30) - 1),
+    ]);
+
+    (0..(FULL_ROUNDS + partial_rounds) * width)
+        .map(|_| loop {
+            // Sampled big endian, values out of the field are skipped.
+            let mut bytes = [0u8; 32];
+            bytes[0] = grain.next_byte(FIELD_BITS as usize % 8);
+            for byte in &mut bytes[1..] {
+                *byte = grain.next_byte(8);
+            }
+
+            let mut limbs = [0u64; 4];
+            for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks(8)) {
+                *limb = u64::from_be_bytes(chunk.try_into().expect("chunks of 8 bytes"));
+            }
+            if let Some(constant) = Fr::from_repr(BigInteger256(limbs)) {
+                break constant;
+            }
+        })
+        .collect()
+}
+
+/// The Grain LFSR in self-shrinking mode, as specified by the Poseidon paper.
+struct Grain {
+    /// Bit `i` of the register is the `i`-th bit of the sequence.
+    state: u128,
+}
+
+impl Grain {
+    fn new(init: &[(usize, u32)]) -> Self {
+        let mut state = 0u128;
+        let mut len = 0;
+        for (bits, value) in init {
+            for i in (0..*bits).rev() {
+                state |= (((value >> i) & 1) as u128) << len;
+                len += 1;
+            }
+        }
+        debug_assert_eq!(len, 80);
+
+        let mut grain = Grain { state };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let bit = |i: usize| (self.state >> i) & 1;
+        let new_bit = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+        self.state = (self.state >> 1) | (new_bit << 79);
+        new_bit == 1
+    }
+
+    fn next_bit(&mut self) -> bool {
+        while !self.step() {
+            self.step();
+        }
+        self.step()
+    }
+
+    fn next_byte(&mut self, bits: usize) -> u8 {
+        (0..bits).fold(0, |acc, _| (acc << 1) | self.next_bit() as u8)
+    }
+}
@@ -0,0 +1,103 @@
+use std::panic::panic_any;
+
+use anyhow::ensure;
+use ark_bls12_381::Fr;
+use ark_ff::{PrimeField, BigInteger};
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+
+use super::{Domain, Element};
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Blake2sDomain(pub [u8; 32]);
+
+impl Blake2sDomain {
+    pub fn trim_to_fr32(&mut self) {
+        // strip last two bits, to ensure result is in Fr.
+        self.0[31] &= 0b0011_1111;
+    }
+}
+
+impl AsRef<Blake2sDomain> for Blake2sDomain {
+    fn as_ref(&self) -> &Self {
+        self
+    }
+}
+
+impl AsRef<[u8]> for Blake2sDomain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl From<Fr> for Blake2sDomain {
+    fn from(val: Fr) -> Self {
+        let le: [u8; 32] = val.into_repr().to_bytes_le().as_slice().try_into().unwrap();
+        Blake2sDomain(le)
+    }
+}
+
+impl From<Blake2sDomain> for Fr {
+    fn from(val: Blake2sDomain) -> Self {
+        Fr::from_le_bytes_mod_order(&val.0)
+    }
+}
+
+
+impl Domain for Blake2sDomain {
+    fn into_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn try_from_bytes(raw: &[u8]) -> anyhow::Result<Self> {
+        ensure!(
+            raw.len() == Blake2sDomain::byte_len(),
+            "invalid number of bytes"
+        );
+
+        let mut res = Blake2sDomain::default();
+        res.0.copy_from_slice(&raw[0..Blake2sDomain::byte_len()]);
+        Ok(res)
+    }
+
+    fn write_bytes(&self, dest: &mut [u8]) -> anyhow::Result<()> {
+        ensure!(
+            dest.len() >= Blake2sDomain::byte_len(),
+            "invalid number of bytes"
+        );
+
+        dest[0..Blake2sDomain::byte_len()].copy_from_slice(&self.0[..]);
+        Ok(())
+    }
+}
+
+impl Element for Blake2sDomain {
+    fn byte_len() -> usize {
+        32
+    }
+
+    fn from_slice(bytes: &[u8]) -> Self {
+        match Blake2sDomain::try_from_bytes(bytes) {
+            Ok(res) => res,
+            Err(err) => panic_any(err),
+        }
+    }
+
+    fn copy_to_slice(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.0);
+    }
+}
+
+impl From<[u8; 32]> for Blake2sDomain {
+    #[inline]
+    fn from(val: [u8; 32]) -> Self {
+        Blake2sDomain(val)
+    }
+}
+
+impl From<Blake2sDomain> for [u8; 32] {
+    #[inline]
+    fn from(val: Blake2sDomain) -> Self {
+        val.0
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 
 use ark_bls12_381::Fr;
 use serde::{Serialize, de::DeserializeOwned};
+pub mod blake2s;
 pub mod sha256;
 pub mod poseidon;
 
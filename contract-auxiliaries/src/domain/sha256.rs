@@ -11,6 +11,13 @@ use super::{Domain, Element};
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize, JsonSchema, Debug)]
 pub struct Sha256Domain(pub [u8; 32]);
 
+impl Sha256Domain {
+    pub fn trim_to_fr32(&mut self) {
+        // strip last two bits, to ensure result is in Fr.
+        self.0[31] &= 0b0011_1111;
+    }
+}
+
 impl AsRef<Sha256Domain> for Sha256Domain {
     fn as_ref(&self) -> &Self {
         self
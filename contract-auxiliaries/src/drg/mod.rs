@@ -1,2 +1,3 @@
 pub mod drgraph;
-pub mod stacked;
\ No newline at end of file
+pub mod stacked;
+pub mod vanilla;
@@ -1,4 +1,4 @@
-use crate::{domain::Domain, drg::drgraph::Graph};
+use crate::{domain::Domain, drg::drgraph::Graph, groth16::batch_verify_proofs};
 
 use super::{
     challenges::ChallengeRequirements,
@@ -181,4 +181,29 @@ impl<H: Domain, G: Domain> VerifierStackedDrg<H, G> {
         };
         Ok(res)
     }
+
+    /// Verifies the proofs of several sectors with a single batch verification, `proofs[i]` being
+    /// the proof of `public_inputs[i]`.
+    pub fn batch_verify(
+        &self,
+        public_params: &PublicParams<H>,
+        public_inputs: &[PublicInputs<H, G>],
+        proofs: &[Proof<Bls12_381>],
+        requirements: &ChallengeRequirements,
+    ) -> Result<bool> {
+        ensure!(
+            public_inputs.len() == proofs.len(),
+            "expected as many public inputs as proofs"
+        );
+        if !Self::satisfies_requirements(public_params, requirements, 1) {
+            return Ok(false);
+        }
+
+        let inputs = public_inputs
+            .iter()
+            .map(|pub_in| Self::generate_public_inputs(pub_in, public_params, Some(0)))
+            .collect::<Result<Vec<_>>>()?;
+
+        batch_verify_proofs(&self.pvk, proofs, &inputs)
+    }
 }
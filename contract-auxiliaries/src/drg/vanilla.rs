@@ -0,0 +1,182 @@
+//! Verifier of the single layer DRG PoRep of `proofs_porep::drg`, used for the small sector
+//! sizes. Its data and replica are both committed with binary trees, and the commitments are
+//! public inputs.
+
+use anyhow::{ensure, Result};
+use ark_bls12_381::Fr;
+use num_bigint::BigUint;
+use num_traits::cast::ToPrimitive;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    domain::Domain,
+    drg::drgraph::{BucketGraph, Graph},
+    groth16::Groth16Backend,
+    utils::ApiVersion,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, JsonSchema)]
+pub struct SetupParams {
+    // Number of nodes
+    pub nodes: u64,
+
+    // Base degree of DRG
+    pub degree: u64,
+
+    pub porep_id: [u8; 32],
+    pub challenges_count: u64,
+    pub api_version: ApiVersion,
+}
+
+pub struct PublicParams<D: Domain> {
+    pub graph: BucketGraph<D>,
+    pub challenges_count: usize,
+}
+
+/// The inputs of a sector, from which the replica id and the challenges are derived.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, JsonSchema)]
+pub struct PublicInputs<D: Domain> {
+    pub prover_id: [u8; 32],
+    pub sector_id: u64,
+    pub ticket: [u8; 32],
+    pub seed: [u8; 32],
+    #[serde(bound = "")]
+    pub comm_d: D,
+    #[serde(bound = "")]
+    pub comm_r: D,
+}
+
+impl<D: Domain> PublicInputs<D> {
+    /// The replica id, as derived by the prover from the ticket and `comm_d`.
+    pub fn replica_id(&self, porep_id: &[u8; 32]) -> Result<D> {
+        let mut hash: [u8; 32] = Sha256::new()
+            .chain_update(self.prover_id)
+            .chain_update(self.sector_id.to_be_bytes())
+            .chain_update(self.ticket)
+            .chain_update(self.comm_d.into_bytes())
+            .chain_update(porep_id)
+            .finalize()
+            .into();
+        hash[31] &= 0b0011_1111;
+
+        D::try_from_bytes(&hash)
+    }
+}
+
+/// Derives the challenged nodes of a replica from the interactive `seed`. The first node has no
+/// parents, so it is never challenged.
+pub fn derive_challenges<D: Domain>(
+    replica_id: &D,
+    seed: &[u8; 32],
+    nodes: usize,
+    challenges_count: usize,
+) -> Vec<usize> {
+    assert!(nodes > 2, "Too few nodes: {}", nodes);
+
+    (0..challenges_count as u32)
+        .map(|j| {
+            let hash = Sha256::new()
+                .chain_update(replica_id.into_bytes())
+                .chain_update(seed)
+                .chain_update(j.to_le_bytes())
+                .finalize();
+
+            let challenge = BigUint::from_bytes_le(hash.as_ref()) % (nodes - 1);
+            challenge
+                .to_usize()
+                .expect("`challenge` exceeds size of `usize`")
+                + 1
+        })
+        .collect()
+}
+
+pub struct VerifierDrgPoRep<D: Domain> {
+    pub public_params: PublicParams<D>,
+    porep_id: [u8; 32],
+}
+
+impl<D: Domain> VerifierDrgPoRep<D> {
+    pub fn setup(sp: &SetupParams) -> Result<Self> {
+        let graph = BucketGraph::new(
+            sp.nodes as usize,
+            sp.degree as usize,
+            0,
+            sp.porep_id,
+            sp.api_version,
+        )?;
+
+        Ok(Self {
+            public_params: PublicParams {
+                graph,
+                challenges_count: sp.challenges_count as usize,
+            },
+            porep_id: sp.porep_id,
+        })
+    }
+
+    /// Generates the public inputs of the DRG PoRep circuit: the replica id, then for every
+    /// challenge the paths and the root of the challenged node and its parents in the replica
+    /// tree, and of the challenged node in the data tree.
+    pub fn generate_public_inputs(&self, pub_in: &PublicInputs<D>) -> Result<Vec<Fr>> {
+        let graph = &self.public_params.graph;
+        let replica_id = pub_in.replica_id(&self.porep_id)?;
+
+        let mut inputs = vec![replica_id.into()];
+
+        let mut parents = vec![0; graph.degree()];
+        for challenge in derive_challenges(
+            &replica_id,
+            &pub_in.seed,
+            graph.size(),
+            self.public_params.challenges_count,
+        ) {
+            ensure!(challenge < graph.size(), "Challenge out of range");
+            graph.parents(challenge, &mut parents)?;
+
+            // Inputs are packed with one `Fr` per path, which is the index of the node.
+            inputs.push(Fr::from(challenge as u64));
+            inputs.push(pub_in.comm_r.into());
+            for parent in &parents {
+                inputs.push(Fr::from(*parent as u64));
+                inputs.push(pub_in.comm_r.into());
+            }
+
+            inputs.push(Fr::from(challenge as u64));
+            inputs.push(pub_in.comm_d.into());
+        }
+
+        Ok(inputs)
+    }
+
+    /// Verifies the proof of `pub_in` against the verifying key `vk` of the DRG PoRep circuit.
+    pub fn verify<B: Groth16Backend>(
+        &self,
+        backend: &B,
+        vk: &[u8],
+        proof: &[u8],
+        pub_in: &PublicInputs<D>,
+    ) -> Result<bool> {
+        let inputs = self.generate_public_inputs(pub_in)?;
+
+        backend.verify(vk, proof, &inputs)
+    }
+
+    /// Verifies the proofs of several sectors with a single batch verification, `pub_ins[i]`
+    /// being the public inputs of `proofs[i]`.
+    pub fn batch_verify<B: Groth16Backend>(
+        &self,
+        backend: &B,
+        vk: &[u8],
+        proofs: &[&[u8]],
+        pub_ins: &[PublicInputs<D>],
+    ) -> Result<bool> {
+        let inputs = pub_ins
+            .iter()
+            .map(|pub_in| self.generate_public_inputs(pub_in))
+            .collect::<Result<Vec<_>>>()?;
+
+        backend.batch_verify(vk, proofs, &inputs)
+    }
+}
@@ -0,0 +1,345 @@
+//! Backends verifying the Groth16 proofs of the contract, either with arkworks in Wasm or with
+//! the verifier of the host.
+//!
+//! The verifying keys and proofs are in the canonical, compressed encoding of arkworks, as they are
+//! stored and submitted to the contract.
+
+use anyhow::{anyhow, ensure, Result};
+use ark_bls12_381::{Bls12_381, Fr, G1Projective};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{BigInteger, Field, PrimeField, Zero};
+use ark_groth16::{prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sha2::{Digest, Sha256};
+
+pub trait Groth16Backend {
+    fn verify(&self, vk: &[u8], proof: &[u8], inputs: &[Fr]) -> Result<bool>;
+
+    /// Verifies several proofs of `vk`, `inputs[i]` being the public inputs of `proofs[i]`.
+    fn batch_verify(&self, vk: &[u8], proofs: &[&[u8]], inputs: &[Vec<Fr>]) -> Result<bool> {
+        ensure!(proofs.len() == inputs.len(), "missing public inputs");
+        for (proof, inputs) in proofs.iter().zip(inputs) {
+            if !self.verify(vk, proof, inputs)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Verifies the proofs with `ark-groth16`, doing the pairings in Wasm.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ArkworksBackend;
+
+impl Groth16Backend for ArkworksBackend {
+    fn verify(&self, vk: &[u8], proof: &[u8], inputs: &[Fr]) -> Result<bool> {
+        let vk = VerifyingKey::<Bls12_381>::deserialize(vk)
+            .map_err(|err| anyhow!("invalid verifying key: {}", err))?;
+        let proof = Proof::<Bls12_381>::deserialize(proof)
+            .map_err(|err| anyhow!("invalid proof: {}", err))?;
+
+        verify_proof(&prepare_verifying_key(&vk), &proof, inputs)
+            .map_err(|err| anyhow!("failed to verify groth16 proof: {}", err))
+    }
+
+    fn batch_verify(&self, vk: &[u8], proofs: &[&[u8]], inputs: &[Vec<Fr>]) -> Result<bool> {
+        let vk = VerifyingKey::<Bls12_381>::deserialize(vk)
+            .map_err(|err| anyhow!("invalid verifying key: {}", err))?;
+        let proofs = proofs
+            .iter()
+            .map(|proof| Proof::<Bls12_381>::deserialize(*proof))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| anyhow!("invalid proof: {}", err))?;
+
+        batch_verify_proofs(&prepare_verifying_key(&vk), &proofs, inputs)
+    }
+}
+
+/// Derives the coefficients of the random linear combination of [`batch_verify_proofs`] from the
+/// hash of the proofs and their public inputs, as 128 bit scalars.
+fn batch_randomness(proofs: &[Proof<Bls12_381>], inputs: &[Vec<Fr>]) -> Result<Vec<Fr>> {
+    let mut transcript = Sha256::new();
+    for (proof, inputs) in proofs.iter().zip(inputs) {
+        let mut bytes = Vec::new();
+        proof
+            .serialize(&mut bytes)
+            .map_err(|err| anyhow!("failed to serialize proof: {}", err))?;
+        transcript.update(&bytes);
+        transcript.update((inputs.len() as u64).to_le_bytes());
+        for input in inputs {
+            transcript.update(input.into_repr().to_bytes_le());
+        }
+    }
+    let seed = transcript.finalize();
+
+    Ok((0..proofs.len() as u64)
+        .map(|i| {
+            let hash = Sha256::new()
+                .chain_update(seed)
+                .chain_update(i.to_le_bytes())
+                .finalize();
+            Fr::from_le_bytes_mod_order(&hash[..16])
+        })
+        .collect())
+}
+
+/// Verifies several proofs of the same verifying key with a single final exponentiation, checking
+/// a random linear combination of their equations
+/// `e(a_i, b_i) == e(alpha, beta) * e(vk_x_i, gamma) * e(c_i, delta)`.
+///
+/// The coefficients are derived from the proofs and public inputs, so that the proofs cannot be
+/// chosen to cancel each other out.
+pub fn batch_verify_proofs(
+    pvk: &PreparedVerifyingKey<Bls12_381>,
+    proofs: &[Proof<Bls12_381>],
+    inputs: &[Vec<Fr>],
+) -> Result<bool> {
+    ensure!(proofs.len() == inputs.len(), "missing public inputs");
+    let ic = &pvk.vk.gamma_abc_g1;
+    for inputs in inputs {
+        ensure!(inputs.len() + 1 == ic.len(), "malformed public inputs");
+    }
+    if proofs.is_empty() {
+        return Ok(true);
+    }
+
+    let randomness = batch_randomness(proofs, inputs)?;
+
+    // The coefficients of the points of `ic` and of `alpha * beta` in the combination.
+    let mut ic_scalars = vec![Fr::zero(); ic.len()];
+    let mut c = G1Projective::zero();
+    let mut pairs = Vec::with_capacity(proofs.len() + 2);
+    for ((proof, inputs), r) in proofs.iter().zip(inputs).zip(&randomness) {
+        ic_scalars[0] += r;
+        for (scalar, input) in ic_scalars[1..].iter_mut().zip(inputs) {
+            *scalar += *r * input;
+        }
+        c += proof.c.mul(r.into_repr());
+        pairs.push((
+            proof.a.mul(r.into_repr()).into_affine().into(),
+            proof.b.into(),
+        ));
+    }
+
+    let mut vk_x = G1Projective::zero();
+    for (point, scalar) in ic.iter().zip(&ic_scalars) {
+        vk_x += point.mul(scalar.into_repr());
+    }
+    pairs.push((vk_x.into_affine().into(), pvk.gamma_g2_neg_pc.clone()));
+    pairs.push((c.into_affine().into(), pvk.delta_g2_neg_pc.clone()));
+
+    let qap = Bls12_381::miller_loop(pairs.iter());
+    let test = Bls12_381::final_exponentiation(&qap)
+        .ok_or_else(|| anyhow!("unexpected identity in the pairing"))?;
+
+    Ok(test == pvk.alpha_g1_beta_g2.pow(ic_scalars[0].into_repr()))
+}
+
+/// The curve identifier of BLS12-381 for [`Groth16Api::groth16_verify`].
+#[cfg(feature = "host-groth16")]
+pub const CURVE_BLS12_381: u8 = 0;
+
+/// The Groth16 verifier exposed by the host, with the signature of the `Api` of the chains
+/// providing it.
+#[cfg(feature = "host-groth16")]
+pub trait Groth16Api {
+    /// `inputs` are the concatenated little-endian public inputs.
+    fn groth16_verify(
+        &self,
+        inputs: &[u8],
+        proof: &[u8],
+        vk: &[u8],
+        curve: u8,
+    ) -> std::result::Result<bool, String>;
+}
+
+/// Verifies the proofs with the host, which is much cheaper than the pairings in Wasm.
+///
+/// The hosts have no call verifying several proofs at once, so `batch_verify` falls back to the
+/// default of one `groth16_verify` call per proof, stopping at the first invalid one. That is
+/// still cheaper than batching the pairings in Wasm as [`ArkworksBackend`] does.
+#[cfg(feature = "host-groth16")]
+pub struct HostBackend<A> {
+    api: A,
+}
+
+#[cfg(feature = "host-groth16")]
+impl<A: Groth16Api> HostBackend<A> {
+    pub fn new(api: A) -> Self {
+        HostBackend { api }
+    }
+}
+
+#[cfg(feature = "host-groth16")]
+impl<A: Groth16Api> Groth16Backend for HostBackend<A> {
+    fn verify(&self, vk: &[u8], proof: &[u8], inputs: &[Fr]) -> Result<bool> {
+        use ark_ff::{BigInteger, PrimeField};
+
+        let inputs = inputs
+            .iter()
+            .flat_map(|input| input.into_repr().to_bytes_le())
+            .collect::<Vec<_>>();
+        self.api
+            .groth16_verify(&inputs, proof, vk, CURVE_BLS12_381)
+            .map_err(|err| anyhow!("host failed to verify groth16 proof: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_groth16::{create_random_proof, generate_random_parameters};
+    use ark_relations::{
+        lc,
+        r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+    };
+    use ark_serialize::CanonicalSerialize;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    const TEST_SEED: [u8; 16] = [
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ];
+
+    /// Proves the knowledge of a square root of the public `square`.
+    struct SquareCircuit {
+        root: Option<Fr>,
+    }
+
+    impl ConstraintSynthesizer<Fr> for SquareCircuit {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<Fr>,
+        ) -> std::result::Result<(), SynthesisError> {
+            let root =
+                cs.new_witness_variable(|| self.root.ok_or(SynthesisError::AssignmentMissing))?;
+            let square = cs.new_input_variable(|| {
+                self.root
+                    .map(|root| root.square())
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            cs.enforce_constraint(lc!() + root, lc!() + root, lc!() + square)
+        }
+    }
+
+    /// Returns a verifying key and a proof for the square `9`, in the encoding of the contract.
+    fn prove() -> (Vec<u8>, Vec<u8>) {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let params =
+            generate_random_parameters::<Bls12_381, _, _>(SquareCircuit { root: None }, rng)
+                .expect("generate_random_parameters failure");
+        let proof = create_random_proof(
+            SquareCircuit {
+                root: Some(Fr::from(3u64)),
+            },
+            &params,
+            rng,
+        )
+        .expect("create_random_proof failure");
+
+        let mut vk = Vec::new();
+        params.vk.serialize(&mut vk).expect("serialize failure");
+        let mut proof_raw = Vec::new();
+        proof.serialize(&mut proof_raw).expect("serialize failure");
+        (vk, proof_raw)
+    }
+
+    fn check_backend<B: Groth16Backend>(backend: &B) {
+        let (vk, proof) = prove();
+
+        assert!(backend
+            .verify(&vk, &proof, &[Fr::from(9u64)])
+            .expect("verify failure"));
+        assert!(!backend
+            .verify(&vk, &proof, &[Fr::from(4u64)])
+            .expect("verify failure"));
+        assert!(backend.verify(&vk, &proof[1..], &[Fr::from(9u64)]).is_err());
+
+        let proofs = [&proof[..], &proof[..]];
+        assert!(backend
+            .batch_verify(&vk, &proofs, &[vec![Fr::from(9u64)], vec![Fr::from(9u64)]])
+            .expect("batch_verify failure"));
+        assert!(!backend
+            .batch_verify(&vk, &proofs, &[vec![Fr::from(9u64)], vec![Fr::from(4u64)]])
+            .expect("batch_verify failure"));
+    }
+
+    #[test]
+    fn test_batch_verify_proofs() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let params =
+            generate_random_parameters::<Bls12_381, _, _>(SquareCircuit { root: None }, rng)
+                .expect("generate_random_parameters failure");
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let (proofs, inputs): (Vec<_>, Vec<_>) = (1..5u64)
+            .map(|root| {
+                let root = Fr::from(root);
+                let proof = create_random_proof(SquareCircuit { root: Some(root) }, &params, rng)
+                    .expect("create_random_proof failure");
+                (proof, vec![root.square()])
+            })
+            .unzip();
+
+        assert!(batch_verify_proofs(&pvk, &proofs, &inputs).expect("batch_verify_proofs failure"));
+        assert!(batch_verify_proofs(&pvk, &[], &[]).expect("batch_verify_proofs failure"));
+
+        // A single invalid proof fails the batch.
+        let mut wrong_inputs = inputs.clone();
+        wrong_inputs[2][0] += Fr::from(1u64);
+        assert!(!batch_verify_proofs(&pvk, &proofs, &wrong_inputs)
+            .expect("batch_verify_proofs failure"));
+
+        // Errors of two proofs cannot cancel out, as they are weighted differently.
+        let mut wrong_proofs = proofs.clone();
+        let shift = params.vk.alpha_g1.mul(Fr::from(5u64).into_repr());
+        wrong_proofs[0].c = (wrong_proofs[0].c.into_projective() + shift).into_affine();
+        wrong_proofs[1].c = (wrong_proofs[1].c.into_projective() - shift).into_affine();
+        assert!(!batch_verify_proofs(&pvk, &wrong_proofs, &inputs)
+            .expect("batch_verify_proofs failure"));
+
+        assert!(batch_verify_proofs(&pvk, &proofs, &inputs[1..]).is_err());
+        assert!(batch_verify_proofs(&pvk, &proofs[..1], &[vec![]]).is_err());
+    }
+
+    #[test]
+    fn test_arkworks_backend() {
+        check_backend(&ArkworksBackend);
+    }
+
+    /// An `Api` verifying with arkworks, as the host does natively.
+    #[cfg(feature = "host-groth16")]
+    struct MockApi;
+
+    #[cfg(feature = "host-groth16")]
+    impl Groth16Api for MockApi {
+        fn groth16_verify(
+            &self,
+            inputs: &[u8],
+            proof: &[u8],
+            vk: &[u8],
+            curve: u8,
+        ) -> std::result::Result<bool, String> {
+            use ark_ff::PrimeField;
+
+            if curve != CURVE_BLS12_381 || !inputs.len().is_multiple_of(32) {
+                return Err("invalid arguments".to_string());
+            }
+            let inputs = inputs
+                .chunks(32)
+                .map(Fr::from_le_bytes_mod_order)
+                .collect::<Vec<_>>();
+            ArkworksBackend
+                .verify(vk, proof, &inputs)
+                .map_err(|err| err.to_string())
+        }
+    }
+
+    #[cfg(feature = "host-groth16")]
+    #[test]
+    fn test_host_backend() {
+        check_backend(&HostBackend::new(MockApi));
+    }
+}
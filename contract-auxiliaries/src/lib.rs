@@ -1,17 +1,36 @@
 use crate::drg::stacked::VerifierSetupParams;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+pub mod beacon;
+pub mod cid;
 pub mod crypto;
 pub mod deserializer;
 pub mod domain;
 pub mod drg;
+pub mod groth16;
+pub mod merkle;
+pub mod pieces;
+pub mod update;
 pub mod utils;
 
 pub type PoRepID = [u8; 32];
 
+/// The proof of replication scheme verified with a set of parameters.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PoRepScheme {
+    /// The stacked DRG PoRep of `drg::stacked`.
+    #[default]
+    StackedDrg,
+    /// The single layer DRG PoRep of `drg::vanilla`, for the small sector sizes.
+    Drg,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, JsonSchema)]
 pub struct VerifierParameters {
     pub setup_params: VerifierSetupParams,
     pub vk: Vec<u8>,
     pub minimum_challenges: u64,
+    #[serde(default)]
+    pub scheme: PoRepScheme,
 }
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use blake2s_simd::Params as Blake2s;
+
+use crate::domain::blake2s::Blake2sDomain;
+
+use super::Hasher;
+
+/// Blake2s trees, as built with `hashers::blake2s::Blake2sHasher`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake2sHasher;
+
+impl Blake2sHasher {
+    pub fn hash2(a: &Blake2sDomain, b: &Blake2sDomain) -> Blake2sDomain {
+        Self::hash_parts(&[*a, *b])
+    }
+
+    fn hash_parts(parts: &[Blake2sDomain]) -> Blake2sDomain {
+        let mut state = Blake2s::new().hash_length(32).to_state();
+        for part in parts {
+            state.update(part.as_ref());
+        }
+        let mut res = Blake2sDomain::default();
+        res.0.copy_from_slice(state.finalize().as_bytes());
+        res.trim_to_fr32();
+        res
+    }
+}
+
+impl Hasher for Blake2sHasher {
+    type Domain = Blake2sDomain;
+
+    fn name() -> String {
+        "Blake2sHasher".into()
+    }
+
+    fn multi_node(parts: &[Blake2sDomain], _height: usize) -> Result<Blake2sDomain> {
+        Ok(Self::hash_parts(parts))
+    }
+}
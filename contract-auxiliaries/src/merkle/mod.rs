@@ -0,0 +1,25 @@
+//! Merkle path verification for the trees built by `proofs_core::merkle`.
+
+use anyhow::Result;
+
+use crate::domain::Domain;
+
+mod blake2s;
+mod poseidon;
+mod proof;
+mod sha256;
+
+pub use blake2s::*;
+pub use poseidon::*;
+pub use proof::*;
+pub use sha256::*;
+
+/// Hash function used to combine the children of a node.
+pub trait Hasher {
+    type Domain: Domain;
+
+    fn name() -> String;
+
+    /// Hashes the children of a node, `height` being the height of the node within its tree.
+    fn multi_node(parts: &[Self::Domain], height: usize) -> Result<Self::Domain>;
+}
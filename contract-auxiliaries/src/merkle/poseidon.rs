@@ -0,0 +1,38 @@
+use anyhow::{anyhow, Result};
+use ark_bls12_381::Fr;
+use ark_ff::{biginteger::BigInteger256, PrimeField};
+
+use crate::{crypto::poseidon::poseidon_hash, domain::poseidon::PoseidonDomain};
+
+use super::Hasher;
+
+/// Poseidon trees, as built with `hashers::poseidon::PoseidonHasher`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoseidonHasher;
+
+impl Hasher for PoseidonHasher {
+    type Domain = PoseidonDomain;
+
+    fn name() -> String {
+        "poseidon_hasher".into()
+    }
+
+    fn multi_node(parts: &[PoseidonDomain], _height: usize) -> Result<PoseidonDomain> {
+        let preimage = parts
+            .iter()
+            .map(fr_from_domain)
+            .collect::<Result<Vec<_>>>()?;
+
+        poseidon_hash(&preimage).map(Into::into)
+    }
+}
+
+/// Unlike `Fr::from`, rejects non canonical encodings, like `hashers` does.
+fn fr_from_domain(domain: &PoseidonDomain) -> Result<Fr> {
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(domain.0.chunks(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into()?);
+    }
+
+    Fr::from_repr(BigInteger256(limbs)).ok_or_else(|| anyhow!("node is not a field element"))
+}
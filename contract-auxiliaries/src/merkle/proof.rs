@@ -0,0 +1,177 @@
+use anyhow::{ensure, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::Domain;
+
+use super::Hasher;
+
+/// The siblings of a node and its position among them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PathElement<D> {
+    pub hashes: Vec<D>,
+    pub index: usize,
+}
+
+impl<D> PathElement<D> {
+    pub fn arity(&self) -> usize {
+        self.hashes.len() + 1
+    }
+}
+
+/// The path from a leaf to the root of a single tree, bottom up.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct InclusionPath<D> {
+    pub path: Vec<PathElement<D>>,
+}
+
+impl<D: Domain> InclusionPath<D> {
+    /// Calculate the root of this path, given the leaf as input.
+    pub fn root<H: Hasher<Domain = D>>(&self, leaf: D) -> Result<D> {
+        self.path
+            .iter()
+            .enumerate()
+            .try_fold(leaf, |node, (height, element)| {
+                ensure!(element.index < element.arity(), "invalid path index");
+                let mut nodes = element.hashes.clone();
+                nodes.insert(element.index, node);
+
+                H::multi_node(&nodes, height)
+            })
+    }
+
+    /// Number of leaves of the tree.
+    pub fn leaves(&self) -> usize {
+        self.path.iter().map(PathElement::arity).product()
+    }
+
+    pub fn path_index(&self) -> usize {
+        self.path
+            .iter()
+            .rev()
+            .fold(0, |acc, element| acc * element.arity() + element.index)
+    }
+
+    fn has_arity(&self, arity: usize) -> bool {
+        self.path.iter().all(|element| element.arity() == arity)
+    }
+}
+
+/// Merkle proof of a `proofs_core::merkle::MerkleProof`, which it can be deserialized from.
+///
+/// Trees with sub trees and top trees, like the `LCTree`s of sealed sectors, are proven by one
+/// path per kind of tree.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MerkleProof<D> {
+    data: ProofData<D>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+enum ProofData<D> {
+    Single {
+        root: D,
+        leaf: D,
+        path: InclusionPath<D>,
+    },
+    Sub {
+        base_proof: InclusionPath<D>,
+        sub_proof: InclusionPath<D>,
+        root: D,
+        leaf: D,
+    },
+    Top {
+        base_proof: InclusionPath<D>,
+        sub_proof: InclusionPath<D>,
+        top_proof: InclusionPath<D>,
+        root: D,
+        leaf: D,
+    },
+}
+
+impl<D: Domain> MerkleProof<D> {
+    pub fn leaf(&self) -> D {
+        match self.data {
+            ProofData::Single { leaf, .. }
+            | ProofData::Sub { leaf, .. }
+            | ProofData::Top { leaf, .. } => leaf,
+        }
+    }
+
+    pub fn root(&self) -> D {
+        match self.data {
+            ProofData::Single { root, .. }
+            | ProofData::Sub { root, .. }
+            | ProofData::Top { root, .. } => root,
+        }
+    }
+
+    /// The paths of the base tree, the sub tree and the top tree, as far as they exist.
+    fn paths(&self) -> Vec<&InclusionPath<D>> {
+        match &self.data {
+            ProofData::Single { path, .. } => vec![path],
+            ProofData::Sub {
+                base_proof,
+                sub_proof,
+                ..
+            } => vec![base_proof, sub_proof],
+            ProofData::Top {
+                base_proof,
+                sub_proof,
+                top_proof,
+                ..
+            } => vec![base_proof, sub_proof, top_proof],
+        }
+    }
+
+    /// Checks that the leaf hashes up to the root.
+    pub fn verify<H: Hasher<Domain = D>>(&self) -> bool {
+        let root = self
+            .paths()
+            .into_iter()
+            .try_fold(self.leaf(), |node, path| path.root::<H>(node));
+
+        matches!(root, Ok(root) if root == self.root())
+    }
+
+    /// Validates the proof and that it corresponds to the supplied node.
+    pub fn validate<H: Hasher<Domain = D>>(&self, node: usize) -> bool {
+        self.verify::<H>() && self.path_index() == node
+    }
+
+    /// Validates the proof and that its leaf is `data`.
+    pub fn validate_data<H: Hasher<Domain = D>>(&self, data: D) -> bool {
+        self.verify::<H>() && self.leaf() == data
+    }
+
+    /// Index of the leaf in the whole tree.
+    pub fn path_index(&self) -> usize {
+        self.paths()
+            .iter()
+            .rev()
+            .fold(0, |acc, path| acc * path.leaves() + path.path_index())
+    }
+
+    /// Returns true if the proof is for a tree of the given arities, where a sub or top arity of
+    /// `0` means the tree has no such layer, and the base trees hold `base_leaves` leaves.
+    pub fn has_shape(
+        &self,
+        base_leaves: usize,
+        base_arity: usize,
+        sub_tree_arity: usize,
+        top_tree_arity: usize,
+    ) -> bool {
+        let expected = [
+            Some((base_arity, base_leaves)),
+            Some((sub_tree_arity, sub_tree_arity)).filter(|(arity, _)| *arity > 0),
+            Some((top_tree_arity, top_tree_arity)).filter(|(arity, _)| *arity > 0),
+        ];
+        let expected = expected.iter().flatten().collect::<Vec<_>>();
+        let paths = self.paths();
+
+        paths.len() == expected.len()
+            && paths
+                .iter()
+                .zip(expected)
+                .all(|(path, (arity, leaves))| path.has_arity(*arity) && path.leaves() == *leaves)
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+use crate::domain::sha256::Sha256Domain;
+
+use super::Hasher;
+
+/// SHA-256 trees, as built with `hashers::sha256::Sha256Hasher`, like the data tree of a sector.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl Sha256Hasher {
+    pub fn hash2(a: &Sha256Domain, b: &Sha256Domain) -> Sha256Domain {
+        Self::hash_parts(&[*a, *b])
+    }
+
+    fn hash_parts(parts: &[Sha256Domain]) -> Sha256Domain {
+        let hashed = parts
+            .iter()
+            .fold(Sha256::new(), |hasher, part| hasher.chain_update(part))
+            .finalize();
+        let mut res = Sha256Domain::default();
+        res.0.copy_from_slice(&hashed[..]);
+        res.trim_to_fr32();
+        res
+    }
+}
+
+impl Hasher for Sha256Hasher {
+    type Domain = Sha256Domain;
+
+    fn name() -> String {
+        "sha256_hasher".into()
+    }
+
+    fn multi_node(parts: &[Sha256Domain], _height: usize) -> Result<Sha256Domain> {
+        Ok(Self::hash_parts(parts))
+    }
+}
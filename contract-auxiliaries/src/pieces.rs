@@ -0,0 +1,74 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{domain::sha256::Sha256Domain, merkle::Sha256Hasher, utils::NODE_SIZE};
+
+/// The `comm_d` of a sector of `sector_size` padded bytes holding only zeros, as a committed
+/// capacity sector does, `None` if the size is not a power of two number of nodes.
+pub fn zero_comm_d(sector_size: u64) -> Option<Sha256Domain> {
+    let leaves = sector_size / NODE_SIZE as u64;
+    if !leaves.is_power_of_two() || leaves * NODE_SIZE as u64 != sector_size {
+        return None;
+    }
+
+    let mut node = Sha256Domain::default();
+    for _ in 0..leaves.trailing_zeros() {
+        node = Sha256Hasher::hash2(&node, &node);
+    }
+    Some(node)
+}
+
+/// Proof that a piece is a subtree of the data tree committed to by `comm_d`,
+/// as generated by `seal::generate_piece_inclusion_proof`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PieceInclusionProof {
+    /// Index of the first leaf of the piece in the data tree.
+    pub position: u64,
+    /// Number of leaves spanned by the piece, a power of two.
+    pub number_of_leaves: u64,
+    /// Siblings on the path from the root of the piece up to `comm_d`, bottom up.
+    pub siblings: Vec<Sha256Domain>,
+}
+
+impl PieceInclusionProof {
+    /// Checks that `comm_p` is the root of a piece of `piece_size` padded bytes, aligned on its
+    /// size in the data tree of a `sector_size` bytes sector whose root is `comm_d`.
+    pub fn verify(
+        &self,
+        comm_d: &Sha256Domain,
+        comm_p: &Sha256Domain,
+        sector_size: u64,
+        piece_size: u64,
+    ) -> bool {
+        let sector_leaves = sector_size / NODE_SIZE as u64;
+        let piece_leaves = piece_size / NODE_SIZE as u64;
+        if !sector_leaves.is_power_of_two()
+            || !piece_leaves.is_power_of_two()
+            || piece_leaves * NODE_SIZE as u64 != piece_size
+            || piece_leaves > sector_leaves
+            || self.number_of_leaves != piece_leaves
+        {
+            return false;
+        }
+        let height = sector_leaves.trailing_zeros() - piece_leaves.trailing_zeros();
+        if self.siblings.len() != height as usize
+            || !self.position.is_multiple_of(piece_leaves)
+            || self.position >= sector_leaves
+        {
+            return false;
+        }
+
+        let mut index = self.position / piece_leaves;
+        let mut node = *comm_p;
+        for sibling in &self.siblings {
+            node = if index & 1 == 0 {
+                Sha256Hasher::hash2(&node, sibling)
+            } else {
+                Sha256Hasher::hash2(sibling, &node)
+            };
+            index >>= 1;
+        }
+
+        node == *comm_d
+    }
+}
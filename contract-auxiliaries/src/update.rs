@@ -0,0 +1,101 @@
+//! Verifier of empty sector updates, which encode new data into the replica of a sector sealed
+//! with no data, and swap its `comm_r`.
+//!
+//! Mirrors `proofs_porep::update` for replicas committed with Poseidon trees and data committed
+//! with SHA-256.
+
+use anyhow::{ensure, Result};
+use ark_bls12_381::Fr;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    crypto::poseidon::poseidon_hash,
+    domain::{poseidon::PoseidonDomain, sha256::Sha256Domain},
+    groth16::Groth16Backend,
+};
+
+/// The parameters of the update circuit of a sector size.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct UpdateSetupParams {
+    /// Number of nodes of the sector.
+    pub nodes: u64,
+    /// Number of challenged nodes.
+    pub challenges_count: u64,
+}
+
+/// The commitments an update proof is checked against.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct UpdatePublicInputs {
+    /// The `comm_r` of the replica before the update.
+    pub comm_r_old: PoseidonDomain,
+    /// The `comm_d` of the new data.
+    pub comm_d_new: Sha256Domain,
+    /// The `comm_r` of the updated replica.
+    pub comm_r_new: PoseidonDomain,
+}
+
+impl UpdatePublicInputs {
+    /// The challenged nodes, derived from `comm_r_new`.
+    pub fn challenges(&self, nodes: u64, challenges_count: u64) -> Vec<u64> {
+        (0..challenges_count)
+            .map(|j| {
+                let hash = Sha256::new()
+                    .chain_update(self.comm_r_new.0)
+                    .chain_update(j.to_le_bytes())
+                    .finalize();
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&hash[..8]);
+                u64::from_le_bytes(bytes) % nodes
+            })
+            .collect()
+    }
+
+    /// The seed `phi = H(comm_d_new || comm_r_old)` of the encoding factors.
+    pub fn phi(&self) -> Result<Fr> {
+        poseidon_hash(&[self.comm_d_new.into(), self.comm_r_old.into()])
+    }
+}
+
+/// The factor `rho = H(phi || node)` applied to the data of `node`.
+pub fn rho(phi: Fr, node: u64) -> Result<Fr> {
+    poseidon_hash(&[phi, Fr::from(node)])
+}
+
+/// Generates the public inputs of the update circuit.
+pub fn generate_public_inputs(
+    pub_in: &UpdatePublicInputs,
+    setup_params: &UpdateSetupParams,
+) -> Result<Vec<Fr>> {
+    ensure!(setup_params.nodes > 0, "sector has no nodes");
+
+    let mut inputs = vec![
+        pub_in.comm_r_old.into(),
+        pub_in.comm_d_new.into(),
+        pub_in.comm_r_new.into(),
+    ];
+
+    let phi = pub_in.phi()?;
+    for challenge in pub_in.challenges(setup_params.nodes, setup_params.challenges_count) {
+        // Inclusion Proofs: the challenged node in tree D, the old and the new tree R. Their
+        // packed paths are the index of the node.
+        inputs.extend([Fr::from(challenge); 3]);
+        inputs.push(rho(phi, challenge)?);
+    }
+
+    Ok(inputs)
+}
+
+/// Verifies the update proof of `pub_in` against the verifying key `vk` of the update circuit.
+pub fn verify_update<B: Groth16Backend>(
+    backend: &B,
+    vk: &[u8],
+    proof: &[u8],
+    pub_in: &UpdatePublicInputs,
+    setup_params: &UpdateSetupParams,
+) -> Result<bool> {
+    let inputs = generate_public_inputs(pub_in, setup_params)?;
+
+    backend.verify(vk, proof, &inputs)
+}
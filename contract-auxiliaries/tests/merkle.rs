@@ -0,0 +1,141 @@
+use blstrs::Scalar as Fr;
+use contract_auxiliaries::{
+    crypto::poseidon::{poseidon_hash, POSEIDON_ARITIES},
+    domain::poseidon::PoseidonDomain,
+    merkle::{self, MerkleProof},
+};
+use ff::{Field, PrimeField};
+use generic_array::typenum::{Unsigned, U0, U2, U4, U8};
+use hashers::{
+    blake2s::Blake2sHasher, poseidon::PoseidonHasher, sha256::Sha256Hasher, Hasher,
+    POSEIDON_CONSTANTS_11, POSEIDON_CONSTANTS_2, POSEIDON_CONSTANTS_4, POSEIDON_CONSTANTS_8,
+};
+use neptune::poseidon::Poseidon;
+use proofs_core::{
+    merkle::{generate_tree, get_base_tree_count, DiskStore, MerkleTreeTrait, MerkleTreeWrapper},
+    TEST_SEED,
+};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+type Tree<H, U, V, W> = MerkleTreeWrapper<H, DiskStore<<H as Hasher>::Domain>, U, V, W>;
+
+fn to_ark(fr: &Fr) -> ark_bls12_381::Fr {
+    PoseidonDomain(fr.to_repr()).into()
+}
+
+#[test]
+fn test_poseidon_hash() {
+    let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+
+    for arity in POSEIDON_ARITIES {
+        let preimage = (0..arity)
+            .map(|_| Fr::random(&mut *rng))
+            .collect::<Vec<_>>();
+        let expected = match arity {
+            2 => Poseidon::new_with_preimage(&preimage, &POSEIDON_CONSTANTS_2).hash(),
+            4 => Poseidon::new_with_preimage(&preimage, &POSEIDON_CONSTANTS_4).hash(),
+            8 => Poseidon::new_with_preimage(&preimage, &POSEIDON_CONSTANTS_8).hash(),
+            11 => Poseidon::new_with_preimage(&preimage, &POSEIDON_CONSTANTS_11).hash(),
+            _ => unreachable!(),
+        };
+
+        let preimage = preimage.iter().map(to_ark).collect::<Vec<_>>();
+        let hash = poseidon_hash(&preimage).expect("poseidon_hash failure");
+        assert_eq!(hash, to_ark(&expected), "arity {}", arity);
+    }
+
+    assert!(poseidon_hash(&[Default::default(); 3]).is_err());
+}
+
+fn test_path<T: 'static + MerkleTreeTrait, H: merkle::Hasher>() {
+    let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+    let base_tree_count = get_base_tree_count::<T>();
+    let leaves = 64 * base_tree_count;
+    let (_, tree) = generate_tree::<T, _>(rng, leaves, None);
+
+    for i in [0, 1, leaves / 2 + 3, leaves - 1] {
+        let proof = tree.gen_proof(i).expect("gen_proof failure");
+        let proof: MerkleProof<H::Domain> =
+            serde_json::from_slice(&serde_json::to_vec(&proof).expect("serialize failure"))
+                .expect("deserialize failure");
+
+        assert!(proof.validate::<H>(i));
+        assert_eq!(
+            AsRef::<[u8]>::as_ref(&proof.root()),
+            AsRef::<[u8]>::as_ref(&tree.root())
+        );
+        assert!(proof.has_shape(
+            leaves / base_tree_count,
+            T::Arity::to_usize(),
+            T::SubTreeArity::to_usize(),
+            T::TopTreeArity::to_usize(),
+        ));
+        assert!(!proof.has_shape(
+            2 * leaves,
+            T::Arity::to_usize(),
+            T::SubTreeArity::to_usize(),
+            T::TopTreeArity::to_usize(),
+        ));
+
+        let mut tampered = serde_json::to_value(&proof).expect("serialize failure");
+        tampered["data"]
+            .as_object_mut()
+            .and_then(|data| data.values_mut().next())
+            .expect("proof data")["leaf"] =
+            serde_json::to_value(H::Domain::default()).expect("serialize failure");
+        let tampered: MerkleProof<H::Domain> =
+            serde_json::from_value(tampered).expect("deserialize failure");
+        assert!(!tampered.verify::<H>());
+    }
+}
+
+#[test]
+fn test_poseidon_path_base_2() {
+    test_path::<Tree<PoseidonHasher, U2, U0, U0>, merkle::PoseidonHasher>();
+}
+
+#[test]
+fn test_poseidon_path_base_4() {
+    test_path::<Tree<PoseidonHasher, U4, U0, U0>, merkle::PoseidonHasher>();
+}
+
+#[test]
+fn test_poseidon_path_base_8() {
+    test_path::<Tree<PoseidonHasher, U8, U0, U0>, merkle::PoseidonHasher>();
+}
+
+#[test]
+fn test_poseidon_path_sub_8_2() {
+    test_path::<Tree<PoseidonHasher, U8, U2, U0>, merkle::PoseidonHasher>();
+}
+
+#[test]
+fn test_poseidon_path_sub_8_8() {
+    test_path::<Tree<PoseidonHasher, U8, U8, U0>, merkle::PoseidonHasher>();
+}
+
+#[test]
+fn test_poseidon_path_top_8_8_2() {
+    test_path::<Tree<PoseidonHasher, U8, U8, U2>, merkle::PoseidonHasher>();
+}
+
+#[test]
+fn test_sha256_path_base_2() {
+    test_path::<Tree<Sha256Hasher, U2, U0, U0>, merkle::Sha256Hasher>();
+}
+
+#[test]
+fn test_sha256_path_base_8() {
+    test_path::<Tree<Sha256Hasher, U8, U0, U0>, merkle::Sha256Hasher>();
+}
+
+#[test]
+fn test_blake2s_path_base_2() {
+    test_path::<Tree<Blake2sHasher, U2, U0, U0>, merkle::Blake2sHasher>();
+}
+
+#[test]
+fn test_blake2s_path_sub_8_2() {
+    test_path::<Tree<Blake2sHasher, U8, U2, U0>, merkle::Blake2sHasher>();
+}
@@ -1,37 +1,78 @@
-#[cfg(not(feature = "library"))]
-use ark_groth16::{prepare_verifying_key, verify_proof as groth16_verify, Proof, VerifyingKey};
-use ark_serialize::CanonicalDeserialize;
+use contract_auxiliaries::beacon::{Beacon, BeaconConfig, SealRandomness};
+use contract_auxiliaries::domain::{poseidon::PoseidonDomain, sha256::Sha256Domain};
 use contract_auxiliaries::drg::stacked::challenges::ChallengeRequirements;
-use contract_auxiliaries::drg::stacked::verifier_params::check_replica_id;
+use contract_auxiliaries::drg::stacked::verifier_params::{check_replica_id, Tau};
+use contract_auxiliaries::drg::vanilla::{
+    PublicInputs as DrgPublicInputs, SetupParams as DrgSetupParams, VerifierDrgPoRep,
+};
+use contract_auxiliaries::groth16::Groth16Backend;
+use contract_auxiliaries::pieces::{zero_comm_d, PieceInclusionProof};
+use contract_auxiliaries::update::{verify_update, UpdatePublicInputs};
 use contract_auxiliaries::utils::ApiVersion;
+use contract_auxiliaries::PoRepScheme;
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult,
+    to_binary, Addr, Api, Binary, Deps, DepsMut, Env, Event, MessageInfo, Order, Response, StdError,
+    StdResult, Storage,
 };
 use cw_storage_plus::Bound;
+use sector_registry::SectorProfile;
 use sha2::{Digest, Sha256};
 
 use crate::error::ContractError;
 use crate::msg::{
-    CurrentRoundResponse, ExecuteMsg, InstantiateMsg, PublicInputsPorep, QueryMsg,
-    SupportedSectorSize, VerifierStackedDrgPorep,MigrateMsg
+    CurrentRoundResponse, ExecuteMsg, FileStatusResponse, InstantiateMsg, PublicInputsPorep,
+    QueryMsg, ReplicationStatus, SectorProof, SectorSizeName, VerifierStackedDrgPorep,MigrateMsg
 };
 use crate::state::{
-    config_read, config_write, params_read, params_write, Config, InfoRound, VerifierParameters,
-    CURRENT_ROUND, ROUND_INFO, SUBMIT_SUCCESS, USER_REWARD,
+    config_read, config_write, params_read, params_write, Config, FileManifest, FileRecord,
+    InfoRound, Replica,
+    StorageOrder, UpdateParameters, VerifierParameters, BEACON, CURRENT_ROUND, DEFAULT_LIVENESS_WINDOW, FILE_MANIFESTS, LIVENESS_WINDOW,
+    REPLICAS, ROUND_INFO, SECTOR_COMM_D, SECTOR_COMM_R, SECTOR_DRG_COMM_D, SECTOR_FILES,
+    SECTOR_PROVEN_ROUND, SECTOR_SEALED_COMM_R, SECTOR_SIZES, SECTOR_TICKET_ROUND, STORAGE_ORDERS, SUBMIT_SUCCESS,
+    UPDATE_PARAMS, USER_REWARD,
 };
 
 // version info for migration info
 const CONTRACT_NAME: &str = "stacked-drg";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// The registered sector profile named `sector_size`.
+fn sector_profile(sector_size: &str) -> StdResult<&'static SectorProfile> {
+    sector_registry::global()
+        .by_name(sector_size)
+        .ok_or_else(|| StdError::generic_err(format!("unsupported sector size: {sector_size}")))
+}
+
+/// The sector sizes in the order of the variants of the former `SupportedSectorSize` enum, whose
+/// discriminant keyed the stored parameters.
+const LEGACY_SECTOR_SIZES: [u64; 10] = [
+    2 << 10,
+    4 << 10,
+    16 << 10,
+    32 << 10,
+    8 << 20,
+    16 << 20,
+    512 << 20,
+    1 << 30,
+    32 << 30,
+    64 << 30,
+];
+
+/// The key of the parameters of a proof system. The sizes of the former `SupportedSectorSize`
+/// enum keep their discriminant, so the parameters stored before the sector registry are still
+/// found, other sizes are keyed by their size in bytes.
 pub fn porep_key(
     porep_id: &[u8],
-    sector_size: SupportedSectorSize,
+    sector_size: &str,
     api_version: &ApiVersion,
 ) -> StdResult<Vec<u8>> {
+    let sector_size = sector_profile(sector_size)?.sector_size;
     let mut messages = porep_id.to_vec();
-    messages.push(sector_size as u8);
+    match LEGACY_SECTOR_SIZES.iter().position(|size| *size == sector_size) {
+        Some(discriminant) => messages.push(discriminant as u8),
+        None => messages.extend_from_slice(&sector_size.to_be_bytes()),
+    }
     messages.push(match api_version {
         ApiVersion::V1_0_0 => 0u8,
         ApiVersion::V1_1_0 => 1u8,
@@ -85,6 +126,7 @@ pub fn execute(
             prover_id,
             sector_id,
             ticket,
+            randomness,
         } => submit_proof(
             deps,
             env,
@@ -96,8 +138,58 @@ pub fn execute(
             prover_id,
             sector_id,
             ticket,
+            randomness,
+            api_version,
+        ),
+        ExecuteMsg::SubmitProofs {
+            porep_id,
+            sector_size,
+            api_version,
+            prover_id,
+            proofs,
+        } => submit_proofs(
+            deps,
+            env,
+            info,
+            porep_id,
+            sector_size,
             api_version,
+            prover_id,
+            proofs,
+        ),
+        ExecuteMsg::SetBeacon { config } => set_beacon(deps, info, config),
+        ExecuteMsg::SetLivenessWindow { rounds } => set_liveness_window(deps, info, rounds),
+        ExecuteMsg::SetUpdateParams {
+            sector_size,
+            params,
+        } => set_update_params(deps, info, sector_size, params),
+        ExecuteMsg::UpdateSector {
+            sector_size,
+            sector_id,
+            proof_raw,
+            comm_r_new,
+            comm_d_new,
+        } => update_sector(
+            deps,
+            info,
+            sector_size,
+            sector_id,
+            proof_raw,
+            comm_r_new.domain()?,
+            comm_d_new.domain()?,
         ),
+        ExecuteMsg::RegisterFile {
+            file_id,
+            manifest,
+            proofs,
+        } => register_file(deps, info, file_id, manifest, proofs),
+        ExecuteMsg::SetStorageOrder {
+            comm_d,
+            replication_factor,
+        } => set_storage_order(deps, info, comm_d.domain()?, replication_factor),
+        ExecuteMsg::CancelStorageOrder { comm_d } => {
+            cancel_storage_order(deps, info, comm_d.domain()?)
+        }
     }
 }
 
@@ -105,10 +197,12 @@ pub fn setup_round_new(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    sector_size: SupportedSectorSize,
+    sector_size: SectorSizeName,
     params: VerifierParameters,
     duration: u64,
 ) -> Result<Response, ContractError> {
+    check_profile(&sector_size, &params)?;
+
     let current_round = CURRENT_ROUND.load(deps.storage)?;
     let now = env.block.time.seconds();
 
@@ -130,7 +224,7 @@ pub fn setup_round_new(
 
     let key = porep_key(
         &params.setup_params.porep_id,
-        sector_size,
+        &sector_size,
         &params.setup_params.api_version,
     )?;
     set_params(deps, info, &key, params)?;
@@ -138,20 +232,149 @@ pub fn setup_round_new(
     Ok(Response::default())
 }
 
+/// Checks that the sector profile, if given, describes the parameters.
+fn check_profile(
+    sector_size: &str,
+    params: &VerifierParameters,
+) -> Result<(), ContractError> {
+    let registered = sector_profile(sector_size)?;
+    let profile = match &params.profile {
+        Some(profile) => profile,
+        None => return Ok(()),
+    };
+    let setup_params = &params.setup_params;
+
+    if profile.validate().is_err()
+        || profile.name != registered.name
+        || profile.sector_size != registered.sector_size
+        || profile.nodes() != setup_params.nodes
+        || profile.layers != setup_params.layer_challenges.layers()
+        || profile.minimum_challenges as u64 != params.minimum_challenges
+    {
+        return Err(ContractError::InvalidProfile {});
+    }
+
+    Ok(())
+}
+
 pub fn submit_proof(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     proof_raw: Binary,
     public_inputs: PublicInputsPorep,
     porep_id: Binary,
-    sector_size: SupportedSectorSize,
+    sector_size: SectorSizeName,
     prover_id: Binary,
     sector_id: u64,
     ticket: Binary,
+    randomness: Option<SealRandomness>,
+    api_version: ApiVersion,
+) -> Result<Response, ContractError> {
+    let user = info.sender;
+    begin_submission(deps.branch(), &env, &user, &prover_id)?;
+    check_randomness(
+        deps.storage,
+        &env,
+        &prover_id,
+        sector_id,
+        &ticket,
+        &public_inputs,
+        randomness.as_ref(),
+    )?;
+
+    let key = porep_key(&porep_id, &sector_size, &api_version)?;
+    let result = verify_proof(
+        deps.as_ref(),
+        &key,
+        &proof_raw,
+        &public_inputs,
+        &porep_id,
+        &prover_id,
+        sector_id,
+        &ticket,
+    )?;
+    if result {
+        record_ticket_round(deps.storage, &prover_id, sector_id, randomness.as_ref())?;
+        let sector_size = sector_profile(&sector_size)?.sector_size;
+        let scheme = params_read(deps.storage, &key)?.scheme;
+        let events = reward_sector(deps, &user, sector_id, sector_size, scheme, &public_inputs)?;
+        Ok(Response::new().add_events(events))
+    } else {
+        Err(ContractError::VerifyProof {})
+    }
+}
+
+/// Submits the proofs of several sectors, verified together for about the cost of one proof.
+pub fn submit_proofs(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    porep_id: Binary,
+    sector_size: SectorSizeName,
     api_version: ApiVersion,
+    prover_id: Binary,
+    proofs: Vec<SectorProof>,
 ) -> Result<Response, ContractError> {
+    if proofs.is_empty() {
+        return Err(ContractError::NoProofs {});
+    }
+    let mut sector_ids = proofs
+        .iter()
+        .map(|proof| proof.sector_id)
+        .collect::<Vec<_>>();
+    sector_ids.sort_unstable();
+    if let Some(ids) = sector_ids.windows(2).find(|ids| ids[0] == ids[1]) {
+        return Err(ContractError::DuplicateSector { sector_id: ids[0] });
+    }
+
     let user = info.sender;
+    begin_submission(deps.branch(), &env, &user, &prover_id)?;
+    for proof in &proofs {
+        check_randomness(
+            deps.storage,
+            &env,
+            &prover_id,
+            proof.sector_id,
+            &proof.ticket,
+            &proof.public_inputs,
+            proof.randomness.as_ref(),
+        )?;
+    }
+
+    let key = porep_key(&porep_id, &sector_size, &api_version)?;
+    if !verify_proofs(deps.as_ref(), &key, &porep_id, &prover_id, &proofs)? {
+        return Err(ContractError::VerifyProof {});
+    }
+    let sector_size = sector_profile(&sector_size)?.sector_size;
+    let scheme = params_read(deps.storage, &key)?.scheme;
+    let mut events = vec![];
+    for proof in &proofs {
+        record_ticket_round(
+            deps.storage,
+            &prover_id,
+            proof.sector_id,
+            proof.randomness.as_ref(),
+        )?;
+        events.extend(reward_sector(
+            deps.branch(),
+            &user,
+            proof.sector_id,
+            sector_size,
+            scheme,
+            &proof.public_inputs,
+        )?);
+    }
+    Ok(Response::new().add_events(events))
+}
+
+/// Checks that `user` may submit proofs in the current round, and marks the round as submitted.
+fn begin_submission(
+    deps: DepsMut,
+    env: &Env,
+    user: &Addr,
+    prover_id: &Binary,
+) -> Result<(), ContractError> {
     let round_current = query_current_round(deps.as_ref())?.current_round - 1;
 
     if ROUND_INFO.load(deps.storage, round_current.to_string())?.time_expire < env.block.time.seconds() {
@@ -166,7 +389,7 @@ pub fn submit_proof(
             .to_vec(),
     );
 
-    if hash_user != prover_id {
+    if hash_user != *prover_id {
         return Err(ContractError::VerifyHash {});
     }
 
@@ -184,32 +407,518 @@ pub fn submit_proof(
         key,
         |_old_state: Option<bool>| -> Result<bool, ContractError> { Ok(true) },
     )?;
-    let key = porep_key(&porep_id, sector_size, &api_version)?;
-    let result = verify_proof(
-        deps.as_ref(),
-        &key,
+    Ok(())
+}
+
+/// Checks that the ticket and the seed of a sector are drawn from the beacon, when one is set. The
+/// ticket age is only checked for the first proof of the sector with that ticket.
+fn check_randomness(
+    storage: &dyn Storage,
+    env: &Env,
+    prover_id: &[u8],
+    sector_id: u64,
+    ticket: &[u8],
+    public_inputs: &PublicInputsPorep,
+    randomness: Option<&SealRandomness>,
+) -> Result<(), ContractError> {
+    let config = match BEACON.may_load(storage)? {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+    let invalid = |reason: String| ContractError::InvalidRandomness { reason };
+
+    let randomness = randomness.ok_or_else(|| invalid("missing beacon entries".to_string()))?;
+    let beacon = Beacon::new(&config).map_err(|err| invalid(err.to_string()))?;
+    let sealed_ticket_round =
+        SECTOR_TICKET_ROUND.may_load(storage, (prover_id.to_vec(), sector_id.to_string()))?;
+    let (expected_ticket, expected_seed) = beacon
+        .seal_randomness(
+            randomness,
+            prover_id,
+            sector_id,
+            env.block.time.seconds(),
+            sealed_ticket_round,
+        )
+        .map_err(|err| invalid(err.to_string()))?;
+    if ticket != expected_ticket {
+        return Err(invalid("ticket is not drawn from the beacon".to_string()));
+    }
+    if public_inputs.seed != expected_seed {
+        return Err(invalid("seed is not drawn from the beacon".to_string()));
+    }
+    Ok(())
+}
+
+/// Records the round of the beacon ticket of a verified sector, so that it can be proven with that
+/// ticket long after it was sealed.
+fn record_ticket_round(
+    storage: &mut dyn Storage,
+    prover_id: &[u8],
+    sector_id: u64,
+    randomness: Option<&SealRandomness>,
+) -> StdResult<()> {
+    if let Some(randomness) = randomness {
+        SECTOR_TICKET_ROUND.save(
+            storage,
+            (prover_id.to_vec(), sector_id.to_string()),
+            &randomness.ticket.round,
+        )?;
+    }
+    Ok(())
+}
+
+/// Rewards `user` for a verified sector, and records its `comm_d`, `comm_r`, size, its replica and
+/// the round it was proven in. Returns the replication events of the sector.
+///
+/// The `comm_d` of a DRG PoRep sector is the root of a Poseidon tree, which pieces and storage
+/// orders can not be checked against, so such sectors only have their `comm_d` recorded, apart.
+///
+/// An updated sector is proven against its new `comm_r`. The PoRep of its original seal only proves
+/// a committed capacity replica, which anyone can rebuild from its replica id, so it is rejected.
+/// Any other seal of the sector replaces the update.
+pub(crate) fn reward_sector(
+    deps: DepsMut,
+    user: &Addr,
+    sector_id: u64,
+    sector_size: u64,
+    scheme: PoRepScheme,
+    public_inputs: &PublicInputsPorep,
+) -> Result<Vec<Event>, ContractError> {
+    let key = (user.to_string(), sector_id.to_string());
+    if let (Some(tau), Some(sealed_comm_r)) = (
+        &public_inputs.tau,
+        SECTOR_SEALED_COMM_R.may_load(deps.storage, key.clone())?,
+    ) {
+        if scheme == PoRepScheme::StackedDrg
+            && tau.comm_r == sealed_comm_r
+            && Some(tau.comm_d) == zero_comm_d(sector_size)
+        {
+            return Err(ContractError::UpdatedSector { sector_id });
+        }
+    }
+    USER_REWARD.update(
+        deps.storage,
+        user.to_string(),
+        |old_state: Option<i32>| -> Result<i32, ContractError> {
+            match old_state {
+                Some(x) => Ok(x + 1),
+                None => Ok(1),
+            }
+        },
+    )?;
+    let round_current = query_current_round(deps.as_ref())?.current_round - 1;
+    SECTOR_PROVEN_ROUND.save(deps.storage, key.clone(), &round_current)?;
+    let mut events = vec![];
+    if let Some(tau) = &public_inputs.tau {
+        SECTOR_SEALED_COMM_R.remove(deps.storage, key.clone());
+        let comm_d = (scheme == PoRepScheme::StackedDrg).then_some(tau.comm_d);
+        let comm_d_old = SECTOR_COMM_D.may_load(deps.storage, key.clone())?;
+        if let Some(comm_d_old) = comm_d_old.filter(|comm_d_old| Some(*comm_d_old) != comm_d) {
+            mark_files_stale(deps.storage, user.as_str(), sector_id)?;
+            events.extend(remove_replica(
+                deps.storage,
+                user,
+                sector_id,
+                &comm_d_old,
+                round_current,
+            )?);
+        }
+        match comm_d {
+            Some(comm_d) => {
+                events.extend(add_replica(
+                    deps.storage,
+                    user,
+                    sector_id,
+                    &comm_d,
+                    public_inputs.replica_id,
+                    round_current,
+                )?);
+                SECTOR_COMM_D.save(deps.storage, key.clone(), &comm_d)?;
+                SECTOR_COMM_R.save(deps.storage, key.clone(), &tau.comm_r)?;
+                SECTOR_SIZES.save(deps.storage, key.clone(), &sector_size)?;
+                SECTOR_DRG_COMM_D.remove(deps.storage, key);
+            }
+            None => {
+                SECTOR_COMM_D.remove(deps.storage, key.clone());
+                SECTOR_COMM_R.remove(deps.storage, key.clone());
+                SECTOR_SIZES.remove(deps.storage, key.clone());
+                SECTOR_DRG_COMM_D.save(deps.storage, key, &drg_comm_d(tau))?;
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Verifies the update of a sector of the sender, keyed by its recorded `comm_r`, and records
+/// its new `comm_r` and `comm_d`.
+pub fn update_sector(
+    deps: DepsMut,
+    info: MessageInfo,
+    sector_size: SectorSizeName,
+    sector_id: u64,
+    proof_raw: Binary,
+    comm_r_new: PoseidonDomain,
+    comm_d_new: Sha256Domain,
+) -> Result<Response, ContractError> {
+    let key = (info.sender.to_string(), sector_id.to_string());
+    let comm_r_old = SECTOR_COMM_R
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::UnknownSector { sector_id })?;
+    // Only the data of an empty sector can be replaced, its replica encodes zeros.
+    let profile = sector_profile(&sector_size)?;
+    if SECTOR_SIZES.may_load(deps.storage, key.clone())? != Some(profile.sector_size) {
+        return Err(ContractError::InvalidProfile {});
+    }
+    let comm_d_old = SECTOR_COMM_D
+        .may_load(deps.storage, key.clone())?
+        .filter(|comm_d| Some(*comm_d) == zero_comm_d(profile.sector_size))
+        .ok_or(ContractError::NotCommittedCapacity { sector_id })?;
+    let params = UPDATE_PARAMS.load(deps.storage, sector_size)?;
+
+    let public_inputs = UpdatePublicInputs {
+        comm_r_old,
+        comm_d_new,
+        comm_r_new,
+    };
+    let verified = verify_update(
+        &groth16_backend(deps.api),
+        &params.vk,
         &proof_raw,
         &public_inputs,
-        &porep_id,
-        &prover_id,
+        &params.setup_params,
+    )
+    .map_err(|err| StdError::generic_err(err.to_string()))?;
+    if !verified {
+        return Err(ContractError::VerifyProof {});
+    }
+
+    let round_current = query_current_round(deps.as_ref())?.current_round - 1;
+    let events = record_update(
+        deps.storage,
+        &info.sender,
         sector_id,
-        &ticket,
+        &public_inputs,
+        &comm_d_old,
+        round_current,
     )?;
-    if result {
-        USER_REWARD.update(
+    Ok(Response::new().add_events(events))
+}
+
+/// Records a verified update of the sector `sector_id` of `prover`, holding `comm_d_old`, and the
+/// `comm_r` of its seal, which no longer proves it. Returns the replication events.
+pub(crate) fn record_update(
+    storage: &mut dyn Storage,
+    prover: &Addr,
+    sector_id: u64,
+    public_inputs: &UpdatePublicInputs,
+    comm_d_old: &Sha256Domain,
+    round_current: i32,
+) -> StdResult<Vec<Event>> {
+    let key = (prover.to_string(), sector_id.to_string());
+    let UpdatePublicInputs {
+        comm_r_old,
+        comm_d_new,
+        comm_r_new,
+    } = *public_inputs;
+
+    // The replica keeps its id, only the data it holds changes.
+    let mut events = vec![];
+    let replica = REPLICAS.may_load(storage, replica_key(comm_d_old, prover, sector_id))?;
+    if let Some(replica) = replica {
+        events.extend(remove_replica(
+            storage,
+            prover,
+            sector_id,
+            comm_d_old,
+            round_current,
+        )?);
+        events.extend(add_replica(
+            storage,
+            prover,
+            sector_id,
+            &comm_d_new,
+            replica.replica_id,
+            round_current,
+        )?);
+    }
+    if comm_d_new != *comm_d_old {
+        mark_files_stale(storage, prover.as_str(), sector_id)?;
+    }
+    if !SECTOR_SEALED_COMM_R.has(storage, key.clone()) {
+        SECTOR_SEALED_COMM_R.save(storage, key.clone(), &comm_r_old)?;
+    }
+    SECTOR_COMM_D.save(storage, key.clone(), &comm_d_new)?;
+    SECTOR_COMM_R.save(storage, key, &comm_r_new)?;
+    Ok(events)
+}
+
+/// Registers the manifest of an erasure coded file, owned by the sender. Its shards must be held
+/// by different sectors, as two shards of the same sector would be lost together, and each must
+/// be proven to be a piece of the `comm_d` recorded for its sector.
+pub fn register_file(
+    deps: DepsMut,
+    info: MessageInfo,
+    file_id: String,
+    manifest: FileManifest,
+    proofs: Vec<PieceInclusionProof>,
+) -> Result<Response, ContractError> {
+    let invalid = |reason: String| ContractError::InvalidManifest { reason };
+
+    if FILE_MANIFESTS.has(deps.storage, file_id.clone()) {
+        return Err(ContractError::FileExists { file_id });
+    }
+    if manifest.data_shards == 0 {
+        return Err(invalid("no data shards".to_string()));
+    }
+    if manifest.shards.len() as u64
+        != u64::from(manifest.data_shards) + u64::from(manifest.parity_shards)
+    {
+        return Err(invalid("number of shards does not match the layout".to_string()));
+    }
+    if proofs.len() != manifest.shards.len() {
+        return Err(invalid("number of proofs does not match the shards".to_string()));
+    }
+    let mut sectors = manifest
+        .shards
+        .iter()
+        .map(|shard| (&shard.prover, shard.sector_id))
+        .collect::<Vec<_>>();
+    sectors.sort_unstable();
+    sectors.dedup();
+    if sectors.len() != manifest.shards.len() {
+        return Err(invalid("several shards are held by the same sector".to_string()));
+    }
+
+    for (i, (shard, proof)) in manifest.shards.iter().zip(&proofs).enumerate() {
+        let comm_p = <[u8; 32]>::try_from(shard.comm_p.as_slice())
+            .map(Sha256Domain)
+            .map_err(|_| invalid(format!("comm_p of shard {i} must be 32 bytes")))?;
+        let key = (shard.prover.clone(), shard.sector_id.to_string());
+        let (comm_d, sector_size) = match (
+            SECTOR_COMM_D.may_load(deps.storage, key.clone())?,
+            SECTOR_SIZES.may_load(deps.storage, key)?,
+        ) {
+            (Some(comm_d), Some(sector_size)) => (comm_d, sector_size),
+            _ => return Err(invalid(format!("sector of shard {i} has no recorded comm_d"))),
+        };
+        if !proof.verify(&comm_d, &comm_p, sector_size, shard.piece_size) {
+            return Err(invalid(format!("shard {i} is not a piece of its sector")));
+        }
+        SECTOR_FILES.save(
             deps.storage,
-            user.to_string(),
-            |old_state: Option<i32>| -> Result<i32, ContractError> {
-                match old_state {
-                    Some(x) => Ok(x + 1),
-                    None => Ok(1),
-                }
-            },
+            (
+                shard.prover.clone(),
+                shard.sector_id.to_string(),
+                file_id.clone(),
+            ),
+            &(i as u32),
         )?;
-        Ok(Response::default())
+    }
+
+    let record = FileRecord {
+        owner: info.sender,
+        manifest,
+        stale_shards: vec![],
+    };
+    FILE_MANIFESTS.save(deps.storage, file_id, &record)?;
+    Ok(Response::default())
+}
+
+/// Marks the shards held by a sector whose `comm_d` changed as stale, the sector no longer holding
+/// the data they were proven in.
+fn mark_files_stale(storage: &mut dyn Storage, prover: &str, sector_id: u64) -> StdResult<()> {
+    let shards = SECTOR_FILES
+        .prefix((prover.to_string(), sector_id.to_string()))
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (file_id, shard) in shards {
+        FILE_MANIFESTS.update(storage, file_id.clone(), |record| -> StdResult<_> {
+            let mut record =
+                record.ok_or_else(|| StdError::not_found(format!("file {file_id}")))?;
+            if !record.stale_shards.contains(&shard) {
+                record.stale_shards.push(shard);
+                record.stale_shards.sort_unstable();
+            }
+            Ok(record)
+        })?;
+        SECTOR_FILES.remove(
+            storage,
+            (prover.to_string(), sector_id.to_string(), file_id),
+        );
+    }
+    Ok(())
+}
+
+/// Orders `replication_factor` replicas of `comm_d`. An existing order may only be changed by its
+/// owner.
+pub fn set_storage_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    comm_d: Sha256Domain,
+    replication_factor: u32,
+) -> Result<Response, ContractError> {
+    if replication_factor == 0 {
+        return Err(ContractError::InvalidStorageOrder {
+            reason: "replication factor must be positive".to_string(),
+        });
+    }
+    if let Some(order) = STORAGE_ORDERS.may_load(deps.storage, comm_d.0.to_vec())? {
+        if order.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+
+    let order = StorageOrder {
+        owner: info.sender,
+        comm_d,
+        replication_factor,
+    };
+    STORAGE_ORDERS.save(deps.storage, comm_d.0.to_vec(), &order)?;
+
+    let round_current = query_current_round(deps.as_ref())?.current_round - 1;
+    let status = replication_status(deps.storage, order, round_current)?;
+    let mut response = Response::new();
+    if status.under_replicated {
+        response = response.add_event(under_replicated_event(&status));
+    }
+    Ok(response)
+}
+
+/// Cancels a storage order of the sender. The replicas of its `comm_d` are still tracked.
+pub fn cancel_storage_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    comm_d: Sha256Domain,
+) -> Result<Response, ContractError> {
+    let order = STORAGE_ORDERS
+        .may_load(deps.storage, comm_d.0.to_vec())?
+        .ok_or(ContractError::KeyNotFound {})?;
+    if order.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STORAGE_ORDERS.remove(deps.storage, comm_d.0.to_vec());
+    Ok(Response::default())
+}
+
+fn replica_key(comm_d: &Sha256Domain, prover: &Addr, sector_id: u64) -> (Vec<u8>, String, String) {
+    (comm_d.0.to_vec(), prover.to_string(), sector_id.to_string())
+}
+
+/// Records that the sector `sector_id` of `prover` holds `comm_d`. If an order asks for it, an
+/// event tells the new number of replicas.
+fn add_replica(
+    storage: &mut dyn Storage,
+    prover: &Addr,
+    sector_id: u64,
+    comm_d: &Sha256Domain,
+    replica_id: PoseidonDomain,
+    round_current: i32,
+) -> StdResult<Vec<Event>> {
+    let replica = Replica {
+        prover: prover.to_string(),
+        sector_id,
+        replica_id,
+    };
+    REPLICAS.save(storage, replica_key(comm_d, prover, sector_id), &replica)?;
+
+    let Some(order) = STORAGE_ORDERS.may_load(storage, comm_d.0.to_vec())? else {
+        return Ok(vec![]);
+    };
+    let status = replication_status(storage, order, round_current)?;
+    Ok(vec![Event::new("replica_added")
+        .add_attribute("comm_d", Binary::from(comm_d.0.as_slice()).to_base64())
+        .add_attribute("prover", prover.to_string())
+        .add_attribute("sector_id", sector_id.to_string())
+        .add_attribute("replicas", status.replicas.len().to_string())
+        .add_attribute(
+            "replication_factor",
+            status.order.replication_factor.to_string(),
+        )])
+}
+
+/// Records that the sector `sector_id` of `prover` no longer holds `comm_d`. If an order asks for
+/// more replicas than are left, an event asks for new seals.
+fn remove_replica(
+    storage: &mut dyn Storage,
+    prover: &Addr,
+    sector_id: u64,
+    comm_d: &Sha256Domain,
+    round_current: i32,
+) -> StdResult<Vec<Event>> {
+    REPLICAS.remove(storage, replica_key(comm_d, prover, sector_id));
+
+    let Some(order) = STORAGE_ORDERS.may_load(storage, comm_d.0.to_vec())? else {
+        return Ok(vec![]);
+    };
+    let status = replication_status(storage, order, round_current)?;
+    if status.under_replicated {
+        Ok(vec![under_replicated_event(&status)])
     } else {
-        Err(ContractError::VerifyProof {})
+        Ok(vec![])
+    }
+}
+
+fn under_replicated_event(status: &ReplicationStatus) -> Event {
+    Event::new("under_replicated")
+        .add_attribute(
+            "comm_d",
+            Binary::from(status.order.comm_d.0.as_slice()).to_base64(),
+        )
+        .add_attribute("replicas", status.replicas.len().to_string())
+        .add_attribute(
+            "replication_factor",
+            status.order.replication_factor.to_string(),
+        )
+}
+
+fn liveness_window(storage: &dyn Storage) -> StdResult<u32> {
+    Ok(LIVENESS_WINDOW
+        .may_load(storage)?
+        .unwrap_or(DEFAULT_LIVENESS_WINDOW))
+}
+
+/// Whether the sector `sector_id` of `prover` was proven within the liveness window set by the
+/// owner, the current round being allowed to still be in progress.
+fn is_sector_live(
+    storage: &dyn Storage,
+    prover: &str,
+    sector_id: u64,
+    round_current: i32,
+) -> StdResult<bool> {
+    let key = (prover.to_string(), sector_id.to_string());
+    let Some(round) = SECTOR_PROVEN_ROUND.may_load(storage, key)? else {
+        return Ok(false);
+    };
+    let window = liveness_window(storage)?;
+    Ok(i64::from(round) + i64::from(window) >= i64::from(round_current))
+}
+
+/// The live replicas of the `comm_d` of `order`. A prover sealing the same data in several
+/// sectors, or sectors sharing a replica id, count as a single replica.
+fn replication_status(
+    storage: &dyn Storage,
+    order: StorageOrder,
+    round_current: i32,
+) -> StdResult<ReplicationStatus> {
+    let mut replicas: Vec<Replica> = vec![];
+    let prefix = REPLICAS.sub_prefix(order.comm_d.0.to_vec());
+    for item in prefix.range(storage, None, None, Order::Ascending) {
+        let (_, replica) = item?;
+        let counted = replicas
+            .iter()
+            .any(|r| r.prover == replica.prover || r.replica_id == replica.replica_id);
+        if !counted && is_sector_live(storage, &replica.prover, replica.sector_id, round_current)? {
+            replicas.push(replica);
+        }
     }
+    let under_replicated = (replicas.len() as u64) < u64::from(order.replication_factor);
+
+    Ok(ReplicationStatus {
+        order,
+        replicas,
+        under_replicated,
+    })
 }
 
 pub fn set_params(
@@ -226,6 +935,20 @@ pub fn set_params(
     }
 }
 
+pub fn set_update_params(
+    deps: DepsMut,
+    info: MessageInfo,
+    sector_size: SectorSizeName,
+    params: UpdateParameters,
+) -> Result<Response, ContractError> {
+    if info.sender != config_read(deps.storage)?.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    sector_profile(&sector_size)?;
+    UPDATE_PARAMS.save(deps.storage, sector_size, &params)?;
+    Ok(Response::default())
+}
+
 pub fn set_owner(
     deps: DepsMut,
     info: MessageInfo,
@@ -241,6 +964,39 @@ pub fn set_owner(
     }
 }
 
+pub fn set_beacon(
+    deps: DepsMut,
+    info: MessageInfo,
+    config: Option<BeaconConfig>,
+) -> Result<Response, ContractError> {
+    if info.sender != config_read(deps.storage)?.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    match config {
+        Some(config) => {
+            Beacon::new(&config).map_err(|err| StdError::generic_err(err.to_string()))?;
+            BEACON.save(deps.storage, &config)?;
+        }
+        None => BEACON.remove(deps.storage),
+    }
+    Ok(Response::default())
+}
+
+pub fn set_liveness_window(
+    deps: DepsMut,
+    info: MessageInfo,
+    rounds: u32,
+) -> Result<Response, ContractError> {
+    if info.sender != config_read(deps.storage)?.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if rounds == 0 {
+        return Err(ContractError::InvalidLivenessWindow {});
+    }
+    LIVENESS_WINDOW.save(deps.storage, &rounds)?;
+    Ok(Response::default())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
     Ok(Response::default())
@@ -248,10 +1004,16 @@ pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Respons
 
 // ---------Query------>
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::QueryRoundCurrent {} => to_binary(&query_current_round(deps)?),
+        QueryMsg::QueryRoundInfo { round } => to_binary(&query_round_info(deps, round)?),
         QueryMsg::QueryUserReward { user } => to_binary(&query_user_reward(deps, user)?),
+        QueryMsg::QuerySubmitted { user, round } => {
+            to_binary(&query_submitted(deps, user, round)?)
+        }
+        QueryMsg::QueryBeacon {} => to_binary(&BEACON.may_load(deps.storage)?),
+        QueryMsg::QueryLivenessWindow {} => to_binary(&liveness_window(deps.storage)?),
         QueryMsg::QueryListUser { limit, last_value } => query_users(deps, limit, last_value),
         QueryMsg::VerifyProof {
             proof_raw,
@@ -262,16 +1024,50 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             prover_id,
             sector_id,
             ticket,
-        } => to_binary(&verify_proof(
+            randomness,
+        } => {
+            check_randomness(
+                deps.storage,
+                &env,
+                &prover_id,
+                sector_id,
+                &ticket,
+                &public_inputs,
+                randomness.as_ref(),
+            )
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+            to_binary(&verify_proof(
+                deps,
+                &porep_key(&porep_id, &sector_size, &api_version)?,
+                &proof_raw,
+                &public_inputs,
+                &porep_id,
+                &prover_id,
+                sector_id,
+                &ticket,
+            )?)
+        }
+        QueryMsg::VerifyPieceInclusion {
+            prover,
+            sector_id,
+            comm_p,
+            piece_size,
+            proof,
+        } => to_binary(&query_piece_inclusion(
             deps,
-            &porep_key(&porep_id, sector_size, &api_version)?,
-            &proof_raw,
-            &public_inputs,
-            &porep_id,
-            &prover_id,
+            prover,
             sector_id,
-            &ticket,
+            comm_p.domain()?,
+            piece_size,
+            &proof,
         )?),
+        QueryMsg::QueryFileStatus { file_id } => to_binary(&query_file_status(deps, file_id)?),
+        QueryMsg::QueryReplication { comm_d } => {
+            to_binary(&query_replication(deps, comm_d.domain()?)?)
+        }
+        QueryMsg::QueryUnderReplicated { limit, start_after } => {
+            to_binary(&query_under_replicated(deps, limit, start_after)?)
+        }
     }
 }
 
@@ -282,11 +1078,104 @@ fn query_current_round(deps: Deps) -> StdResult<CurrentRoundResponse> {
     })
 }
 
+fn query_round_info(deps: Deps, round: i32) -> StdResult<InfoRound> {
+    ROUND_INFO.load(deps.storage, round.to_string())
+}
+
+fn query_piece_inclusion(
+    deps: Deps,
+    prover: String,
+    sector_id: u64,
+    comm_p: Sha256Domain,
+    piece_size: u64,
+    proof: &PieceInclusionProof,
+) -> StdResult<bool> {
+    let key = (prover, sector_id.to_string());
+    let comm_d = SECTOR_COMM_D.load(deps.storage, key.clone())?;
+    let sector_size = SECTOR_SIZES.load(deps.storage, key)?;
+
+    Ok(proof.verify(&comm_d, &comm_p, sector_size, piece_size))
+}
+
+/// A shard is live while its sector is proven within the liveness window, see `is_sector_live`.
+fn query_file_status(deps: Deps, file_id: String) -> StdResult<FileStatusResponse> {
+    let FileRecord {
+        owner,
+        manifest,
+        stale_shards,
+    } = FILE_MANIFESTS.load(deps.storage, file_id)?;
+    let round_current = query_current_round(deps)?.current_round - 1;
+
+    let mut live_shards = vec![];
+    for (i, shard) in manifest.shards.iter().enumerate() {
+        if !stale_shards.contains(&(i as u32))
+            && is_sector_live(deps.storage, &shard.prover, shard.sector_id, round_current)?
+        {
+            live_shards.push(i as u32);
+        }
+    }
+    let recoverable = live_shards.len() >= manifest.data_shards as usize;
+
+    Ok(FileStatusResponse {
+        owner,
+        manifest,
+        stale_shards,
+        live_shards,
+        recoverable,
+    })
+}
+
+fn query_replication(deps: Deps, comm_d: Sha256Domain) -> StdResult<ReplicationStatus> {
+    let order = STORAGE_ORDERS.load(deps.storage, comm_d.0.to_vec())?;
+    let round_current = query_current_round(deps)?.current_round - 1;
+    replication_status(deps.storage, order, round_current)
+}
+
+/// Up to `limit` under-replicated orders, for a repair service to schedule new seals. The orders
+/// are scanned in `comm_d` order from `start_after`.
+fn query_under_replicated(
+    deps: Deps,
+    limit: u32,
+    start_after: Option<Sha256Domain>,
+) -> StdResult<Vec<ReplicationStatus>> {
+    let round_current = query_current_round(deps)?.current_round - 1;
+    let start = start_after.map(|comm_d| Bound::exclusive(comm_d.0.to_vec()));
+
+    let mut statuses = vec![];
+    for item in STORAGE_ORDERS.range(deps.storage, start, None, Order::Ascending) {
+        if statuses.len() >= limit as usize {
+            break;
+        }
+        let (_, order) = item?;
+        let status = replication_status(deps.storage, order, round_current)?;
+        if status.under_replicated {
+            statuses.push(status);
+        }
+    }
+    Ok(statuses)
+}
+
 fn query_user_reward(deps: Deps, user: String) -> StdResult<i32> {
     let reward = USER_REWARD.load(deps.storage, user).unwrap_or_default();
     Ok(reward)
 }
 
+fn query_submitted(deps: Deps, user: String, round: i32) -> StdResult<bool> {
+    let submitted = SUBMIT_SUCCESS.may_load(deps.storage, (user, round.to_string()))?;
+    Ok(submitted.unwrap_or_default())
+}
+
+/// The backend verifying the proofs, selected by the `host-groth16` feature.
+#[cfg(not(feature = "host-groth16"))]
+fn groth16_backend(_api: &dyn Api) -> impl Groth16Backend {
+    contract_auxiliaries::groth16::ArkworksBackend
+}
+
+#[cfg(feature = "host-groth16")]
+fn groth16_backend(api: &dyn Api) -> impl Groth16Backend + '_ {
+    contract_auxiliaries::groth16::HostBackend::new(crate::host::HostApi(api))
+}
+
 pub fn verify_proof(
     deps: Deps,
     key: &[u8],
@@ -310,7 +1199,15 @@ pub fn verify_proof(
         return Ok(false);
     }
 
-    let public_params = VerifierStackedDrgPorep::setup(&params.setup_params).unwrap();
+    if params.scheme == PoRepScheme::Drg {
+        let pub_in = drg_public_inputs(public_inputs, prover_id, sector_id, ticket)?;
+        return drg_verifier(&params)?
+            .verify(&groth16_backend(deps.api), &params.vk, proof_raw, &pub_in)
+            .map_err(|err| StdError::generic_err(err.to_string()));
+    }
+
+    let public_params = VerifierStackedDrgPorep::setup(&params.setup_params)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
 
     let requirements = ChallengeRequirements {
         minimum_challenges: params.minimum_challenges,
@@ -322,25 +1219,135 @@ pub fn verify_proof(
 
     let inputs =
         VerifierStackedDrgPorep::generate_public_inputs(public_inputs, &public_params, Some(0))
-            .unwrap();
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
 
-    // using groth16_verify
-    let res = groth16_verify(
-        &prepare_verifying_key(
-            &VerifyingKey::<ark_bls12_381::Bls12_381>::deserialize(&params.vk as &[u8]).unwrap(),
-        ),
-        &Proof::deserialize(proof_raw).unwrap(),
-        &inputs,
-    );
+    groth16_backend(deps.api)
+        .verify(&params.vk, proof_raw, &inputs)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
 
-    // let res = deps.api.groth16_verify(
-    //     &arkworks_native_gadgets::from_field_elements(&inputs).unwrap(),
-    //     proof_raw,
-    //     &params.vk,
-    //     0,
-    // );
+/// Verifies the proofs of several sectors with a single batch verification.
+pub fn verify_proofs(
+    deps: Deps,
+    key: &[u8],
+    porep_id: &[u8],
+    prover_id: &[u8],
+    proofs: &[SectorProof],
+) -> StdResult<bool> {
+    let params = params_read(deps.storage, key)?;
+
+    for proof in proofs {
+        let comm_d = proof
+            .public_inputs
+            .tau
+            .as_ref()
+            .ok_or(StdError::generic_err("missing_tau"))?
+            .comm_d;
+        if !check_replica_id(
+            prover_id,
+            proof.sector_id,
+            &proof.ticket,
+            comm_d,
+            porep_id,
+            proof.public_inputs.replica_id,
+        ) {
+            return Ok(false);
+        }
+    }
+
+    let proofs_raw = proofs
+        .iter()
+        .map(|proof| proof.proof_raw.as_slice())
+        .collect::<Vec<_>>();
+
+    if params.scheme == PoRepScheme::Drg {
+        let pub_ins = proofs
+            .iter()
+            .map(|proof| {
+                drg_public_inputs(
+                    &proof.public_inputs,
+                    prover_id,
+                    proof.sector_id,
+                    &proof.ticket,
+                )
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        return drg_verifier(&params)?
+            .batch_verify(&groth16_backend(deps.api), &params.vk, &proofs_raw, &pub_ins)
+            .map_err(|err| StdError::generic_err(err.to_string()));
+    }
+
+    let public_params = VerifierStackedDrgPorep::setup(&params.setup_params)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let requirements = ChallengeRequirements {
+        minimum_challenges: params.minimum_challenges,
+    };
+
+    if !VerifierStackedDrgPorep::satisfies_requirements(&public_params, &requirements, 1) {
+        return Err(StdError::generic_err("failed to satisfy requirements"));
+    }
+
+    let inputs = proofs
+        .iter()
+        .map(|proof| {
+            VerifierStackedDrgPorep::generate_public_inputs(
+                &proof.public_inputs,
+                &public_params,
+                Some(0),
+            )
+            .map_err(|err| StdError::generic_err(err.to_string()))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    groth16_backend(deps.api)
+        .batch_verify(&params.vk, &proofs_raw, &inputs)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// The verifier of the single layer DRG PoRep, which reuses the graph of the stacked setup
+/// parameters and challenges `minimum_challenges` nodes.
+fn drg_verifier(params: &VerifierParameters) -> StdResult<VerifierDrgPoRep<PoseidonDomain>> {
+    VerifierDrgPoRep::setup(&DrgSetupParams {
+        nodes: params.setup_params.nodes,
+        degree: params.setup_params.degree,
+        porep_id: params.setup_params.porep_id,
+        challenges_count: params.minimum_challenges,
+        api_version: params.setup_params.api_version,
+    })
+    .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// The public inputs of a DRG PoRep proof. Both trees of the DRG PoRep are Poseidon trees.
+fn drg_public_inputs(
+    public_inputs: &PublicInputsPorep,
+    prover_id: &[u8],
+    sector_id: u64,
+    ticket: &[u8],
+) -> StdResult<DrgPublicInputs<PoseidonDomain>> {
+    let tau = public_inputs
+        .tau
+        .as_ref()
+        .ok_or(StdError::generic_err("missing_tau"))?;
+
+    Ok(DrgPublicInputs {
+        prover_id: prover_id
+            .try_into()
+            .map_err(|_| StdError::generic_err("invalid prover_id"))?,
+        sector_id,
+        ticket: ticket
+            .try_into()
+            .map_err(|_| StdError::generic_err("invalid ticket"))?,
+        seed: public_inputs.seed,
+        comm_d: drg_comm_d(tau),
+        comm_r: tau.comm_r,
+    })
+}
 
-    res.map_err(|err| StdError::generic_err(err.to_string()))
+/// The DRG PoRep commits to the data with a Poseidon tree, whose root is carried in the SHA-256
+/// typed `comm_d` of `tau`.
+fn drg_comm_d(tau: &Tau<PoseidonDomain, Sha256Domain>) -> PoseidonDomain {
+    PoseidonDomain(tau.comm_d.0)
 }
 
 pub fn query_users(deps: Deps, limit: u32, last_value: Option<String>) -> StdResult<Binary> {
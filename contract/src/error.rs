@@ -26,6 +26,39 @@ pub enum ContractError {
 
     #[error("Already submit proof")]
     AlreadySubmitProof {},
+
+    #[error("sector profile does not match the verifier parameters")]
+    InvalidProfile {},
+
+    #[error("no proofs submitted")]
+    NoProofs {},
+
+    #[error("sector {sector_id} submitted more than once")]
+    DuplicateSector { sector_id: u64 },
+
+    #[error("invalid beacon randomness: {reason}")]
+    InvalidRandomness { reason: String },
+
+    #[error("sector {sector_id} has no verified proof")]
+    UnknownSector { sector_id: u64 },
+
+    #[error("sector {sector_id} is not an empty committed capacity sector")]
+    NotCommittedCapacity { sector_id: u64 },
+
+    #[error("sector {sector_id} was updated, its proofs must be made against its new comm_r")]
+    UpdatedSector { sector_id: u64 },
+
+    #[error("invalid file manifest: {reason}")]
+    InvalidManifest { reason: String },
+
+    #[error("file {file_id} is already registered")]
+    FileExists { file_id: String },
+
+    #[error("liveness window must be at least one round")]
+    InvalidLivenessWindow {},
+
+    #[error("invalid storage order: {reason}")]
+    InvalidStorageOrder { reason: String },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
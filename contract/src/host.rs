@@ -0,0 +1,26 @@
+//! The Groth16 verifier of the host, on the chains whose VM provides it.
+//!
+//! The `Api` of the upstream `cosmwasm-std` has no Groth16 verifier. Building with the
+//! `host-groth16` feature takes the `cosmwasm-std` of a chain whose `Api` has
+//! `groth16_verify(inputs, proof, vk, curve) -> StdResult<bool>`, patched in with
+//! `[patch.crates-io]`, so that a contract is never built against a verifier its VM lacks.
+
+use contract_auxiliaries::groth16::Groth16Api;
+use cosmwasm_std::Api;
+
+/// The `Api` of the host, from `deps.api`.
+pub struct HostApi<'a>(pub &'a dyn Api);
+
+impl Groth16Api for HostApi<'_> {
+    fn groth16_verify(
+        &self,
+        inputs: &[u8],
+        proof: &[u8],
+        vk: &[u8],
+        curve: u8,
+    ) -> Result<bool, String> {
+        self.0
+            .groth16_verify(inputs, proof, vk, curve)
+            .map_err(|err| err.to_string())
+    }
+}
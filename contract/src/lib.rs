@@ -1,5 +1,7 @@
 pub mod contract;
 pub mod error;
+#[cfg(feature = "host-groth16")]
+pub mod host;
 pub mod msg;
 pub mod state;
 
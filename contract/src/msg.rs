@@ -1,12 +1,17 @@
 use contract_auxiliaries::{
-    domain::{poseidon::PoseidonDomain, sha256::Sha256Domain},
+    beacon::{BeaconConfig, SealRandomness},
+    cid,
+    domain::{poseidon::PoseidonDomain, sha256::Sha256Domain, Domain},
     drg::stacked::{verifier_params::PublicInputs, VerifierStackedDrg},
+    pieces::PieceInclusionProof,
     utils::ApiVersion,
 };
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Binary};
+use cosmwasm_std::{Addr, Binary, StdError, StdResult};
 
-use crate::state::VerifierParameters;
+use crate::state::{
+    FileManifest, InfoRound, Replica, StorageOrder, UpdateParameters, VerifierParameters,
+};
 
 pub type PublicInputsPorep = PublicInputs<PoseidonDomain, Sha256Domain>;
 pub type VerifierStackedDrgPorep = VerifierStackedDrg<PoseidonDomain, Sha256Domain>;
@@ -14,27 +19,16 @@ pub type VerifierStackedDrgPorep = VerifierStackedDrg<PoseidonDomain, Sha256Doma
 #[cw_serde]
 pub struct MigrateMsg {}
 
-#[cw_serde]
-pub enum SupportedSectorSize {
-    SectorSize2Kib,
-    SectorSize4Kib,
-    SectorSize16Kib,
-    SectorSize32Kib,
-    SectorSize8Mib,
-    SectorSize16Mib,
-    SectorSize512Mib,
-    SectorSize1Gib,
-    SectorSize32Gib,
-    SectorSize64Gib,
-}
-
 #[cw_serde]
 pub struct InstantiateMsg {}
 
+/// The name of a profile of the sector registry, e.g. `sector_size2_kib`.
+pub type SectorSizeName = String;
+
 #[cw_serde]
 pub enum ExecuteMsg {
     SetVerifierParams {
-        sector_size: SupportedSectorSize,
+        sector_size: SectorSizeName,
         params: VerifierParameters,
         duration: u64,
     },
@@ -45,21 +39,161 @@ pub enum ExecuteMsg {
         proof_raw: Binary,
         public_inputs: PublicInputsPorep,
         porep_id: Binary,
-        sector_size: SupportedSectorSize,
+        sector_size: SectorSizeName,
         api_version: ApiVersion,
         prover_id: Binary,
         sector_id: u64,
         ticket: Binary,
+        /// The beacon entries of the ticket and the seed, required once a beacon is set.
+        randomness: Option<SealRandomness>,
     },
+    /// Submits the proofs of several sectors, which are verified together in a single batch.
+    SubmitProofs {
+        porep_id: Binary,
+        sector_size: SectorSizeName,
+        api_version: ApiVersion,
+        prover_id: Binary,
+        proofs: Vec<SectorProof>,
+    },
+    /// Sets the randomness beacon the tickets and seeds are drawn from, or removes it.
+    SetBeacon {
+        config: Option<BeaconConfig>,
+    },
+    /// Sets the number of rounds a sector stays live after the round it was last proven in, which
+    /// covers the rounds its prover takes to prove all of its sectors.
+    SetLivenessWindow {
+        rounds: u32,
+    },
+    /// Sets the parameters of the empty sector update proofs of a sector size.
+    SetUpdateParams {
+        sector_size: SectorSizeName,
+        params: UpdateParameters,
+    },
+    /// Encodes new data into an empty committed capacity sector of the sender, replacing its
+    /// `comm_r` and `comm_d`.
+    UpdateSector {
+        sector_size: SectorSizeName,
+        sector_id: u64,
+        proof_raw: Binary,
+        comm_r_new: SealedCommitment,
+        comm_d_new: UnsealedCommitment,
+    },
+    /// Registers the shards of an erasure coded file, and the sectors holding them. `proofs` has
+    /// the inclusion proof of each shard in the `comm_d` recorded for its sector.
+    RegisterFile {
+        file_id: String,
+        manifest: FileManifest,
+        proofs: Vec<PieceInclusionProof>,
+    },
+    /// Orders `replication_factor` replicas of `comm_d`, or changes the factor of an order of the
+    /// sender.
+    SetStorageOrder {
+        comm_d: UnsealedCommitment,
+        replication_factor: u32,
+    },
+    /// Cancels a storage order of the sender.
+    CancelStorageOrder {
+        comm_d: UnsealedCommitment,
+    },
+}
+
+/// A `comm_p` or a `comm_d`, given as its bytes, its CID or the base64 of its bytes.
+#[cw_serde]
+#[serde(untagged)]
+pub enum UnsealedCommitment {
+    Bytes(Sha256Domain),
+    Encoded(String),
+}
+
+impl UnsealedCommitment {
+    pub fn domain(&self) -> StdResult<Sha256Domain> {
+        match self {
+            UnsealedCommitment::Bytes(domain) => Ok(*domain),
+            UnsealedCommitment::Encoded(encoded) => {
+                decode_commitment(encoded, cid::unsealed_from_cid).map(Sha256Domain)
+            }
+        }
+    }
+}
+
+/// A `comm_r`, given as its bytes, its CID or the base64 of its bytes.
+#[cw_serde]
+#[serde(untagged)]
+pub enum SealedCommitment {
+    Bytes(PoseidonDomain),
+    Encoded(String),
+}
+
+impl SealedCommitment {
+    pub fn domain(&self) -> StdResult<PoseidonDomain> {
+        match self {
+            SealedCommitment::Bytes(domain) => Ok(*domain),
+            SealedCommitment::Encoded(encoded) => {
+                decode_commitment(encoded, cid::sealed_from_cid).map(PoseidonDomain)
+            }
+        }
+    }
+}
+
+impl From<Sha256Domain> for UnsealedCommitment {
+    fn from(domain: Sha256Domain) -> Self {
+        UnsealedCommitment::Bytes(domain)
+    }
+}
+
+impl From<PoseidonDomain> for SealedCommitment {
+    fn from(domain: PoseidonDomain) -> Self {
+        SealedCommitment::Bytes(domain)
+    }
+}
+
+/// A CID is told apart from base64 by its length: the 39 bytes of a commitment CID take 63
+/// base32 characters, the 32 bytes of a commitment 44 base64 characters.
+fn decode_commitment<D: Domain, E: std::fmt::Display>(
+    encoded: &str,
+    from_cid: impl Fn(&str) -> Result<D, E>,
+) -> StdResult<[u8; 32]> {
+    let bytes = if encoded.len() == 44 {
+        Binary::from_base64(encoded)?.to_vec()
+    } else {
+        from_cid(encoded)
+            .map_err(|err| StdError::generic_err(format!("invalid CID {encoded}: {err}")))?
+            .into_bytes()
+    };
+    bytes
+        .try_into()
+        .map_err(|_| StdError::generic_err("commitment is not 32 bytes"))
+}
+
+/// The proof of one of the sectors of [`ExecuteMsg::SubmitProofs`].
+#[cw_serde]
+pub struct SectorProof {
+    pub proof_raw: Binary,
+    pub public_inputs: PublicInputsPorep,
+    pub sector_id: u64,
+    pub ticket: Binary,
+    pub randomness: Option<SealRandomness>,
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
+#[allow(clippy::large_enum_variant)]
 pub enum QueryMsg {
     #[returns(CurrentRoundResponse)]
     QueryRoundCurrent {},
+    #[returns(InfoRound)]
+    QueryRoundInfo { round: i32 },
     #[returns(i32)]
     QueryUserReward { user: String },
+    /// Whether `user` already submitted its proof for `round`.
+    #[returns(bool)]
+    QuerySubmitted { user: String, round: i32 },
+    /// The beacon the tickets and seeds are drawn from, `None` while provers pick them.
+    #[returns(Option<BeaconConfig>)]
+    QueryBeacon {},
+    /// The number of rounds a sector stays live after the round it was last proven in.
+    #[returns(u32)]
+    QueryLivenessWindow {},
     #[returns(Vec<String>)]
     QueryListUser {
         limit: u32,
@@ -68,13 +202,36 @@ pub enum QueryMsg {
     #[returns(bool)]
     VerifyProof {
         porep_id: Binary,
-        sector_size: SupportedSectorSize,
+        sector_size: SectorSizeName,
         api_version: ApiVersion,
         proof_raw: Binary,
         public_inputs: PublicInputsPorep,
         prover_id: Binary,
         sector_id: u64,
         ticket: Binary,
+        randomness: Option<SealRandomness>,
+    },
+    /// Checks that a piece of `piece_size` padded bytes is part of the data of a sector proven by
+    /// `prover`.
+    #[returns(bool)]
+    VerifyPieceInclusion {
+        prover: String,
+        sector_id: u64,
+        comm_p: UnsealedCommitment,
+        piece_size: u64,
+        proof: PieceInclusionProof,
+    },
+    /// Which shards of a file are in sectors proven within the liveness window.
+    #[returns(FileStatusResponse)]
+    QueryFileStatus { file_id: String },
+    /// The replicas of the `comm_d` of a storage order.
+    #[returns(ReplicationStatus)]
+    QueryReplication { comm_d: UnsealedCommitment },
+    /// The storage orders with fewer replicas than required, ordered by `comm_d`.
+    #[returns(Vec<ReplicationStatus>)]
+    QueryUnderReplicated {
+        limit: u32,
+        start_after: Option<Sha256Domain>,
     },
 }
 // We define a custom struct for each query response
@@ -82,3 +239,24 @@ pub enum QueryMsg {
 pub struct CurrentRoundResponse {
     pub current_round: i32,
 }
+
+#[cw_serde]
+pub struct FileStatusResponse {
+    pub owner: Addr,
+    pub manifest: FileManifest,
+    /// Indices of the shards whose sectors were sealed or updated with other data since.
+    pub stale_shards: Vec<u32>,
+    /// Indices of the shards which are not stale and whose sectors keep being proven.
+    pub live_shards: Vec<u32>,
+    /// Whether at least `data_shards` shards are live.
+    pub recoverable: bool,
+}
+
+#[cw_serde]
+pub struct ReplicationStatus {
+    pub order: StorageOrder,
+    /// The sectors proven in the current or the previous round to hold `comm_d`, at most one per
+    /// prover.
+    pub replicas: Vec<Replica>,
+    pub under_replicated: bool,
+}
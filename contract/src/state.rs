@@ -1,8 +1,13 @@
+use contract_auxiliaries::beacon::BeaconConfig;
+use contract_auxiliaries::domain::{poseidon::PoseidonDomain, sha256::Sha256Domain};
 use contract_auxiliaries::drg::stacked::VerifierSetupParams;
+use contract_auxiliaries::update::UpdateSetupParams;
+use contract_auxiliaries::PoRepScheme;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Binary, StdResult, Storage};
 use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
 use cw_storage_plus::{Item, Map};
+use sector_registry::SectorProfile;
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -12,6 +17,65 @@ pub struct VerifierParameters {
     pub setup_params: VerifierSetupParams,
     pub vk: Binary,
     pub minimum_challenges: u64,
+    /// Sector profile the parameters were generated with.
+    pub profile: Option<SectorProfile>,
+    /// The scheme of the proofs. The single layer DRG PoRep uses the nodes, degree, PoRep id and
+    /// API version of `setup_params`, and `minimum_challenges` challenges.
+    #[serde(default)]
+    pub scheme: PoRepScheme,
+}
+
+/// The parameters of the empty sector update proofs of a sector size.
+#[cw_serde]
+pub struct UpdateParameters {
+    pub setup_params: UpdateSetupParams,
+    pub vk: Binary,
+}
+
+/// A file erasure coded in shards held by different sectors, any `data_shards` of which recover
+/// the file.
+#[cw_serde]
+pub struct FileManifest {
+    pub data_shards: u32,
+    pub parity_shards: u32,
+    pub file_size: u64,
+    /// The data shards, then the parity shards.
+    pub shards: Vec<FileShard>,
+}
+
+/// A shard of a file, added as the piece `comm_p` of `piece_size` padded bytes to a sector of
+/// `prover`.
+#[cw_serde]
+pub struct FileShard {
+    pub comm_p: Binary,
+    pub piece_size: u64,
+    pub prover: String,
+    pub sector_id: u64,
+}
+
+/// A registered file, and the shards whose sector was sealed or updated with other data since.
+#[cw_serde]
+pub struct FileRecord {
+    pub owner: Addr,
+    pub manifest: FileManifest,
+    /// Indices of the shards whose sector does not hold the `comm_d` they were proven in anymore.
+    pub stale_shards: Vec<u32>,
+}
+
+/// A storage order, asking for `replication_factor` replicas of `comm_d` by different provers.
+#[cw_serde]
+pub struct StorageOrder {
+    pub owner: Addr,
+    pub comm_d: Sha256Domain,
+    pub replication_factor: u32,
+}
+
+/// A sector proven to hold a replica of some `comm_d`.
+#[cw_serde]
+pub struct Replica {
+    pub prover: String,
+    pub sector_id: u64,
+    pub replica_id: PoseidonDomain,
 }
 
 #[cw_serde]
@@ -54,4 +118,40 @@ pub const CURRENT_ROUND: Item<i32> = Item::new("current_round");
 pub const ROUND_INFO: Map<String, InfoRound> = Map::new("round_info");
 pub const USER_REWARD: Map<String, i32> = Map::new("user_reward");
 pub const SUBMIT_SUCCESS: Map<(String, String), bool> = Map::new("submit_success");
+// comm_d of the last verified proof of a sector, by (prover, sector id)
+pub const SECTOR_COMM_D: Map<(String, String), Sha256Domain> = Map::new("sector_comm_d");
+// comm_r of the last verified proof or update of a sector, by (prover, sector id)
+pub const SECTOR_COMM_R: Map<(String, String), PoseidonDomain> = Map::new("sector_comm_r");
+// comm_d of the last verified DRG PoRep proof of a sector, by (prover, sector id). Its data tree
+// is hashed with Poseidon, so it is kept out of the SHA-256 data roots of SECTOR_COMM_D, REPLICAS
+// and STORAGE_ORDERS.
+pub const SECTOR_DRG_COMM_D: Map<(String, String), PoseidonDomain> =
+    Map::new("sector_drg_comm_d");
+// comm_r of the seal of a sector whose data was replaced by an empty sector update since, by
+// (prover, sector id). Proofs of that seal, whose comm_d is the zero comm_d of its size, are
+// rejected until the sector is proven against its new comm_r.
+pub const SECTOR_SEALED_COMM_R: Map<(String, String), PoseidonDomain> =
+    Map::new("sector_sealed_comm_r");
+// size in bytes of the last verified proof of a sector, by (prover, sector id)
+pub const SECTOR_SIZES: Map<(String, String), u64> = Map::new("sector_sizes");
+// the parameters of the empty sector update proofs, by sector size name
+pub const UPDATE_PARAMS: Map<String, UpdateParameters> = Map::new("update_params");
+// the last round a proof of a sector was verified in, by (prover, sector id)
+pub const SECTOR_PROVEN_ROUND: Map<(String, String), i32> = Map::new("sector_proven_round");
+// the number of rounds a sector stays live after the round it was last proven in, set by the owner
+pub const LIVENESS_WINDOW: Item<u32> = Item::new("liveness_window");
+// the liveness window until the owner sets one: a sector is proven in every round
+pub const DEFAULT_LIVENESS_WINDOW: u32 = 1;
+// the erasure coded files, by file id
+pub const FILE_MANIFESTS: Map<String, FileRecord> = Map::new("file_manifests");
+// the index of the shard of a file held by a sector, by (prover, sector id, file id)
+pub const SECTOR_FILES: Map<(String, String, String), u32> = Map::new("sector_files");
+// the storage orders, by comm_d
+pub const STORAGE_ORDERS: Map<Vec<u8>, StorageOrder> = Map::new("storage_orders");
+// the sectors holding a comm_d, by (comm_d, prover, sector id)
+pub const REPLICAS: Map<(Vec<u8>, String, String), Replica> = Map::new("replicas");
+// the beacon the tickets and seeds are drawn from, if any
+pub const BEACON: Item<BeaconConfig> = Item::new("beacon");
+// the beacon round of the ticket a sector was first proven with, by (prover id, sector id)
+pub const SECTOR_TICKET_ROUND: Map<(Vec<u8>, String), u64> = Map::new("sector_ticket_round");
 // pub static PREFIX_SUBMIT_SUCCESS: &[u8] = b"submit_proof_success"; // this is tick with value is the total orders
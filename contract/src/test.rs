@@ -6,9 +6,9 @@ use converter::{
     groth16::convert_fr,
     serializer::{serialize_proof, serialize_verifying_key},
 };
-use ff::Field;
+use ff::{Field, PrimeField};
 use fr32::fr_into_bytes;
-use generic_array::typenum::{U2, U4, U8};
+use generic_array::typenum::{U0, U2, U4, U8};
 use hashers::{poseidon::PoseidonHasher, sha256::Sha256Hasher, Hasher};
 use merkletree::store::StoreConfig;
 use proofs_core::{
@@ -17,6 +17,8 @@ use proofs_core::{
     compound_proof::{self, CompoundProof},
     drgraph::BASE_DEGREE,
     merkle::{get_base_tree_count, DiskTree, MerkleTreeTrait},
+    proof::ProofScheme,
+    sector::SectorId,
     test_helper::setup_replica,
     util::default_rows_to_discard,
     TEST_SEED,
@@ -26,6 +28,7 @@ use proofs_porep::{
         ChallengeRequirements, LayerChallenges, PrivateInputs, PublicInputs, SetupParams,
         StackedCompound, StackedDrg, TemporaryAux, TemporaryAuxCache, BINARY_ARITY, EXP_DEGREE,
     },
+    update::{self, EmptySectorUpdate, EmptySectorUpdateCompound},
     PoRep,
 };
 
@@ -36,12 +39,121 @@ use contract_auxiliaries::{
         challenges::LayerChallenges as VerifierLayerChallenges, VerifierSetupParams,
         VerifierStackedDrg,
     },
+    drg::vanilla::{
+        PublicInputs as DrgPublicInputs, SetupParams as DrgSetupParams, VerifierDrgPoRep,
+    },
+    update::{UpdatePublicInputs, UpdateSetupParams},
     utils::ApiVersion as VerifierApiVersion,
 };
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
+use seal::{get_drg_inputs, types::PoRepConfig};
 use tempfile::tempdir;
 
+#[test]
+fn test_update_public_inputs() {
+    type Tree = DiskTree<PoseidonHasher, U8, U0, U0>;
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let nodes = 64;
+    let challenges_count = 4;
+
+    let comm_r_old = Fr::random(&mut rng);
+    let comm_d_new = Fr::random(&mut rng);
+    let comm_r_new = Fr::random(&mut rng);
+
+    let public_params = EmptySectorUpdate::<Tree, Sha256Hasher>::setup(&update::SetupParams {
+        nodes,
+        challenges_count,
+    })
+    .expect("setup failed");
+    let expected_pubins =
+        EmptySectorUpdateCompound::<Tree, Sha256Hasher>::generate_public_inputs(
+            &update::PublicInputs {
+                comm_r_old: comm_r_old.into(),
+                comm_d_new: comm_d_new.into(),
+                comm_r_new: comm_r_new.into(),
+            },
+            &public_params,
+            None,
+        )
+        .expect("failed to gen public inputs");
+
+    let actual_pubins = contract_auxiliaries::update::generate_public_inputs(
+        &UpdatePublicInputs {
+            comm_r_old: PoseidonDomain(comm_r_old.to_repr()),
+            comm_d_new: Sha256Domain(comm_d_new.to_repr()),
+            comm_r_new: PoseidonDomain(comm_r_new.to_repr()),
+        },
+        &UpdateSetupParams {
+            nodes: nodes as u64,
+            challenges_count: challenges_count as u64,
+        },
+    )
+    .expect("failed to gen verifier public inputs");
+
+    assert_eq!(actual_pubins.len(), expected_pubins.len());
+    actual_pubins
+        .into_iter()
+        .zip(expected_pubins)
+        .for_each(|(a, b)| assert_eq!(a, convert_fr(b)));
+}
+
+#[test]
+fn test_drg_public_inputs() {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let porep_id = [5; 32];
+    let profile = sector_registry::global()
+        .by_name("sector_size2_kib")
+        .cloned()
+        .unwrap();
+    let porep_config =
+        PoRepConfig::new_groth16(profile.sector_size, porep_id, ApiVersion::V1_1_0);
+
+    let comm_r = Fr::random(&mut rng).to_repr();
+    let comm_d = Fr::random(&mut rng).to_repr();
+    let prover_id = rng.gen();
+    let sector_id = rng.gen();
+    let ticket = rng.gen();
+    let seed = rng.gen();
+
+    let expected_pubins = get_drg_inputs(
+        &porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        SectorId::from(sector_id),
+        ticket,
+        seed,
+    )
+    .expect("failed to gen public inputs");
+
+    let verifier = VerifierDrgPoRep::<PoseidonDomain>::setup(&DrgSetupParams {
+        nodes: profile.nodes(),
+        degree: BASE_DEGREE as u64,
+        porep_id,
+        challenges_count: profile.minimum_challenges as u64,
+        api_version: VerifierApiVersion::V1_1_0,
+    })
+    .expect("verifier setup failed");
+    let actual_pubins = verifier
+        .generate_public_inputs(&DrgPublicInputs {
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            comm_d: PoseidonDomain(comm_d),
+            comm_r: PoseidonDomain(comm_r),
+        })
+        .expect("failed to gen verifier public inputs");
+
+    assert_eq!(actual_pubins.len(), expected_pubins.len());
+    actual_pubins
+        .into_iter()
+        .zip(expected_pubins)
+        .for_each(|(a, b)| assert_eq!(a, convert_fr(b)));
+}
+
 #[test]
 fn test_gen_pubins_top_8_4_2() {
     test_generate_public_inputs::<DiskTree<PoseidonHasher, U8, U4, U2>>();
@@ -284,20 +396,10 @@ mod test {
 
     fn get_file_size(path: &Path) -> u64 {
         let metadata = fs::metadata(path).expect("failed to fetch file's metadata");
-        let size = metadata.len();
-        match size {
-            x if x <= SECTOR_SIZE_2_KIB => SECTOR_SIZE_2_KIB,
-            x if x <= SECTOR_SIZE_4_KIB => SECTOR_SIZE_4_KIB,
-            x if x <= SECTOR_SIZE_16_KIB => SECTOR_SIZE_16_KIB,
-            x if x <= SECTOR_SIZE_32_KIB => SECTOR_SIZE_32_KIB,
-            x if x <= SECTOR_SIZE_8_MIB => SECTOR_SIZE_8_MIB,
-            x if x <= SECTOR_SIZE_16_MIB => SECTOR_SIZE_16_MIB,
-            x if x <= SECTOR_SIZE_512_MIB => SECTOR_SIZE_512_MIB,
-            x if x <= SECTOR_SIZE_1_GIB => SECTOR_SIZE_1_GIB,
-            x if x <= SECTOR_SIZE_32_GIB => SECTOR_SIZE_32_GIB,
-            x if x <= SECTOR_SIZE_64_GIB => SECTOR_SIZE_64_GIB,
-            _ => panic!("file is too large (maximum supported size: 64GiB)"),
-        }
+        sector_registry::global()
+            .fit(metadata.len())
+            .expect("file is too large")
+            .sector_size
     }
 
     #[test]
@@ -362,4 +464,989 @@ mod test {
 
         assert!(valid_replica, "invalid replica id");
     }
+
+    #[test]
+    fn test_set_verifier_params_checks_profile() {
+        use contract_auxiliaries::{
+            drg::stacked::{challenges::LayerChallenges, VerifierSetupParams},
+            utils::ApiVersion as VerifierApiVersion,
+        };
+        use cosmwasm_std::{
+            testing::{mock_dependencies, mock_env, mock_info},
+            Binary,
+        };
+
+        use crate::contract::{execute, instantiate};
+        use crate::msg::{ExecuteMsg, InstantiateMsg};
+        use crate::state::VerifierParameters;
+        use crate::ContractError;
+
+        let mut deps = mock_dependencies();
+        let info = mock_info("owner", &[]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), InstantiateMsg {}).unwrap();
+
+        let profile = sector_registry::global()
+            .by_name("sector_size2_kib")
+            .cloned()
+            .unwrap();
+        let params = VerifierParameters {
+            setup_params: VerifierSetupParams {
+                nodes: profile.nodes(),
+                degree: DRG_DEGREE as u64,
+                expansion_degree: EXP_DEGREE as u64,
+                porep_id: [0; 32],
+                layer_challenges: LayerChallenges::new(profile.layers, profile.minimum_challenges),
+                api_version: VerifierApiVersion::V1_1_0,
+            },
+            vk: Binary::default(),
+            minimum_challenges: profile.minimum_challenges as u64,
+            profile: Some(profile),
+            scheme: Default::default(),
+        };
+        let msg = |sector_size| ExecuteMsg::SetVerifierParams {
+            sector_size,
+            params: params.clone(),
+            duration: 100,
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            msg("sector_size4_kib".to_owned()),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidProfile {}));
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            msg("sector_size3_kib".to_owned()),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unsupported sector size"));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            msg("sector_size2_kib".to_owned()),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_submit_proofs_checks_sectors() {
+        use contract_auxiliaries::{domain::Domain, utils::ApiVersion as VerifierApiVersion};
+        use cosmwasm_std::{
+            testing::{mock_dependencies, mock_env, mock_info},
+            Binary,
+        };
+
+        use crate::contract::{execute, instantiate};
+        use crate::msg::{ExecuteMsg, InstantiateMsg, SectorProof};
+        use crate::ContractError;
+
+        let mut deps = mock_dependencies();
+        let info = mock_info("prover", &[]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), InstantiateMsg {}).unwrap();
+
+        let sector_proof = |sector_id| SectorProof {
+            proof_raw: Binary::default(),
+            public_inputs: PublicInputs {
+                replica_id: PoseidonDomain::try_from_bytes(&[0; 32]).unwrap(),
+                seed: [0; 32],
+                tau: None,
+                k: None,
+            },
+            sector_id,
+            ticket: Binary::default(),
+            randomness: None,
+        };
+        let msg = |proofs| ExecuteMsg::SubmitProofs {
+            porep_id: Binary::default(),
+            sector_size: "sector_size2_kib".to_owned(),
+            api_version: VerifierApiVersion::V1_1_0,
+            prover_id: Binary::default(),
+            proofs,
+        };
+
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg(vec![])).unwrap_err();
+        assert!(matches!(err, ContractError::NoProofs {}));
+
+        let proofs = vec![sector_proof(1), sector_proof(2), sector_proof(1)];
+        let err = execute(deps.as_mut(), mock_env(), info, msg(proofs)).unwrap_err();
+        assert!(matches!(err, ContractError::DuplicateSector { sector_id: 1 }));
+    }
+
+    #[test]
+    fn test_beacon_randomness() {
+        use contract_auxiliaries::{
+            beacon::{Beacon, BeaconConfig, BeaconEntry, BeaconScheme, SealRandomness},
+            domain::Domain,
+            utils::ApiVersion as VerifierApiVersion,
+        };
+        use cosmwasm_std::{
+            testing::{mock_dependencies, mock_env, mock_info},
+            Binary, Timestamp,
+        };
+        use serde_json::Value;
+
+        use crate::contract::{execute, instantiate, query};
+        use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+        use crate::ContractError;
+
+        let fixtures: Value = serde_json::from_str(include_str!(
+            "../../contract-auxiliaries/tests/fixtures/beacon.json"
+        ))
+        .unwrap();
+        let hex = |value: &Value| hex::decode(value.as_str().unwrap()).unwrap();
+        let chain = &fixtures["mainnet"];
+        let config = BeaconConfig {
+            public_key: hex(&chain["info"]["public_key"]),
+            scheme: BeaconScheme::Chained,
+            genesis_time: chain["info"]["genesis_time"].as_u64().unwrap(),
+            period: chain["info"]["period"].as_u64().unwrap(),
+            seed_lookback: 0,
+            ticket_max_age: 4,
+            seed_max_age: 1,
+        };
+        let round = &chain["rounds"][0];
+        let entry = BeaconEntry {
+            round: round["round"].as_u64().unwrap(),
+            signature: hex(&round["signature"]),
+            previous_signature: Some(hex(&round["previous_signature"])),
+        };
+        // The recorded round serves both as the ticket and the seed, without lookback.
+        let randomness = SealRandomness {
+            ticket: entry.clone(),
+            seed: entry.clone(),
+        };
+        let now = config.genesis_time + (entry.round - 1) * config.period;
+
+        let mut deps = mock_dependencies();
+        let owner = mock_info("owner", &[]);
+        instantiate(deps.as_mut(), mock_env(), owner.clone(), InstantiateMsg {}).unwrap();
+        let msg = ExecuteMsg::SetBeacon {
+            config: Some(config.clone()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("prover", &[]), msg.clone())
+            .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+
+        let prover_id = [1u8; 32];
+        let (ticket, seed) = Beacon::new(&config)
+            .unwrap()
+            .seal_randomness(&randomness, &prover_id, 1, now, None)
+            .unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(now);
+        let verify = |ticket: [u8; 32], seed: [u8; 32], randomness: Option<SealRandomness>| {
+            let msg = QueryMsg::VerifyProof {
+                porep_id: Binary::default(),
+                sector_size: "sector_size2_kib".to_owned(),
+                api_version: VerifierApiVersion::V1_1_0,
+                proof_raw: Binary::default(),
+                public_inputs: PublicInputs {
+                    replica_id: PoseidonDomain::try_from_bytes(&[0; 32]).unwrap(),
+                    seed,
+                    tau: None,
+                    k: None,
+                },
+                prover_id: Binary::from(prover_id),
+                sector_id: 1,
+                ticket: Binary::from(ticket),
+                randomness,
+            };
+            query(deps.as_ref(), env.clone(), msg).unwrap_err().to_string()
+        };
+
+        assert!(verify(ticket, seed, None).contains("missing beacon entries"));
+        assert!(verify(seed, seed, Some(randomness.clone())).contains("ticket is not drawn"));
+        assert!(verify(ticket, ticket, Some(randomness.clone())).contains("seed is not drawn"));
+        let mut forged = randomness.clone();
+        forged.ticket.round += 1;
+        assert!(verify(ticket, seed, Some(forged)).contains("less than 0 rounds after"));
+        let mut forged = randomness.clone();
+        forged.seed.previous_signature = Some(entry.signature.clone());
+        assert!(verify(ticket, seed, Some(forged)).contains("invalid signature"));
+        // With the beacon randomness, the query goes on to the verifier parameters, which are
+        // not set.
+        assert!(!verify(ticket, seed, Some(randomness)).contains("randomness"));
+    }
+
+    #[test]
+    fn test_query_piece_inclusion() {
+        use contract_auxiliaries::domain::sha256::Sha256Domain;
+        use cosmwasm_std::{
+            from_binary,
+            testing::{mock_dependencies, mock_env},
+            Binary,
+        };
+        use hashers::{sha256::Sha256Hasher, Domain, Hasher};
+        use proofs_core::{merkle::BinaryMerkleTree, pieces::PieceSpec};
+
+        use seal::types::{commitment_to_cid, CommitmentKind};
+
+        use crate::contract::query;
+        use crate::msg::{QueryMsg, UnsealedCommitment};
+        use crate::state::{SECTOR_COMM_D, SECTOR_SIZES};
+
+        let mut rng = thread_rng();
+        let leaves: Vec<_> = (0..64)
+            .map(|_| <Sha256Hasher as Hasher>::Domain::random(&mut rng))
+            .collect();
+        let tree_d =
+            BinaryMerkleTree::<Sha256Hasher>::try_from_iter(leaves.iter().copied().map(Ok))
+                .unwrap();
+        let piece =
+            BinaryMerkleTree::<Sha256Hasher>::try_from_iter(leaves[16..32].iter().copied().map(Ok))
+                .unwrap();
+
+        let mut comm_d = [0; 32];
+        tree_d.root().write_bytes(&mut comm_d).unwrap();
+        let mut comm_p = [0; 32];
+        piece.root().write_bytes(&mut comm_p).unwrap();
+
+        let spec = PieceSpec {
+            comm_p,
+            position: 16,
+            number_of_leaves: 16,
+        };
+        let proof = generate_piece_inclusion_proof(&tree_d, &spec).unwrap();
+
+        let mut deps = mock_dependencies();
+        SECTOR_COMM_D
+            .save(
+                deps.as_mut().storage,
+                ("prover".to_string(), "1".to_string()),
+                &Sha256Domain(comm_d),
+            )
+            .unwrap();
+        SECTOR_SIZES
+            .save(
+                deps.as_mut().storage,
+                ("prover".to_string(), "1".to_string()),
+                &(64 * 32),
+            )
+            .unwrap();
+
+        let query_proof = |comm_p: UnsealedCommitment, piece_size, proof| -> bool {
+            let msg = QueryMsg::VerifyPieceInclusion {
+                prover: "prover".to_string(),
+                sector_id: 1,
+                comm_p,
+                piece_size,
+                proof,
+            };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let proof: contract_auxiliaries::pieces::PieceInclusionProof =
+            serde_json::from_value(serde_json::to_value(&proof).unwrap()).unwrap();
+        let verify = |comm_p| query_proof(comm_p, 16 * 32, proof.clone());
+
+        // The piece may be given by its bytes, their base64 or its CID.
+        assert!(verify(Sha256Domain(comm_p).into()));
+        assert!(verify(UnsealedCommitment::Encoded(
+            Binary::from(comm_p).to_base64()
+        )));
+        assert!(verify(UnsealedCommitment::Encoded(commitment_to_cid(
+            CommitmentKind::Unsealed,
+            &comm_p
+        ))));
+        assert!(!verify(Sha256Domain(comm_d).into()));
+
+        // The proof must place a piece of the given size on its alignment, up to the sector root.
+        assert!(!query_proof(Sha256Domain(comm_p).into(), 32 * 32, proof.clone()));
+        let mut unaligned = proof.clone();
+        unaligned.position = 8;
+        assert!(!query_proof(Sha256Domain(comm_p).into(), 16 * 32, unaligned));
+        let mut extended = proof.clone();
+        extended.siblings.push(Sha256Domain(comm_d));
+        assert!(!query_proof(Sha256Domain(comm_p).into(), 16 * 32, extended));
+    }
+
+    #[test]
+    fn test_commitment_cid() {
+        use contract_auxiliaries::domain::{poseidon::PoseidonDomain, sha256::Sha256Domain};
+
+        use crate::msg::{SealedCommitment, UnsealedCommitment};
+
+        // The piece CID of 2KiB of zeros.
+        let piece_cid = "baga6ea4seaqpy7usqklokfx2vxuynmupslkeutzexe2uqurdg5vhtebhxqmpqmy";
+        let comm_p =
+            hex::decode("fc7e928296e516faade986b28f92d44a4f24b935485223376a799027bc18f833")
+                .unwrap();
+        let commitment: UnsealedCommitment =
+            serde_json::from_value(serde_json::json!(piece_cid)).unwrap();
+        assert_eq!(commitment.domain().unwrap().0.to_vec(), comm_p);
+
+        let sealed_cid = "bagboea4b5abcaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let commitment: SealedCommitment =
+            serde_json::from_value(serde_json::json!(sealed_cid)).unwrap();
+        assert_eq!(commitment.domain().unwrap(), PoseidonDomain([0; 32]));
+
+        // The raw bytes are still accepted.
+        let commitment: UnsealedCommitment =
+            serde_json::from_value(serde_json::json!(vec![7u8; 32])).unwrap();
+        assert_eq!(commitment.domain().unwrap(), Sha256Domain([7; 32]));
+
+        // A CID of the wrong kind, and a truncated one.
+        assert!(UnsealedCommitment::Encoded(sealed_cid.to_string())
+            .domain()
+            .is_err());
+        assert!(SealedCommitment::Encoded(piece_cid.to_string())
+            .domain()
+            .is_err());
+        assert!(UnsealedCommitment::Encoded(piece_cid[..60].to_string())
+            .domain()
+            .is_err());
+    }
+
+    #[test]
+    fn test_file_status() {
+        use contract_auxiliaries::{
+            drg::stacked::verifier_params::Tau, merkle::Sha256Hasher, pieces::PieceInclusionProof,
+            PoRepScheme,
+        };
+        use cosmwasm_std::{
+            from_binary,
+            testing::{mock_dependencies, mock_env, mock_info},
+            Addr, Binary,
+        };
+
+        use crate::contract::{execute, instantiate, query, reward_sector};
+        use crate::msg::{ExecuteMsg, FileStatusResponse, InstantiateMsg, QueryMsg};
+        use crate::state::{
+            FileManifest, FileShard, CURRENT_ROUND, SECTOR_COMM_D, SECTOR_PROVEN_ROUND,
+            SECTOR_SIZES,
+        };
+        use crate::ContractError;
+
+        let mut deps = mock_dependencies();
+        let info = mock_info("client", &[]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), InstantiateMsg {}).unwrap();
+
+        // Each shard fills the first half of a 2KiB sector.
+        let sibling = Sha256Domain([9; 32]);
+        let comm_p = |sector_id: u64| Sha256Domain([sector_id as u8; 32]);
+        let shard = |prover: &str, sector_id| FileShard {
+            comm_p: Binary::from(comm_p(sector_id).0),
+            piece_size: 1024,
+            prover: prover.to_string(),
+            sector_id,
+        };
+        let proofs = vec![
+            PieceInclusionProof {
+                position: 0,
+                number_of_leaves: 32,
+                siblings: vec![sibling],
+            };
+            3
+        ];
+        let manifest = FileManifest {
+            data_shards: 2,
+            parity_shards: 1,
+            file_size: 3000,
+            shards: vec![shard("a", 1), shard("b", 1), shard("a", 2)],
+        };
+        let register = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>,
+                        manifest: &FileManifest,
+                        proofs: &[PieceInclusionProof]| {
+            let msg = ExecuteMsg::RegisterFile {
+                file_id: "file".to_string(),
+                manifest: manifest.clone(),
+                proofs: proofs.to_vec(),
+            };
+            execute(deps.as_mut(), mock_env(), info.clone(), msg)
+        };
+
+        let mut same_sector = manifest.clone();
+        same_sector.shards[2] = shard("a", 1);
+        let err = register(&mut deps, &same_sector, &proofs).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidManifest { .. }));
+
+        let mut missing_shard = manifest.clone();
+        missing_shard.shards.pop();
+        let err = register(&mut deps, &missing_shard, &proofs[..2]).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidManifest { .. }));
+        let err = register(&mut deps, &manifest, &proofs[..2]).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidManifest { .. }));
+
+        // The shards are checked against the comm_d proven for their sectors.
+        let err = register(&mut deps, &manifest, &proofs).unwrap_err();
+        assert!(err.to_string().contains("no recorded comm_d"));
+        for (prover, sector_id) in [("a", 1), ("b", 1), ("a", 2)] {
+            let key = (prover.to_string(), sector_id.to_string());
+            let comm_d = Sha256Hasher::hash2(&comm_p(sector_id), &sibling);
+            let storage = deps.as_mut().storage;
+            SECTOR_COMM_D.save(storage, key.clone(), &comm_d).unwrap();
+            SECTOR_SIZES.save(storage, key, &2048).unwrap();
+        }
+        let mut wrong_proofs = proofs.clone();
+        wrong_proofs[1].position = 32;
+        let err = register(&mut deps, &manifest, &wrong_proofs).unwrap_err();
+        assert!(err.to_string().contains("shard 1 is not a piece of its sector"));
+        let mut wrong_size = manifest.clone();
+        wrong_size.shards[0].piece_size = 2048;
+        let err = register(&mut deps, &wrong_size, &proofs).unwrap_err();
+        assert!(err.to_string().contains("shard 0 is not a piece of its sector"));
+
+        register(&mut deps, &manifest, &proofs).unwrap();
+        let err = register(&mut deps, &manifest, &proofs).unwrap_err();
+        assert!(matches!(err, ContractError::FileExists { .. }));
+
+        // Two of the sectors are proven in the current round.
+        for (prover, sector_id) in [("a", "2"), ("b", "1")] {
+            SECTOR_PROVEN_ROUND
+                .save(
+                    deps.as_mut().storage,
+                    (prover.to_string(), sector_id.to_string()),
+                    &0,
+                )
+                .unwrap();
+        }
+        let status = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> FileStatusResponse {
+            let msg = QueryMsg::QueryFileStatus {
+                file_id: "file".to_string(),
+            };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let response = status(&deps);
+        assert_eq!(response.owner, info.sender);
+        assert_eq!(response.manifest, manifest);
+        assert!(response.stale_shards.is_empty());
+        assert_eq!(response.live_shards, vec![1, 2]);
+        assert!(response.recoverable);
+
+        // Sealed again with other data, a sector does not hold its shard anymore.
+        let public_inputs = PublicInputs {
+            replica_id: PoseidonDomain([1; 32]),
+            seed: [2; 32],
+            tau: Some(Tau {
+                comm_d: Sha256Domain([3; 32]),
+                comm_r: PoseidonDomain([4; 32]),
+            }),
+            k: None,
+        };
+        let prover = Addr::unchecked("a");
+        reward_sector(deps.as_mut(), &prover, 2, 2048, PoRepScheme::StackedDrg, &public_inputs)
+            .unwrap();
+        let response = status(&deps);
+        assert_eq!(response.stale_shards, vec![2]);
+        assert_eq!(response.live_shards, vec![1]);
+        assert!(!response.recoverable);
+
+        // Missing the next rounds, the shards are not live anymore.
+        CURRENT_ROUND.save(deps.as_mut().storage, &3).unwrap();
+        let response = status(&deps);
+        assert!(response.live_shards.is_empty());
+        assert!(!response.recoverable);
+    }
+
+    #[test]
+    fn test_replication() {
+        use contract_auxiliaries::domain::{poseidon::PoseidonDomain, sha256::Sha256Domain};
+        use cosmwasm_std::{
+            from_binary,
+            testing::{mock_dependencies, mock_env, mock_info},
+        };
+
+        use crate::contract::{execute, instantiate, query};
+        use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ReplicationStatus};
+        use crate::state::{Replica, CURRENT_ROUND, REPLICAS, SECTOR_PROVEN_ROUND};
+        use crate::ContractError;
+
+        let mut deps = mock_dependencies();
+        let client = mock_info("client", &[]);
+        instantiate(deps.as_mut(), mock_env(), client.clone(), InstantiateMsg {}).unwrap();
+
+        let comm_d = Sha256Domain([1; 32]);
+        let other_comm_d = Sha256Domain([2; 32]);
+        let order = |replication_factor| ExecuteMsg::SetStorageOrder {
+            comm_d: comm_d.into(),
+            replication_factor,
+        };
+
+        let err = execute(deps.as_mut(), mock_env(), client.clone(), order(0)).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidStorageOrder { .. }));
+
+        // A new order has no replicas yet.
+        let response = execute(deps.as_mut(), mock_env(), client.clone(), order(2)).unwrap();
+        assert_eq!(response.events.len(), 1);
+        assert_eq!(response.events[0].ty, "under_replicated");
+        let err =
+            execute(deps.as_mut(), mock_env(), mock_info("other", &[]), order(1)).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            client.clone(),
+            ExecuteMsg::SetStorageOrder {
+                comm_d: other_comm_d.into(),
+                replication_factor: 1,
+            },
+        )
+        .unwrap();
+
+        // Prover `a` holds `comm_d` in two sectors, and `b` in a sector sharing a replica id with
+        // one of them: they count as a single replica.
+        for (prover, sector_id, replica_id) in [("a", 1, 1), ("a", 2, 2), ("b", 1, 1)] {
+            let replica = Replica {
+                prover: prover.to_string(),
+                sector_id,
+                replica_id: PoseidonDomain([replica_id; 32]),
+            };
+            REPLICAS
+                .save(
+                    deps.as_mut().storage,
+                    (comm_d.0.to_vec(), prover.to_string(), sector_id.to_string()),
+                    &replica,
+                )
+                .unwrap();
+            SECTOR_PROVEN_ROUND
+                .save(
+                    deps.as_mut().storage,
+                    (prover.to_string(), sector_id.to_string()),
+                    &0,
+                )
+                .unwrap();
+        }
+        let replication = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> ReplicationStatus {
+            let msg = QueryMsg::QueryReplication {
+                comm_d: comm_d.into(),
+            };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let under_replicated = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> Vec<ReplicationStatus> {
+            let msg = QueryMsg::QueryUnderReplicated {
+                limit: 10,
+                start_after: None,
+            };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let status = replication(&deps);
+        assert_eq!(status.replicas.len(), 1);
+        assert!(status.under_replicated);
+        assert_eq!(under_replicated(&deps).len(), 2);
+
+        // A sector of `c` with its own replica id completes the order.
+        let replica = Replica {
+            prover: "c".to_string(),
+            sector_id: 7,
+            replica_id: PoseidonDomain([3; 32]),
+        };
+        REPLICAS
+            .save(
+                deps.as_mut().storage,
+                (comm_d.0.to_vec(), "c".to_string(), "7".to_string()),
+                &replica,
+            )
+            .unwrap();
+        SECTOR_PROVEN_ROUND
+            .save(
+                deps.as_mut().storage,
+                ("c".to_string(), "7".to_string()),
+                &0,
+            )
+            .unwrap();
+        let status = replication(&deps);
+        assert_eq!(status.replicas.len(), 2);
+        assert!(!status.under_replicated);
+        let statuses = under_replicated(&deps);
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].order.comm_d, other_comm_d);
+
+        // Missing the next rounds, the replicas are not live anymore.
+        CURRENT_ROUND.save(deps.as_mut().storage, &3).unwrap();
+        assert!(replication(&deps).replicas.is_empty());
+        assert_eq!(under_replicated(&deps).len(), 2);
+
+        let cancel = ExecuteMsg::CancelStorageOrder {
+            comm_d: comm_d.into(),
+        };
+        execute(deps.as_mut(), mock_env(), client, cancel).unwrap();
+        assert_eq!(under_replicated(&deps).len(), 1);
+    }
+
+    #[test]
+    fn test_replication_rotation() {
+        use contract_auxiliaries::{drg::stacked::verifier_params::Tau, PoRepScheme};
+        use cosmwasm_std::{
+            from_binary,
+            testing::{mock_dependencies, mock_env, mock_info},
+            Addr,
+        };
+
+        use crate::contract::{execute, instantiate, query, reward_sector};
+        use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ReplicationStatus};
+        use crate::state::CURRENT_ROUND;
+        use crate::ContractError;
+
+        let mut deps = mock_dependencies();
+        let client = mock_info("client", &[]);
+        instantiate(deps.as_mut(), mock_env(), client.clone(), InstantiateMsg {}).unwrap();
+
+        // The owner gives the provers four rounds to prove each of their sectors.
+        let window = |rounds| ExecuteMsg::SetLivenessWindow { rounds };
+        let err =
+            execute(deps.as_mut(), mock_env(), mock_info("a", &[]), window(4)).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = execute(deps.as_mut(), mock_env(), client.clone(), window(0)).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidLivenessWindow {}));
+        let query_window = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> u32 {
+            let msg = QueryMsg::QueryLivenessWindow {};
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        assert_eq!(query_window(&deps), 1);
+        execute(deps.as_mut(), mock_env(), client.clone(), window(4)).unwrap();
+        assert_eq!(query_window(&deps), 4);
+
+        // Prover `a` proves one of its four sectors per round, only the first one holding the
+        // ordered `comm_d`.
+        let comm_d = Sha256Domain([1; 32]);
+        let order = ExecuteMsg::SetStorageOrder {
+            comm_d: comm_d.into(),
+            replication_factor: 1,
+        };
+        execute(deps.as_mut(), mock_env(), client, order).unwrap();
+        let prover = Addr::unchecked("a");
+        let prove = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, round: i32, sector_id: u64| {
+            CURRENT_ROUND.save(deps.as_mut().storage, &(round + 1)).unwrap();
+            let public_inputs = PublicInputs {
+                replica_id: PoseidonDomain([sector_id as u8; 32]),
+                seed: [2; 32],
+                tau: Some(Tau {
+                    comm_d: if sector_id == 1 { comm_d } else { Sha256Domain([9; 32]) },
+                    comm_r: PoseidonDomain([sector_id as u8; 32]),
+                }),
+                k: None,
+            };
+            reward_sector(
+                deps.as_mut(),
+                &prover,
+                sector_id,
+                2048,
+                PoRepScheme::StackedDrg,
+                &public_inputs,
+            )
+            .unwrap();
+        };
+        let replicas = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> usize {
+            let msg = QueryMsg::QueryReplication {
+                comm_d: comm_d.into(),
+            };
+            let status: ReplicationStatus =
+                from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+            status.replicas.len()
+        };
+
+        for (round, sector_id) in [(1, 1), (2, 2), (3, 3), (4, 4)] {
+            prove(&mut deps, round, sector_id);
+            assert_eq!(replicas(&deps), 1);
+        }
+        // The sector stays live until its turn comes again, and while that round is in progress.
+        CURRENT_ROUND.save(deps.as_mut().storage, &6).unwrap();
+        assert_eq!(replicas(&deps), 1);
+        prove(&mut deps, 5, 1);
+        assert_eq!(replicas(&deps), 1);
+        // Proving more sectors does not stretch the window.
+        for sector_id in 5..=12 {
+            prove(&mut deps, 6, sector_id);
+        }
+        CURRENT_ROUND.save(deps.as_mut().storage, &10).unwrap();
+        assert_eq!(replicas(&deps), 1);
+        // Missing its turn, it is not live anymore.
+        CURRENT_ROUND.save(deps.as_mut().storage, &11).unwrap();
+        assert_eq!(replicas(&deps), 0);
+    }
+
+    #[test]
+    fn test_reward_drg_sector() {
+        use contract_auxiliaries::{drg::stacked::verifier_params::Tau, PoRepScheme};
+        use cosmwasm_std::{
+            testing::{mock_dependencies, mock_env, mock_info},
+            Addr,
+        };
+
+        use crate::contract::{instantiate, reward_sector};
+        use crate::msg::InstantiateMsg;
+        use crate::state::{
+            REPLICAS, SECTOR_COMM_D, SECTOR_COMM_R, SECTOR_DRG_COMM_D, SECTOR_SIZES,
+        };
+
+        let mut deps = mock_dependencies();
+        let owner = mock_info("owner", &[]);
+        instantiate(deps.as_mut(), mock_env(), owner, InstantiateMsg {}).unwrap();
+
+        let prover = Addr::unchecked("prover");
+        let key = ("prover".to_string(), "1".to_string());
+        let replica_key = (vec![3; 32], "prover".to_string(), "1".to_string());
+        let public_inputs = PublicInputs {
+            replica_id: PoseidonDomain([1; 32]),
+            seed: [2; 32],
+            tau: Some(Tau {
+                comm_d: Sha256Domain([3; 32]),
+                comm_r: PoseidonDomain([4; 32]),
+            }),
+            k: None,
+        };
+        let reward = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, scheme| {
+            reward_sector(deps.as_mut(), &prover, 1, 2048, scheme, &public_inputs).unwrap();
+        };
+
+        reward(&mut deps, PoRepScheme::StackedDrg);
+        let storage = deps.as_ref().storage;
+        assert_eq!(SECTOR_COMM_D.load(storage, key.clone()).unwrap(), Sha256Domain([3; 32]));
+        assert!(REPLICAS.has(storage, replica_key.clone()));
+
+        // The Poseidon root of a DRG sector is not taken for a SHA-256 data root.
+        reward(&mut deps, PoRepScheme::Drg);
+        let storage = deps.as_ref().storage;
+        assert_eq!(
+            SECTOR_DRG_COMM_D.load(storage, key.clone()).unwrap(),
+            PoseidonDomain([3; 32])
+        );
+        assert!(!SECTOR_COMM_D.has(storage, key.clone()));
+        assert!(!SECTOR_COMM_R.has(storage, key.clone()));
+        assert!(!SECTOR_SIZES.has(storage, key.clone()));
+        assert!(!REPLICAS.has(storage, replica_key));
+
+        reward(&mut deps, PoRepScheme::StackedDrg);
+        assert!(!SECTOR_DRG_COMM_D.has(deps.as_ref().storage, key));
+    }
+
+    #[test]
+    fn test_update_sector_checks_sector() {
+        use contract_auxiliaries::{pieces::zero_comm_d, update::UpdateSetupParams};
+        use cosmwasm_std::{
+            testing::{mock_dependencies, mock_env, mock_info},
+            Binary,
+        };
+
+        use crate::contract::{execute, instantiate};
+        use crate::msg::{ExecuteMsg, InstantiateMsg};
+        use crate::state::{UpdateParameters, SECTOR_COMM_D, SECTOR_COMM_R, SECTOR_SIZES};
+        use crate::ContractError;
+
+        let mut deps = mock_dependencies();
+        let owner = mock_info("owner", &[]);
+        let prover = mock_info("prover", &[]);
+        instantiate(deps.as_mut(), mock_env(), owner.clone(), InstantiateMsg {}).unwrap();
+
+        let msg = ExecuteMsg::SetUpdateParams {
+            sector_size: "sector_size2_kib".to_owned(),
+            params: UpdateParameters {
+                setup_params: UpdateSetupParams {
+                    nodes: 64,
+                    challenges_count: 2,
+                },
+                vk: Binary::default(),
+            },
+        };
+        let err = execute(deps.as_mut(), mock_env(), prover.clone(), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+
+        let comm_r_old = PoseidonDomain([1; 32]);
+        let update = ExecuteMsg::UpdateSector {
+            sector_size: "sector_size2_kib".to_owned(),
+            sector_id: 1,
+            proof_raw: Binary::default(),
+            comm_r_new: PoseidonDomain([2; 32]).into(),
+            comm_d_new: Sha256Domain([3; 32]).into(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), prover.clone(), update.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownSector { sector_id: 1 }));
+
+        let key = ("prover".to_string(), "1".to_string());
+        SECTOR_COMM_R
+            .save(deps.as_mut().storage, key.clone(), &comm_r_old)
+            .unwrap();
+        let err = execute(deps.as_mut(), mock_env(), prover.clone(), update.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidProfile {}));
+
+        // Only the sectors proven to hold zeros can be updated.
+        SECTOR_SIZES
+            .save(deps.as_mut().storage, key.clone(), &2048)
+            .unwrap();
+        let err = execute(deps.as_mut(), mock_env(), prover.clone(), update.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::NotCommittedCapacity { sector_id: 1 }));
+        SECTOR_COMM_D
+            .save(deps.as_mut().storage, key.clone(), &Sha256Domain([3; 32]))
+            .unwrap();
+        let err = execute(deps.as_mut(), mock_env(), prover.clone(), update.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::NotCommittedCapacity { sector_id: 1 }));
+
+        let zero_comm_d = zero_comm_d(2048).unwrap();
+        assert_eq!(
+            zero_comm_d.0,
+            seal::pieces::compute_comm_d(seal::types::SectorSize(2048), &[]).unwrap()
+        );
+        SECTOR_COMM_D
+            .save(deps.as_mut().storage, key.clone(), &zero_comm_d)
+            .unwrap();
+        let err = execute(deps.as_mut(), mock_env(), prover, update).unwrap_err();
+        assert!(err.to_string().contains("invalid verifying key"));
+        assert_eq!(SECTOR_COMM_R.load(deps.as_ref().storage, key).unwrap(), comm_r_old);
+    }
+
+    #[test]
+    fn test_prove_updated_sector() {
+        use contract_auxiliaries::{
+            drg::stacked::verifier_params::Tau, pieces::zero_comm_d, update::UpdatePublicInputs,
+            PoRepScheme,
+        };
+        use cosmwasm_std::{
+            testing::{mock_dependencies, mock_env, mock_info},
+            Addr, Binary,
+        };
+
+        use crate::contract::{instantiate, record_update, reward_sector};
+        use crate::msg::InstantiateMsg;
+        use crate::state::{
+            FileManifest, FileRecord, FileShard, CURRENT_ROUND, FILE_MANIFESTS, REPLICAS,
+            SECTOR_COMM_D, SECTOR_COMM_R, SECTOR_FILES, SECTOR_PROVEN_ROUND,
+            SECTOR_SEALED_COMM_R, USER_REWARD,
+        };
+        use crate::ContractError;
+
+        let mut deps = mock_dependencies();
+        let owner = mock_info("owner", &[]);
+        instantiate(deps.as_mut(), mock_env(), owner.clone(), InstantiateMsg {}).unwrap();
+
+        let prover = Addr::unchecked("prover");
+        let key = ("prover".to_string(), "1".to_string());
+        let zero_comm_d = zero_comm_d(2048).unwrap();
+        let comm_r_old = PoseidonDomain([4; 32]);
+        let comm_d_new = Sha256Domain([5; 32]);
+        let comm_r_new = PoseidonDomain([6; 32]);
+        let sealed = |comm_d, comm_r| PublicInputs {
+            replica_id: PoseidonDomain([1; 32]),
+            seed: [2; 32],
+            tau: Some(Tau { comm_d, comm_r }),
+            k: None,
+        };
+
+        // A committed capacity sector, updated with some data holding a shard of a file.
+        reward_sector(
+            deps.as_mut(),
+            &prover,
+            1,
+            2048,
+            PoRepScheme::StackedDrg,
+            &sealed(zero_comm_d, comm_r_old),
+        )
+        .unwrap();
+        let update = UpdatePublicInputs {
+            comm_r_old,
+            comm_d_new,
+            comm_r_new,
+        };
+        record_update(deps.as_mut().storage, &prover, 1, &update, &zero_comm_d, 1).unwrap();
+        let record = FileRecord {
+            owner: owner.sender,
+            manifest: FileManifest {
+                data_shards: 1,
+                parity_shards: 0,
+                file_size: 127,
+                shards: vec![FileShard {
+                    comm_p: Binary::from(comm_d_new.0.as_slice()),
+                    piece_size: 2048,
+                    prover: "prover".to_string(),
+                    sector_id: 1,
+                }],
+            },
+            stale_shards: vec![],
+        };
+        let storage = deps.as_mut().storage;
+        FILE_MANIFESTS.save(storage, "file".to_string(), &record).unwrap();
+        let file_key = ("prover".to_string(), "1".to_string(), "file".to_string());
+        SECTOR_FILES.save(storage, file_key.clone(), &0).unwrap();
+        assert_eq!(SECTOR_SEALED_COMM_R.load(storage, key.clone()).unwrap(), comm_r_old);
+
+        // A proof of the original seal does not keep the replica of the update live, anyone being
+        // able to rebuild a committed capacity replica.
+        CURRENT_ROUND.save(storage, &3).unwrap();
+        let err = reward_sector(
+            deps.as_mut(),
+            &prover,
+            1,
+            2048,
+            PoRepScheme::StackedDrg,
+            &sealed(zero_comm_d, comm_r_old),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::UpdatedSector { sector_id: 1 }));
+        let storage = deps.as_ref().storage;
+        assert_eq!(SECTOR_PROVEN_ROUND.load(storage, key.clone()).unwrap(), 0);
+        assert_eq!(USER_REWARD.load(storage, "prover".to_string()).unwrap(), 1);
+        assert_eq!(SECTOR_SEALED_COMM_R.load(storage, key.clone()).unwrap(), comm_r_old);
+
+        // A proof against the new comm_r keeps the update in place.
+        reward_sector(
+            deps.as_mut(),
+            &prover,
+            1,
+            2048,
+            PoRepScheme::StackedDrg,
+            &sealed(comm_d_new, comm_r_new),
+        )
+        .unwrap();
+        let storage = deps.as_ref().storage;
+        assert_eq!(SECTOR_PROVEN_ROUND.load(storage, key.clone()).unwrap(), 2);
+        assert_eq!(SECTOR_COMM_D.load(storage, key.clone()).unwrap(), comm_d_new);
+        assert_eq!(SECTOR_COMM_R.load(storage, key.clone()).unwrap(), comm_r_new);
+        assert!(!SECTOR_SEALED_COMM_R.has(storage, key.clone()));
+        assert!(REPLICAS.has(storage, (comm_d_new.0.to_vec(), key.0.clone(), key.1.clone())));
+        assert!(!REPLICAS.has(storage, (zero_comm_d.0.to_vec(), key.0.clone(), key.1.clone())));
+        assert!(SECTOR_FILES.has(storage, file_key.clone()));
+        let stale_shards = FILE_MANIFESTS
+            .load(storage, "file".to_string())
+            .unwrap()
+            .stale_shards;
+        assert!(stale_shards.is_empty());
+
+        // A new seal of the sector replaces the update.
+        let comm_d_resealed = Sha256Domain([7; 32]);
+        reward_sector(
+            deps.as_mut(),
+            &prover,
+            1,
+            2048,
+            PoRepScheme::StackedDrg,
+            &sealed(comm_d_resealed, PoseidonDomain([8; 32])),
+        )
+        .unwrap();
+        let storage = deps.as_ref().storage;
+        assert_eq!(SECTOR_COMM_D.load(storage, key.clone()).unwrap(), comm_d_resealed);
+        assert!(!SECTOR_SEALED_COMM_R.has(storage, key));
+        assert!(!SECTOR_FILES.has(storage, file_key));
+        let stale_shards = FILE_MANIFESTS
+            .load(storage, "file".to_string())
+            .unwrap()
+            .stale_shards;
+        assert_eq!(stale_shards, vec![0]);
+    }
+
+    #[test]
+    fn test_porep_key() {
+        use contract_auxiliaries::utils::ApiVersion;
+
+        use crate::contract::porep_key;
+
+        // The parameters stored with the `SupportedSectorSize` enum were keyed by its discriminant.
+        let porep_id = [5u8; 32];
+        assert_eq!(
+            hex::encode(porep_key(&porep_id, "sector_size2_kib", &ApiVersion::V1_1_0).unwrap()),
+            "2f58f2145e6b257028ec86d6b0c5e3a3279511fc069c00bc4b0142b4d09efbba"
+        );
+        assert_eq!(
+            hex::encode(porep_key(&porep_id, "sector_size32_gib", &ApiVersion::V1_1_0).unwrap()),
+            "8eba03c68b6808ccaf4c8fbe7d298203aeb61b795aca7efcfe9c020d95a72bc5"
+        );
+        assert!(porep_key(&porep_id, "sector_size3_kib", &ApiVersion::V1_1_0).is_err());
+    }
 }
@@ -0,0 +1,31 @@
+use ark_serialize::SerializationError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid {0} point")]
+    InvalidPoint(&'static str),
+
+    #[error("invalid {0} element")]
+    InvalidField(&'static str),
+
+    #[error("invalid encoding: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("invalid hex: {0}")]
+    Hex(#[from] hex::FromHexError),
+
+    #[error("invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("invalid json: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid calldata: {0}")]
+    InvalidCalldata(&'static str),
+
+    #[error("failed to generate public inputs: {0}")]
+    PublicInputs(anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
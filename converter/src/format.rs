@@ -0,0 +1,115 @@
+//! Encodings of the arkworks types, like the proofs and verifying keys converted by
+//! [`crate::groth16`].
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// The canonical binary encodings of arkworks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// Points are stored as their x coordinate and a flag, the default of arkworks.
+    Compressed,
+    /// Points are stored as both coordinates, which is faster to decode.
+    Uncompressed,
+}
+
+pub fn to_bytes<T: CanonicalSerialize>(value: &T, encoding: Encoding) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match encoding {
+        Encoding::Compressed => value.serialize(&mut bytes)?,
+        Encoding::Uncompressed => value.serialize_uncompressed(&mut bytes)?,
+    }
+    Ok(bytes)
+}
+
+/// Decodes `bytes`, checking that the points are on the curve and in the right subgroup.
+pub fn from_bytes<T: CanonicalDeserialize>(bytes: &[u8], encoding: Encoding) -> Result<T> {
+    Ok(match encoding {
+        Encoding::Compressed => T::deserialize(bytes)?,
+        Encoding::Uncompressed => T::deserialize_uncompressed(bytes)?,
+    })
+}
+
+/// Encodes `value` as lowercase hex, without a `0x` prefix.
+pub fn to_hex<T: CanonicalSerialize>(value: &T, encoding: Encoding) -> Result<String> {
+    Ok(hex::encode(to_bytes(value, encoding)?))
+}
+
+/// Decodes hex, with or without a `0x` prefix.
+pub fn from_hex<T: CanonicalDeserialize>(hex: &str, encoding: Encoding) -> Result<T> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    from_bytes(&hex::decode(hex)?, encoding)
+}
+
+/// Encodes `value` as standard, padded base64.
+pub fn to_base64<T: CanonicalSerialize>(value: &T, encoding: Encoding) -> Result<String> {
+    Ok(general_purpose::STANDARD.encode(to_bytes(value, encoding)?))
+}
+
+pub fn from_base64<T: CanonicalDeserialize>(base64: &str, encoding: Encoding) -> Result<T> {
+    from_bytes(&general_purpose::STANDARD.decode(base64)?, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::{Proof, VerifyingKey};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::groth16::{
+        convert_proof, convert_verifying_key,
+        tests::{random_proof, random_verifying_key},
+    };
+
+    #[test]
+    fn test_encodings() {
+        let rng = &mut XorShiftRng::from_seed([7u8; 16]);
+        let proof = convert_proof(&random_proof(rng)).expect("convert_proof failure");
+        let vk = convert_verifying_key(&random_verifying_key(rng))
+            .expect("convert_verifying_key failure");
+
+        for encoding in [Encoding::Compressed, Encoding::Uncompressed] {
+            let bytes = to_bytes(&proof, encoding).expect("to_bytes failure");
+            assert_eq!(
+                bytes.len(),
+                match encoding {
+                    Encoding::Compressed => 192,
+                    Encoding::Uncompressed => 384,
+                }
+            );
+            assert_eq!(
+                from_bytes::<Proof<Bls12_381>>(&bytes, encoding).expect("from_bytes failure"),
+                proof
+            );
+
+            let hex = to_hex(&vk, encoding).expect("to_hex failure");
+            assert_eq!(
+                from_hex::<VerifyingKey<Bls12_381>>(&hex, encoding).expect("from_hex failure"),
+                vk
+            );
+            assert_eq!(
+                from_hex::<VerifyingKey<Bls12_381>>(&format!("0x{}", hex), encoding)
+                    .expect("from_hex failure"),
+                vk
+            );
+
+            let base64 = to_base64(&proof, encoding).expect("to_base64 failure");
+            assert_eq!(
+                from_base64::<Proof<Bls12_381>>(&base64, encoding).expect("from_base64 failure"),
+                proof
+            );
+        }
+
+        // Compressed points cannot be decoded as uncompressed ones.
+        let bytes = to_bytes(&proof, Encoding::Compressed).expect("to_bytes failure");
+        assert!(from_bytes::<Proof<Bls12_381>>(&bytes, Encoding::Uncompressed).is_err());
+        assert!(from_hex::<Proof<Bls12_381>>("0xzz", Encoding::Compressed).is_err());
+    }
+}
@@ -1,77 +1,282 @@
-use ark_bls12_381::{Bls12_381, Fr as Fr_ark, G1Affine as ArkG1Affine, G2Affine as ArkG2Affine};
-use ark_ff::{FromBytes, PrimeField as ArkPrimeField};
-use ark_groth16::{VerifyingKey as ArkVerifyingKey, Proof as ArkProof};
-use blstrs::{Bls12, G1Affine, G2Affine, Scalar as Fr};
+//! Conversions between the Groth16 types of `bellperson`, used by the prover, and of
+//! `ark-groth16`, used by the verifier.
+//!
+//! Points are converted coordinate by coordinate, from the canonical limbs of the field elements.
 
-use bellperson::groth16::{VerifyingKey, Proof};
+use ark_bls12_381::{
+    Bls12_381, Fq, Fq2, Fr as ArkFr, G1Affine as ArkG1Affine, G2Affine as ArkG2Affine,
+};
+use ark_ff::{BigInteger256, BigInteger384, PrimeField, Zero};
+use ark_groth16::{Proof as ArkProof, VerifyingKey as ArkVerifyingKey};
+use bellperson::groth16::{Proof, VerifyingKey};
+use blstrs::{Bls12, Fp, Fp2, G1Affine, G2Affine, Scalar as Fr};
+use group::prime::PrimeCurveAffine;
 
-pub fn convert_fr(scalar: Fr) -> Fr_ark {
-    let scalar_le = scalar.to_bytes_le();
-    Fr_ark::from_le_bytes_mod_order(&scalar_le)
+use crate::error::{Error, Result};
+
+fn limbs<const N: usize>(bytes_le: &[u8]) -> [u64; N] {
+    let mut limbs = [0u64; N];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes_le.chunks(8)) {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(chunk);
+        *limb = u64::from_le_bytes(bytes);
+    }
+    limbs
+}
+
+fn limbs_to_bytes<const N: usize>(limbs: &[u64]) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    for (chunk, limb) in bytes.chunks_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    bytes
+}
+
+pub fn convert_fr(scalar: Fr) -> ArkFr {
+    ArkFr::from_repr(BigInteger256(limbs(&scalar.to_bytes_le())))
+        .expect("scalars are always canonical")
+}
+
+pub fn convert_ark_fr(scalar: ArkFr) -> Fr {
+    Fr::from_bytes_le(&limbs_to_bytes(&scalar.into_repr().0)).expect("scalars are always canonical")
+}
+
+pub fn convert_public_inputs(inputs: &[Fr]) -> Vec<ArkFr> {
+    inputs.iter().copied().map(convert_fr).collect()
+}
+
+pub fn convert_ark_public_inputs(inputs: &[ArkFr]) -> Vec<Fr> {
+    inputs.iter().copied().map(convert_ark_fr).collect()
+}
+
+fn convert_fp(fp: Fp) -> Result<Fq> {
+    Fq::from_repr(BigInteger384(limbs(&fp.to_bytes_le()))).ok_or(Error::InvalidField("Fq"))
 }
 
-pub fn convert_g1(point: G1Affine) -> Result<ArkG1Affine, String> {
-    let x = point.x();
-    let y = point.y();
+fn convert_ark_fq(fq: Fq) -> Result<Fp> {
+    Option::from(Fp::from_bytes_le(&limbs_to_bytes(&fq.into_repr().0)))
+        .ok_or(Error::InvalidField("Fp"))
+}
+
+fn convert_fp2(fp2: Fp2) -> Result<Fq2> {
+    Ok(Fq2::new(convert_fp(fp2.c0())?, convert_fp(fp2.c1())?))
+}
 
-    let x_le = x.to_bytes_le();
-    let y_le = y.to_bytes_le();
+fn convert_ark_fq2(fq2: Fq2) -> Result<Fp2> {
+    Ok(Fp2::new(convert_ark_fq(fq2.c0)?, convert_ark_fq(fq2.c1)?))
+}
 
-    let mut p_le = Vec::new();
-    p_le.extend_from_slice(&x_le);
-    p_le.extend_from_slice(&y_le);
-    p_le.push(0);
+pub fn convert_g1(point: G1Affine) -> Result<ArkG1Affine> {
+    if point.is_identity().into() {
+        return Ok(ArkG1Affine::zero());
+    }
 
-    ArkG1Affine::read(p_le.as_slice()).map_err(|_e| String::from("Failed to convert BLS G1Affine"))
+    let point = ArkG1Affine::new(convert_fp(point.x())?, convert_fp(point.y())?, false);
+    if !point.is_on_curve() {
+        return Err(Error::InvalidPoint("G1"));
+    }
+    Ok(point)
 }
 
-pub fn convert_g2(point: G2Affine) -> Result<ArkG2Affine, String> {
-    let ax = point.x();
-    let ay = point.y();
+pub fn convert_ark_g1(point: ArkG1Affine) -> Result<G1Affine> {
+    if point.is_zero() {
+        return Ok(G1Affine::identity());
+    }
 
-    let ax_le_0 = ax.c0().to_bytes_le();
-    let ax_le_1 = ax.c1().to_bytes_le();
-    let ay_le_0 = ay.c0().to_bytes_le();
-    let ay_le_1 = ay.c1().to_bytes_le();
+    let point =
+        G1Affine::from_raw_unchecked(convert_ark_fq(point.x)?, convert_ark_fq(point.y)?, false);
+    if !bool::from(point.is_on_curve() & point.is_torsion_free()) {
+        return Err(Error::InvalidPoint("G1"));
+    }
+    Ok(point)
+}
 
-    let mut pa_le = Vec::new();
-    pa_le.extend_from_slice(&ax_le_0);
-    pa_le.extend_from_slice(&ax_le_1);
-    pa_le.extend_from_slice(&ay_le_0);
-    pa_le.extend_from_slice(&ay_le_1);
-    pa_le.push(0);
+pub fn convert_g2(point: G2Affine) -> Result<ArkG2Affine> {
+    if point.is_identity().into() {
+        return Ok(ArkG2Affine::zero());
+    }
 
-    ArkG2Affine::read(pa_le.as_slice()).map_err(|_e| String::from("Failed to convert BLS G2Affine"))
+    let point = ArkG2Affine::new(convert_fp2(point.x())?, convert_fp2(point.y())?, false);
+    if !point.is_on_curve() {
+        return Err(Error::InvalidPoint("G2"));
+    }
+    Ok(point)
 }
 
-pub fn convert_verifying_key(
-    vk: &VerifyingKey<Bls12>,
-) -> Result<ArkVerifyingKey<Bls12_381>, String> {
-    let alpha_g1 = convert_g1(vk.alpha_g1.clone())?;
-    let beta_g2 = convert_g2(vk.beta_g2.clone())?;
-    let gamma_g2 = convert_g2(vk.gamma_g2.clone())?;
-    let delta_g2 = convert_g2(vk.delta_g2.clone())?;
-    let mut gamma_abc_g1 = Vec::new();
-    for i in 0..vk.ic.len(){
-        let new_ic = convert_g1(vk.ic[i].clone())?;
-        gamma_abc_g1.push(new_ic);
+pub fn convert_ark_g2(point: ArkG2Affine) -> Result<G2Affine> {
+    if point.is_zero() {
+        return Ok(G2Affine::identity());
     }
+
+    let point =
+        G2Affine::from_raw_unchecked(convert_ark_fq2(point.x)?, convert_ark_fq2(point.y)?, false);
+    if !bool::from(point.is_on_curve() & point.is_torsion_free()) {
+        return Err(Error::InvalidPoint("G2"));
+    }
+    Ok(point)
+}
+
+pub fn convert_verifying_key(vk: &VerifyingKey<Bls12>) -> Result<ArkVerifyingKey<Bls12_381>> {
     Ok(ArkVerifyingKey::<Bls12_381> {
-        alpha_g1,
-        beta_g2,
-        gamma_g2,
-        delta_g2,
-        gamma_abc_g1,
+        alpha_g1: convert_g1(vk.alpha_g1)?,
+        beta_g2: convert_g2(vk.beta_g2)?,
+        gamma_g2: convert_g2(vk.gamma_g2)?,
+        delta_g2: convert_g2(vk.delta_g2)?,
+        gamma_abc_g1: vk
+            .ic
+            .iter()
+            .copied()
+            .map(convert_g1)
+            .collect::<Result<_>>()?,
     })
 }
 
-pub fn convert_proof(
-    proof: &Proof<Bls12>
-) -> Result<ArkProof<Bls12_381>, String>{
-    let a = convert_g1(proof.a.clone())?;
-    let b = convert_g2(proof.b.clone())?;
-    let c = convert_g1(proof.c.clone())?;
-    Ok(ArkProof::<Bls12_381>{
-        a, b, c
+/// Converts a verifying key back to `bellperson`.
+///
+/// The arkworks key has no `beta_g1` and `delta_g1`, which are not used for verification, so they
+/// are left at the identity.
+pub fn convert_ark_verifying_key(vk: &ArkVerifyingKey<Bls12_381>) -> Result<VerifyingKey<Bls12>> {
+    Ok(VerifyingKey::<Bls12> {
+        alpha_g1: convert_ark_g1(vk.alpha_g1)?,
+        beta_g1: G1Affine::identity(),
+        beta_g2: convert_ark_g2(vk.beta_g2)?,
+        gamma_g2: convert_ark_g2(vk.gamma_g2)?,
+        delta_g1: G1Affine::identity(),
+        delta_g2: convert_ark_g2(vk.delta_g2)?,
+        ic: vk
+            .gamma_abc_g1
+            .iter()
+            .copied()
+            .map(convert_ark_g1)
+            .collect::<Result<_>>()?,
     })
-}
\ No newline at end of file
+}
+
+pub fn convert_proof(proof: &Proof<Bls12>) -> Result<ArkProof<Bls12_381>> {
+    Ok(ArkProof::<Bls12_381> {
+        a: convert_g1(proof.a)?,
+        b: convert_g2(proof.b)?,
+        c: convert_g1(proof.c)?,
+    })
+}
+
+pub fn convert_ark_proof(proof: &ArkProof<Bls12_381>) -> Result<Proof<Bls12>> {
+    Ok(Proof::<Bls12> {
+        a: convert_ark_g1(proof.a)?,
+        b: convert_ark_g2(proof.b)?,
+        c: convert_ark_g1(proof.c)?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use blstrs::{G1Projective, G2Projective};
+    use group::{ff::Field, Curve, Group};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::serializer::{serialize_proof, serialize_verifying_key};
+
+    const TEST_SEED: [u8; 16] = [
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ];
+
+    pub(crate) fn random_proof(rng: &mut XorShiftRng) -> Proof<Bls12> {
+        Proof {
+            a: G1Projective::random(&mut *rng).to_affine(),
+            b: G2Projective::random(&mut *rng).to_affine(),
+            c: G1Projective::random(&mut *rng).to_affine(),
+        }
+    }
+
+    pub(crate) fn random_verifying_key(rng: &mut XorShiftRng) -> VerifyingKey<Bls12> {
+        VerifyingKey {
+            alpha_g1: G1Projective::random(&mut *rng).to_affine(),
+            beta_g1: G1Affine::identity(),
+            beta_g2: G2Projective::random(&mut *rng).to_affine(),
+            gamma_g2: G2Projective::random(&mut *rng).to_affine(),
+            delta_g1: G1Affine::identity(),
+            delta_g2: G2Projective::random(&mut *rng).to_affine(),
+            ic: (0..3)
+                .map(|_| G1Projective::random(&mut *rng).to_affine())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_convert_round_trip() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+
+        let proof = random_proof(rng);
+        let ark_proof = convert_proof(&proof).expect("convert_proof failure");
+        assert_eq!(
+            convert_ark_proof(&ark_proof).expect("convert_ark_proof failure"),
+            proof
+        );
+
+        let vk = random_verifying_key(rng);
+        let ark_vk = convert_verifying_key(&vk).expect("convert_verifying_key failure");
+        assert!(
+            convert_ark_verifying_key(&ark_vk).expect("convert_ark_verifying_key failure") == vk
+        );
+
+        let inputs = (0..4).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+        assert_eq!(
+            convert_ark_public_inputs(&convert_public_inputs(&inputs)),
+            inputs
+        );
+
+        assert!(convert_g1(G1Affine::identity())
+            .expect("convert_g1 failure")
+            .is_zero());
+        assert!(bool::from(
+            convert_ark_g2(ArkG2Affine::zero())
+                .expect("convert_ark_g2 failure")
+                .is_identity()
+        ));
+    }
+
+    #[test]
+    fn test_convert_matches_serializer() {
+        use ark_ff::FromBytes;
+
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+
+        // The conversions give the same points as reading the little-endian serialization.
+        let proof = random_proof(rng);
+        let proof_le = serialize_proof(&proof);
+        let ark_proof = convert_proof(&proof).expect("convert_proof failure");
+        assert_eq!(
+            ArkG1Affine::read(&proof_le[..97]).expect("failed to read a"),
+            ark_proof.a
+        );
+        assert_eq!(
+            ArkG2Affine::read(&proof_le[97..290]).expect("failed to read b"),
+            ark_proof.b
+        );
+        assert_eq!(
+            ArkG1Affine::read(&proof_le[290..]).expect("failed to read c"),
+            ark_proof.c
+        );
+
+        let vk = random_verifying_key(rng);
+        let vk_le = serialize_verifying_key(&vk);
+        let ark_vk = convert_verifying_key(&vk).expect("convert_verifying_key failure");
+        assert_eq!(
+            ArkG1Affine::read(&vk_le[..97]).expect("failed to read alpha_g1"),
+            ark_vk.alpha_g1
+        );
+    }
+
+    #[test]
+    fn test_convert_invalid_point() {
+        let point = ArkG1Affine::new(Fq::from(1u64), Fq::from(1u64), false);
+        assert!(matches!(
+            convert_ark_g1(point),
+            Err(Error::InvalidPoint("G1"))
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+//! A JSON format of proofs, verifying keys and public inputs for tooling.
+//!
+//! Field elements are `0x` prefixed big-endian hex strings, and the point at infinity has all
+//! coordinates set to zero, as expected by the EVM precompiles.
+
+use std::convert::TryFrom;
+
+use ark_bls12_381::{
+    Bls12_381, Fq, Fq2, Fr as ArkFr, G1Affine as ArkG1Affine, G2Affine as ArkG2Affine,
+};
+use ark_ec::{models::SWModelParameters, short_weierstrass_jacobian::GroupAffine};
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_groth16::{Proof as ArkProof, VerifyingKey as ArkVerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct G1Json {
+    pub x: String,
+    pub y: String,
+}
+
+/// A point of G2, with the coordinates in `Fq2` as `[c0, c1]`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct G2Json {
+    pub x: [String; 2],
+    pub y: [String; 2],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofJson {
+    pub a: G1Json,
+    pub b: G2Json,
+    pub c: G1Json,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyingKeyJson {
+    pub alpha_g1: G1Json,
+    pub beta_g2: G2Json,
+    pub gamma_g2: G2Json,
+    pub delta_g2: G2Json,
+    pub gamma_abc_g1: Vec<G1Json>,
+}
+
+pub fn field_to_hex<F: PrimeField>(element: F) -> String {
+    format!("0x{}", hex::encode(element.into_repr().to_bytes_be()))
+}
+
+/// Parses a field element from hex, which must be canonical.
+pub fn field_from_hex<F: PrimeField>(hex: &str, name: &'static str) -> Result<F> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    let bytes = if hex.len() % 2 == 1 {
+        hex::decode(format!("0{}", hex))?
+    } else {
+        hex::decode(hex)?
+    };
+    field_from_be_bytes(&bytes, name)
+}
+
+/// Parses a field element from big-endian bytes, which must be canonical.
+pub fn field_from_be_bytes<F: PrimeField>(bytes: &[u8], name: &'static str) -> Result<F> {
+    let mut repr = F::BigInt::default();
+    let len = repr.as_ref().len() * 8;
+    if bytes.len() > len {
+        return Err(Error::InvalidField(name));
+    }
+    let mut bytes_le = bytes.to_vec();
+    bytes_le.reverse();
+    bytes_le.resize(len, 0);
+    for (limb, chunk) in repr.as_mut().iter_mut().zip(bytes_le.chunks(8)) {
+        let mut limb_bytes = [0u8; 8];
+        limb_bytes.copy_from_slice(chunk);
+        *limb = u64::from_le_bytes(limb_bytes);
+    }
+    F::from_repr(repr).ok_or(Error::InvalidField(name))
+}
+
+/// Checks a point parsed from JSON, where all zero coordinates are the point at infinity.
+fn checked_point<P: SWModelParameters>(
+    x: P::BaseField,
+    y: P::BaseField,
+    name: &'static str,
+) -> Result<GroupAffine<P>> {
+    if x.is_zero() && y.is_zero() {
+        return Ok(GroupAffine::zero());
+    }
+
+    let point = GroupAffine::new(x, y, false);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint(name));
+    }
+    Ok(point)
+}
+
+impl From<&ArkG1Affine> for G1Json {
+    fn from(point: &ArkG1Affine) -> Self {
+        let (x, y) = if point.is_zero() {
+            (Fq::zero(), Fq::zero())
+        } else {
+            (point.x, point.y)
+        };
+        G1Json {
+            x: field_to_hex(x),
+            y: field_to_hex(y),
+        }
+    }
+}
+
+impl TryFrom<&G1Json> for ArkG1Affine {
+    type Error = Error;
+
+    fn try_from(point: &G1Json) -> Result<Self> {
+        checked_point(
+            field_from_hex(&point.x, "Fq")?,
+            field_from_hex(&point.y, "Fq")?,
+            "G1",
+        )
+    }
+}
+
+fn fq2_to_hex(element: Fq2) -> [String; 2] {
+    [field_to_hex(element.c0), field_to_hex(element.c1)]
+}
+
+fn fq2_from_hex(element: &[String; 2]) -> Result<Fq2> {
+    Ok(Fq2::new(
+        field_from_hex(&element[0], "Fq")?,
+        field_from_hex(&element[1], "Fq")?,
+    ))
+}
+
+impl From<&ArkG2Affine> for G2Json {
+    fn from(point: &ArkG2Affine) -> Self {
+        let (x, y) = if point.is_zero() {
+            (Fq2::zero(), Fq2::zero())
+        } else {
+            (point.x, point.y)
+        };
+        G2Json {
+            x: fq2_to_hex(x),
+            y: fq2_to_hex(y),
+        }
+    }
+}
+
+impl TryFrom<&G2Json> for ArkG2Affine {
+    type Error = Error;
+
+    fn try_from(point: &G2Json) -> Result<Self> {
+        checked_point(fq2_from_hex(&point.x)?, fq2_from_hex(&point.y)?, "G2")
+    }
+}
+
+impl From<&ArkProof<Bls12_381>> for ProofJson {
+    fn from(proof: &ArkProof<Bls12_381>) -> Self {
+        ProofJson {
+            a: (&proof.a).into(),
+            b: (&proof.b).into(),
+            c: (&proof.c).into(),
+        }
+    }
+}
+
+impl TryFrom<&ProofJson> for ArkProof<Bls12_381> {
+    type Error = Error;
+
+    fn try_from(proof: &ProofJson) -> Result<Self> {
+        Ok(ArkProof {
+            a: (&proof.a).try_into()?,
+            b: (&proof.b).try_into()?,
+            c: (&proof.c).try_into()?,
+        })
+    }
+}
+
+impl From<&ArkVerifyingKey<Bls12_381>> for VerifyingKeyJson {
+    fn from(vk: &ArkVerifyingKey<Bls12_381>) -> Self {
+        VerifyingKeyJson {
+            alpha_g1: (&vk.alpha_g1).into(),
+            beta_g2: (&vk.beta_g2).into(),
+            gamma_g2: (&vk.gamma_g2).into(),
+            delta_g2: (&vk.delta_g2).into(),
+            gamma_abc_g1: vk.gamma_abc_g1.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<&VerifyingKeyJson> for ArkVerifyingKey<Bls12_381> {
+    type Error = Error;
+
+    fn try_from(vk: &VerifyingKeyJson) -> Result<Self> {
+        Ok(ArkVerifyingKey {
+            alpha_g1: (&vk.alpha_g1).try_into()?,
+            beta_g2: (&vk.beta_g2).try_into()?,
+            gamma_g2: (&vk.gamma_g2).try_into()?,
+            delta_g2: (&vk.delta_g2).try_into()?,
+            gamma_abc_g1: vk
+                .gamma_abc_g1
+                .iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+pub fn proof_to_json(proof: &ArkProof<Bls12_381>) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&ProofJson::from(proof))?)
+}
+
+pub fn proof_from_json(json: &str) -> Result<ArkProof<Bls12_381>> {
+    let proof: ProofJson = serde_json::from_str(json)?;
+    ArkProof::try_from(&proof)
+}
+
+pub fn verifying_key_to_json(vk: &ArkVerifyingKey<Bls12_381>) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&VerifyingKeyJson::from(vk))?)
+}
+
+pub fn verifying_key_from_json(json: &str) -> Result<ArkVerifyingKey<Bls12_381>> {
+    let vk: VerifyingKeyJson = serde_json::from_str(json)?;
+    ArkVerifyingKey::try_from(&vk)
+}
+
+/// Public inputs as a JSON array of field elements.
+pub fn public_inputs_to_json(inputs: &[ArkFr]) -> Result<String> {
+    let inputs = inputs.iter().copied().map(field_to_hex).collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&inputs)?)
+}
+
+pub fn public_inputs_from_json(json: &str) -> Result<Vec<ArkFr>> {
+    let inputs: Vec<String> = serde_json::from_str(json)?;
+    inputs
+        .iter()
+        .map(|input| field_from_hex(input, "Fr"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_ff::{FpParameters, One};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::groth16::{
+        convert_proof, convert_public_inputs, convert_verifying_key,
+        tests::{random_proof, random_verifying_key},
+    };
+
+    #[test]
+    fn test_json_round_trip() {
+        let rng = &mut XorShiftRng::from_seed([7u8; 16]);
+        let mut proof = convert_proof(&random_proof(rng)).expect("convert_proof failure");
+        let vk = convert_verifying_key(&random_verifying_key(rng))
+            .expect("convert_verifying_key failure");
+
+        let json = proof_to_json(&proof).expect("proof_to_json failure");
+        assert_eq!(
+            proof_from_json(&json).expect("proof_from_json failure"),
+            proof
+        );
+
+        let json = verifying_key_to_json(&vk).expect("verifying_key_to_json failure");
+        assert_eq!(
+            verifying_key_from_json(&json).expect("verifying_key_from_json failure"),
+            vk
+        );
+
+        let inputs =
+            convert_public_inputs(&[blstrs::Scalar::from(5u64), -blstrs::Scalar::from(1u64)]);
+        let json = public_inputs_to_json(&inputs).expect("public_inputs_to_json failure");
+        assert_eq!(
+            public_inputs_from_json(&json).expect("public_inputs_from_json failure"),
+            inputs
+        );
+
+        // The point at infinity has zero coordinates.
+        proof.c = ArkG1Affine::zero();
+        let json = ProofJson::from(&proof);
+        assert_eq!(json.c.x, format!("0x{}", "0".repeat(96)));
+        assert_eq!(
+            ArkProof::try_from(&json).expect("try_from failure").c,
+            ArkG1Affine::zero()
+        );
+    }
+
+    #[test]
+    fn test_json_invalid() {
+        let modulus = format!(
+            "0x{}",
+            hex::encode(<ArkFr as PrimeField>::Params::MODULUS.to_bytes_be())
+        );
+        assert!(field_from_hex::<ArkFr>(&modulus, "Fr").is_err());
+        assert_eq!(
+            field_from_hex::<ArkFr>("0x1", "Fr").expect("field_from_hex failure"),
+            ArkFr::one()
+        );
+
+        let point = G1Json {
+            x: "0x1".to_string(),
+            y: "0x1".to_string(),
+        };
+        assert!(matches!(
+            ArkG1Affine::try_from(&point),
+            Err(Error::InvalidPoint("G1"))
+        ));
+        assert!(proof_from_json("{}").is_err());
+    }
+}
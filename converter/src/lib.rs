@@ -1,2 +1,8 @@
+pub mod error;
+pub mod format;
 pub mod groth16;
-pub mod serializer;
\ No newline at end of file
+pub mod json;
+pub mod serializer;
+pub mod solidity;
+
+pub use error::{Error, Result};
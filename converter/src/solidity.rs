@@ -0,0 +1,453 @@
+//! A Solidity verifier of the Groth16 proofs, with the BLS12-381 precompiles of EIP-2537, and the
+//! encoding of its calldata.
+//!
+//! The precompiles take an `Fp` element as 64 big-endian bytes, the first 16 being zero, an `Fp2`
+//! element as `c0 || c1` and a point as `x || y`, with the point at infinity being all zeros.
+
+use ark_bls12_381::{Bls12_381, Fq, Fq2, Fr as ArkFr, G1Affine, G2Affine};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{BigInteger, One, PrimeField, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use contract_auxiliaries::{
+    domain::Domain,
+    drg::stacked::{
+        verifier_params::{PublicInputs, PublicParams},
+        VerifierStackedDrg,
+    },
+};
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    error::{Error, Result},
+    json::field_from_be_bytes,
+};
+
+/// The Solidity signature of the verifier function.
+pub const VERIFY_PROOF_SIGNATURE: &str = "verifyProof(bytes,uint256[])";
+
+const FQ_LENGTH: usize = 64;
+const FQ_PADDING: usize = FQ_LENGTH - 48;
+const G1_LENGTH: usize = 2 * FQ_LENGTH;
+const G2_LENGTH: usize = 4 * FQ_LENGTH;
+const WORD_LENGTH: usize = 32;
+
+/// Length of an encoded proof, `a || b || c`.
+pub const PROOF_LENGTH: usize = 2 * G1_LENGTH + G2_LENGTH;
+
+const TEMPLATE: &str = include_str!("verifier.sol");
+
+pub fn encode_fq(element: Fq) -> [u8; FQ_LENGTH] {
+    let mut bytes = [0u8; FQ_LENGTH];
+    bytes[FQ_PADDING..].copy_from_slice(&element.into_repr().to_bytes_be());
+    bytes
+}
+
+fn decode_fq(bytes: &[u8]) -> Result<Fq> {
+    if bytes[..FQ_PADDING].iter().any(|b| *b != 0) {
+        return Err(Error::InvalidField("Fq"));
+    }
+    field_from_be_bytes(&bytes[FQ_PADDING..], "Fq")
+}
+
+fn encode_fq2(element: Fq2, bytes: &mut [u8]) {
+    bytes[..FQ_LENGTH].copy_from_slice(&encode_fq(element.c0));
+    bytes[FQ_LENGTH..].copy_from_slice(&encode_fq(element.c1));
+}
+
+fn decode_fq2(bytes: &[u8]) -> Result<Fq2> {
+    Ok(Fq2::new(
+        decode_fq(&bytes[..FQ_LENGTH])?,
+        decode_fq(&bytes[FQ_LENGTH..])?,
+    ))
+}
+
+pub fn encode_g1(point: &G1Affine) -> [u8; G1_LENGTH] {
+    let mut bytes = [0u8; G1_LENGTH];
+    if !point.is_zero() {
+        bytes[..FQ_LENGTH].copy_from_slice(&encode_fq(point.x));
+        bytes[FQ_LENGTH..].copy_from_slice(&encode_fq(point.y));
+    }
+    bytes
+}
+
+/// Decodes a point of G1, checking it like the precompiles do.
+pub fn decode_g1(bytes: &[u8]) -> Result<G1Affine> {
+    if bytes.len() != G1_LENGTH {
+        return Err(Error::InvalidPoint("G1"));
+    }
+    if bytes.iter().all(|b| *b == 0) {
+        return Ok(G1Affine::zero());
+    }
+
+    let point = G1Affine::new(
+        decode_fq(&bytes[..FQ_LENGTH])?,
+        decode_fq(&bytes[FQ_LENGTH..])?,
+        false,
+    );
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint("G1"));
+    }
+    Ok(point)
+}
+
+pub fn encode_g2(point: &G2Affine) -> [u8; G2_LENGTH] {
+    let mut bytes = [0u8; G2_LENGTH];
+    if !point.is_zero() {
+        encode_fq2(point.x, &mut bytes[..2 * FQ_LENGTH]);
+        encode_fq2(point.y, &mut bytes[2 * FQ_LENGTH..]);
+    }
+    bytes
+}
+
+/// Decodes a point of G2, checking it like the precompiles do.
+pub fn decode_g2(bytes: &[u8]) -> Result<G2Affine> {
+    if bytes.len() != G2_LENGTH {
+        return Err(Error::InvalidPoint("G2"));
+    }
+    if bytes.iter().all(|b| *b == 0) {
+        return Ok(G2Affine::zero());
+    }
+
+    let point = G2Affine::new(
+        decode_fq2(&bytes[..2 * FQ_LENGTH])?,
+        decode_fq2(&bytes[2 * FQ_LENGTH..])?,
+        false,
+    );
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint("G2"));
+    }
+    Ok(point)
+}
+
+/// Encodes `proof` as the `proof` argument of the verifier.
+pub fn encode_proof(proof: &Proof<Bls12_381>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(PROOF_LENGTH);
+    bytes.extend_from_slice(&encode_g1(&proof.a));
+    bytes.extend_from_slice(&encode_g2(&proof.b));
+    bytes.extend_from_slice(&encode_g1(&proof.c));
+    bytes
+}
+
+/// The function selector of [`VERIFY_PROOF_SIGNATURE`].
+pub fn selector() -> [u8; 4] {
+    let hash = Keccak256::digest(VERIFY_PROOF_SIGNATURE.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn encode_word(value: usize) -> [u8; WORD_LENGTH] {
+    let mut word = [0u8; WORD_LENGTH];
+    word[WORD_LENGTH - 8..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+/// Encodes the calldata of a call of `verifyProof(proof, inputs)`.
+pub fn encode_calldata(proof: &Proof<Bls12_381>, inputs: &[ArkFr]) -> Vec<u8> {
+    let proof = encode_proof(proof);
+    let inputs_offset = 2 * WORD_LENGTH + WORD_LENGTH + PROOF_LENGTH;
+
+    let mut calldata = Vec::with_capacity(4 + inputs_offset + WORD_LENGTH * (inputs.len() + 1));
+    calldata.extend_from_slice(&selector());
+    calldata.extend_from_slice(&encode_word(2 * WORD_LENGTH));
+    calldata.extend_from_slice(&encode_word(inputs_offset));
+    calldata.extend_from_slice(&encode_word(PROOF_LENGTH));
+    calldata.extend_from_slice(&proof);
+    calldata.extend_from_slice(&encode_word(inputs.len()));
+    for input in inputs {
+        calldata.extend_from_slice(&input.into_repr().to_bytes_be());
+    }
+    calldata
+}
+
+/// Encodes the calldata verifying a stacked DRG proof, with the public inputs generated by
+/// [`VerifierStackedDrg::generate_public_inputs`] for the partition `k`.
+pub fn encode_stacked_calldata<H: Domain, G: Domain>(
+    proof: &Proof<Bls12_381>,
+    pub_in: &PublicInputs<H, G>,
+    pub_params: &PublicParams<H>,
+    k: Option<usize>,
+) -> Result<Vec<u8>> {
+    let inputs = VerifierStackedDrg::<H, G>::generate_public_inputs(pub_in, pub_params, k)
+        .map_err(Error::PublicInputs)?;
+    Ok(encode_calldata(proof, &inputs))
+}
+
+/// The constants of the verifier for a verifying key, as they are embedded in the contract.
+struct VerifierConstants {
+    alpha_g1: [u8; G1_LENGTH],
+    neg_beta_g2: [u8; G2_LENGTH],
+    neg_gamma_g2: [u8; G2_LENGTH],
+    neg_delta_g2: [u8; G2_LENGTH],
+    ic: Vec<u8>,
+}
+
+impl VerifierConstants {
+    fn new(vk: &VerifyingKey<Bls12_381>) -> Self {
+        VerifierConstants {
+            alpha_g1: encode_g1(&vk.alpha_g1),
+            neg_beta_g2: encode_g2(&-vk.beta_g2),
+            neg_gamma_g2: encode_g2(&-vk.gamma_g2),
+            neg_delta_g2: encode_g2(&-vk.delta_g2),
+            ic: vk.gamma_abc_g1.iter().flat_map(encode_g1).collect(),
+        }
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.ic.len() / G1_LENGTH - 1
+    }
+}
+
+/// Generates the source of a Solidity contract `contract_name`, verifying the proofs of `vk`.
+///
+/// The verifying key is embedded in the bytecode, which bounds the number of public inputs by the
+/// contract size limit.
+pub fn generate_verifier(vk: &VerifyingKey<Bls12_381>, contract_name: &str) -> String {
+    let constants = VerifierConstants::new(vk);
+    TEMPLATE
+        .replace("__CONTRACT_NAME__", contract_name)
+        .replace("__NUM_INPUTS__", &constants.num_inputs().to_string())
+        .replace("__ALPHA_G1__", &hex::encode(constants.alpha_g1))
+        .replace("__NEG_BETA_G2__", &hex::encode(constants.neg_beta_g2))
+        .replace("__NEG_GAMMA_G2__", &hex::encode(constants.neg_gamma_g2))
+        .replace("__NEG_DELTA_G2__", &hex::encode(constants.neg_delta_g2))
+        .replace("__IC__", &hex::encode(&constants.ic))
+}
+
+fn read_word(calldata: &[u8], offset: usize) -> Result<usize> {
+    let word = calldata
+        .get(offset..offset + WORD_LENGTH)
+        .ok_or(Error::InvalidCalldata("out of bounds"))?;
+    if word[..WORD_LENGTH - 8].iter().any(|b| *b != 0) {
+        return Err(Error::InvalidCalldata("offset or length too large"));
+    }
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&word[WORD_LENGTH - 8..]);
+    usize::try_from(u64::from_be_bytes(bytes))
+        .map_err(|_| Error::InvalidCalldata("offset or length too large"))
+}
+
+/// Returns the `len` bytes after the length prefix at `offset` of `args`.
+fn read_dynamic(args: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    let start = offset
+        .checked_add(WORD_LENGTH)
+        .ok_or(Error::InvalidCalldata("out of bounds"))?;
+    start
+        .checked_add(len)
+        .and_then(|end| args.get(start..end))
+        .ok_or(Error::InvalidCalldata("out of bounds"))
+}
+
+/// Runs the verifier of `vk` on `calldata`, as the generated contract does.
+///
+/// This returns an error where the contract reverts, and `false` where it returns `false`.
+pub fn verify_calldata(vk: &VerifyingKey<Bls12_381>, calldata: &[u8]) -> Result<bool> {
+    if calldata.get(..4) != Some(&selector()[..]) {
+        return Err(Error::InvalidCalldata("unknown function selector"));
+    }
+    let args = &calldata[4..];
+
+    let proof_offset = read_word(args, 0)?;
+    let proof_len = read_word(args, proof_offset)?;
+    let proof = read_dynamic(args, proof_offset, proof_len)?;
+
+    let inputs_offset = read_word(args, WORD_LENGTH)?;
+    let num_inputs = read_word(args, inputs_offset)?;
+    let inputs_len = num_inputs
+        .checked_mul(WORD_LENGTH)
+        .ok_or(Error::InvalidCalldata("out of bounds"))?;
+    let inputs = read_dynamic(args, inputs_offset, inputs_len)?;
+
+    let constants = VerifierConstants::new(vk);
+    if proof_len != PROOF_LENGTH {
+        return Err(Error::InvalidCalldata("invalid proof length"));
+    }
+    if num_inputs != constants.num_inputs() {
+        return Err(Error::InvalidCalldata("invalid number of inputs"));
+    }
+
+    // The multi-scalar multiplication, from the embedded points.
+    let ic = constants
+        .ic
+        .chunks(G1_LENGTH)
+        .map(decode_g1)
+        .collect::<Result<Vec<_>>>()?;
+    let mut vk_x = ic[0].into_projective();
+    for (input, point) in inputs.chunks(WORD_LENGTH).zip(&ic[1..]) {
+        let input = field_from_be_bytes::<ArkFr>(input, "Fr")
+            .map_err(|_| Error::InvalidCalldata("input not in the scalar field"))?;
+        vk_x += point.mul(input.into_repr());
+    }
+    let vk_x = vk_x.into_affine();
+
+    // The pairing check, where the precompile fails for invalid points of the proof.
+    let points = (
+        decode_g1(&proof[..G1_LENGTH]),
+        decode_g2(&proof[G1_LENGTH..G1_LENGTH + G2_LENGTH]),
+        decode_g1(&proof[G1_LENGTH + G2_LENGTH..]),
+    );
+    let (a, b, c) = match points {
+        (Ok(a), Ok(b), Ok(c)) => (a, b, c),
+        _ => return Ok(false),
+    };
+    let pairs = [
+        (a.into(), b.into()),
+        (
+            decode_g1(&constants.alpha_g1)?.into(),
+            decode_g2(&constants.neg_beta_g2)?.into(),
+        ),
+        (vk_x.into(), decode_g2(&constants.neg_gamma_g2)?.into()),
+        (c.into(), decode_g2(&constants.neg_delta_g2)?.into()),
+    ];
+    Ok(Bls12_381::product_of_pairings(&pairs).is_one())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_ff::FpParameters;
+    use ark_groth16::{prepare_verifying_key, verify_proof};
+    use bellperson::{
+        groth16::{create_random_proof, generate_random_parameters},
+        Circuit, ConstraintSystem, SynthesisError,
+    };
+    use blstrs::{Bls12, Scalar};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::groth16::{convert_proof, convert_public_inputs, convert_verifying_key};
+
+    const TEST_SEED: [u8; 16] = [
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ];
+
+    /// Proves the knowledge of `x` and `y` with `x * y == product` and `x + y == sum`.
+    struct ProductCircuit {
+        x: Option<Scalar>,
+        y: Option<Scalar>,
+    }
+
+    impl Circuit<Scalar> for ProductCircuit {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> std::result::Result<(), SynthesisError> {
+            let values = self.x.zip(self.y);
+            let value = |f: fn((Scalar, Scalar)) -> Scalar| {
+                values.map(f).ok_or(SynthesisError::AssignmentMissing)
+            };
+
+            let x = cs.alloc(|| "x", || value(|(x, _)| x))?;
+            let y = cs.alloc(|| "y", || value(|(_, y)| y))?;
+            let product = cs.alloc_input(|| "product", || value(|(x, y)| x * y))?;
+            let sum = cs.alloc_input(|| "sum", || value(|(x, y)| x + y))?;
+            cs.enforce(|| "product", |lc| lc + x, |lc| lc + y, |lc| lc + product);
+            cs.enforce(
+                || "sum",
+                |lc| lc + x + y,
+                |lc| lc + CS::one(),
+                |lc| lc + sum,
+            );
+            Ok(())
+        }
+    }
+
+    fn prove() -> (VerifyingKey<Bls12_381>, Proof<Bls12_381>, Vec<ArkFr>) {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let params =
+            generate_random_parameters::<Bls12, _, _>(ProductCircuit { x: None, y: None }, rng)
+                .expect("generate_random_parameters failure");
+        let (x, y) = (Scalar::from(3u64), Scalar::from(7u64));
+        let proof = create_random_proof(
+            ProductCircuit {
+                x: Some(x),
+                y: Some(y),
+            },
+            &params,
+            rng,
+        )
+        .expect("create_random_proof failure");
+
+        (
+            convert_verifying_key(&params.vk).expect("convert_verifying_key failure"),
+            convert_proof(&proof).expect("convert_proof failure"),
+            convert_public_inputs(&[x * y, x + y]),
+        )
+    }
+
+    #[test]
+    fn test_encode_points() {
+        let g1 = G1Affine::prime_subgroup_generator();
+        let g2 = G2Affine::prime_subgroup_generator();
+        assert_eq!(decode_g1(&encode_g1(&g1)).expect("decode_g1 failure"), g1);
+        assert_eq!(decode_g2(&encode_g2(&g2)).expect("decode_g2 failure"), g2);
+        assert_eq!(encode_g1(&G1Affine::zero()), [0u8; G1_LENGTH]);
+        assert!(decode_g2(&[0u8; G2_LENGTH])
+            .expect("decode_g2 failure")
+            .is_zero());
+
+        // The padding of the coordinates must be zero.
+        let mut bytes = encode_g1(&g1);
+        bytes[0] = 1;
+        assert!(decode_g1(&bytes).is_err());
+
+        let mut bytes = encode_g1(&g1);
+        bytes[G1_LENGTH - 1] ^= 1;
+        assert!(matches!(decode_g1(&bytes), Err(Error::InvalidPoint("G1"))));
+    }
+
+    #[test]
+    fn test_verify_calldata() {
+        let (vk, proof, inputs) = prove();
+        let pvk = prepare_verifying_key(&vk);
+        assert!(verify_proof(&pvk, &proof, &inputs).expect("verify_proof failure"));
+
+        let calldata = encode_calldata(&proof, &inputs);
+        assert_eq!(calldata.len(), 4 + 3 * 32 + PROOF_LENGTH + 32 + 2 * 32);
+        assert!(verify_calldata(&vk, &calldata).expect("verify_calldata failure"));
+
+        // Wrong inputs are rejected by both verifiers.
+        let wrong_inputs = [inputs[0], inputs[0]];
+        assert!(!verify_proof(&pvk, &proof, &wrong_inputs).expect("verify_proof failure"));
+        let calldata = encode_calldata(&proof, &wrong_inputs);
+        assert!(!verify_calldata(&vk, &calldata).expect("verify_calldata failure"));
+
+        // The contract reverts for non canonical inputs and a wrong number of inputs.
+        let mut calldata = encode_calldata(&proof, &inputs);
+        let len = calldata.len();
+        calldata[len - WORD_LENGTH..].copy_from_slice(&[0xff; WORD_LENGTH]);
+        assert!(matches!(
+            verify_calldata(&vk, &calldata),
+            Err(Error::InvalidCalldata("input not in the scalar field"))
+        ));
+        let calldata = encode_calldata(&proof, &inputs[..1]);
+        assert!(verify_calldata(&vk, &calldata).is_err());
+
+        // Invalid points of the proof make the verifier return false.
+        let mut calldata = encode_calldata(&proof, &inputs);
+        calldata[4 + 3 * 32 + G1_LENGTH - 1] ^= 1;
+        assert!(!verify_calldata(&vk, &calldata).expect("verify_calldata failure"));
+    }
+
+    #[test]
+    fn test_generate_verifier() {
+        let (vk, _, _) = prove();
+        let source = generate_verifier(&vk, "ProductVerifier");
+
+        assert!(source.contains("contract ProductVerifier {"));
+        assert!(source.contains("uint256 constant NUM_INPUTS = 2;"));
+        assert!(source.contains(&format!(
+            "bytes constant NEG_DELTA_G2 = hex\"{}\";",
+            hex::encode(encode_g2(&-vk.delta_g2))
+        )));
+        assert!(source.contains(&hex::encode(encode_g1(&vk.gamma_abc_g1[2]))));
+        assert!(!source.contains("__"));
+
+        // The modulus in the contract is the one of the scalar field.
+        let modulus = <<ArkFr as PrimeField>::Params as FpParameters>::MODULUS;
+        assert!(source.contains(&format!(
+            "uint256 constant R = 0x{};",
+            hex::encode(modulus.to_bytes_be())
+        )));
+    }
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MIT
+// Generated by `converter::solidity::generate_verifier`, do not edit.
+pragma solidity ^0.8.24;
+
+/// Verifies Groth16 proofs over BLS12-381 for a fixed verifying key, with the precompiles of
+/// EIP-2537.
+contract __CONTRACT_NAME__ {
+    /// The order of the scalar field.
+    uint256 constant R = 0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001;
+
+    address constant G1_MSM = address(0x0c);
+    address constant PAIRING = address(0x0f);
+
+    uint256 constant PROOF_LENGTH = 512;
+    uint256 constant NUM_INPUTS = __NUM_INPUTS__;
+
+    bytes constant ALPHA_G1 = hex"__ALPHA_G1__";
+    bytes constant NEG_BETA_G2 = hex"__NEG_BETA_G2__";
+    bytes constant NEG_GAMMA_G2 = hex"__NEG_GAMMA_G2__";
+    bytes constant NEG_DELTA_G2 = hex"__NEG_DELTA_G2__";
+    /// The points `gamma_abc_g1` of the verifying key, 128 bytes each.
+    bytes constant IC = hex"__IC__";
+
+    /// Returns whether `proof`, the points `a || b || c` in the encoding of the precompiles, is
+    /// valid for the public `input`.
+    function verifyProof(bytes calldata proof, uint256[] calldata input) external view returns (bool) {
+        require(proof.length == PROOF_LENGTH, "invalid proof length");
+        require(input.length == NUM_INPUTS, "invalid number of inputs");
+
+        // vk_x = ic[0] + sum(input[i] * ic[i + 1]), as a single multi-scalar multiplication.
+        bytes memory ic = IC;
+        bytes memory msm = new bytes(160 * (NUM_INPUTS + 1));
+        for (uint256 i = 0; i <= NUM_INPUTS; i++) {
+            uint256 scalar = 1;
+            if (i > 0) {
+                scalar = input[i - 1];
+                require(scalar < R, "input not in the scalar field");
+            }
+            assembly {
+                let src := add(add(ic, 32), mul(i, 128))
+                let dst := add(add(msm, 32), mul(i, 160))
+                mstore(dst, mload(src))
+                mstore(add(dst, 32), mload(add(src, 32)))
+                mstore(add(dst, 64), mload(add(src, 64)))
+                mstore(add(dst, 96), mload(add(src, 96)))
+                mstore(add(dst, 128), scalar)
+            }
+        }
+        (bool ok, bytes memory vkX) = G1_MSM.staticcall(msm);
+        require(ok && vkX.length == 128, "multi-scalar multiplication failed");
+
+        // e(a, b) * e(alpha, -beta) * e(vk_x, -gamma) * e(c, -delta) == 1, where the precompile
+        // fails for points not in the right subgroup.
+        bytes memory pairs = abi.encodePacked(
+            proof[:384], ALPHA_G1, NEG_BETA_G2, vkX, NEG_GAMMA_G2, proof[384:], NEG_DELTA_G2
+        );
+        bytes memory result;
+        (ok, result) = PAIRING.staticcall(pairs);
+        return ok && result.length == 32 && abi.decode(result, (uint256)) == 1;
+    }
+}
@@ -1,7 +1,13 @@
 mod convert;
 mod padding;
+mod parallel;
 mod reader;
+mod unpad_reader;
+mod writer;
 
 pub use convert::*;
 pub use padding::*;
+pub use parallel::*;
 pub use reader::*;
+pub use unpad_reader::*;
+pub use writer::*;
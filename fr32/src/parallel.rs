@@ -0,0 +1,142 @@
+use std::io::{self, Error, Read};
+
+use rayon::prelude::*;
+
+use crate::{
+    padding::write_unpadded,
+    reader::{Fr32Reader, NUM_BYTES_IN_BLOCK, NUM_BYTES_OUT_BLOCK},
+};
+
+/// Pads `source` the same way as [`Fr32Reader`] does, the full blocks of 127 bytes in parallel.
+pub fn pad_parallel(source: &[u8]) -> Vec<u8> {
+    let blocks = source.chunks_exact(NUM_BYTES_IN_BLOCK);
+    let remainder = blocks.remainder();
+
+    let mut padded = vec![0u8; blocks.len() * NUM_BYTES_OUT_BLOCK];
+    source
+        .par_chunks_exact(NUM_BYTES_IN_BLOCK)
+        .zip(padded.par_chunks_exact_mut(NUM_BYTES_OUT_BLOCK))
+        .for_each(|(block, target)| {
+            Fr32Reader::new(block)
+                .read_exact(target)
+                .expect("in-memory read failed");
+        });
+
+    // The incomplete last block is padded to whole `Fr32`s only.
+    Fr32Reader::new(remainder)
+        .read_to_end(&mut padded)
+        .expect("in-memory read failed");
+
+    padded
+}
+
+/// Returns `len` bytes of the data unpadded from `source`, starting at the unpadded `offset`,
+/// like [`write_unpadded`] does.
+///
+/// The full blocks of the range are unpadded in parallel, while the parts of the range that do
+/// not cover a full block at its edges are unpadded serially.
+pub fn unpad_parallel(source: &[u8], offset: usize, len: usize) -> io::Result<Vec<u8>> {
+    let end = offset + len;
+    let first_block = offset.div_ceil(NUM_BYTES_IN_BLOCK);
+    let last_block = end / NUM_BYTES_IN_BLOCK;
+    if first_block >= last_block {
+        let mut unpadded = Vec::with_capacity(len);
+        write_unpadded(source, &mut unpadded, offset, len)?;
+        return Ok(unpadded);
+    }
+
+    let padded_end = last_block * NUM_BYTES_OUT_BLOCK;
+    if source.len() < padded_end {
+        return Err(Error::other(format!(
+            "requested extraction of {} raw data bytes when there's at most {} in the source",
+            end,
+            source.len() / NUM_BYTES_OUT_BLOCK * NUM_BYTES_IN_BLOCK
+        )));
+    }
+
+    let head_len = first_block * NUM_BYTES_IN_BLOCK - offset;
+    let tail_start = last_block * NUM_BYTES_IN_BLOCK;
+    let mut unpadded = vec![0u8; len];
+    let (head, rest) = unpadded.split_at_mut(head_len);
+    let (blocks, tail) = rest.split_at_mut(tail_start - offset - head_len);
+
+    write_unpadded(source, &mut &mut head[..], offset, head_len)?;
+    source[first_block * NUM_BYTES_OUT_BLOCK..padded_end]
+        .par_chunks_exact(NUM_BYTES_OUT_BLOCK)
+        .zip(blocks.par_chunks_exact_mut(NUM_BYTES_IN_BLOCK))
+        .try_for_each(|(block, mut target)| {
+            write_unpadded(block, &mut target, 0, NUM_BYTES_IN_BLOCK).map(|_| ())
+        })?;
+    write_unpadded(source, &mut &mut tail[..], tail_start, end - tail_start)?;
+
+    Ok(unpadded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    const TEST_SEED: [u8; 16] = [
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ];
+
+    fn pad(data: &[u8]) -> Vec<u8> {
+        let mut padded = Vec::new();
+        Fr32Reader::new(data)
+            .read_to_end(&mut padded)
+            .expect("in-memory read failed");
+        padded
+    }
+
+    #[test]
+    fn test_parallel_large() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let data = (0..NUM_BYTES_IN_BLOCK * 4096)
+            .map(|_| rng.gen())
+            .collect::<Vec<u8>>();
+
+        let padded = pad_parallel(&data);
+        assert_eq!(padded, pad(&data));
+
+        let unpadded = unpad_parallel(&padded, 0, data.len()).expect("unpad failed");
+        assert_eq!(unpadded, data);
+    }
+
+    #[test]
+    fn test_unpad_out_of_range() {
+        let padded = pad(&[42u8; 1000]);
+        assert!(unpad_parallel(&padded, 0, 2000).is_err());
+        assert!(unpad_parallel(&padded, 900, 200).is_err());
+        assert!(unpad_parallel(&padded, 100, 10).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn test_pad_parallel(data in prop::collection::vec(any::<u8>(), 0..3000)) {
+            prop_assert_eq!(pad_parallel(&data), pad(&data));
+        }
+
+        #[test]
+        fn test_unpad_parallel(
+            data in prop::collection::vec(any::<u8>(), 1..3000),
+            start in any::<prop::sample::Index>(),
+            len in any::<prop::sample::Index>(),
+        ) {
+            let start = start.index(data.len());
+            let len = len.index(data.len() - start + 1);
+            let padded = pad(&data);
+
+            let mut expected = Vec::new();
+            write_unpadded(&padded, &mut expected, start, len).expect("write_unpadded failed");
+            let unpadded = unpad_parallel(&padded, start, len).expect("unpad failed");
+
+            prop_assert_eq!(&unpadded, &expected);
+            prop_assert_eq!(&unpadded[..], &data[start..start + len]);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::cmp::min;
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom};
+
+use crate::{
+    padding::{to_unpadded_bytes, write_unpadded},
+    reader::{NUM_BYTES_IN_BLOCK, NUM_BYTES_OUT_BLOCK},
+};
+
+/// Maximum number of padded blocks read from the source by a single `read`.
+const MAX_BLOCKS_PER_READ: usize = 1000;
+
+/// An `io::Read` adapter that recovers the unpadded data from an `Fr32` padded source.
+///
+/// Positions, as used by `Seek`, are offsets into the unpadded data, which can be read starting
+/// at any byte. As the padded form is made of whole `Fr32`s, the unpadded data ends with the zero
+/// bits used to fill the last of them, up to [`to_unpadded_bytes`] of the padded length.
+pub struct Fr32UnpadReader<R> {
+    /// The padded source.
+    source: R,
+    /// The current offset into the unpadded data.
+    pos: u64,
+    /// Padded data of the current read.
+    buffer: Vec<u8>,
+}
+
+impl<R: Read + Seek> Fr32UnpadReader<R> {
+    /// Creates a reader of the padded data starting at the beginning of `source`.
+    pub fn new(source: R) -> Self {
+        Fr32UnpadReader {
+            source,
+            pos: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Length of the unpadded data.
+    pub fn unpadded_len(&mut self) -> io::Result<u64> {
+        let padded_len = self.source.seek(SeekFrom::End(0))?;
+        Ok(to_unpadded_bytes(padded_len))
+    }
+
+    /// Reads as much of the padded blocks as available into the buffer.
+    fn fill_buffer(&mut self, first_block: u64, blocks: usize) -> io::Result<usize> {
+        self.buffer.resize(blocks * NUM_BYTES_OUT_BLOCK, 0);
+        self.source
+            .seek(SeekFrom::Start(first_block * NUM_BYTES_OUT_BLOCK as u64))?;
+
+        let mut bytes_read = 0;
+        while bytes_read < self.buffer.len() {
+            match self.source.read(&mut self.buffer[bytes_read..]) {
+                Ok(0) => break,
+                Ok(n) => bytes_read += n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Read + Seek> Read for Fr32UnpadReader<R> {
+    fn read(&mut self, target: &mut [u8]) -> io::Result<usize> {
+        if target.is_empty() {
+            return Ok(0);
+        }
+
+        // Padding starts over at every block, so read from the start of the current one.
+        let block = self.pos / NUM_BYTES_IN_BLOCK as u64;
+        let offset = (self.pos % NUM_BYTES_IN_BLOCK as u64) as usize;
+        let blocks = min(
+            (offset + target.len()).div_ceil(NUM_BYTES_IN_BLOCK),
+            MAX_BLOCKS_PER_READ,
+        );
+        let bytes_read = self.fill_buffer(block, blocks)?;
+
+        let available = to_unpadded_bytes(bytes_read as u64) as usize;
+        if available <= offset {
+            return Ok(0);
+        }
+        let len = min(target.len(), available - offset);
+
+        let mut out = &mut target[..len];
+        let written = write_unpadded(&self.buffer[..bytes_read], &mut out, offset, len)?;
+        self.pos += written as u64;
+
+        Ok(written)
+    }
+}
+
+impl<R: Read + Seek> Seek for Fr32UnpadReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) => self.unpadded_len()?.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+
+        self.pos = pos.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Cursor, Write};
+
+    use proptest::prelude::*;
+
+    use crate::{Fr32Reader, Fr32Writer};
+
+    fn pad(data: &[u8]) -> Vec<u8> {
+        let mut padded = Vec::new();
+        Fr32Reader::new(data)
+            .read_to_end(&mut padded)
+            .expect("in-memory read failed");
+        padded
+    }
+
+    #[test]
+    fn test_unpad_to_end() {
+        let data = vec![255u8; 300];
+        let padded = pad(&data);
+
+        let mut reader = Fr32UnpadReader::new(Cursor::new(&padded));
+        assert_eq!(
+            reader.unpadded_len().expect("in-memory seek failed"),
+            to_unpadded_bytes(padded.len() as u64)
+        );
+
+        let mut unpadded = Vec::new();
+        reader
+            .read_to_end(&mut unpadded)
+            .expect("in-memory read failed");
+        assert_eq!(
+            unpadded.len() as u64,
+            to_unpadded_bytes(padded.len() as u64)
+        );
+        assert_eq!(&unpadded[..300], &data[..]);
+        assert!(unpadded[300..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_seek() {
+        let data = (0..=255u8).cycle().take(1016).collect::<Vec<_>>();
+        let mut reader = Fr32UnpadReader::new(Cursor::new(pad(&data)));
+
+        let mut byte = [0u8];
+        assert_eq!(reader.seek(SeekFrom::Start(500)).expect("seek failed"), 500);
+        reader.read_exact(&mut byte).expect("read failed");
+        assert_eq!(byte[0], data[500]);
+
+        assert_eq!(
+            reader.seek(SeekFrom::Current(-2)).expect("seek failed"),
+            499
+        );
+        reader.read_exact(&mut byte).expect("read failed");
+        assert_eq!(byte[0], data[499]);
+
+        assert_eq!(reader.seek(SeekFrom::End(-1)).expect("seek failed"), 1015);
+        reader.read_exact(&mut byte).expect("read failed");
+        assert_eq!(byte[0], data[1015]);
+        assert_eq!(reader.read(&mut byte).expect("read failed"), 0);
+
+        assert!(reader.seek(SeekFrom::Current(-2000)).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_unpad_range(
+            data in prop::collection::vec(any::<u8>(), 1..3000),
+            start in any::<prop::sample::Index>(),
+            len in 0usize..600,
+        ) {
+            let start = start.index(data.len());
+            let len = len.min(data.len() - start);
+
+            let mut reader = Fr32UnpadReader::new(Cursor::new(pad(&data)));
+            reader.seek(SeekFrom::Start(start as u64)).expect("seek failed");
+            let mut unpadded = vec![0u8; len];
+            reader.read_exact(&mut unpadded).expect("read failed");
+
+            prop_assert_eq!(&unpadded[..], &data[start..start + len]);
+        }
+
+        #[test]
+        fn test_round_trip(data in prop::collection::vec(any::<u8>(), 0..3000)) {
+            let mut writer = Fr32Writer::new(Vec::new());
+            writer.write_all(&data).expect("in-memory write failed");
+            let padded = writer.finish().expect("in-memory write failed");
+
+            let mut unpadded = Vec::new();
+            Fr32UnpadReader::new(Cursor::new(padded))
+                .read_to_end(&mut unpadded)
+                .expect("in-memory read failed");
+
+            prop_assert_eq!(&unpadded[..data.len()], &data[..]);
+            prop_assert!(unpadded[data.len()..].iter().all(|byte| *byte == 0));
+        }
+    }
+}
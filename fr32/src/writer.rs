@@ -0,0 +1,172 @@
+use std::io::{self, Read, Write};
+
+use crate::reader::{Fr32Reader, NUM_BYTES_IN_BLOCK, NUM_BYTES_OUT_BLOCK};
+
+/// An `io::Write` adapter that writes the `Fr32` padded form of the unpadded data written to it.
+///
+/// The output is the same as the one of [`Fr32Reader`] for the same data, however it is split
+/// into writes. Data is padded in blocks of 127 bytes, the last incomplete block is padded and
+/// written by [`Fr32Writer::finish`], or when the writer is dropped.
+pub struct Fr32Writer<W: Write> {
+    /// The target of the padded data, only `None` once finished.
+    target: Option<W>,
+    /// The unpadded data of the current, incomplete block.
+    in_buffer: Vec<u8>,
+    /// Number of unpadded bytes written so far.
+    written: u64,
+}
+
+impl<W: Write> Fr32Writer<W> {
+    pub fn new(target: W) -> Self {
+        Fr32Writer {
+            target: Some(target),
+            in_buffer: Vec::with_capacity(NUM_BYTES_IN_BLOCK),
+            written: 0,
+        }
+    }
+
+    /// Number of unpadded bytes written so far.
+    pub fn unpadded_bytes(&self) -> u64 {
+        self.written
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.target.as_ref().expect("writer is finished")
+    }
+
+    /// Pads and writes the buffered data, and returns the target.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_remainder()?;
+        Ok(self.target.take().expect("writer is finished"))
+    }
+
+    fn write_remainder(&mut self) -> io::Result<()> {
+        if let Some(target) = self.target.as_mut() {
+            if !self.in_buffer.is_empty() {
+                let mut padded = Vec::with_capacity(NUM_BYTES_OUT_BLOCK);
+                Fr32Reader::new(&self.in_buffer[..]).read_to_end(&mut padded)?;
+                target.write_all(&padded)?;
+                self.in_buffer.clear();
+            }
+            target.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Pads a full block of unpadded data.
+fn pad_block(block: &[u8], target: &mut [u8; NUM_BYTES_OUT_BLOCK]) -> io::Result<()> {
+    debug_assert_eq!(block.len(), NUM_BYTES_IN_BLOCK);
+    Fr32Reader::new(block).read_exact(target)
+}
+
+impl<W: Write> Write for Fr32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let target = self.target.as_mut().expect("writer is finished");
+        let mut padded = [0u8; NUM_BYTES_OUT_BLOCK];
+        let mut data = buf;
+
+        // Complete the buffered block first.
+        if !self.in_buffer.is_empty() {
+            let len = data.len().min(NUM_BYTES_IN_BLOCK - self.in_buffer.len());
+            self.in_buffer.extend_from_slice(&data[..len]);
+            data = &data[len..];
+
+            if self.in_buffer.len() < NUM_BYTES_IN_BLOCK {
+                self.written += buf.len() as u64;
+                return Ok(buf.len());
+            }
+            pad_block(&self.in_buffer, &mut padded)?;
+            target.write_all(&padded)?;
+            self.in_buffer.clear();
+        }
+
+        let mut blocks = data.chunks_exact(NUM_BYTES_IN_BLOCK);
+        for block in &mut blocks {
+            pad_block(block, &mut padded)?;
+            target.write_all(&padded)?;
+        }
+        self.in_buffer.extend_from_slice(blocks.remainder());
+
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    /// Flushes the target. Data of an incomplete block stays buffered, as its padded form
+    /// depends on the data that follows.
+    fn flush(&mut self) -> io::Result<()> {
+        match self.target.as_mut() {
+            Some(target) => target.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for Fr32Writer<W> {
+    fn drop(&mut self) {
+        // Errors are ignored, call `finish` to handle them.
+        let _ = self.write_remainder();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn pad(data: &[u8]) -> Vec<u8> {
+        let mut padded = Vec::new();
+        Fr32Reader::new(data)
+            .read_to_end(&mut padded)
+            .expect("in-memory read failed");
+        padded
+    }
+
+    #[test]
+    fn test_write_block() {
+        let data = vec![255u8; 127];
+        let mut writer = Fr32Writer::new(Vec::new());
+        writer.write_all(&data).expect("in-memory write failed");
+        // A full block is written right away.
+        assert_eq!(writer.get_ref().len(), 128);
+
+        let padded = writer.finish().expect("in-memory write failed");
+        assert_eq!(padded, pad(&data));
+    }
+
+    #[test]
+    fn test_write_on_drop() {
+        let data = vec![3u8; 30];
+        let mut padded = Vec::new();
+        {
+            let mut writer = Fr32Writer::new(&mut padded);
+            writer.write_all(&data).expect("in-memory write failed");
+        }
+        assert_eq!(padded.len(), 32);
+        assert_eq!(padded, pad(&data));
+    }
+
+    proptest! {
+        #[test]
+        fn test_write_chunked(
+            data in prop::collection::vec(any::<u8>(), 0..2000),
+            chunk_sizes in prop::collection::vec(1usize..300, 1..20),
+        ) {
+            let mut writer = Fr32Writer::new(Vec::new());
+            let mut rest = &data[..];
+            for size in chunk_sizes.iter().cycle() {
+                if rest.is_empty() {
+                    break;
+                }
+                let (chunk, tail) = rest.split_at((*size).min(rest.len()));
+                writer.write_all(chunk).expect("in-memory write failed");
+                rest = tail;
+            }
+            prop_assert_eq!(writer.unpadded_bytes(), data.len() as u64);
+
+            let padded = writer.finish().expect("in-memory write failed");
+            prop_assert_eq!(padded, pad(&data));
+        }
+    }
+}
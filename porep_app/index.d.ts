@@ -9,9 +9,21 @@ import {
     SupportedSectorSize
 } from './types';
 
+export interface SectorProfile {
+    name: SupportedSectorSize;
+    sector_size: number;
+    shape: 'base' | 'sub2' | 'sub8' | 'top2';
+    layers: number;
+    partitions: number;
+    minimum_challenges: number;
+    window_post_sector_count: number;
+}
+
 export interface SetupResult {
     setup_params: SetupParams;
     vk_raw: Binary;
+    minimum_challenges: number;
+    profile: SectorProfile;
 }
 
 export interface Setup {
@@ -50,3 +62,133 @@ export function setup(args: { sector_size: string; api_version: string; porep_id
  * @returns {string}
  */
 export function seal(args: { file_path: string; ticket: string; sector_id: number; api_version: any; prover_id: string; porep_id: string }): SealResult;
+
+export type Commitment = number[]; // 32 bytes
+
+export interface SectorConfig {
+    sector_size: number;
+    porep_id: string; // base64
+    api_version: string;
+}
+
+export interface PieceInfo {
+    commitment: Commitment;
+    size: number;
+}
+
+export interface SealPreCommitOutput {
+    comm_r: Commitment;
+    comm_d: Commitment;
+}
+
+export interface SealCommitOutput {
+    proof: number[];
+}
+
+export interface PreCommitPhase1 {
+    config: SectorConfig;
+    cache_path: string;
+    staged_sector_path: string;
+    sealed_sector_path: string;
+    prover_id: string; // base64
+    sector_id: number;
+    ticket: string; // base64
+    piece_infos: PieceInfo[];
+}
+
+export interface PreCommitPhase2 {
+    config: SectorConfig;
+    phase1_output: unknown;
+    cache_path: string;
+    sealed_sector_path: string;
+}
+
+export interface CommitPhase1 {
+    config: SectorConfig;
+    cache_path: string;
+    sealed_sector_path: string;
+    prover_id: string; // base64
+    sector_id: number;
+    ticket: string; // base64
+    seed: string; // base64
+    pre_commit_output: SealPreCommitOutput;
+    piece_infos: PieceInfo[];
+}
+
+export interface CommitPhase2 {
+    config: SectorConfig;
+    phase1_output: unknown;
+    prover_id: string; // base64
+    sector_id: number;
+}
+
+export interface CommitPhase2Result extends SealResult {
+    commit_output: SealCommitOutput;
+}
+
+export interface VerifySeal {
+    config: SectorConfig;
+    comm_r: Commitment;
+    comm_d: Commitment;
+    prover_id: string; // base64
+    sector_id: number;
+    ticket: string; // base64
+    seed: string; // base64
+    commit_output: SealCommitOutput;
+}
+
+export interface PieceCommitment {
+    piece_path: string;
+    piece_size: number;
+}
+
+export interface AddPiece {
+    piece_path: string;
+    staged_sector_path: string;
+    piece_size: number;
+    piece_lengths: number[];
+}
+
+export interface AddPieceResult {
+    piece_info: PieceInfo;
+    written: number;
+}
+
+export interface ClearCache {
+    sector_size: number;
+    cache_path: string;
+}
+
+export interface UnsealRange {
+    config: SectorConfig;
+    cache_path: string;
+    sealed_sector_path: string;
+    output_path: string;
+    prover_id: string; // base64
+    sector_id: number;
+    comm_d: Commitment;
+    ticket: string; // base64
+    offset: number;
+    num_bytes: number;
+}
+
+export function seal_pre_commit_phase1(args: PreCommitPhase1): Promise<unknown>;
+
+export function seal_pre_commit_phase2(args: PreCommitPhase2): Promise<SealPreCommitOutput>;
+
+export function seal_commit_phase1(args: CommitPhase1): Promise<unknown>;
+
+export function seal_commit_phase2(args: CommitPhase2): Promise<CommitPhase2Result>;
+
+export function verify_seal(args: VerifySeal): Promise<boolean>;
+
+export function generate_piece_commitment(args: PieceCommitment): Promise<PieceInfo>;
+
+export function add_piece(args: AddPiece): Promise<AddPieceResult>;
+
+export function clear_cache(args: ClearCache): Promise<boolean>;
+
+/**
+ * @returns the number of unpadded bytes written to `output_path`
+ */
+export function unseal_range(args: UnsealRange): Promise<number>;
@@ -1,4 +1,10 @@
-const {setup, seal} = require('./dist');
+const dist = require('./dist');
+const {setup, seal} = dist;
+
+// The lifecycle bindings take a JSON string and resolve to a JSON string.
+const lifecycle = (name) => async (args) => {
+    return JSON.parse(await dist[name](JSON.stringify(args)));
+};
 
 module.exports = {
     setup: (args) => {
@@ -14,4 +20,13 @@ module.exports = {
             }),
         );
     },
+    seal_pre_commit_phase1: lifecycle('seal_pre_commit_phase1'),
+    seal_pre_commit_phase2: lifecycle('seal_pre_commit_phase2'),
+    seal_commit_phase1: lifecycle('seal_commit_phase1'),
+    seal_commit_phase2: lifecycle('seal_commit_phase2'),
+    verify_seal: lifecycle('verify_seal'),
+    generate_piece_commitment: lifecycle('generate_piece_commitment'),
+    add_piece: lifecycle('add_piece'),
+    clear_cache: lifecycle('clear_cache'),
+    unseal_range: lifecycle('unseal_range'),
 };
@@ -1,11 +1,13 @@
-use ark_serialize::CanonicalSerialize;
+use anyhow::Context;
 use base64::{engine::general_purpose, Engine as _};
 use contract_auxiliaries::{
-    deserializer::{deserialize_proof, deserialize_verifying_key},
     drg::stacked::{challenges::LayerChallenges as VerifierLayerChallenges, VerifierSetupParams},
     utils::ApiVersion as VerifierApiVersion,
 };
-use converter::serializer::{serialize_proof, serialize_verifying_key};
+use converter::{
+    format::{to_base64, Encoding},
+    groth16::{convert_proof, convert_verifying_key},
+};
 use node_bindgen::{
     core::val::{JsEnv, JsObject},
     core::{JSValue, NjError},
@@ -18,47 +20,48 @@ use seal::*;
 use serde::{Deserialize, Serialize};
 use std::{fs, path::Path};
 
+mod lifecycle;
+
+/// Decodes base64 encoded bytes, zero padded to 32 bytes.
 #[inline]
-fn from_base64(data: &String) -> [u8; 32] {
-    let mut bytes = general_purpose::STANDARD.decode(data).unwrap();
+fn from_base64(data: &str) -> anyhow::Result<[u8; 32]> {
+    let mut bytes = general_purpose::STANDARD
+        .decode(data)
+        .map_err(|err| anyhow::anyhow!("invalid base64 {:?}: {}", data, err))?;
+    anyhow::ensure!(
+        bytes.len() <= 32,
+        "expected at most 32 bytes, got {}",
+        bytes.len()
+    );
     bytes.resize(32, 0);
-    let bytes: [u8; 32] = bytes.try_into().unwrap();
-    return bytes;
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&bytes);
+    Ok(out)
 }
 
-enum SupportedSectorSize {
-    SectorSize2Kib,
-    SectorSize4Kib,
-    SectorSize16Kib,
-    SectorSize32Kib,
-    SectorSize8Mib,
-    SectorSize16Mib,
-    SectorSize512Mib,
-    SectorSize1Gib,
-    SectorSize32Gib,
-    SectorSize64Gib,
+/// Parses the name of an `ApiVersion`, e.g. `V1_1_0`.
+fn parse_api_version(name: &str) -> anyhow::Result<ApiVersion> {
+    match name {
+        "V1_0_0" => Ok(ApiVersion::V1_0_0),
+        "V1_1_0" => Ok(ApiVersion::V1_1_0),
+        _ => anyhow::bail!("unknown api version: {}", name),
+    }
 }
 
-impl SupportedSectorSize {
-    fn from_str(value: &str) -> SupportedSectorSize {
-        match value {
-            "sector_size2_kib" => SupportedSectorSize::SectorSize2Kib,
-            "sector_size4_kib" => SupportedSectorSize::SectorSize4Kib,
-            "sector_size16_kib" => SupportedSectorSize::SectorSize16Kib,
-            "sector_size32_kib" => SupportedSectorSize::SectorSize32Kib,
-            "sector_size8_mib" => SupportedSectorSize::SectorSize8Mib,
-            "sector_size16_mib" => SupportedSectorSize::SectorSize16Mib,
-            "sector_size512_mib" => SupportedSectorSize::SectorSize512Mib,
-            "sector_size1_gib" => SupportedSectorSize::SectorSize1Gib,
-            "sector_size32_gib" => SupportedSectorSize::SectorSize32Gib,
-            "sector_size64_gib" => SupportedSectorSize::SectorSize64Gib,
-            _ => panic!("Unknown value: {}", value),
-        }
+/// Size of the sector profile named `name`, e.g. `sector_size2_kib`.
+fn sector_size_by_name(name: &str) -> anyhow::Result<u64> {
+    match sector_registry::global().by_name(name) {
+        Some(profile) => Ok(profile.sector_size),
+        None => anyhow::bail!("unknown sector size: {}", name),
     }
 }
 
+fn to_js_result<T>(result: anyhow::Result<T>) -> Result<T, NjError> {
+    result.map_err(|err| NjError::Other(format!("{err:#}")))
+}
+
 struct Setup {
-    sector_size: SupportedSectorSize,
+    sector_size: u64,
     porep_id: String,
     api_version: String,
 }
@@ -67,13 +70,14 @@ impl JSValue<'_> for Setup {
     fn convert_to_rust(env: &JsEnv, js_value: napi_value) -> Result<Self, NjError> {
         if let Ok(js_obj) = env.convert_to_rust::<JsObject>(js_value) {
             let json = Self {
-                sector_size: SupportedSectorSize::from_str(
+                sector_size: sector_size_by_name(
                     &js_obj
                         .get_property("sector_size")?
                         .unwrap()
                         .as_value::<String>()
                         .unwrap(),
-                ),
+                )
+                .map_err(|err| NjError::Other(err.to_string()))?,
                 api_version: js_obj
                     .get_property("api_version")?
                     .unwrap()
@@ -156,25 +160,22 @@ struct SealResult<T: Serialize + ?Sized> {
     pub public_inputs: T,
 }
 
-fn process_seal_and_unseal<T: 'static + MerkleTreeTrait>(args: &Seal) -> String {
+fn process_seal_and_unseal<T: 'static + MerkleTreeTrait>(args: &Seal) -> anyhow::Result<String> {
     let file_path = Path::new(&args.file_path);
 
-    let sector_size = get_file_size(file_path);
+    let sector_size = get_file_size(file_path)?;
 
     let mut rng = thread_rng();
-    let porep_id = from_base64(&args.porep_id);
-    let prover_id = from_base64(&args.prover_id);
-    let ticket = from_base64(&args.ticket);
+    let porep_id = from_base64(&args.porep_id).context("invalid porep_id")?;
+    let prover_id = from_base64(&args.prover_id).context("invalid prover_id")?;
+    let ticket = from_base64(&args.ticket).context("invalid ticket")?;
 
     let seed = match &args.seed {
-        Some(id) => from_base64(id),
+        Some(id) => from_base64(id).context("invalid seed")?,
         None => rng.gen(),
     };
 
-    let api_version = match args.api_version.as_str() {
-        "V1_0_0" => ApiVersion::V1_0_0,
-        _ => ApiVersion::V1_1_0,
-    };
+    let api_version = parse_api_version(&args.api_version)?;
 
     let (pre_commit_output, commit_output) = seal::<ThreadRng, T>(
         &mut rng,
@@ -187,7 +188,7 @@ fn process_seal_and_unseal<T: 'static + MerkleTreeTrait>(args: &Seal) -> String
         api_version,
         file_path,
     )
-    .expect("failed to seal file");
+    .context("failed to seal file")?;
 
     let config = porep_config(sector_size, porep_id, api_version);
     let (public_inputs, proofs) = generate_proof_and_public_inputs::<T, _>(
@@ -201,46 +202,28 @@ fn process_seal_and_unseal<T: 'static + MerkleTreeTrait>(args: &Seal) -> String
         commit_output.proof.as_slice(),
         Some(&mut rng),
     )
-    .expect("failed to generate proof and public inputs");
+    .context("failed to generate proof and public inputs")?;
 
     // convert bellperson to ark-groth16
-    let proof = deserialize_proof(&serialize_proof(&proofs[0])).unwrap();
-    let mut proof_raw = vec![];
-    proof
-        .serialize(&mut proof_raw)
-        .expect("failed to serialize proof");
+    let proof = convert_proof(&proofs[0]).context("failed to convert proof")?;
 
     let result = SealResult {
-        proof_raw: general_purpose::STANDARD.encode(proof_raw),
+        proof_raw: to_base64(&proof, Encoding::Compressed).context("failed to serialize proof")?,
         public_inputs,
     };
 
-    serde_json::to_string(&result).unwrap_or_default()
+    Ok(serde_json::to_string(&result)?)
 }
 
-fn process_setup<T: 'static + MerkleTreeTrait>(args: &Setup) -> String {
+fn process_setup<T: 'static + MerkleTreeTrait>(args: &Setup) -> anyhow::Result<String> {
     let mut rng = thread_rng();
-    let api_version = match args.api_version.as_str() {
-        "V1_0_0" => ApiVersion::V1_0_0,
-        _ => ApiVersion::V1_1_0,
-    };
-    let porep_id = from_base64(&args.porep_id);
-
-    let sector_size = match args.sector_size {
-        SupportedSectorSize::SectorSize2Kib => SECTOR_SIZE_2_KIB,
-        SupportedSectorSize::SectorSize4Kib => SECTOR_SIZE_4_KIB,
-        SupportedSectorSize::SectorSize16Kib => SECTOR_SIZE_16_KIB,
-        SupportedSectorSize::SectorSize32Kib => SECTOR_SIZE_32_KIB,
-        SupportedSectorSize::SectorSize8Mib => SECTOR_SIZE_8_MIB,
-        SupportedSectorSize::SectorSize16Mib => SECTOR_SIZE_16_MIB,
-        SupportedSectorSize::SectorSize512Mib => SECTOR_SIZE_512_MIB,
-        SupportedSectorSize::SectorSize1Gib => SECTOR_SIZE_1_GIB,
-        SupportedSectorSize::SectorSize32Gib => SECTOR_SIZE_32_GIB,
-        SupportedSectorSize::SectorSize64Gib => SECTOR_SIZE_64_GIB,
-    };
+    let api_version = parse_api_version(&args.api_version)?;
+    let porep_id = from_base64(&args.porep_id).context("invalid porep_id")?;
+
+    let sector_size = args.sector_size;
     let config = porep_config(sector_size, porep_id, api_version);
     let (compound_setup_params, verifying_key, challenge_requirements) =
-        generate_setup_params::<T, _>(&config, Some(&mut rng)).unwrap();
+        generate_setup_params::<T, _>(&config, Some(&mut rng))?;
 
     let vanilla_params = compound_setup_params.vanilla_params;
     let verifier_setup_params = VerifierSetupParams {
@@ -259,34 +242,35 @@ fn process_setup<T: 'static + MerkleTreeTrait>(args: &Setup) -> String {
     };
 
     // convert bellperson to ark-groth16
-    let vk = deserialize_verifying_key(&serialize_verifying_key(&verifying_key)).unwrap();
-    let mut vk_raw: Vec<u8> = vec![];
-    vk.serialize(&mut vk_raw).unwrap();
+    let vk = convert_verifying_key(&verifying_key).context("failed to convert verifying key")?;
+
+    // parent caches only matter to the prover, keep them out of the contract
+    let mut profile = sector_registry::global()
+        .get(sector_size)
+        .cloned()
+        .context("unknown sector size")?;
+    profile.parent_cache.clear();
 
     let result = SetupResult {
         setup_params: verifier_setup_params,
-        vk_raw: general_purpose::STANDARD.encode(vk_raw),
+        vk_raw: to_base64(&vk, Encoding::Compressed)
+            .context("failed to serialize verifying key")?,
         minimum_challenges: challenge_requirements.minimum_challenges as u64,
+        profile,
     };
 
-    serde_json::to_string(&result).unwrap_or_default()
+    Ok(serde_json::to_string(&result)?)
 }
 
-fn get_file_size(path: &Path) -> u64 {
-    let metadata = fs::metadata(path).expect("failed to fetch file's metadata");
-    let size = metadata.len();
-    match size {
-        x if x <= SECTOR_SIZE_2_KIB => SECTOR_SIZE_2_KIB,
-        x if x <= SECTOR_SIZE_4_KIB => SECTOR_SIZE_4_KIB,
-        x if x <= SECTOR_SIZE_16_KIB => SECTOR_SIZE_16_KIB,
-        x if x <= SECTOR_SIZE_32_KIB => SECTOR_SIZE_32_KIB,
-        x if x <= SECTOR_SIZE_8_MIB => SECTOR_SIZE_8_MIB,
-        x if x <= SECTOR_SIZE_16_MIB => SECTOR_SIZE_16_MIB,
-        x if x <= SECTOR_SIZE_512_MIB => SECTOR_SIZE_512_MIB,
-        x if x <= SECTOR_SIZE_1_GIB => SECTOR_SIZE_1_GIB,
-        x if x <= SECTOR_SIZE_32_GIB => SECTOR_SIZE_32_GIB,
-        x if x <= SECTOR_SIZE_64_GIB => SECTOR_SIZE_64_GIB,
-        _ => panic!("file is too large (maximum supported size: 64GiB)"),
+fn get_file_size(path: &Path) -> anyhow::Result<u64> {
+    let metadata = fs::metadata(path).context("failed to fetch file's metadata")?;
+    let registry = sector_registry::global();
+    match registry.fit(metadata.len()) {
+        Some(profile) => Ok(profile.sector_size),
+        None => anyhow::bail!(
+            "file is too large (maximum supported size: {} bytes)",
+            registry.profiles().last().map_or(0, |profile| profile.sector_size)
+        ),
     }
 }
 
@@ -295,41 +279,19 @@ struct SetupResult {
     pub setup_params: VerifierSetupParams,
     pub vk_raw: String,
     pub minimum_challenges: u64,
+    pub profile: SectorProfile,
 }
 
 #[node_bindgen]
-fn setup(args: Setup) -> String {
-    match args.sector_size {
-        SupportedSectorSize::SectorSize2Kib => process_setup::<SectorShape2KiB>(&args),
-        SupportedSectorSize::SectorSize4Kib => process_setup::<SectorShape4KiB>(&args),
-        SupportedSectorSize::SectorSize16Kib => process_setup::<SectorShape16KiB>(&args),
-        SupportedSectorSize::SectorSize32Kib => process_setup::<SectorShape32KiB>(&args),
-        SupportedSectorSize::SectorSize8Mib => process_setup::<SectorShape8MiB>(&args),
-        SupportedSectorSize::SectorSize16Mib => process_setup::<SectorShape16MiB>(&args),
-        SupportedSectorSize::SectorSize512Mib => process_setup::<SectorShape512MiB>(&args),
-        SupportedSectorSize::SectorSize1Gib => process_setup::<SectorShape1GiB>(&args),
-        SupportedSectorSize::SectorSize32Gib => process_setup::<SectorShape32GiB>(&args),
-        SupportedSectorSize::SectorSize64Gib => process_setup::<SectorShape64GiB>(&args),
-    }
+fn setup(args: Setup) -> Result<String, NjError> {
+    to_js_result(try_with_shape!(args.sector_size, process_setup, &args))
 }
 
 #[node_bindgen]
-fn seal(args: Seal) -> String {
+fn seal(args: Seal) -> Result<String, NjError> {
     let file_path = Path::new(&args.file_path);
 
-    let sector_size = get_file_size(file_path);
-
-    match sector_size {
-        SECTOR_SIZE_2_KIB => process_seal_and_unseal::<SectorShape2KiB>(&args),
-        SECTOR_SIZE_4_KIB => process_seal_and_unseal::<SectorShape4KiB>(&args),
-        SECTOR_SIZE_16_KIB => process_seal_and_unseal::<SectorShape16KiB>(&args),
-        SECTOR_SIZE_32_KIB => process_seal_and_unseal::<SectorShape32KiB>(&args),
-        SECTOR_SIZE_8_MIB => process_seal_and_unseal::<SectorShape8MiB>(&args),
-        SECTOR_SIZE_16_MIB => process_seal_and_unseal::<SectorShape16MiB>(&args),
-        SECTOR_SIZE_512_MIB => process_seal_and_unseal::<SectorShape512MiB>(&args),
-        SECTOR_SIZE_1_GIB => process_seal_and_unseal::<SectorShape1GiB>(&args),
-        SECTOR_SIZE_32_GIB => process_seal_and_unseal::<SectorShape32GiB>(&args),
-        SECTOR_SIZE_64_GIB => process_seal_and_unseal::<SectorShape64GiB>(&args),
-        _ => panic!("unexpected sector size"),
-    }
-}
\ No newline at end of file
+    let sector_size = to_js_result(get_file_size(file_path))?;
+
+    to_js_result(try_with_shape!(sector_size, process_seal_and_unseal, &args))
+}
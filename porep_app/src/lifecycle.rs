@@ -0,0 +1,442 @@
+//! Bindings for the individual steps of the sealing lifecycle.
+//!
+//! Every binding takes a single JSON string and resolves to a JSON string, so
+//! phase outputs can be persisted by the caller and handed back to the next
+//! phase later on (e.g. precommit now, prove once the chain provides a seed).
+
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use converter::{
+    format::{to_base64, Encoding},
+    groth16::convert_proof,
+};
+use node_bindgen::{
+    core::val::JsEnv,
+    core::{JSValue, NjError},
+    derive::node_bindgen,
+    sys::napi_value,
+};
+use proofs_core::{merkle::MerkleTreeTrait, sector::SectorId};
+use rand::thread_rng;
+use seal::try_with_shape;
+use seal::types::{
+    Commitment, PieceInfo, PoRepConfig, SealCommitOutput, SealCommitPhase1Output,
+    SealPreCommitOutput, SealPreCommitPhase1Output, UnpaddedByteIndex, UnpaddedBytesAmount,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{from_base64, parse_api_version, to_js_result, SealResult};
+
+/// A binding argument passed from JS as a JSON string.
+pub struct JsonArgs<T>(T);
+
+impl<T: DeserializeOwned> JSValue<'_> for JsonArgs<T> {
+    fn convert_to_rust(env: &JsEnv, js_value: napi_value) -> Result<Self, NjError> {
+        let json = env.convert_to_rust::<String>(js_value)?;
+        serde_json::from_str(&json)
+            .map(JsonArgs)
+            .map_err(|err| NjError::Other(format!("invalid arguments: {err}")))
+    }
+}
+
+/// Parameters identifying the proof system a sector is sealed with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SectorConfig {
+    pub sector_size: u64,
+    pub porep_id: String, // base64
+    pub api_version: String,
+}
+
+impl SectorConfig {
+    fn porep_config(&self) -> Result<PoRepConfig> {
+        let api_version = parse_api_version(&self.api_version)?;
+        let porep_id = from_base64(&self.porep_id).context("invalid porep_id")?;
+        Ok(PoRepConfig::new_groth16(
+            self.sector_size,
+            porep_id,
+            api_version,
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PreCommitPhase1Args {
+    pub config: SectorConfig,
+    pub cache_path: String,
+    pub staged_sector_path: String,
+    pub sealed_sector_path: String,
+    pub prover_id: String, // base64
+    pub sector_id: u64,
+    pub ticket: String, // base64
+    pub piece_infos: Vec<PieceInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct PreCommitPhase2Args {
+    pub config: SectorConfig,
+    pub phase1_output: serde_json::Value,
+    pub cache_path: String,
+    pub sealed_sector_path: String,
+}
+
+#[derive(Deserialize)]
+pub struct CommitPhase1Args {
+    pub config: SectorConfig,
+    pub cache_path: String,
+    pub sealed_sector_path: String,
+    pub prover_id: String, // base64
+    pub sector_id: u64,
+    pub ticket: String, // base64
+    pub seed: String,   // base64
+    pub pre_commit_output: SealPreCommitOutput,
+    pub piece_infos: Vec<PieceInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct CommitPhase2Args {
+    pub config: SectorConfig,
+    pub phase1_output: serde_json::Value,
+    pub prover_id: String, // base64
+    pub sector_id: u64,
+}
+
+#[derive(Serialize)]
+pub struct CommitPhase2Result<T: Serialize> {
+    pub commit_output: SealCommitOutput,
+    #[serde(flatten)]
+    pub seal_result: SealResult<T>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifySealArgs {
+    pub config: SectorConfig,
+    pub comm_r: Commitment,
+    pub comm_d: Commitment,
+    pub prover_id: String, // base64
+    pub sector_id: u64,
+    pub ticket: String, // base64
+    pub seed: String,   // base64
+    pub commit_output: SealCommitOutput,
+}
+
+#[derive(Deserialize)]
+pub struct PieceCommitmentArgs {
+    pub piece_path: String,
+    pub piece_size: u64,
+}
+
+#[derive(Deserialize)]
+pub struct AddPieceArgs {
+    pub piece_path: String,
+    pub staged_sector_path: String,
+    pub piece_size: u64,
+    pub piece_lengths: Vec<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PreCommitPhase2Result {
+    #[serde(flatten)]
+    pub output: SealPreCommitOutput,
+    pub comm_r_cid: String,
+    pub comm_d_cid: String,
+}
+
+#[derive(Serialize)]
+pub struct PieceCommitmentResult {
+    #[serde(flatten)]
+    pub piece_info: PieceInfo,
+    pub piece_cid: String,
+}
+
+#[derive(Serialize)]
+pub struct AddPieceResult {
+    pub piece_info: PieceInfo,
+    pub piece_cid: String,
+    pub written: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ClearCacheArgs {
+    pub sector_size: u64,
+    pub cache_path: String,
+}
+
+#[derive(Deserialize)]
+pub struct UnsealRangeArgs {
+    pub config: SectorConfig,
+    pub cache_path: String,
+    pub sealed_sector_path: String,
+    pub output_path: String,
+    pub prover_id: String, // base64
+    pub sector_id: u64,
+    pub comm_d: Commitment,
+    pub ticket: String, // base64
+    pub offset: u64,
+    pub num_bytes: u64,
+}
+
+fn process_pre_commit_phase1<Tree: 'static + MerkleTreeTrait>(
+    args: &PreCommitPhase1Args,
+) -> Result<String> {
+    let output = seal::seal_pre_commit_phase1::<_, _, _, Tree>(
+        &args.config.porep_config()?,
+        &args.cache_path,
+        &args.staged_sector_path,
+        &args.sealed_sector_path,
+        from_base64(&args.prover_id).context("invalid prover_id")?,
+        SectorId::from(args.sector_id),
+        from_base64(&args.ticket).context("invalid ticket")?,
+        &args.piece_infos,
+    )?;
+
+    Ok(serde_json::to_string(&output)?)
+}
+
+fn process_pre_commit_phase2<Tree: 'static + MerkleTreeTrait>(
+    args: &PreCommitPhase2Args,
+) -> Result<String> {
+    let phase1_output: SealPreCommitPhase1Output<Tree> =
+        serde_json::from_value(args.phase1_output.clone())
+            .context("invalid precommit phase1 output")?;
+
+    let output = seal::seal_pre_commit_phase2(
+        &args.config.porep_config()?,
+        phase1_output,
+        &args.cache_path,
+        &args.sealed_sector_path,
+    )?;
+
+    Ok(serde_json::to_string(&PreCommitPhase2Result {
+        comm_r_cid: output.comm_r_cid(),
+        comm_d_cid: output.comm_d_cid(),
+        output,
+    })?)
+}
+
+fn process_commit_phase1<Tree: 'static + MerkleTreeTrait>(
+    args: &CommitPhase1Args,
+) -> Result<String> {
+    let output = seal::seal_commit_phase1::<_, Tree>(
+        &args.config.porep_config()?,
+        PathBuf::from(&args.cache_path),
+        PathBuf::from(&args.sealed_sector_path),
+        from_base64(&args.prover_id).context("invalid prover_id")?,
+        SectorId::from(args.sector_id),
+        from_base64(&args.ticket).context("invalid ticket")?,
+        from_base64(&args.seed).context("invalid seed")?,
+        args.pre_commit_output.clone(),
+        &args.piece_infos,
+    )?;
+
+    Ok(serde_json::to_string(&output)?)
+}
+
+fn process_commit_phase2<Tree: 'static + MerkleTreeTrait>(
+    args: &CommitPhase2Args,
+) -> Result<String> {
+    let phase1_output: SealCommitPhase1Output<Tree> =
+        serde_json::from_value(args.phase1_output.clone())
+            .context("invalid commit phase1 output")?;
+    let (comm_r, comm_d) = (phase1_output.comm_r, phase1_output.comm_d);
+    let (ticket, seed) = (phase1_output.ticket, phase1_output.seed);
+
+    let mut rng = thread_rng();
+    let config = args.config.porep_config()?;
+    let prover_id = from_base64(&args.prover_id).context("invalid prover_id")?;
+    let sector_id = SectorId::from(args.sector_id);
+
+    let commit_output =
+        seal::seal_commit_phase2(&config, phase1_output, prover_id, sector_id, Some(&mut rng))?;
+
+    let (public_inputs, proofs) = seal::generate_proof_and_public_inputs::<Tree, _>(
+        &config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        &commit_output.proof,
+        Some(&mut rng),
+    )?;
+
+    // convert bellperson to ark-groth16
+    let proof = convert_proof(&proofs[0])?;
+
+    let result = CommitPhase2Result {
+        commit_output,
+        seal_result: SealResult {
+            proof_raw: to_base64(&proof, Encoding::Compressed)?,
+            public_inputs,
+        },
+    };
+
+    Ok(serde_json::to_string(&result)?)
+}
+
+fn process_verify_seal<Tree: 'static + MerkleTreeTrait>(args: &VerifySealArgs) -> Result<String> {
+    let mut rng = thread_rng();
+    let verified = seal::verify_seal::<Tree, _>(
+        &args.config.porep_config()?,
+        args.comm_r,
+        args.comm_d,
+        from_base64(&args.prover_id).context("invalid prover_id")?,
+        SectorId::from(args.sector_id),
+        from_base64(&args.ticket).context("invalid ticket")?,
+        from_base64(&args.seed).context("invalid seed")?,
+        &args.commit_output.proof,
+        Some(&mut rng),
+    )?;
+
+    Ok(serde_json::to_string(&verified)?)
+}
+
+fn process_clear_cache<Tree: 'static + MerkleTreeTrait>(args: &ClearCacheArgs) -> Result<String> {
+    seal::clear_cache::<Tree>(args.cache_path.as_ref())?;
+    Ok(serde_json::to_string(&true)?)
+}
+
+fn process_unseal_range<Tree: 'static + MerkleTreeTrait>(args: &UnsealRangeArgs) -> Result<String> {
+    let sealed_sector = File::open(&args.sealed_sector_path)
+        .with_context(|| format!("could not open sealed sector={}", args.sealed_sector_path))?;
+    let output = File::create(&args.output_path)
+        .with_context(|| format!("could not create output={}", args.output_path))?;
+
+    let written = seal::unseal_range::<_, _, _, Tree>(
+        &args.config.porep_config()?,
+        &args.cache_path,
+        sealed_sector,
+        output,
+        from_base64(&args.prover_id).context("invalid prover_id")?,
+        SectorId::from(args.sector_id),
+        args.comm_d,
+        from_base64(&args.ticket).context("invalid ticket")?,
+        UnpaddedByteIndex(args.offset),
+        UnpaddedBytesAmount(args.num_bytes),
+    )?;
+
+    Ok(serde_json::to_string(&written.0)?)
+}
+
+fn process_generate_piece_commitment(args: &PieceCommitmentArgs) -> Result<String> {
+    let piece_file = File::open(&args.piece_path)
+        .with_context(|| format!("could not open piece={}", args.piece_path))?;
+    let piece_info =
+        seal::generate_piece_commitment(piece_file, UnpaddedBytesAmount(args.piece_size))?;
+
+    Ok(serde_json::to_string(&PieceCommitmentResult {
+        piece_cid: piece_info.cid(),
+        piece_info,
+    })?)
+}
+
+fn process_add_piece(args: &AddPieceArgs) -> Result<String> {
+    let piece_file = File::open(&args.piece_path)
+        .with_context(|| format!("could not open piece={}", args.piece_path))?;
+    let staged_sector = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&args.staged_sector_path)
+        .with_context(|| format!("could not open staged sector={}", args.staged_sector_path))?;
+    let piece_lengths: Vec<_> = args
+        .piece_lengths
+        .iter()
+        .copied()
+        .map(UnpaddedBytesAmount)
+        .collect();
+
+    let (piece_info, written) = seal::add_piece(
+        piece_file,
+        staged_sector,
+        UnpaddedBytesAmount(args.piece_size),
+        &piece_lengths,
+    )?;
+
+    Ok(serde_json::to_string(&AddPieceResult {
+        piece_cid: piece_info.cid(),
+        piece_info,
+        written: written.0,
+    })?)
+}
+
+#[node_bindgen]
+async fn seal_pre_commit_phase1(args: JsonArgs<PreCommitPhase1Args>) -> Result<String, NjError> {
+    let args = args.0;
+    to_js_result(try_with_shape!(
+        args.config.sector_size,
+        process_pre_commit_phase1,
+        &args
+    ))
+}
+
+#[node_bindgen]
+async fn seal_pre_commit_phase2(args: JsonArgs<PreCommitPhase2Args>) -> Result<String, NjError> {
+    let args = args.0;
+    to_js_result(try_with_shape!(
+        args.config.sector_size,
+        process_pre_commit_phase2,
+        &args
+    ))
+}
+
+#[node_bindgen]
+async fn seal_commit_phase1(args: JsonArgs<CommitPhase1Args>) -> Result<String, NjError> {
+    let args = args.0;
+    to_js_result(try_with_shape!(
+        args.config.sector_size,
+        process_commit_phase1,
+        &args
+    ))
+}
+
+#[node_bindgen]
+async fn seal_commit_phase2(args: JsonArgs<CommitPhase2Args>) -> Result<String, NjError> {
+    let args = args.0;
+    to_js_result(try_with_shape!(
+        args.config.sector_size,
+        process_commit_phase2,
+        &args
+    ))
+}
+
+#[node_bindgen]
+async fn verify_seal(args: JsonArgs<VerifySealArgs>) -> Result<String, NjError> {
+    let args = args.0;
+    to_js_result(try_with_shape!(
+        args.config.sector_size,
+        process_verify_seal,
+        &args
+    ))
+}
+
+#[node_bindgen]
+async fn generate_piece_commitment(args: JsonArgs<PieceCommitmentArgs>) -> Result<String, NjError> {
+    to_js_result(process_generate_piece_commitment(&args.0))
+}
+
+#[node_bindgen]
+async fn add_piece(args: JsonArgs<AddPieceArgs>) -> Result<String, NjError> {
+    to_js_result(process_add_piece(&args.0))
+}
+
+#[node_bindgen]
+async fn clear_cache(args: JsonArgs<ClearCacheArgs>) -> Result<String, NjError> {
+    let args = args.0;
+    to_js_result(try_with_shape!(
+        args.sector_size,
+        process_clear_cache,
+        &args
+    ))
+}
+
+#[node_bindgen]
+async fn unseal_range(args: JsonArgs<UnsealRangeArgs>) -> Result<String, NjError> {
+    let args = args.0;
+    to_js_result(try_with_shape!(
+        args.config.sector_size,
+        process_unseal_range,
+        &args
+    ))
+}
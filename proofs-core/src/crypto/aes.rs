@@ -1,12 +1,29 @@
+use std::cmp::min;
+use std::io::{self, Read, Write};
+
 use aes::cipher::block_padding::ZeroPadding;
 use aes::cipher::crypto_common::KeyIvInit;
 use aes::cipher::{BlockDecryptMut, BlockEncryptMut};
-use anyhow::{ensure, Context};
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, ensure, Context};
+use rand::RngCore;
 
 use crate::error::Result;
 
 const IV: [u8; 16] = [0u8; 16];
 
+/// Number of plaintext bytes in a segment of an encrypted stream.
+pub const STREAM_SEGMENT_SIZE: usize = 64 * 1024;
+/// Size of the authentication tag appended to every segment.
+pub const STREAM_TAG_SIZE: usize = 16;
+/// Size of the nonce prefix of a stream, the last 5 bytes of the nonces being the segment
+/// counter and the last segment flag.
+pub const STREAM_NONCE_SIZE: usize = 7;
+/// Size of the nonce of a wrapped key.
+const WRAP_NONCE_SIZE: usize = 12;
+
 pub fn encode(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
     ensure!(key.len() == 32, "invalid key length");
 
@@ -26,6 +43,173 @@ pub fn decode(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
     Ok(res)
 }
 
+/// Number of bytes of the encrypted stream of `plaintext_len` bytes. Every segment, and at least
+/// one, carries a tag.
+pub fn stream_ciphertext_len(plaintext_len: u64) -> u64 {
+    let segments = plaintext_len.div_ceil(STREAM_SEGMENT_SIZE as u64).max(1);
+
+    plaintext_len + segments * STREAM_TAG_SIZE as u64
+}
+
+/// Wraps the data key `key` with the key-encryption key `kek`, as `nonce || ciphertext || tag`.
+pub fn wrap_key<R: RngCore>(kek: &[u8], key: &[u8], rng: &mut R) -> Result<Vec<u8>> {
+    ensure!(kek.len() == 32, "invalid key length");
+
+    let mut nonce = [0u8; WRAP_NONCE_SIZE];
+    rng.fill_bytes(&mut nonce);
+
+    let cipher = Aes256Gcm::new_from_slice(kek).context("invalid key")?;
+    let wrapped = cipher
+        .encrypt(Nonce::from_slice(&nonce), key)
+        .map_err(|_| anyhow!("failed to wrap key"))?;
+
+    Ok([&nonce[..], &wrapped].concat())
+}
+
+/// Unwraps a data key wrapped with `wrap_key`, failing if `kek` is not the key it was wrapped
+/// with.
+pub fn unwrap_key(kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>> {
+    ensure!(kek.len() == 32, "invalid key length");
+    ensure!(
+        wrapped.len() > WRAP_NONCE_SIZE + STREAM_TAG_SIZE,
+        "invalid wrapped key length"
+    );
+
+    let cipher = Aes256Gcm::new_from_slice(kek).context("invalid key")?;
+    let (nonce, wrapped) = wrapped.split_at(WRAP_NONCE_SIZE);
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), wrapped)
+        .map_err(|_| anyhow!("failed to unwrap key"))
+}
+
+/// Reads the AES-256-GCM encryption of the `plaintext_len` bytes of `source`, in the STREAM
+/// construction: the plaintext is split in segments of `STREAM_SEGMENT_SIZE` bytes, each one
+/// encrypted and authenticated on its own, the last one being marked as such.
+pub struct EncryptReader<R> {
+    source: R,
+    encryptor: Option<EncryptorBE32<Aes256Gcm>>,
+    remaining: u64,
+    segment: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> EncryptReader<R> {
+    pub fn new(
+        key: &[u8],
+        nonce: &[u8; STREAM_NONCE_SIZE],
+        source: R,
+        plaintext_len: u64,
+    ) -> Result<Self> {
+        ensure!(key.len() == 32, "invalid key length");
+
+        let cipher = Aes256Gcm::new_from_slice(key).context("invalid key")?;
+
+        Ok(EncryptReader {
+            source,
+            encryptor: Some(EncryptorBE32::from_aead(cipher, nonce.into())),
+            remaining: plaintext_len,
+            segment: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    /// Encrypts the next segment, returning false once the last one was read.
+    fn next_segment(&mut self) -> io::Result<bool> {
+        let encryptor = match self.encryptor.as_mut() {
+            Some(encryptor) => encryptor,
+            None => return Ok(false),
+        };
+
+        let len = min(self.remaining, STREAM_SEGMENT_SIZE as u64);
+        let mut plaintext = vec![0u8; len as usize];
+        self.source.read_exact(&mut plaintext)?;
+        self.remaining -= len;
+
+        let segment = if self.remaining == 0 {
+            let encryptor = self.encryptor.take().expect("encryptor failure");
+            encryptor.encrypt_last(plaintext.as_slice())
+        } else {
+            encryptor.encrypt_next(plaintext.as_slice())
+        };
+        self.segment = segment.map_err(|_| io::Error::other("failed to encrypt"))?;
+        self.pos = 0;
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for EncryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.segment.len() && !self.next_segment()? {
+            return Ok(0);
+        }
+
+        let n = min(buf.len(), self.segment.len() - self.pos);
+        buf[..n].copy_from_slice(&self.segment[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+/// Writes the decryption of a stream encrypted by `EncryptReader` to `target`. Segments are only
+/// written once authenticated, and `finish` must be called to authenticate the last one.
+pub struct DecryptWriter<W: Write> {
+    target: W,
+    decryptor: DecryptorBE32<Aes256Gcm>,
+    segment: Vec<u8>,
+}
+
+impl<W: Write> DecryptWriter<W> {
+    pub fn new(key: &[u8], nonce: &[u8; STREAM_NONCE_SIZE], target: W) -> Result<Self> {
+        ensure!(key.len() == 32, "invalid key length");
+
+        let cipher = Aes256Gcm::new_from_slice(key).context("invalid key")?;
+
+        Ok(DecryptWriter {
+            target,
+            decryptor: DecryptorBE32::from_aead(cipher, nonce.into()),
+            segment: Vec::new(),
+        })
+    }
+
+    /// Decrypts the last segment and returns the target.
+    pub fn finish(mut self) -> Result<W> {
+        let plaintext = self
+            .decryptor
+            .decrypt_last(self.segment.as_slice())
+            .map_err(|_| anyhow!("failed to decrypt"))?;
+        self.target.write_all(&plaintext)?;
+        self.target.flush()?;
+
+        Ok(self.target)
+    }
+}
+
+impl<W: Write> Write for DecryptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        const SEGMENT_LEN: usize = STREAM_SEGMENT_SIZE + STREAM_TAG_SIZE;
+
+        self.segment.extend_from_slice(buf);
+        // The last segment is kept until `finish`, as it is decrypted differently.
+        while self.segment.len() > SEGMENT_LEN {
+            let plaintext = self
+                .decryptor
+                .decrypt_next(&self.segment[..SEGMENT_LEN])
+                .map_err(|_| io::Error::other("failed to decrypt"))?;
+            self.target.write_all(&plaintext)?;
+            self.segment.drain(..SEGMENT_LEN);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.target.flush()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,4 +241,63 @@ mod tests {
             assert_eq!(plaintext, roundtrip, "failed to roundtrip");
         }
     }
+
+    #[test]
+    fn test_aes_stream() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let key: [u8; 32] = rng.gen();
+        let nonce: [u8; STREAM_NONCE_SIZE] = rng.gen();
+
+        for len in [
+            0,
+            1,
+            STREAM_SEGMENT_SIZE - 1,
+            STREAM_SEGMENT_SIZE,
+            3 * STREAM_SEGMENT_SIZE + 7,
+        ] {
+            let plaintext: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut ciphertext = Vec::new();
+            EncryptReader::new(&key, &nonce, plaintext.as_slice(), len as u64)
+                .expect("failed to create encryptor")
+                .read_to_end(&mut ciphertext)
+                .expect("failed to encrypt");
+            assert_eq!(ciphertext.len() as u64, stream_ciphertext_len(len as u64));
+
+            let mut writer =
+                DecryptWriter::new(&key, &nonce, Vec::new()).expect("failed to create decryptor");
+            // Writes in chunks not aligned with the segments.
+            for chunk in ciphertext.chunks(1000) {
+                writer.write_all(chunk).expect("failed to decrypt");
+            }
+            let roundtrip = writer.finish().expect("failed to decrypt");
+            assert_eq!(plaintext, roundtrip, "failed to roundtrip");
+
+            // Any modification of the ciphertext is detected.
+            ciphertext[len / 2] ^= 1;
+            let mut writer =
+                DecryptWriter::new(&key, &nonce, Vec::new()).expect("failed to create decryptor");
+            let res = writer
+                .write_all(&ciphertext)
+                .map_err(Into::into)
+                .and_then(|_| writer.finish());
+            assert!(res.is_err(), "tampered ciphertext was decrypted");
+        }
+    }
+
+    #[test]
+    fn test_wrap_key() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let kek: [u8; 32] = rng.gen();
+        let key: [u8; 32] = rng.gen();
+
+        let wrapped = wrap_key(&kek, &key, &mut rng).expect("failed to wrap key");
+        assert_eq!(
+            unwrap_key(&kek, &wrapped).expect("failed to unwrap key"),
+            key
+        );
+
+        let other_kek: [u8; 32] = rng.gen();
+        assert!(unwrap_key(&other_kek, &wrapped).is_err());
+    }
 }
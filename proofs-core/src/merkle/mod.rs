@@ -9,10 +9,12 @@ use generic_array::typenum::{U0, U2};
 use merkletree::store::LevelCacheStore;
 
 mod builders;
+mod multi_proof;
 mod proof;
 mod tree;
 
 pub use builders::*;
+pub use multi_proof::*;
 pub use proof::*;
 pub use tree::*;
 
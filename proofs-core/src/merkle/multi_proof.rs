@@ -0,0 +1,377 @@
+#![allow(clippy::len_without_is_empty)]
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use anyhow::{ensure, Context, Result};
+use generic_array::typenum::U0;
+use hashers::{Hasher, PoseidonArity};
+use merkletree::hash::Algorithm;
+use serde::{Deserialize, Serialize};
+
+use crate::merkle::{base_path_length, MerkleProofTrait};
+
+/// Merkle proof of several leaves of the same tree.
+///
+/// Nodes shared by the paths of the leaves are included only once, and nodes which can be
+/// computed from the proven leaves are left out. `siblings` holds the remaining nodes, level by
+/// level from the bottom up and ordered by position within each level, which is also the order
+/// they are consumed in by `verify`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiMerkleProof<
+    H: Hasher,
+    BaseArity: PoseidonArity,
+    SubTreeArity: PoseidonArity = U0,
+    TopTreeArity: PoseidonArity = U0,
+> {
+    /// Proven leaf indices, sorted and without duplicates.
+    indices: Vec<usize>,
+    /// The leaves at `indices`.
+    #[serde(bound(
+        serialize = "H::Domain: Serialize",
+        deserialize = "H::Domain: Deserialize<'de>"
+    ))]
+    leaves: Vec<H::Domain>,
+    #[serde(bound(
+        serialize = "H::Domain: Serialize",
+        deserialize = "H::Domain: Deserialize<'de>"
+    ))]
+    siblings: Vec<H::Domain>,
+    /// Root of the merkle tree.
+    #[serde(bound(
+        serialize = "H::Domain: Serialize",
+        deserialize = "H::Domain: Deserialize<'de>"
+    ))]
+    root: H::Domain,
+    /// Number of levels of the base trees.
+    base_height: usize,
+    #[serde(skip)]
+    _arity: PhantomData<(BaseArity, SubTreeArity, TopTreeArity)>,
+}
+
+impl<
+        H: Hasher,
+        BaseArity: 'static + PoseidonArity,
+        SubTreeArity: 'static + PoseidonArity,
+        TopTreeArity: 'static + PoseidonArity,
+    > MultiMerkleProof<H, BaseArity, SubTreeArity, TopTreeArity>
+{
+    /// Merges single proofs of the same tree into a multi proof.
+    pub fn from_proofs<P>(proofs: &[P]) -> Result<Self>
+    where
+        P: MerkleProofTrait<
+            Hasher = H,
+            Arity = BaseArity,
+            SubTreeArity = SubTreeArity,
+            TopTreeArity = TopTreeArity,
+        >,
+    {
+        ensure!(!proofs.is_empty(), "no proofs to merge");
+        let root = proofs[0].root();
+        let height = proofs[0].path().len();
+        let base_height = height
+            .checked_sub(Self::upper_levels())
+            .context("path is too short for the tree shape")?;
+
+        // Every node the single proofs know of, by level and position.
+        let mut nodes = HashMap::new();
+        let mut leaves = Vec::with_capacity(proofs.len());
+        for proof in proofs {
+            ensure!(proof.root() == root, "proofs are for different trees");
+            let path = proof.path();
+            ensure!(path.len() == height, "proofs have different lengths");
+
+            let mut position = proof.path_index();
+            leaves.push((position, proof.leaf()));
+            for (level, ((hashes, index), arity)) in path
+                .iter()
+                .zip(Self::arities(base_height).map(|(arity, _)| arity))
+                .enumerate()
+            {
+                ensure!(position % arity == *index, "invalid path index");
+                let first = position - index;
+                let slots = (0..arity).filter(|slot| slot != index);
+                for (slot, hash) in slots.zip(hashes) {
+                    nodes.insert((level, first + slot), *hash);
+                }
+                position /= arity;
+            }
+        }
+        leaves.sort_by_key(|(index, _)| *index);
+        leaves.dedup_by_key(|(index, _)| *index);
+
+        let mut siblings = Vec::new();
+        let calculated_root = Self::fold(base_height, &leaves, |level, position| {
+            let node = nodes.get(&(level, position)).copied()?;
+            siblings.push(node);
+            Some(node)
+        });
+        ensure!(
+            calculated_root == Some(root),
+            "proofs do not hash up to their root"
+        );
+
+        let (indices, leaves) = leaves.into_iter().unzip();
+
+        Ok(MultiMerkleProof {
+            indices,
+            leaves,
+            siblings,
+            root,
+            base_height,
+            _arity: PhantomData,
+        })
+    }
+
+    /// Checks that all leaves hash up to the root of a tree of `leaves` leaves, consuming every
+    /// sibling exactly once.
+    pub fn verify(&self, leaves: usize) -> bool {
+        if self.indices.is_empty()
+            || self.indices.len() != self.leaves.len()
+            || self.indices.windows(2).any(|pair| pair[0] >= pair[1])
+            || self.indices.iter().any(|index| *index >= leaves)
+            || self.base_height != base_path_length::<BaseArity, SubTreeArity, TopTreeArity>(leaves)
+        {
+            return false;
+        }
+
+        let leaves = self
+            .indices
+            .iter()
+            .copied()
+            .zip(self.leaves.iter().copied())
+            .collect::<Vec<_>>();
+        let mut siblings = self.siblings.iter();
+        let calculated_root =
+            Self::fold(self.base_height, &leaves, |_, _| siblings.next().copied());
+
+        calculated_root == Some(self.root) && siblings.next().is_none()
+    }
+
+    /// Returns true if the proof covers all of the given challenges.
+    pub fn proves_challenges(&self, challenges: &[usize]) -> bool {
+        challenges
+            .iter()
+            .all(|challenge| self.indices.binary_search(challenge).is_ok())
+    }
+
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    pub fn leaves(&self) -> &[H::Domain] {
+        &self.leaves
+    }
+
+    /// The leaf at the given index, if it is proven.
+    pub fn leaf(&self, index: usize) -> Option<H::Domain> {
+        self.indices
+            .binary_search(&index)
+            .ok()
+            .map(|i| self.leaves[i])
+    }
+
+    pub fn root(&self) -> H::Domain {
+        self.root
+    }
+
+    /// Number of sibling nodes in the proof.
+    pub fn len(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Number of levels above the base trees.
+    fn upper_levels() -> usize {
+        (SubTreeArity::to_usize() > 0) as usize + (TopTreeArity::to_usize() > 0) as usize
+    }
+
+    /// Arity of every level from the bottom up, along with its height within its tree.
+    fn arities(base_height: usize) -> impl Iterator<Item = (usize, usize)> {
+        let sub = Some(SubTreeArity::to_usize()).filter(|arity| *arity > 0);
+        let top = Some(TopTreeArity::to_usize()).filter(|arity| *arity > 0);
+
+        (0..base_height)
+            .map(|height| (BaseArity::to_usize(), height))
+            .chain(sub.map(|arity| (arity, 0)))
+            .chain(top.map(|arity| (arity, 0)))
+    }
+
+    /// Hashes the sorted `(position, node)` pairs of the leaves up to the root, asking `sibling`
+    /// for every node that is not known, in the order they are stored in `siblings`.
+    fn fold<F>(
+        base_height: usize,
+        leaves: &[(usize, H::Domain)],
+        mut sibling: F,
+    ) -> Option<H::Domain>
+    where
+        F: FnMut(usize, usize) -> Option<H::Domain>,
+    {
+        let mut a = H::Function::default();
+        let mut nodes = leaves.to_vec();
+
+        for (level, (arity, height)) in Self::arities(base_height).enumerate() {
+            let mut parents = Vec::new();
+            let mut known = nodes.iter().peekable();
+
+            while let Some((position, _)) = known.peek() {
+                let parent = position / arity;
+                let mut children = Vec::with_capacity(arity);
+                for position in parent * arity..(parent + 1) * arity {
+                    match known.next_if(|(known, _)| *known == position) {
+                        Some((_, node)) => children.push(*node),
+                        None => children.push(sibling(level, position)?),
+                    }
+                }
+
+                a.reset();
+                parents.push((parent, a.multi_node(&children, height)));
+            }
+            nodes = parents;
+        }
+
+        match nodes[..] {
+            [(0, root)] => Some(root),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use generic_array::typenum::{Unsigned, U2, U8};
+    use hashers::{poseidon::PoseidonHasher, sha256::Sha256Hasher};
+    use rand::thread_rng;
+
+    use crate::merkle::{
+        generate_tree, get_base_tree_count, DiskStore, MerkleTreeTrait, MerkleTreeWrapper,
+    };
+
+    fn multi_proof<Tree: 'static + MerkleTreeTrait>() {
+        let nodes = 64 * get_base_tree_count::<Tree>();
+
+        let mut rng = thread_rng();
+        let (_, tree) = generate_tree::<Tree, _>(&mut rng, nodes, None);
+
+        // Siblings, neighbouring groups, other sub trees and a duplicate.
+        let indices = [0, 1, 9, 17, nodes / 2, nodes - 1, 9];
+        let proofs = indices
+            .iter()
+            .map(|i| tree.gen_proof(*i).expect("gen_proof failure"))
+            .collect::<Vec<_>>();
+        let multi_proof = MultiMerkleProof::<
+            Tree::Hasher,
+            Tree::Arity,
+            Tree::SubTreeArity,
+            Tree::TopTreeArity,
+        >::from_proofs(&proofs)
+        .expect("from_proofs failure");
+
+        assert!(multi_proof.verify(nodes), "failed to validate");
+        assert!(multi_proof.proves_challenges(&indices));
+        assert!(!multi_proof.proves_challenges(&[2]));
+        assert_eq!(multi_proof.root(), tree.root());
+        for proof in &proofs {
+            assert_eq!(multi_proof.leaf(proof.path_index()), Some(proof.leaf()));
+        }
+
+        let single_len = proofs[..6]
+            .iter()
+            .map(|proof| {
+                proof
+                    .path()
+                    .iter()
+                    .map(|(hashes, _)| hashes.len())
+                    .sum::<usize>()
+            })
+            .sum::<usize>();
+        assert!(multi_proof.len() < single_len);
+
+        let serialized = serde_json::to_string(&multi_proof).expect("serialize failure");
+        let deserialized: MultiMerkleProof<
+            Tree::Hasher,
+            Tree::Arity,
+            Tree::SubTreeArity,
+            Tree::TopTreeArity,
+        > = serde_json::from_str(&serialized).expect("deserialize failure");
+        assert!(deserialized.verify(nodes));
+
+        let mut bad = multi_proof.clone();
+        bad.leaves.swap(0, 1);
+        assert!(!bad.verify(nodes), "swapped leaves must not verify");
+
+        let mut bad = multi_proof.clone();
+        bad.siblings.push(bad.root);
+        assert!(!bad.verify(nodes), "unused siblings must not verify");
+
+        let mut bad = multi_proof.clone();
+        bad.indices[0] = 2;
+        assert!(!bad.verify(nodes), "wrong indices must not verify");
+
+        let mut bad = multi_proof;
+        bad.base_height -= 1;
+        assert!(!bad.verify(nodes), "lowered base height must not verify");
+
+        // The internal nodes of a shorter tree do not pass as leaves of the expected one.
+        let short_nodes = nodes / Tree::Arity::to_usize();
+        let (_, short_tree) = generate_tree::<Tree, _>(&mut rng, short_nodes, None);
+        let short_proof = short_tree
+            .gen_multi_proof(&[0, short_nodes - 1])
+            .expect("gen_multi_proof failure");
+        assert!(short_proof.verify(short_nodes));
+        assert!(!short_proof.verify(nodes), "shorter tree must not verify");
+    }
+
+    #[test]
+    fn multi_proof_sha256_2() {
+        multi_proof::<
+            MerkleTreeWrapper<
+                Sha256Hasher,
+                DiskStore<<Sha256Hasher as Hasher>::Domain>,
+                U2,
+                U0,
+                U0,
+            >,
+        >();
+    }
+
+    #[test]
+    fn multi_proof_poseidon_8() {
+        multi_proof::<
+            MerkleTreeWrapper<
+                PoseidonHasher,
+                DiskStore<<PoseidonHasher as Hasher>::Domain>,
+                U8,
+                U0,
+                U0,
+            >,
+        >();
+    }
+
+    #[test]
+    fn multi_proof_poseidon_8_2() {
+        multi_proof::<
+            MerkleTreeWrapper<
+                PoseidonHasher,
+                DiskStore<<PoseidonHasher as Hasher>::Domain>,
+                U8,
+                U2,
+                U0,
+            >,
+        >();
+    }
+
+    #[test]
+    fn multi_proof_poseidon_8_8_2() {
+        multi_proof::<
+            MerkleTreeWrapper<
+                PoseidonHasher,
+                DiskStore<<PoseidonHasher as Hasher>::Domain>,
+                U8,
+                U8,
+                U2,
+            >,
+        >();
+    }
+}
@@ -14,7 +14,7 @@ use merkletree::{
 };
 use rayon::prelude::{IndexedParallelIterator, IntoParallelIterator};
 
-use crate::merkle::{LCTree, MerkleProof, MerkleProofTrait};
+use crate::merkle::{LCTree, MerkleProof, MerkleProofTrait, MultiMerkleProof};
 
 /// Trait used to abstract over the way Merkle Trees are constructed and stored.
 pub trait MerkleTreeTrait: Send + Sync + Debug {
@@ -150,6 +150,43 @@ impl<
     }
 }
 
+impl<
+        H: 'static + Hasher,
+        S: Store<<H as Hasher>::Domain>,
+        U: 'static + PoseidonArity,
+        V: 'static + PoseidonArity,
+        W: 'static + PoseidonArity,
+    > MerkleTreeWrapper<H, S, U, V, W>
+{
+    /// Creates a single merkle proof of the nodes at the given indices, sharing common nodes.
+    pub fn gen_multi_proof(&self, indices: &[usize]) -> Result<MultiMerkleProof<H, U, V, W>> {
+        let proofs = unique(indices)
+            .map(|i| self.gen_proof(i))
+            .collect::<Result<Vec<_>>>()?;
+
+        MultiMerkleProof::from_proofs(&proofs)
+    }
+
+    pub fn gen_cached_multi_proof(
+        &self,
+        indices: &[usize],
+        rows_to_discard: Option<usize>,
+    ) -> Result<MultiMerkleProof<H, U, V, W>> {
+        let proofs = unique(indices)
+            .map(|i| self.gen_cached_proof(i, rows_to_discard))
+            .collect::<Result<Vec<_>>>()?;
+
+        MultiMerkleProof::from_proofs(&proofs)
+    }
+}
+
+fn unique(indices: &[usize]) -> impl Iterator<Item = usize> {
+    let mut indices = indices.to_vec();
+    indices.sort_unstable();
+    indices.dedup();
+    indices.into_iter()
+}
+
 impl<
         H: Hasher,
         S: Store<<H as Hasher>::Domain>,
@@ -6,6 +6,8 @@ use hashers::{Domain, HashFunction, Hasher, PoseidonArity};
 use fr32::bytes_into_fr_repr_safe;
 use generic_array::typenum::{U0, U2};
 use merkletree::store::{ReplicaConfig, StoreConfig};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -617,6 +619,33 @@ pub fn replica_id<H: Hasher>(prover_id: [u8; 32], sector_id: [u8; 32]) -> H::Dom
     H::Function::hash_leaf(&to_hash)
 }
 
+/// Derives the challenged nodes of a replica from the interactive `seed`. The first node has no
+/// parents, so it is never challenged.
+pub fn derive_challenges<D: Domain>(
+    replica_id: &D,
+    seed: &[u8; 32],
+    nodes: usize,
+    challenges_count: usize,
+) -> Vec<usize> {
+    assert!(nodes > 2, "Too few nodes: {}", nodes);
+
+    (0..challenges_count as u32)
+        .map(|j| {
+            let hash = Sha256::new()
+                .chain_update(replica_id.into_bytes())
+                .chain_update(seed)
+                .chain_update(j.to_le_bytes())
+                .finalize();
+
+            let challenge = BigUint::from_bytes_le(hash.as_ref()) % (nodes - 1);
+            challenge
+                .to_usize()
+                .expect("`challenge` exceeds size of `usize`")
+                + 1
+        })
+        .collect()
+}
+
 fn sloth_encode<H: Hasher>(key: &H::Domain, ciphertext: &H::Domain) -> H::Domain {
     // TODO: validate this is how sloth should work in this case
     let k = (*key).into();
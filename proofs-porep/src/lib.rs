@@ -11,6 +11,7 @@ use proofs_core::{error::Result, merkle::BinaryMerkleTree, proof::ProofScheme, D
 
 pub mod drg;
 pub mod stacked;
+pub mod update;
 
 mod encode;
 
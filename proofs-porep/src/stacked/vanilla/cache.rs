@@ -26,8 +26,6 @@ use crate::stacked::vanilla::graph::{StackedGraph, DEGREE};
 /// u32 = 4 bytes
 const NODE_BYTES: usize = 4;
 
-pub const PARENT_CACHE_DATA: &str = include_str!("../../../parent_cache.json");
-
 pub type ParentCacheDataMap = BTreeMap<String, ParentCacheData>;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -37,8 +35,20 @@ pub struct ParentCacheData {
 }
 
 lazy_static! {
-    pub static ref PARENT_CACHE: ParentCacheDataMap =
-        serde_json::from_str(PARENT_CACHE_DATA).expect("Invalid parent_cache.json");
+    /// Known parent cache digests, taken from the sector profiles.
+    pub static ref PARENT_CACHE: ParentCacheDataMap = sector_registry::global()
+        .profiles()
+        .iter()
+        .flat_map(|profile| {
+            profile.parent_cache.iter().map(|(id, digest)| {
+                let data = ParentCacheData {
+                    digest: digest.clone(),
+                    sector_size: profile.sector_size,
+                };
+                (id.clone(), data)
+            })
+        })
+        .collect();
     static ref PARENT_CACHE_ACCESS_LOCK: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
 }
 
@@ -1,12 +1,12 @@
 use std::marker::PhantomData;
 use std::mem;
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use hashers::Hasher;
 use generic_array::typenum::Unsigned;
 use log::info;
 use merkletree::store::{DiskStore, Store, StoreConfig};
-use sha2raw::Sha256;
+use sha2raw::{Sha256, Sha256Multi, MAX_MESSAGES};
 use proofs_core::{
     drgraph::Graph,
     merkle::MerkleTreeTrait,
@@ -49,30 +49,14 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
 
         parents_cache.reset()?;
 
-        if layer == 1 {
-            for node in 0..graph.size() {
-                create_label(
-                    graph,
-                    Some(parents_cache),
-                    &replica_id,
-                    &mut layer_labels,
-                    layer,
-                    node,
-                )?;
-            }
-        } else {
-            for node in 0..graph.size() {
-                create_label_exp(
-                    graph,
-                    Some(parents_cache),
-                    &replica_id,
-                    &exp_labels,
-                    &mut layer_labels,
-                    layer,
-                    node,
-                )?;
-            }
-        }
+        create_layer_labels(
+            graph,
+            parents_cache,
+            &replica_id,
+            &exp_labels,
+            &mut layer_labels,
+            layer,
+        )?;
 
         // Write the result to disk to avoid keeping it in memory all the time.
         let layer_config = &layer_state.config;
@@ -121,30 +105,14 @@ pub fn create_labels_for_decoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
 
         parents_cache.reset()?;
 
-        if layer == 1 {
-            for node in 0..graph.size() {
-                create_label(
-                    graph,
-                    Some(parents_cache),
-                    &replica_id,
-                    &mut layer_labels,
-                    layer,
-                    node,
-                )?;
-            }
-        } else {
-            for node in 0..graph.size() {
-                create_label_exp(
-                    graph,
-                    Some(parents_cache),
-                    &replica_id,
-                    &exp_labels,
-                    &mut layer_labels,
-                    layer,
-                    node,
-                )?;
-            }
-        }
+        create_layer_labels(
+            graph,
+            parents_cache,
+            &replica_id,
+            &exp_labels,
+            &mut layer_labels,
+            layer,
+        )?;
 
         // Write the result to disk to avoid keeping it in memory all the time.
         info!("  storing labels on disk");
@@ -170,6 +138,117 @@ pub fn create_labels_for_decoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
     Ok(LabelsCache::<Tree> { labels })
 }
 
+/// Labels the nodes of a layer of the replica `replica_id`, `exp_labels` holding its previous
+/// layer.
+fn create_layer_labels<H: Hasher, T: AsRef<[u8]>>(
+    graph: &StackedBucketGraph<H>,
+    parents_cache: &mut ParentCache,
+    replica_id: &T,
+    exp_labels: &[u8],
+    layer_labels: &mut [u8],
+    layer: usize,
+) -> Result<()> {
+    if layer == 1 {
+        for node in 0..graph.size() {
+            create_label(
+                graph,
+                Some(parents_cache),
+                replica_id,
+                layer_labels,
+                layer,
+                node,
+            )?;
+        }
+    } else {
+        for node in 0..graph.size() {
+            create_label_exp(
+                graph,
+                Some(parents_cache),
+                replica_id,
+                exp_labels,
+                layer_labels,
+                layer,
+                node,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Labels the nodes of a layer of each of the replicas `replica_ids`, sealed with the same graph.
+/// `exp_labels[i]` holds the previous layer of the replica `replica_ids[i]`, and
+/// `layer_labels[i]` receives its labels.
+///
+/// With at least `Sha256Multi::lanes()` replicas, they are labeled through the multi-buffer
+/// labelers, up to `MAX_MESSAGES` at a time. Fewer replicas would leave the lanes idle, so they
+/// are labeled one after the other.
+pub fn create_layer_labels_multi<H: Hasher, T: AsRef<[u8]>>(
+    graph: &StackedBucketGraph<H>,
+    parents_cache: &mut ParentCache,
+    replica_ids: &[T],
+    exp_labels: &[&[u8]],
+    layer_labels: &mut [&mut [u8]],
+    layer: usize,
+) -> Result<()> {
+    ensure!(
+        replica_ids.len() == layer_labels.len() && replica_ids.len() == exp_labels.len(),
+        "expected labels for {} replicas",
+        replica_ids.len()
+    );
+
+    if replica_ids.len() < Sha256Multi::lanes() {
+        for ((replica_id, exp_labels), layer_labels) in replica_ids
+            .iter()
+            .zip(exp_labels)
+            .zip(layer_labels.iter_mut())
+        {
+            parents_cache.reset()?;
+            create_layer_labels(
+                graph,
+                parents_cache,
+                replica_id,
+                exp_labels,
+                layer_labels,
+                layer,
+            )?;
+        }
+        return Ok(());
+    }
+
+    for ((replica_ids, exp_labels), layer_labels) in replica_ids
+        .chunks(MAX_MESSAGES)
+        .zip(exp_labels.chunks(MAX_MESSAGES))
+        .zip(layer_labels.chunks_mut(MAX_MESSAGES))
+    {
+        parents_cache.reset()?;
+        for node in 0..graph.size() {
+            if layer == 1 {
+                create_label_multi(
+                    graph,
+                    Some(parents_cache),
+                    replica_ids,
+                    layer_labels,
+                    layer,
+                    node,
+                )?;
+            } else {
+                create_label_exp_multi(
+                    graph,
+                    Some(parents_cache),
+                    replica_ids,
+                    exp_labels,
+                    layer_labels,
+                    layer,
+                    node,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub fn create_label<H: Hasher, T: AsRef<[u8]>>(
     graph: &StackedBucketGraph<H>,
     cache: Option<&mut ParentCache>,
@@ -244,3 +323,230 @@ pub fn create_label_exp<H: Hasher, T: AsRef<[u8]>>(
 
     Ok(())
 }
+
+/// Input of the labels of `node` for up to `MAX_MESSAGES` replicas, all in the same layer.
+fn label_input_multi<T: AsRef<[u8]>>(
+    replica_ids: &[T],
+    layer_index: usize,
+    node: usize,
+) -> Sha256Multi {
+    let mut hasher = Sha256Multi::new(replica_ids.len());
+    let mut buffer = [0u8; 32];
+
+    buffer[..4].copy_from_slice(&(layer_index as u32).to_be_bytes());
+    buffer[4..12].copy_from_slice(&(node as u64).to_be_bytes());
+    let mut blocks = [[&buffer[..]; 2]; MAX_MESSAGES];
+    for (blocks, replica_id) in blocks.iter_mut().zip(replica_ids) {
+        blocks[0] = replica_id.as_ref();
+    }
+    let mut input: [&[&[u8]]; MAX_MESSAGES] = [&[]; MAX_MESSAGES];
+    for (input, blocks) in input.iter_mut().zip(&blocks) {
+        *input = &blocks[..];
+    }
+    hasher.input(&input[..replica_ids.len()]);
+
+    hasher
+}
+
+/// Stores the labels of `node`, as generated for each replica.
+fn store_labels_multi(layer_labels: &mut [&mut [u8]], node: usize, hashes: &[[u8; 32]]) {
+    let start = data_at_node_offset(node);
+    let end = start + NODE_SIZE;
+    for (labels, hash) in layer_labels.iter_mut().zip(hashes) {
+        labels[start..end].copy_from_slice(&hash[..]);
+
+        // strip last two bits, to ensure result is in Fr.
+        labels[end - 1] &= 0b0011_1111;
+    }
+}
+
+/// Creates the label of `node` for up to `MAX_MESSAGES` replicas at once, sealed with the same graph, where
+/// `layer_labels[i]` holds the labels of the replica `replica_ids[i]`. The replicas are hashed
+/// in parallel using the SIMD lanes of `Sha256Multi`, and share the reads of the parent cache.
+///
+/// The nodes of a single layer have to be labeled in order, as a node depends on its
+/// predecessor.
+pub fn create_label_multi<H: Hasher, T: AsRef<[u8]>>(
+    graph: &StackedBucketGraph<H>,
+    cache: Option<&mut ParentCache>,
+    replica_ids: &[T],
+    layer_labels: &mut [&mut [u8]],
+    layer_index: usize,
+    node: usize,
+) -> Result<()> {
+    ensure!(
+        replica_ids.len() == layer_labels.len(),
+        "expected labels for {} replicas",
+        replica_ids.len()
+    );
+    ensure!(
+        replica_ids.len() <= MAX_MESSAGES,
+        "at most {} replicas are labeled at once",
+        MAX_MESSAGES
+    );
+    let hasher = label_input_multi(replica_ids, layer_index, node);
+
+    // hash parents for all non 0 nodes
+    let hashes = if node > 0 {
+        let mut base_data = [&[][..]; MAX_MESSAGES];
+        for (base_data, labels) in base_data.iter_mut().zip(layer_labels.iter()) {
+            *base_data = &labels[..];
+        }
+        graph.copy_parents_data_multi(
+            node as u32,
+            &base_data[..replica_ids.len()],
+            hasher,
+            cache,
+        )?
+    } else {
+        hasher.finish()
+    };
+
+    store_labels_multi(layer_labels, node, &hashes);
+
+    Ok(())
+}
+
+/// Like [`create_label_multi`], for the layers with expander parents, `exp_parents_data[i]`
+/// being the labels of the previous layer of the replica `replica_ids[i]`.
+pub fn create_label_exp_multi<H: Hasher, T: AsRef<[u8]>>(
+    graph: &StackedBucketGraph<H>,
+    cache: Option<&mut ParentCache>,
+    replica_ids: &[T],
+    exp_parents_data: &[&[u8]],
+    layer_labels: &mut [&mut [u8]],
+    layer_index: usize,
+    node: usize,
+) -> Result<()> {
+    ensure!(
+        replica_ids.len() == layer_labels.len() && replica_ids.len() == exp_parents_data.len(),
+        "expected labels for {} replicas",
+        replica_ids.len()
+    );
+    ensure!(
+        replica_ids.len() <= MAX_MESSAGES,
+        "at most {} replicas are labeled at once",
+        MAX_MESSAGES
+    );
+    let hasher = label_input_multi(replica_ids, layer_index, node);
+
+    // hash parents for all non 0 nodes
+    let hashes = if node > 0 {
+        let mut base_data = [&[][..]; MAX_MESSAGES];
+        for (base_data, labels) in base_data.iter_mut().zip(layer_labels.iter()) {
+            *base_data = &labels[..];
+        }
+        graph.copy_parents_data_exp_multi(
+            node as u32,
+            &base_data[..replica_ids.len()],
+            exp_parents_data,
+            hasher,
+            cache,
+        )?
+    } else {
+        hasher.finish()
+    };
+
+    store_labels_multi(layer_labels, node, &hashes);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hashers::poseidon::PoseidonHasher;
+    use proofs_core::{api_version::ApiVersion, drgraph::BASE_DEGREE, TEST_SEED};
+    use rand::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::stacked::vanilla::EXP_DEGREE;
+
+    #[test]
+    fn test_create_label_multi() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let nodes = 64;
+        let graph = StackedBucketGraph::<PoseidonHasher>::new_stacked(
+            nodes,
+            BASE_DEGREE,
+            EXP_DEGREE,
+            [7u8; 32],
+            ApiVersion::V1_1_0,
+        )
+        .expect("stacked graph new failure");
+
+        for replicas in [1, 3, MAX_MESSAGES] {
+            let replica_ids = (0..replicas)
+                .map(|_| {
+                    let mut replica_id = [0u8; 32];
+                    rng.fill_bytes(&mut replica_id);
+                    replica_id[31] &= 0b0011_1111;
+                    replica_id
+                })
+                .collect::<Vec<_>>();
+
+            let mut exp_labels = vec![vec![0u8; nodes * NODE_SIZE]; replicas];
+            for layer in 1..=2 {
+                let mut labels = vec![vec![0u8; nodes * NODE_SIZE]; replicas];
+                for node in 0..nodes {
+                    let mut layer_labels = labels
+                        .iter_mut()
+                        .map(|labels| &mut labels[..])
+                        .collect::<Vec<_>>();
+                    if layer == 1 {
+                        create_label_multi(
+                            &graph,
+                            None,
+                            &replica_ids,
+                            &mut layer_labels,
+                            layer,
+                            node,
+                        )
+                        .expect("create_label_multi failure");
+                    } else {
+                        let exp_parents_data =
+                            exp_labels.iter().map(|l| &l[..]).collect::<Vec<_>>();
+                        create_label_exp_multi(
+                            &graph,
+                            None,
+                            &replica_ids,
+                            &exp_parents_data,
+                            &mut layer_labels,
+                            layer,
+                            node,
+                        )
+                        .expect("create_label_exp_multi failure");
+                    }
+                }
+
+                // Each replica is labeled the same way as on its own.
+                for ((replica_id, exp_labels), labels) in
+                    replica_ids.iter().zip(&exp_labels).zip(&labels)
+                {
+                    let mut layer_labels = vec![0u8; nodes * NODE_SIZE];
+                    for node in 0..nodes {
+                        if layer == 1 {
+                            create_label(&graph, None, replica_id, &mut layer_labels, layer, node)
+                                .expect("create_label failure");
+                        } else {
+                            create_label_exp(
+                                &graph,
+                                None,
+                                replica_id,
+                                exp_labels,
+                                &mut layer_labels,
+                                layer,
+                                node,
+                            )
+                            .expect("create_label_exp failure");
+                        }
+                    }
+                    assert_eq!(&layer_labels, labels);
+                }
+
+                exp_labels = labels;
+            }
+        }
+    }
+}
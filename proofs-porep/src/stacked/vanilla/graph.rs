@@ -5,7 +5,7 @@ use std::marker::PhantomData;
 use anyhow::ensure;
 use hashers::Hasher;
 use log::info;
-use sha2raw::Sha256;
+use sha2raw::{Sha256, Sha256Multi, MAX_MESSAGES};
 use proofs_core::{
     api_version::ApiVersion,
     crypto::{
@@ -182,6 +182,100 @@ where
         }
     }
 
+    /// Like `copy_parents_data_exp`, but for up to `MAX_MESSAGES` replicas at once, which share
+    /// the parents of `node`. `base_data[i]` and `exp_data[i]` are the labels of the `i`th
+    /// replica, whose label data is returned in the `i`th entry.
+    pub fn copy_parents_data_exp_multi(
+        &self,
+        node: u32,
+        base_data: &[&[u8]],
+        exp_data: &[&[u8]],
+        hasher: Sha256Multi,
+        cache: Option<&mut ParentCache>,
+    ) -> Result<[[u8; 32]; MAX_MESSAGES]> {
+        let cache_parents = self.read_parents(node, cache)?;
+        let replicas = hasher.messages();
+
+        let mut parents = [[&[][..]; DEGREE]; MAX_MESSAGES];
+        for ((parents, base_data), exp_data) in parents.iter_mut().zip(base_data).zip(exp_data) {
+            prefetch(&cache_parents[..BASE_DEGREE], base_data);
+            prefetch(&cache_parents[BASE_DEGREE..], exp_data);
+
+            for (i, parent) in parents.iter_mut().enumerate() {
+                let data = if i < BASE_DEGREE { base_data } else { exp_data };
+                *parent = read_node(i, &cache_parents, data);
+            }
+        }
+        let mut all: [&[&[u8]]; MAX_MESSAGES] = [&[]; MAX_MESSAGES];
+        let mut first: [&[&[u8]]; MAX_MESSAGES] = [&[]; MAX_MESSAGES];
+        let mut last = [&[][..]; MAX_MESSAGES];
+        for (i, p) in parents.iter().enumerate() {
+            all[i] = &p[..];
+            first[i] = &p[..8];
+            last[i] = p[8];
+        }
+
+        let mut hasher = hasher;
+        // round 1 (14)
+        hasher.input(&all[..replicas]);
+
+        // round 2 (14)
+        hasher.input(&all[..replicas]);
+
+        // round 3 (9)
+        hasher.input(&first[..replicas]);
+        Ok(hasher.finish_with(&last[..replicas]))
+    }
+
+    /// Like `copy_parents_data`, but for up to `MAX_MESSAGES` replicas at once, which share the
+    /// parents of `node`. `base_data[i]` are the labels of the `i`th replica, whose label data is
+    /// returned in the `i`th entry.
+    pub fn copy_parents_data_multi(
+        &self,
+        node: u32,
+        base_data: &[&[u8]],
+        hasher: Sha256Multi,
+        cache: Option<&mut ParentCache>,
+    ) -> Result<[[u8; 32]; MAX_MESSAGES]> {
+        let cache_parents = self.read_parents(node, cache)?;
+        let replicas = hasher.messages();
+
+        let mut parents = [[&[][..]; BASE_DEGREE]; MAX_MESSAGES];
+        for (parents, base_data) in parents.iter_mut().zip(base_data) {
+            prefetch(&cache_parents[..BASE_DEGREE], base_data);
+
+            for (i, parent) in parents.iter_mut().enumerate() {
+                *parent = read_node(i, &cache_parents, base_data);
+            }
+        }
+        let mut all: [&[&[u8]]; MAX_MESSAGES] = [&[]; MAX_MESSAGES];
+        let mut first = [&[][..]; MAX_MESSAGES];
+        for (i, p) in parents.iter().enumerate() {
+            all[i] = &p[..];
+            first[i] = p[0];
+        }
+
+        let mut hasher = hasher;
+        // rounds 1 to 6 (0..36)
+        for _ in 0..6 {
+            hasher.input(&all[..replicas]);
+        }
+
+        // round 7 (37)
+        Ok(hasher.finish_with(&first[..replicas]))
+    }
+
+    fn read_parents(&self, node: u32, cache: Option<&mut ParentCache>) -> Result<[u32; DEGREE]> {
+        match cache {
+            Some(cache) => cache.read(node),
+            None => {
+                let mut parents = [0u32; DEGREE];
+                self.parents(node as usize, &mut parents[..])?;
+                Ok(parents)
+            }
+        }
+    }
+
     fn copy_parents_data_inner_exp(
         &self,
         cache_parents: &[u32],
@@ -0,0 +1,259 @@
+use std::marker::PhantomData;
+
+use bellperson::{gadgets::num::AllocatedNum, Circuit, ConstraintSystem, SynthesisError};
+use blstrs::Scalar as Fr;
+use generic_array::typenum::{U0, U2};
+use hashers::{HashFunction, Hasher, PoseidonArity};
+use proofs_core::{
+    compound_proof::CircuitComponent,
+    gadgets::{
+        constraint,
+        por::{AuthPath, PoRCircuit},
+        variables::Root,
+    },
+    merkle::{DiskStore, MerkleProofTrait, MerkleTreeTrait, MerkleTreeWrapper},
+};
+
+use crate::update::vanilla::{ChallengeProof as VanillaChallengeProof, PublicParams};
+
+type TreeAuthPath<T> = AuthPath<
+    <T as MerkleTreeTrait>::Hasher,
+    <T as MerkleTreeTrait>::Arity,
+    <T as MerkleTreeTrait>::SubTreeArity,
+    <T as MerkleTreeTrait>::TopTreeArity,
+>;
+
+/// Proof for a single challenge.
+#[derive(Debug)]
+pub struct ChallengeProof<Tree: MerkleTreeTrait, G: Hasher> {
+    /// The value of the challenged node in the new data.
+    pub data_leaf: Option<Fr>,
+    /// Inclusion path of the challenged data node in tree D.
+    pub data_path: AuthPath<G, U2, U0, U0>,
+    /// The value of the challenged node in the old replica.
+    pub replica_old_leaf: Option<Fr>,
+    /// Inclusion path of the challenged node in the old tree R.
+    pub replica_old_path: TreeAuthPath<Tree>,
+    /// The value of the challenged node in the new replica.
+    pub replica_new_leaf: Option<Fr>,
+    /// Inclusion path of the challenged node in the new tree R.
+    pub replica_new_path: TreeAuthPath<Tree>,
+    /// The encoding factor of the challenged node.
+    pub rho: Option<Fr>,
+    _t: PhantomData<Tree>,
+}
+
+impl<Tree: MerkleTreeTrait, G: 'static + Hasher> ChallengeProof<Tree, G> {
+    /// Create an empty proof, used in `blank_circuit`s.
+    pub fn empty(params: &PublicParams) -> Self {
+        ChallengeProof {
+            data_leaf: None,
+            data_path: AuthPath::blank(params.nodes),
+            replica_old_leaf: None,
+            replica_old_path: AuthPath::blank(params.nodes),
+            replica_new_leaf: None,
+            replica_new_path: AuthPath::blank(params.nodes),
+            rho: None,
+            _t: PhantomData,
+        }
+    }
+
+    pub fn from_vanilla(proof: VanillaChallengeProof<Tree, G>, rho: Fr) -> Self
+    where
+        Tree::Hasher: 'static,
+    {
+        ChallengeProof {
+            data_leaf: Some(proof.proof_d_new.leaf().into()),
+            data_path: proof.proof_d_new.as_options().into(),
+            replica_old_leaf: Some(proof.proof_r_old.leaf().into()),
+            replica_old_path: proof.proof_r_old.as_options().into(),
+            replica_new_leaf: Some(proof.proof_r_new.leaf().into()),
+            replica_new_path: proof.proof_r_new.as_options().into(),
+            rho: Some(rho),
+            _t: PhantomData,
+        }
+    }
+
+    /// Circuit synthesis.
+    pub fn synthesize<CS: ConstraintSystem<Fr>>(
+        self,
+        mut cs: CS,
+        comm_d_new: &AllocatedNum<Fr>,
+        comm_r_last_old: &AllocatedNum<Fr>,
+        comm_r_last_new: &AllocatedNum<Fr>,
+    ) -> Result<(), SynthesisError> {
+        let ChallengeProof {
+            data_leaf,
+            data_path,
+            replica_old_leaf,
+            replica_old_path,
+            replica_new_leaf,
+            replica_new_path,
+            rho,
+            ..
+        } = self;
+
+        let data_leaf_num = AllocatedNum::alloc(cs.namespace(|| "data_leaf"), || {
+            data_leaf.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        enforce_inclusion(
+            cs.namespace(|| "comm_d_new_inclusion"),
+            data_path,
+            comm_d_new,
+            &data_leaf_num,
+        )?;
+
+        let replica_old_leaf_num =
+            AllocatedNum::alloc(cs.namespace(|| "replica_old_leaf"), || {
+                replica_old_leaf.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+        enforce_inclusion(
+            cs.namespace(|| "comm_r_last_old_inclusion"),
+            replica_old_path,
+            comm_r_last_old,
+            &replica_old_leaf_num,
+        )?;
+
+        let replica_new_leaf_num =
+            AllocatedNum::alloc(cs.namespace(|| "replica_new_leaf"), || {
+                replica_new_leaf.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+        enforce_inclusion(
+            cs.namespace(|| "comm_r_last_new_inclusion"),
+            replica_new_path,
+            comm_r_last_new,
+            &replica_new_leaf_num,
+        )?;
+
+        // PublicInput: rho
+        let rho_num = AllocatedNum::alloc(cs.namespace(|| "rho"), || {
+            rho.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        rho_num.inputize(cs.namespace(|| "rho_input"))?;
+
+        // data_leaf * rho = replica_new_leaf - replica_old_leaf
+        cs.enforce(
+            || "enforce encoding",
+            |lc| lc + data_leaf_num.get_variable(),
+            |lc| lc + rho_num.get_variable(),
+            |lc| lc + replica_new_leaf_num.get_variable() - replica_old_leaf_num.get_variable(),
+        );
+
+        Ok(())
+    }
+}
+
+/// Enforce the inclusion of the given path, to the given leaf and the root.
+fn enforce_inclusion<H, U, V, W, CS: ConstraintSystem<Fr>>(
+    cs: CS,
+    path: AuthPath<H, U, V, W>,
+    root: &AllocatedNum<Fr>,
+    leaf: &AllocatedNum<Fr>,
+) -> Result<(), SynthesisError>
+where
+    H: 'static + Hasher,
+    U: 'static + PoseidonArity,
+    V: 'static + PoseidonArity,
+    W: 'static + PoseidonArity,
+{
+    let root = Root::from_allocated::<CS>(root.clone());
+    let leaf = Root::from_allocated::<CS>(leaf.clone());
+
+    PoRCircuit::<MerkleTreeWrapper<H, DiskStore<H::Domain>, U, V, W>>::synthesize(
+        cs, leaf, path, root, true,
+    )
+}
+
+/// Empty sector update circuit.
+///
+/// # Public Inputs
+///
+/// * `comm_r_old`, `comm_d_new` and `comm_r_new`.
+/// * For every challenge, the packed paths in tree D, the old and the new tree R, then `rho`.
+pub struct EmptySectorUpdateCircuit<Tree: MerkleTreeTrait, G: Hasher> {
+    pub comm_r_old: Option<Fr>,
+    pub comm_d_new: Option<Fr>,
+    pub comm_r_new: Option<Fr>,
+    pub comm_c: Option<Fr>,
+    pub comm_r_last_old: Option<Fr>,
+    pub comm_r_last_new: Option<Fr>,
+
+    // one proof per challenge
+    pub challenge_proofs: Vec<ChallengeProof<Tree, G>>,
+}
+
+impl<Tree: MerkleTreeTrait, G: Hasher> CircuitComponent for EmptySectorUpdateCircuit<Tree, G> {
+    type ComponentPrivateInputs = ();
+}
+
+impl<Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> Circuit<Fr>
+    for EmptySectorUpdateCircuit<Tree, G>
+{
+    fn synthesize<CS: ConstraintSystem<Fr>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let EmptySectorUpdateCircuit {
+            comm_r_old,
+            comm_d_new,
+            comm_r_new,
+            comm_c,
+            comm_r_last_old,
+            comm_r_last_new,
+            challenge_proofs,
+        } = self;
+
+        let alloc = |cs: &mut CS, name: &'static str, value: Option<Fr>| {
+            AllocatedNum::alloc(cs.namespace(|| name), || {
+                value.ok_or(SynthesisError::AssignmentMissing)
+            })
+        };
+
+        let comm_r_old_num = alloc(cs, "comm_r_old", comm_r_old)?;
+        comm_r_old_num.inputize(cs.namespace(|| "comm_r_old_input"))?;
+
+        let comm_d_new_num = alloc(cs, "comm_d_new", comm_d_new)?;
+        comm_d_new_num.inputize(cs.namespace(|| "comm_d_new_input"))?;
+
+        let comm_r_new_num = alloc(cs, "comm_r_new", comm_r_new)?;
+        comm_r_new_num.inputize(cs.namespace(|| "comm_r_new_input"))?;
+
+        let comm_c_num = alloc(cs, "comm_c", comm_c)?;
+        let comm_r_last_old_num = alloc(cs, "comm_r_last_old", comm_r_last_old)?;
+        let comm_r_last_new_num = alloc(cs, "comm_r_last_new", comm_r_last_new)?;
+
+        // Verify comm_r_old = H(comm_c || comm_r_last_old)
+        let hash_num = <Tree::Hasher as Hasher>::Function::hash2_circuit(
+            cs.namespace(|| "H_comm_c_comm_r_last_old"),
+            &comm_c_num,
+            &comm_r_last_old_num,
+        )?;
+        constraint::equal(
+            cs,
+            || "enforce comm_r_old = H(comm_c || comm_r_last_old)",
+            &comm_r_old_num,
+            &hash_num,
+        );
+
+        // Verify comm_r_new = H(comm_c || comm_r_last_new)
+        let hash_num = <Tree::Hasher as Hasher>::Function::hash2_circuit(
+            cs.namespace(|| "H_comm_c_comm_r_last_new"),
+            &comm_c_num,
+            &comm_r_last_new_num,
+        )?;
+        constraint::equal(
+            cs,
+            || "enforce comm_r_new = H(comm_c || comm_r_last_new)",
+            &comm_r_new_num,
+            &hash_num,
+        );
+
+        for (i, proof) in challenge_proofs.into_iter().enumerate() {
+            proof.synthesize(
+                cs.namespace(|| format!("challenge_{}", i)),
+                &comm_d_new_num,
+                &comm_r_last_old_num,
+                &comm_r_last_new_num,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::marker::PhantomData;
+
+use anyhow::ensure;
+use bellperson::Circuit;
+use blstrs::Scalar as Fr;
+use hashers::Hasher;
+use proofs_core::{
+    compound_proof::{CircuitComponent, CompoundProof},
+    error::Result,
+    gadgets::por::PoRCompound,
+    merkle::{BinaryMerkleTree, MerkleTreeTrait},
+    parameter_cache::{CacheableParameters, ParameterSetMetadata},
+    por::{self, PoR},
+    proof::ProofScheme,
+};
+
+use crate::update::{circuit::ChallengeProof, EmptySectorUpdate, EmptySectorUpdateCircuit};
+
+pub struct EmptySectorUpdateCompound<Tree: MerkleTreeTrait, G: Hasher> {
+    _t: PhantomData<Tree>,
+    _g: PhantomData<G>,
+}
+
+impl<C: Circuit<Fr>, P: ParameterSetMetadata, Tree: MerkleTreeTrait, G: Hasher>
+    CacheableParameters<C, P> for EmptySectorUpdateCompound<Tree, G>
+{
+    fn cache_prefix() -> String {
+        format!("empty-sector-update-{}-{}", Tree::display(), G::name())
+    }
+}
+
+impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher>
+    CompoundProof<'a, EmptySectorUpdate<'a, Tree, G>, EmptySectorUpdateCircuit<Tree, G>>
+    for EmptySectorUpdateCompound<Tree, G>
+{
+    fn generate_public_inputs(
+        pub_in: &<EmptySectorUpdate<'a, Tree, G> as ProofScheme<'a>>::PublicInputs,
+        pub_params: &<EmptySectorUpdate<'a, Tree, G> as ProofScheme<'a>>::PublicParams,
+        k: Option<usize>,
+    ) -> Result<Vec<Fr>> {
+        let mut inputs = vec![
+            pub_in.comm_r_old.into(),
+            pub_in.comm_d_new.into(),
+            pub_in.comm_r_new.into(),
+        ];
+
+        let por_setup_params = por::SetupParams {
+            leaves: pub_params.nodes,
+            private: true,
+        };
+        let por_params = PoR::<Tree>::setup(&por_setup_params)?;
+        let por_params_d = PoR::<BinaryMerkleTree<G>>::setup(&por_setup_params)?;
+
+        let phi = EmptySectorUpdate::<Tree, G>::phi(&pub_in.comm_d_new, &pub_in.comm_r_old);
+        for challenge in pub_in.challenges(pub_params.nodes, pub_params.challenges_count) {
+            inputs.extend(generate_inclusion_inputs::<BinaryMerkleTree<G>>(
+                &por_params_d,
+                challenge,
+                k,
+            )?);
+            inputs.extend(generate_inclusion_inputs::<Tree>(
+                &por_params,
+                challenge,
+                k,
+            )?);
+            inputs.extend(generate_inclusion_inputs::<Tree>(
+                &por_params,
+                challenge,
+                k,
+            )?);
+            inputs.push(EmptySectorUpdate::<Tree, G>::rho(&phi, challenge));
+        }
+
+        Ok(inputs)
+    }
+
+    fn circuit(
+        public_inputs: &<EmptySectorUpdate<'a, Tree, G> as ProofScheme<'a>>::PublicInputs,
+        _component_private_inputs: <EmptySectorUpdateCircuit<Tree, G> as CircuitComponent>::ComponentPrivateInputs,
+        vanilla_proof: &<EmptySectorUpdate<'a, Tree, G> as ProofScheme<'a>>::Proof,
+        public_params: &<EmptySectorUpdate<'a, Tree, G> as ProofScheme<'a>>::PublicParams,
+        _partition_k: Option<usize>,
+    ) -> Result<EmptySectorUpdateCircuit<Tree, G>> {
+        let challenges =
+            public_inputs.challenges(public_params.nodes, public_params.challenges_count);
+        ensure!(
+            vanilla_proof.challenge_proofs.len() == challenges.len(),
+            "invalid number of challenge proofs"
+        );
+
+        let phi =
+            EmptySectorUpdate::<Tree, G>::phi(&public_inputs.comm_d_new, &public_inputs.comm_r_old);
+        let challenge_proofs = vanilla_proof
+            .challenge_proofs
+            .iter()
+            .zip(challenges)
+            .map(|(proof, challenge)| {
+                ChallengeProof::from_vanilla(
+                    proof.clone(),
+                    EmptySectorUpdate::<Tree, G>::rho(&phi, challenge),
+                )
+            })
+            .collect();
+
+        Ok(EmptySectorUpdateCircuit {
+            comm_r_old: Some(public_inputs.comm_r_old.into()),
+            comm_d_new: Some(public_inputs.comm_d_new.into()),
+            comm_r_new: Some(public_inputs.comm_r_new.into()),
+            comm_c: Some(vanilla_proof.comm_c.into()),
+            comm_r_last_old: Some(vanilla_proof.comm_r_last_old.into()),
+            comm_r_last_new: Some(vanilla_proof.comm_r_last_new.into()),
+            challenge_proofs,
+        })
+    }
+
+    fn blank_circuit(
+        public_params: &<EmptySectorUpdate<'a, Tree, G> as ProofScheme<'a>>::PublicParams,
+    ) -> EmptySectorUpdateCircuit<Tree, G> {
+        EmptySectorUpdateCircuit {
+            comm_r_old: None,
+            comm_d_new: None,
+            comm_r_new: None,
+            comm_c: None,
+            comm_r_last_old: None,
+            comm_r_last_new: None,
+            challenge_proofs: (0..public_params.challenges_count)
+                .map(|_| ChallengeProof::empty(public_params))
+                .collect(),
+        }
+    }
+}
+
+/// Helper to generate public inputs for inclusion proofs.
+fn generate_inclusion_inputs<Tree: 'static + MerkleTreeTrait>(
+    por_params: &por::PublicParams,
+    challenge: usize,
+    k: Option<usize>,
+) -> Result<Vec<Fr>> {
+    let pub_inputs = por::PublicInputs::<<Tree::Hasher as Hasher>::Domain> {
+        challenge,
+        commitment: None,
+    };
+
+    PoRCompound::<Tree>::generate_public_inputs(&pub_inputs, por_params, k)
+}
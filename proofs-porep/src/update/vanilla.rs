@@ -0,0 +1,433 @@
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context};
+use blstrs::Scalar as Fr;
+use ff::Field;
+use generic_array::typenum::{Unsigned, U0, U2};
+use hashers::{Domain, HashFunction, Hasher};
+use log::{info, trace};
+use merkletree::{merkle::get_merkle_tree_leafs, store::StoreConfig};
+use proofs_core::{
+    error::Result,
+    merkle::{
+        create_lc_tree, get_base_tree_count, split_config_and_replica, BinaryMerkleTree, LCTree,
+        MerkleProof, MerkleProofTrait, MerkleTreeTrait,
+    },
+    parameter_cache::ParameterSetMetadata,
+    proof::{NoRequirements, ProofScheme},
+    util::NODE_SIZE,
+};
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSlice, ParallelSliceMut};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// The last layer tree of a replica, whose leaves are read back from the replica file.
+pub type TreeRLast<Tree> = LCTree<
+    <Tree as MerkleTreeTrait>::Hasher,
+    <Tree as MerkleTreeTrait>::Arity,
+    <Tree as MerkleTreeTrait>::SubTreeArity,
+    <Tree as MerkleTreeTrait>::TopTreeArity,
+>;
+
+#[derive(Debug, Clone)]
+pub struct SetupParams {
+    /// Number of nodes of the sector.
+    pub nodes: usize,
+    /// Number of nodes challenged by a proof.
+    pub challenges_count: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct PublicParams {
+    pub nodes: usize,
+    pub challenges_count: usize,
+}
+
+impl ParameterSetMetadata for PublicParams {
+    fn identifier(&self) -> String {
+        format!(
+            "update::PublicParams{{nodes: {}, challenges_count: {}}}",
+            self.nodes, self.challenges_count,
+        )
+    }
+
+    fn sector_size(&self) -> u64 {
+        (self.nodes * NODE_SIZE) as u64
+    }
+}
+
+/// The commitments of an update: the replica before it, the new data and the replica after it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicInputs<T: Domain, S: Domain> {
+    #[serde(bound = "")]
+    pub comm_r_old: T,
+    #[serde(bound = "")]
+    pub comm_d_new: S,
+    #[serde(bound = "")]
+    pub comm_r_new: T,
+}
+
+impl<T: Domain, S: Domain> PublicInputs<T, S> {
+    /// The challenged nodes, drawn from `comm_r_new` so the prover cannot choose them before
+    /// committing to the new replica.
+    pub fn challenges(&self, nodes: usize, challenges_count: usize) -> Vec<usize> {
+        (0..challenges_count)
+            .map(|j| {
+                let hash = Sha256::new()
+                    .chain_update(self.comm_r_new.as_ref())
+                    .chain_update((j as u64).to_le_bytes())
+                    .finalize();
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&hash[..8]);
+                (u64::from_le_bytes(bytes) % nodes as u64) as usize
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct PrivateInputs<'a, Tree: MerkleTreeTrait, G: Hasher> {
+    /// The `comm_c` of the original sealing, which the update keeps.
+    pub comm_c: <Tree::Hasher as Hasher>::Domain,
+    pub tree_d_new: &'a BinaryMerkleTree<G>,
+    pub tree_r_old: &'a TreeRLast<Tree>,
+    pub tree_r_new: &'a TreeRLast<Tree>,
+    /// The rows discarded from both `tree_r_last`s.
+    pub tree_r_rows_to_discard: usize,
+}
+
+/// The openings of a single challenged node.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallengeProof<Tree: MerkleTreeTrait, G: Hasher> {
+    #[serde(bound(
+        serialize = "MerkleProof<G, U2>: Serialize",
+        deserialize = "MerkleProof<G, U2>: Deserialize<'de>"
+    ))]
+    pub proof_d_new: MerkleProof<G, U2>,
+    #[serde(bound(
+        serialize = "MerkleProof<Tree::Hasher, Tree::Arity, Tree::SubTreeArity, Tree::TopTreeArity>: Serialize",
+        deserialize = "MerkleProof<Tree::Hasher, Tree::Arity, Tree::SubTreeArity, Tree::TopTreeArity>: Deserialize<'de>"
+    ))]
+    pub proof_r_old: MerkleProof<Tree::Hasher, Tree::Arity, Tree::SubTreeArity, Tree::TopTreeArity>,
+    #[serde(bound(
+        serialize = "MerkleProof<Tree::Hasher, Tree::Arity, Tree::SubTreeArity, Tree::TopTreeArity>: Serialize",
+        deserialize = "MerkleProof<Tree::Hasher, Tree::Arity, Tree::SubTreeArity, Tree::TopTreeArity>: Deserialize<'de>"
+    ))]
+    pub proof_r_new: MerkleProof<Tree::Hasher, Tree::Arity, Tree::SubTreeArity, Tree::TopTreeArity>,
+}
+
+impl<Tree: MerkleTreeTrait, G: Hasher> Clone for ChallengeProof<Tree, G> {
+    fn clone(&self) -> Self {
+        Self {
+            proof_d_new: self.proof_d_new.clone(),
+            proof_r_old: self.proof_r_old.clone(),
+            proof_r_new: self.proof_r_new.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Proof<Tree: MerkleTreeTrait, G: Hasher> {
+    #[serde(bound = "")]
+    pub comm_c: <Tree::Hasher as Hasher>::Domain,
+    #[serde(bound = "")]
+    pub comm_r_last_old: <Tree::Hasher as Hasher>::Domain,
+    #[serde(bound = "")]
+    pub comm_r_last_new: <Tree::Hasher as Hasher>::Domain,
+    #[serde(bound(
+        serialize = "ChallengeProof<Tree, G>: Serialize",
+        deserialize = "ChallengeProof<Tree, G>: Deserialize<'de>"
+    ))]
+    pub challenge_proofs: Vec<ChallengeProof<Tree, G>>,
+}
+
+impl<Tree: MerkleTreeTrait, G: Hasher> Clone for Proof<Tree, G> {
+    fn clone(&self) -> Self {
+        Self {
+            comm_c: self.comm_c,
+            comm_r_last_old: self.comm_r_last_old,
+            comm_r_last_new: self.comm_r_last_new,
+            challenge_proofs: self.challenge_proofs.clone(),
+        }
+    }
+}
+
+/// Re-encoding of an already sealed replica with new data.
+///
+/// Every node of the new replica is `r_new = r_old + d_new * rho`, where `rho` is derived from
+/// `comm_d_new`, `comm_r_old` and the node index, so the expensive labeling of the original
+/// sealing is reused and only `tree_r_last` is rebuilt.
+#[derive(Debug)]
+pub struct EmptySectorUpdate<'a, Tree: MerkleTreeTrait, G: Hasher> {
+    _a: PhantomData<&'a Tree>,
+    _g: PhantomData<G>,
+}
+
+impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> EmptySectorUpdate<'a, Tree, G> {
+    /// The randomness `phi = H(comm_d_new || comm_r_old)` shared by all nodes.
+    pub fn phi(
+        comm_d_new: &G::Domain,
+        comm_r_old: &<Tree::Hasher as Hasher>::Domain,
+    ) -> <Tree::Hasher as Hasher>::Domain {
+        let comm_d_new: Fr = (*comm_d_new).into();
+        <Tree::Hasher as Hasher>::Function::hash2(&comm_d_new.into(), comm_r_old)
+    }
+
+    /// The factor `rho = H(phi || node)` applied to the data of `node`.
+    pub fn rho(phi: &<Tree::Hasher as Hasher>::Domain, node: usize) -> Fr {
+        let node = Fr::from(node as u64);
+        <Tree::Hasher as Hasher>::Function::hash2(phi, &node.into()).into()
+    }
+
+    /// Encodes `data` into `replica`, which holds the old replica and is overwritten with the
+    /// new one.
+    pub fn encode(
+        comm_d_new: &G::Domain,
+        comm_r_old: &<Tree::Hasher as Hasher>::Domain,
+        replica: &mut [u8],
+        data: &[u8],
+    ) -> Result<()> {
+        ensure!(
+            replica.len() == data.len() && replica.len() % NODE_SIZE == 0,
+            "replica and data must have the same number of nodes"
+        );
+        let phi = Self::phi(comm_d_new, comm_r_old);
+
+        replica
+            .par_chunks_mut(NODE_SIZE)
+            .zip(data.par_chunks(NODE_SIZE))
+            .enumerate()
+            .try_for_each(|(node, (replica_node, data_node))| {
+                let r_old: Fr =
+                    <Tree::Hasher as Hasher>::Domain::try_from_bytes(replica_node)?.into();
+                let d_new: Fr = G::Domain::try_from_bytes(data_node)?.into();
+                let r_new = r_old + d_new * Self::rho(&phi, node);
+                <Tree::Hasher as Hasher>::Domain::from(r_new).write_bytes(replica_node)
+            })
+    }
+
+    /// Decodes the data of `replica_new` into `data`, given the replica it was encoded into.
+    pub fn decode(
+        comm_d_new: &G::Domain,
+        comm_r_old: &<Tree::Hasher as Hasher>::Domain,
+        replica_new: &[u8],
+        replica_old: &[u8],
+        data: &mut [u8],
+    ) -> Result<()> {
+        ensure!(
+            replica_new.len() == replica_old.len()
+                && replica_new.len() == data.len()
+                && data.len() % NODE_SIZE == 0,
+            "replicas and data must have the same number of nodes"
+        );
+        let phi = Self::phi(comm_d_new, comm_r_old);
+
+        data.par_chunks_mut(NODE_SIZE)
+            .zip(replica_new.par_chunks(NODE_SIZE))
+            .zip(replica_old.par_chunks(NODE_SIZE))
+            .enumerate()
+            .try_for_each(|(node, ((data_node, new_node), old_node))| {
+                let r_new: Fr = <Tree::Hasher as Hasher>::Domain::try_from_bytes(new_node)?.into();
+                let r_old: Fr = <Tree::Hasher as Hasher>::Domain::try_from_bytes(old_node)?.into();
+                let rho_inv = Option::<Fr>::from(Self::rho(&phi, node).invert())
+                    .context("rho is not invertible")?;
+                G::Domain::from((r_new - r_old) * rho_inv).write_bytes(data_node)
+            })
+    }
+
+    /// Builds `tree_r_last` over `replica`, which must already be written to `replica_path`.
+    /// `nodes_count` is the number of nodes of each base tree.
+    pub fn generate_tree_r_last(
+        replica: &[u8],
+        nodes_count: usize,
+        tree_count: usize,
+        tree_r_last_config: StoreConfig,
+        replica_path: PathBuf,
+    ) -> Result<TreeRLast<Tree>> {
+        ensure!(
+            replica.len() == nodes_count * tree_count * NODE_SIZE,
+            "replica does not match the tree size"
+        );
+        let (configs, replica_config) = split_config_and_replica(
+            tree_r_last_config.clone(),
+            replica_path,
+            nodes_count,
+            tree_count,
+        )?;
+
+        for (i, (config, nodes)) in configs
+            .iter()
+            .zip(replica.chunks(nodes_count * NODE_SIZE))
+            .enumerate()
+        {
+            info!("building updated tree_r_last {}/{}", i + 1, tree_count);
+            let elements = nodes
+                .chunks(NODE_SIZE)
+                .map(<Tree::Hasher as Hasher>::Domain::try_from_bytes)
+                .collect::<Result<Vec<_>>>()?;
+
+            let store_path = StoreConfig::data_path(&config.path, &config.id);
+            if Path::new(&store_path).exists() {
+                std::fs::remove_file(&store_path)
+                    .with_context(|| format!("could not remove {:?}", store_path))?;
+            }
+
+            LCTree::<Tree::Hasher, Tree::Arity, U0, U0>::from_par_iter_with_config(
+                elements,
+                config.clone(),
+            )
+            .with_context(|| format!("failed tree_r_last {}/{}", i + 1, tree_count))?;
+        }
+
+        create_lc_tree::<TreeRLast<Tree>>(
+            tree_r_last_config.size.context("config size failure")?,
+            &configs,
+            &replica_config,
+        )
+    }
+
+    /// Opens a `tree_r_last` persisted with `tree_r_last_config` over the replica at
+    /// `replica_path`.
+    pub fn open_tree_r_last(
+        tree_r_last_config: &StoreConfig,
+        replica_path: PathBuf,
+    ) -> Result<TreeRLast<Tree>> {
+        // tree_r_last_size stored in the config is the base tree size
+        let tree_r_last_size = tree_r_last_config.size.context("config size failure")?;
+        let tree_count = get_base_tree_count::<Tree>();
+        trace!(
+            "opening tree r last [count {}] with size {}",
+            tree_count,
+            tree_r_last_size
+        );
+        let (configs, replica_config) = split_config_and_replica(
+            tree_r_last_config.clone(),
+            replica_path,
+            get_merkle_tree_leafs(tree_r_last_size, Tree::Arity::to_usize())?,
+            tree_count,
+        )?;
+
+        create_lc_tree::<TreeRLast<Tree>>(tree_r_last_size, &configs, &replica_config)
+    }
+}
+
+impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> ProofScheme<'a>
+    for EmptySectorUpdate<'a, Tree, G>
+{
+    type PublicParams = PublicParams;
+    type SetupParams = SetupParams;
+    type PublicInputs = PublicInputs<<Tree::Hasher as Hasher>::Domain, G::Domain>;
+    type PrivateInputs = PrivateInputs<'a, Tree, G>;
+    type Proof = Proof<Tree, G>;
+    type Requirements = NoRequirements;
+
+    fn setup(sp: &Self::SetupParams) -> Result<Self::PublicParams> {
+        ensure!(sp.nodes > 0, "sector must not be empty");
+        ensure!(
+            sp.challenges_count > 0,
+            "at least one challenge is required"
+        );
+
+        Ok(PublicParams {
+            nodes: sp.nodes,
+            challenges_count: sp.challenges_count,
+        })
+    }
+
+    fn prove<'b>(
+        pub_params: &'b Self::PublicParams,
+        pub_inputs: &'b Self::PublicInputs,
+        priv_inputs: &'b Self::PrivateInputs,
+    ) -> Result<Self::Proof> {
+        let PrivateInputs {
+            comm_c,
+            tree_d_new,
+            tree_r_old,
+            tree_r_new,
+            tree_r_rows_to_discard,
+        } = priv_inputs;
+        let hash2 = <Tree::Hasher as Hasher>::Function::hash2;
+
+        ensure!(
+            tree_d_new.root() == pub_inputs.comm_d_new,
+            "tree_d_new does not match comm_d_new"
+        );
+        ensure!(
+            hash2(comm_c, &tree_r_old.root()) == pub_inputs.comm_r_old,
+            "tree_r_old does not match comm_r_old"
+        );
+        ensure!(
+            hash2(comm_c, &tree_r_new.root()) == pub_inputs.comm_r_new,
+            "tree_r_new does not match comm_r_new"
+        );
+
+        let challenge_proofs = pub_inputs
+            .challenges(pub_params.nodes, pub_params.challenges_count)
+            .into_iter()
+            .map(|challenge| {
+                Ok(ChallengeProof {
+                    proof_d_new: tree_d_new.gen_proof(challenge)?,
+                    proof_r_old: tree_r_old
+                        .gen_cached_proof(challenge, Some(*tree_r_rows_to_discard))?,
+                    proof_r_new: tree_r_new
+                        .gen_cached_proof(challenge, Some(*tree_r_rows_to_discard))?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Proof {
+            comm_c: *comm_c,
+            comm_r_last_old: tree_r_old.root(),
+            comm_r_last_new: tree_r_new.root(),
+            challenge_proofs,
+        })
+    }
+
+    fn verify(
+        pub_params: &Self::PublicParams,
+        pub_inputs: &Self::PublicInputs,
+        proof: &Self::Proof,
+    ) -> Result<bool> {
+        let hash2 = <Tree::Hasher as Hasher>::Function::hash2;
+
+        if hash2(&proof.comm_c, &proof.comm_r_last_old) != pub_inputs.comm_r_old
+            || hash2(&proof.comm_c, &proof.comm_r_last_new) != pub_inputs.comm_r_new
+        {
+            return Ok(false);
+        }
+
+        let challenges = pub_inputs.challenges(pub_params.nodes, pub_params.challenges_count);
+        if proof.challenge_proofs.len() != challenges.len() {
+            return Ok(false);
+        }
+
+        let phi = Self::phi(&pub_inputs.comm_d_new, &pub_inputs.comm_r_old);
+        for (challenge, challenge_proof) in challenges.into_iter().zip(&proof.challenge_proofs) {
+            let ChallengeProof {
+                proof_d_new,
+                proof_r_old,
+                proof_r_new,
+            } = challenge_proof;
+
+            if !proof_d_new.validate(challenge)
+                || proof_d_new.root() != pub_inputs.comm_d_new
+                || !proof_r_old.validate(challenge)
+                || proof_r_old.root() != proof.comm_r_last_old
+                || !proof_r_new.validate(challenge)
+                || proof_r_new.root() != proof.comm_r_last_new
+            {
+                return Ok(false);
+            }
+
+            let d_new: Fr = proof_d_new.leaf().into();
+            let r_old: Fr = proof_r_old.leaf().into();
+            let r_new: Fr = proof_r_new.leaf().into();
+            if r_new != r_old + d_new * Self::rho(&phi, challenge) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
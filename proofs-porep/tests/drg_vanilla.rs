@@ -381,3 +381,22 @@ fn test_drg_porep_verify_fails_on_wrong_parents() {
     test_prove_verify_aux::<BinaryMerkleTree<Sha256Hasher>>(8, 5, false, true);
     test_prove_verify_aux::<BinaryMerkleTree<Blake2sHasher>>(8, 5, false, true);
 }
+
+#[test]
+fn test_drg_derive_challenges() {
+    let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+    let replica_id = <Sha256Hasher as Hasher>::Domain::random(rng);
+    let nodes = 64;
+
+    let challenges = drg::derive_challenges(&replica_id, &[1; 32], nodes, 20);
+    assert_eq!(challenges.len(), 20);
+    assert!(challenges.iter().all(|&c| c > 0 && c < nodes));
+    assert_eq!(
+        challenges,
+        drg::derive_challenges(&replica_id, &[1; 32], nodes, 20)
+    );
+    assert_ne!(
+        challenges,
+        drg::derive_challenges(&replica_id, &[2; 32], nodes, 20)
+    );
+}
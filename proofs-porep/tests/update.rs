@@ -0,0 +1,163 @@
+use std::fs;
+use std::path::Path;
+
+use bellperson::{util_cs::test_cs::TestConstraintSystem, Circuit};
+use blstrs::Scalar as Fr;
+use ff::Field;
+use fr32::fr_into_bytes;
+use generic_array::typenum::{Unsigned, U0, U2, U8};
+use hashers::{poseidon::PoseidonHasher, sha256::Sha256Hasher, HashFunction, Hasher};
+use merkletree::{merkle::get_merkle_tree_len, store::StoreConfig};
+use proofs_core::{
+    cache_key::CacheKey,
+    compound_proof::CompoundProof,
+    merkle::{
+        create_base_merkle_tree, get_base_tree_count, BinaryMerkleTree, DiskTree, MerkleTreeTrait,
+    },
+    proof::ProofScheme,
+    util::default_rows_to_discard,
+    TEST_SEED,
+};
+use proofs_porep::update::{
+    EmptySectorUpdate, EmptySectorUpdateCompound, PrivateInputs, PublicInputs, SetupParams,
+    TreeRLast,
+};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use tempfile::tempdir;
+
+type Update<'a, Tree> = EmptySectorUpdate<'a, Tree, Sha256Hasher>;
+
+#[test]
+fn test_empty_sector_update_base_8() {
+    test_empty_sector_update::<DiskTree<PoseidonHasher, U8, U0, U0>>();
+}
+
+#[test]
+fn test_empty_sector_update_sub_8_2() {
+    test_empty_sector_update::<DiskTree<PoseidonHasher, U8, U2, U0>>();
+}
+
+fn random_nodes(rng: &mut XorShiftRng, nodes: usize) -> Vec<u8> {
+    (0..nodes)
+        .flat_map(|_| fr_into_bytes(&Fr::random(&mut *rng)))
+        .collect()
+}
+
+fn build_tree_r_last<Tree: 'static + MerkleTreeTrait>(
+    replica: &[u8],
+    cache_path: &Path,
+) -> (TreeRLast<Tree>, usize) {
+    let tree_count = get_base_tree_count::<Tree>();
+    let nodes_count = replica.len() / 32 / tree_count;
+    let arity = Tree::Arity::to_usize();
+
+    let config = StoreConfig::new(
+        cache_path,
+        CacheKey::CommRLastTree.to_string(),
+        default_rows_to_discard(nodes_count, arity),
+    );
+    let config = StoreConfig::from_config(
+        &config,
+        CacheKey::CommRLastTree.to_string(),
+        Some(get_merkle_tree_len(nodes_count, arity).expect("tree len failure")),
+    );
+    let rows_to_discard = config.rows_to_discard;
+
+    let replica_path = cache_path.join("replica");
+    fs::write(&replica_path, replica).expect("replica write failure");
+    let tree = Update::<Tree>::generate_tree_r_last(
+        replica,
+        nodes_count,
+        tree_count,
+        config,
+        replica_path,
+    )
+    .expect("generate_tree_r_last failure");
+
+    (tree, rows_to_discard)
+}
+
+fn test_empty_sector_update<Tree: 'static + MerkleTreeTrait>() {
+    let nodes = 8 * get_base_tree_count::<Tree>();
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+    // The replica of a sealed sector and its commitments.
+    let replica_old = random_nodes(&mut rng, nodes);
+    let old_dir = tempdir().expect("tempdir failure");
+    let (tree_r_old, rows_to_discard) = build_tree_r_last::<Tree>(&replica_old, old_dir.path());
+    let comm_c = <Tree::Hasher as Hasher>::Domain::from(Fr::random(&mut rng));
+    let comm_r_old = <Tree::Hasher as Hasher>::Function::hash2(&comm_c, &tree_r_old.root());
+
+    // The new data.
+    let data = random_nodes(&mut rng, nodes);
+    let tree_d_new = create_base_merkle_tree::<BinaryMerkleTree<Sha256Hasher>>(None, nodes, &data)
+        .expect("tree_d failure");
+    let comm_d_new = tree_d_new.root();
+
+    let mut replica_new = replica_old.clone();
+    Update::<Tree>::encode(&comm_d_new, &comm_r_old, &mut replica_new, &data)
+        .expect("encode failure");
+    assert_ne!(
+        replica_new, replica_old,
+        "encoding did not change the replica"
+    );
+
+    let mut decoded = vec![0; data.len()];
+    Update::<Tree>::decode(
+        &comm_d_new,
+        &comm_r_old,
+        &replica_new,
+        &replica_old,
+        &mut decoded,
+    )
+    .expect("decode failure");
+    assert_eq!(decoded, data, "decoding did not recover the data");
+
+    let new_dir = tempdir().expect("tempdir failure");
+    let (tree_r_new, _) = build_tree_r_last::<Tree>(&replica_new, new_dir.path());
+    let comm_r_new = <Tree::Hasher as Hasher>::Function::hash2(&comm_c, &tree_r_new.root());
+
+    let pp = Update::<Tree>::setup(&SetupParams {
+        nodes,
+        challenges_count: 2,
+    })
+    .expect("setup failure");
+    let pub_inputs = PublicInputs {
+        comm_r_old,
+        comm_d_new,
+        comm_r_new,
+    };
+    let priv_inputs = PrivateInputs::<Tree, Sha256Hasher> {
+        comm_c,
+        tree_d_new: &tree_d_new,
+        tree_r_old: &tree_r_old,
+        tree_r_new: &tree_r_new,
+        tree_r_rows_to_discard: rows_to_discard,
+    };
+
+    let proof = Update::<Tree>::prove(&pp, &pub_inputs, &priv_inputs).expect("prove failure");
+    assert!(Update::<Tree>::verify(&pp, &pub_inputs, &proof).expect("verify failure"));
+
+    // The proof is bound to the new data.
+    let mut wrong_inputs = pub_inputs.clone();
+    wrong_inputs.comm_r_new = comm_r_old;
+    assert!(!Update::<Tree>::verify(&pp, &wrong_inputs, &proof).expect("verify failure"));
+
+    // The circuit is satisfied by the proof and takes the expected public inputs.
+    let mut cs = TestConstraintSystem::<Fr>::new();
+    EmptySectorUpdateCompound::<Tree, Sha256Hasher>::circuit(&pub_inputs, (), &proof, &pp, None)
+        .expect("circuit failure")
+        .synthesize(&mut cs)
+        .expect("synthesis failure");
+    assert!(cs.is_satisfied(), "constraints not satisfied");
+
+    let inputs = EmptySectorUpdateCompound::<Tree, Sha256Hasher>::generate_public_inputs(
+        &pub_inputs,
+        &pp,
+        None,
+    )
+    .expect("generate_public_inputs failure");
+    assert_eq!(inputs.len(), 3 + 4 * pp.challenges_count);
+    assert!(cs.verify(&inputs), "public inputs mismatch");
+}
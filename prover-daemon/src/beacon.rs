@@ -0,0 +1,97 @@
+//! Access to the drand beacon the contract draws the tickets and seeds from.
+//!
+//! Rounds are fetched from the HTTP API of a drand relay. Their signatures are
+//! checked by the contract, the client only checks that the published
+//! randomness is the digest of the signature.
+
+use std::time::Duration;
+
+use anyhow::{ensure, Context, Result};
+use contract_auxiliaries::beacon::{draw_randomness, BeaconEntry, SEED_TAG, TICKET_TAG};
+use serde::Deserialize;
+
+/// A round as served by the HTTP API of drand, hex encoded.
+#[derive(Deserialize)]
+struct DrandRound {
+    round: u64,
+    randomness: String,
+    signature: String,
+    #[serde(default)]
+    previous_signature: Option<String>,
+}
+
+pub struct DrandClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl DrandClient {
+    /// `url` is the chain's endpoint, e.g. `https://api.drand.sh/<chain hash>`.
+    pub fn new(url: &str) -> Self {
+        DrandClient {
+            url: url.trim_end_matches('/').to_owned(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<BeaconEntry> {
+        let url = format!("{}/public/{path}", self.url);
+        let round: DrandRound = self
+            .agent
+            .get(&url)
+            .call()
+            .with_context(|| format!("request to {url} failed"))?
+            .into_json()
+            .context("unexpected beacon response")?;
+
+        let entry = BeaconEntry {
+            round: round.round,
+            signature: hex::decode(&round.signature).context("invalid beacon signature")?,
+            previous_signature: round
+                .previous_signature
+                .map(hex::decode)
+                .transpose()
+                .context("invalid beacon previous signature")?,
+        };
+        ensure!(
+            hex::encode(entry.randomness()) == round.randomness,
+            "randomness of beacon round {} is not the digest of its signature",
+            entry.round
+        );
+
+        Ok(entry)
+    }
+
+    pub fn entry(&self, round: u64) -> Result<BeaconEntry> {
+        let entry = self.get(&round.to_string())?;
+        ensure!(
+            entry.round == round,
+            "asked for beacon round {round}, got {}",
+            entry.round
+        );
+
+        Ok(entry)
+    }
+
+    /// The latest published round.
+    pub fn latest(&self) -> Result<BeaconEntry> {
+        self.get("latest")
+    }
+}
+
+/// What the contract personalizes the randomness of a sector with.
+fn entropy(prover_id: &[u8; 32], sector_id: u64) -> Vec<u8> {
+    [prover_id.as_slice(), &sector_id.to_be_bytes()].concat()
+}
+
+/// The ticket the contract expects a sector sealed from `entry` to use.
+pub fn draw_ticket(entry: &BeaconEntry, prover_id: &[u8; 32], sector_id: u64) -> [u8; 32] {
+    draw_randomness(TICKET_TAG, entry, &entropy(prover_id, sector_id))
+}
+
+/// The seed the contract expects a proof drawn from `entry` to use.
+pub fn draw_seed(entry: &BeaconEntry, prover_id: &[u8; 32], sector_id: u64) -> [u8; 32] {
+    draw_randomness(SEED_TAG, entry, &entropy(prover_id, sector_id))
+}
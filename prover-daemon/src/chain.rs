@@ -0,0 +1,213 @@
+//! Access to the verifier contract.
+//!
+//! Queries go through the LCD (REST) endpoint of a node. Transactions are
+//! signed and broadcast by the chain's own CLI, so the prover key stays in the
+//! node's keyring.
+
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::DateTime;
+use contract_auxiliaries::beacon::{BeaconConfig, SealRandomness};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use seal::sector_registry;
+
+/// A round accepting proofs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundInfo {
+    pub round: i32,
+    /// Block time, in seconds, after which proofs for the round are rejected.
+    pub time_expire: u64,
+    pub porep_id: [u8; 32],
+}
+
+/// Arguments of the contract's `SubmitProof` execute message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SubmitProof {
+    pub proof_raw: String, // base64
+    pub public_inputs: Value,
+    pub porep_id: String, // base64
+    pub sector_size: String,
+    pub api_version: String,
+    pub prover_id: String, // base64
+    pub sector_id: u64,
+    pub ticket: String, // base64
+    /// The beacon rounds the ticket and the seed are drawn from, when the contract has a beacon.
+    pub randomness: Option<SealRandomness>,
+}
+
+pub trait Chain {
+    /// The round currently accepting proofs, `None` until the first one is set up.
+    fn open_round(&self) -> Result<Option<RoundInfo>>;
+
+    /// Time of the latest block, in seconds since the epoch.
+    fn block_time(&self) -> Result<u64>;
+
+    /// The beacon the contract expects tickets and seeds to be drawn from.
+    fn beacon(&self) -> Result<Option<BeaconConfig>>;
+
+    /// Whether the prover's proof for `round` is already on chain.
+    fn submitted(&self, round: i32) -> Result<bool>;
+
+    /// Broadcasts a `SubmitProof` message and returns the transaction hash.
+    fn submit_proof(&self, msg: &SubmitProof) -> Result<String>;
+}
+
+/// The contract's name of a sector size.
+pub fn sector_size_name(sector_size: u64) -> Result<&'static str> {
+    match sector_registry::global().get(sector_size) {
+        Some(profile) => Ok(&profile.name),
+        None => bail!("unsupported sector size: {sector_size}"),
+    }
+}
+
+/// How transactions are signed and broadcast.
+pub struct TxConfig {
+    /// The chain CLI, e.g. `oraid`.
+    pub binary: String,
+    /// Key name in the CLI keyring.
+    pub from: String,
+    pub chain_id: String,
+    /// Tendermint RPC endpoint passed to `--node`.
+    pub node: String,
+    pub gas_prices: String,
+}
+
+pub struct CosmWasmChain {
+    lcd_url: String,
+    contract: String,
+    tx: TxConfig,
+    agent: ureq::Agent,
+}
+
+#[derive(Deserialize)]
+struct CurrentRoundResponse {
+    current_round: i32,
+}
+
+#[derive(Deserialize)]
+struct InfoRound {
+    time_expire: u64,
+    porep_id: [u8; 32],
+}
+
+impl CosmWasmChain {
+    pub fn new(lcd_url: &str, contract: &str, tx: TxConfig) -> Self {
+        CosmWasmChain {
+            lcd_url: lcd_url.trim_end_matches('/').to_owned(),
+            contract: contract.to_owned(),
+            tx,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<Value> {
+        let url = format!("{}{path}", self.lcd_url);
+        let response = self
+            .agent
+            .get(&url)
+            .call()
+            .with_context(|| format!("request to {url} failed"))?;
+
+        Ok(response.into_json()?)
+    }
+
+    fn query_smart<T: for<'de> Deserialize<'de>>(&self, query: Value) -> Result<T> {
+        let query = general_purpose::STANDARD.encode(query.to_string());
+        let response = self.get(&format!(
+            "/cosmwasm/wasm/v1/contract/{}/smart/{query}",
+            self.contract
+        ))?;
+
+        serde_json::from_value(response["data"].clone()).context("unexpected query response")
+    }
+
+    /// The address of the signing key, as the contract knows the prover.
+    fn address(&self) -> Result<String> {
+        let output = Command::new(&self.tx.binary)
+            .args(["keys", "show", &self.tx.from, "--address"])
+            .output()
+            .with_context(|| format!("could not run {}", self.tx.binary))?;
+        ensure!(
+            output.status.success(),
+            "could not find key {}: {}",
+            self.tx.from,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+}
+
+impl Chain for CosmWasmChain {
+    fn open_round(&self) -> Result<Option<RoundInfo>> {
+        let CurrentRoundResponse { current_round } =
+            self.query_smart(json!({ "query_round_current": {} }))?;
+        // `current_round` is the next round to be set up, proofs go to the one before.
+        let round = current_round - 1;
+        if round < 1 {
+            return Ok(None);
+        }
+        let info: InfoRound =
+            self.query_smart(json!({ "query_round_info": { "round": round } }))?;
+
+        Ok(Some(RoundInfo {
+            round,
+            time_expire: info.time_expire,
+            porep_id: info.porep_id,
+        }))
+    }
+
+    fn block_time(&self) -> Result<u64> {
+        let block = self.get("/cosmos/base/tendermint/v1beta1/blocks/latest")?;
+        let time = block["block"]["header"]["time"]
+            .as_str()
+            .context("latest block without time")?;
+        let time = DateTime::parse_from_rfc3339(time)
+            .with_context(|| format!("invalid block time={time}"))?;
+
+        Ok(time.timestamp().max(0) as u64)
+    }
+
+    fn beacon(&self) -> Result<Option<BeaconConfig>> {
+        self.query_smart(json!({ "query_beacon": {} }))
+    }
+
+    fn submitted(&self, round: i32) -> Result<bool> {
+        let user = self.address()?;
+        self.query_smart(json!({ "query_submitted": { "user": user, "round": round } }))
+    }
+
+    fn submit_proof(&self, msg: &SubmitProof) -> Result<String> {
+        let execute_msg = json!({ "submit_proof": msg }).to_string();
+        let output = Command::new(&self.tx.binary)
+            .args(["tx", "wasm", "execute", &self.contract, &execute_msg])
+            .args(["--from", &self.tx.from])
+            .args(["--chain-id", &self.tx.chain_id])
+            .args(["--node", &self.tx.node])
+            .args(["--gas", "auto", "--gas-adjustment", "1.3"])
+            .args(["--gas-prices", &self.tx.gas_prices])
+            .args(["--output", "json", "-y"])
+            .output()
+            .with_context(|| format!("could not run {}", self.tx.binary))?;
+        ensure!(
+            output.status.success(),
+            "broadcast failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        let response: Value =
+            serde_json::from_slice(&output.stdout).context("unexpected broadcast response")?;
+        if response["code"].as_u64().unwrap_or_default() != 0 {
+            bail!("transaction rejected: {}", response["raw_log"]);
+        }
+
+        Ok(response["txhash"].as_str().unwrap_or_default().to_owned())
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use log::{error, info};
+use proofs_core::api_version::ApiVersion;
+use seal::types::{PoRepConfig, ProverId};
+use serde_json::Value;
+
+use crate::beacon::DrandClient;
+use crate::jobs::{Job, JobKind, JobState, JobStore};
+use crate::phase::{Permit, Phase, PhaseLimits};
+use crate::sectors::SectorStore;
+use crate::worker;
+
+pub struct DaemonConfig {
+    pub data_dir: PathBuf,
+    pub sector_size: u64,
+    pub porep_id: [u8; 32],
+    pub api_version: ApiVersion,
+    pub prover_id: ProverId,
+    pub pc1_limit: usize,
+    pub pc2_limit: usize,
+    pub c2_limit: usize,
+    /// The drand chain endpoint tickets and seeds are drawn from.
+    pub beacon_url: Option<String>,
+}
+
+impl DaemonConfig {
+    pub fn porep_config(&self) -> PoRepConfig {
+        PoRepConfig::new_groth16(self.sector_size, self.porep_id, self.api_version)
+    }
+}
+
+struct Queue {
+    store: JobStore,
+    /// Sectors with a running job.
+    busy: HashSet<u64>,
+}
+
+pub struct Daemon {
+    pub config: DaemonConfig,
+    queue: Mutex<Queue>,
+    queue_changed: Condvar,
+    sectors: Mutex<SectorStore>,
+    limits: PhaseLimits,
+    beacon: Option<DrandClient>,
+}
+
+/// Decodes a base64 string into 32 bytes, zero padding shorter values.
+pub fn from_base64(data: &str) -> Result<[u8; 32]> {
+    let mut bytes = general_purpose::STANDARD
+        .decode(data)
+        .with_context(|| format!("invalid base64 value={data}"))?;
+    anyhow::ensure!(bytes.len() <= 32, "value longer than 32 bytes={data}");
+    bytes.resize(32, 0);
+
+    Ok(bytes.try_into().expect("resized to 32 bytes"))
+}
+
+impl Daemon {
+    pub fn open(config: DaemonConfig) -> Result<Self> {
+        let store = JobStore::open(&config.data_dir.join("jobs"))?;
+        let sectors = SectorStore::open(&config.data_dir.join("sectors"))?;
+        let limits = PhaseLimits::new(config.pc1_limit, config.pc2_limit, config.c2_limit);
+        let beacon = config.beacon_url.as_deref().map(DrandClient::new);
+
+        Ok(Daemon {
+            config,
+            queue: Mutex::new(Queue {
+                store,
+                busy: HashSet::new(),
+            }),
+            queue_changed: Condvar::new(),
+            sectors: Mutex::new(sectors),
+            limits,
+            beacon,
+        })
+    }
+
+    pub fn submit(&self, kind: JobKind) -> Result<Job> {
+        let job = self.queue.lock().unwrap().store.submit(kind)?;
+        info!("job {} queued: {:?}", job.id, job.kind);
+        self.queue_changed.notify_all();
+
+        Ok(job)
+    }
+
+    pub fn job(&self, id: u64) -> Option<Job> {
+        self.queue.lock().unwrap().store.get(id).cloned()
+    }
+
+    pub fn jobs(&self, state: Option<JobState>) -> Vec<Job> {
+        let queue = self.queue.lock().unwrap();
+        queue
+            .store
+            .list()
+            .filter(|job| state.is_none() || state == Some(job.state))
+            .cloned()
+            .collect()
+    }
+
+    /// The proof system this daemon seals with.
+    pub fn info(&self) -> Value {
+        serde_json::json!({
+            "sector_size": self.config.sector_size,
+            "porep_id": general_purpose::STANDARD.encode(self.config.porep_id),
+            "prover_id": general_purpose::STANDARD.encode(self.config.prover_id),
+            "api_version": self.config.api_version.to_string(),
+        })
+    }
+
+    pub fn beacon(&self) -> Result<&DrandClient> {
+        self.beacon
+            .as_ref()
+            .context("no beacon configured, see --beacon-url")
+    }
+
+    pub fn sectors(&self) -> MutexGuard<'_, SectorStore> {
+        self.sectors.lock().unwrap()
+    }
+
+    /// Records that the job moved on to `phase` and blocks until the phase's
+    /// concurrency limit lets it in.
+    pub fn enter_phase(&self, job_id: u64, phase: Phase) -> Result<Option<Permit<'_>>> {
+        self.queue
+            .lock()
+            .unwrap()
+            .store
+            .update(job_id, |job| job.phase = Some(phase))?;
+        info!("job {job_id} entering {phase:?}");
+
+        Ok(self.limits.enter(phase))
+    }
+
+    /// Starts queued jobs as soon as their sector is free. Never returns.
+    pub fn run(self: Arc<Self>) {
+        loop {
+            let job = {
+                let mut queue = self.queue.lock().unwrap();
+                loop {
+                    if let Some(job) = queue.store.next_runnable(&queue.busy) {
+                        let id = job.id;
+                        match queue.store.update(id, |job| job.state = JobState::Running) {
+                            Ok(job) => {
+                                queue.busy.insert(job.kind.sector_id());
+                                break job;
+                            }
+                            Err(err) => error!("could not start job {id}: {err:#}"),
+                        }
+                    }
+                    queue = self.queue_changed.wait(queue).unwrap();
+                }
+            };
+
+            let daemon = Arc::clone(&self);
+            thread::spawn(move || daemon.execute(job));
+        }
+    }
+
+    fn execute(&self, job: Job) {
+        info!("job {} started", job.id);
+        let outcome = worker::run(self, &job);
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.busy.remove(&job.kind.sector_id());
+        let updated = queue.store.update(job.id, |job| {
+            job.phase = None;
+            match outcome {
+                Ok(result) => {
+                    info!("job {} done", job.id);
+                    job.state = JobState::Done;
+                    job.result = Some(result);
+                }
+                Err(err) => {
+                    error!("job {} failed: {err:#}", job.id);
+                    job.state = JobState::Failed;
+                    job.error = Some(format!("{err:#}"));
+                }
+            }
+        });
+        if let Err(err) = updated {
+            error!("could not record outcome of job {}: {err:#}", job.id);
+        }
+        drop(queue);
+        self.queue_changed.notify_all();
+    }
+}
@@ -0,0 +1,450 @@
+//! Persistent job queue.
+//!
+//! Every job is stored as `<data_dir>/jobs/<id>.json` and rewritten on each
+//! state change, so the queue survives a daemon restart. Jobs that were
+//! running when the daemon went down are put back in the queue on startup.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{ensure, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use log::warn;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::phase::Phase;
+
+/// Work a client can submit to the daemon.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    /// Append a piece to the staged file of an open sector.
+    AddPiece {
+        sector_id: u64,
+        piece_path: String,
+        piece_size: u64,
+        /// Encrypts the piece, its data key being wrapped with this key.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<ClientKey>,
+        /// Checks that the piece is a CARv1 archive, and indexes its blocks.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        car: bool,
+    },
+    /// Run both precommit phases over the staged file of a sector.
+    SealSector {
+        sector_id: u64,
+        /// Drawn from the latest round of the beacon when missing.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ticket: Option<String>, // base64
+    },
+    /// Prove a sealed sector against the randomness of a challenge round.
+    GenerateProof {
+        sector_id: u64,
+        round: u64,
+        seed: String, // base64
+    },
+    /// Extract a range of the original data from a sealed sector.
+    Unseal {
+        sector_id: u64,
+        output_path: String,
+        offset: u64,
+        num_bytes: u64,
+    },
+    /// Extract a piece from a sealed sector, decrypting it if it was added
+    /// with a key.
+    UnsealPiece {
+        sector_id: u64,
+        piece_index: usize,
+        output_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<ClientKey>,
+    },
+    /// Extract a block of a CAR piece from a sealed sector, by its CID.
+    UnsealBlock {
+        sector_id: u64,
+        cid: String,
+        output_path: String,
+    },
+    /// Package a sealed sector, its cache and its metadata into an archive,
+    /// to move it to another daemon.
+    ExportSector { sector_id: u64, output_path: String },
+    /// Restore a sector from an archive written by `ExportSector`, once its
+    /// files are checked against their digests and the sector against `comm_r`.
+    ImportSector {
+        sector_id: u64,
+        archive_path: String,
+        comm_r: String, // CID
+    },
+}
+
+impl JobKind {
+    pub fn sector_id(&self) -> u64 {
+        match self {
+            JobKind::AddPiece { sector_id, .. }
+            | JobKind::SealSector { sector_id, .. }
+            | JobKind::GenerateProof { sector_id, .. }
+            | JobKind::Unseal { sector_id, .. }
+            | JobKind::UnsealPiece { sector_id, .. }
+            | JobKind::UnsealBlock { sector_id, .. }
+            | JobKind::ExportSector { sector_id, .. }
+            | JobKind::ImportSector { sector_id, .. } => *sector_id,
+        }
+    }
+
+    pub fn client_key(&self) -> Option<&ClientKey> {
+        match self {
+            JobKind::AddPiece { key, .. } | JobKind::UnsealPiece { key, .. } => key.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Written in place of a client key, which is never serialized.
+const REDACTED_KEY: &str = "redacted";
+
+/// The base64 encoded 32 bytes key of a client, kept out of the logs, the job
+/// files and the RPC responses. It is only held in memory, for the job to run.
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ClientKey(pub String);
+
+impl ClientKey {
+    pub fn decode(&self) -> Result<[u8; 32]> {
+        ensure!(
+            self.0 != REDACTED_KEY,
+            "the key was not kept over a restart of the daemon"
+        );
+        let bytes = general_purpose::STANDARD
+            .decode(&self.0)
+            .context("invalid base64 key")?;
+        ensure!(bytes.len() == 32, "key must be 32 bytes long");
+
+        Ok(bytes.try_into().expect("checked length"))
+    }
+}
+
+impl Serialize for ClientKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED_KEY)
+    }
+}
+
+impl fmt::Debug for ClientKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClientKey(..)")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub kind: JobKind,
+    pub state: JobState,
+    /// The phase the job is currently in, while it is running.
+    pub phase: Option<Phase>,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+pub struct JobStore {
+    dir: PathBuf,
+    jobs: BTreeMap<u64, Job>,
+    next_id: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl JobStore {
+    /// Loads every job found in `dir`, re-queueing the ones that were
+    /// interrupted while running. The unfinished jobs needing a client key fail,
+    /// as the key is not persisted.
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("could not create job dir={}", dir.display()))?;
+
+        let mut store = JobStore {
+            dir: dir.to_path_buf(),
+            jobs: BTreeMap::new(),
+            next_id: 1,
+        };
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let job: Job = match fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))
+            {
+                Ok(job) => job,
+                Err(err) => {
+                    warn!("skipping unreadable job file {}: {err:#}", path.display());
+                    continue;
+                }
+            };
+            store.next_id = store.next_id.max(job.id + 1);
+            store.jobs.insert(job.id, job);
+        }
+
+        let keyless: Vec<u64> = store
+            .jobs
+            .values()
+            .filter(|job| matches!(job.state, JobState::Queued | JobState::Running))
+            .filter(|job| job.kind.client_key().is_some())
+            .map(|job| job.id)
+            .collect();
+        for id in keyless {
+            store.update(id, |job| {
+                job.state = JobState::Failed;
+                job.phase = None;
+                job.error = Some("the client key was not kept over a restart".to_string());
+            })?;
+        }
+
+        let interrupted: Vec<u64> = store
+            .jobs
+            .values()
+            .filter(|job| job.state == JobState::Running)
+            .map(|job| job.id)
+            .collect();
+        for id in interrupted {
+            store.update(id, |job| {
+                job.state = JobState::Queued;
+                job.phase = None;
+            })?;
+        }
+
+        Ok(store)
+    }
+
+    pub fn submit(&mut self, kind: JobKind) -> Result<Job> {
+        let timestamp = now();
+        let job = Job {
+            id: self.next_id,
+            kind,
+            state: JobState::Queued,
+            phase: None,
+            result: None,
+            error: None,
+            created_at: timestamp,
+            updated_at: timestamp,
+        };
+        self.persist(&job)?;
+        self.next_id += 1;
+        self.jobs.insert(job.id, job.clone());
+
+        Ok(job)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Job> {
+        self.jobs.get(&id)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.values()
+    }
+
+    /// Applies `f` to the job and writes it back to disk.
+    pub fn update<F: FnOnce(&mut Job)>(&mut self, id: u64, f: F) -> Result<Job> {
+        let mut job = self
+            .jobs
+            .get(&id)
+            .cloned()
+            .with_context(|| format!("unknown job id={id}"))?;
+        f(&mut job);
+        job.updated_at = now();
+        self.persist(&job)?;
+        self.jobs.insert(id, job.clone());
+
+        Ok(job)
+    }
+
+    /// Returns the oldest queued job whose sector is not in `busy_sectors`.
+    ///
+    /// Jobs touching the same sector are run one at a time, in submission order.
+    pub fn next_runnable(&self, busy_sectors: &HashSet<u64>) -> Option<&Job> {
+        let mut blocked = busy_sectors.clone();
+        for job in self.jobs.values() {
+            if job.state != JobState::Queued {
+                continue;
+            }
+            let sector_id = job.kind.sector_id();
+            if blocked.insert(sector_id) {
+                return Some(job);
+            }
+        }
+        None
+    }
+
+    fn persist(&self, job: &Job) -> Result<()> {
+        let path = self.dir.join(format!("{}.json", job.id));
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(job)?)
+            .with_context(|| format!("could not write job file={}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("could not write job file={}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seal_job(sector_id: u64) -> JobKind {
+        JobKind::SealSector {
+            sector_id,
+            ticket: None,
+        }
+    }
+
+    #[test]
+    fn test_requeue_interrupted_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = JobStore::open(dir.path()).unwrap();
+        let first = store.submit(seal_job(1)).unwrap();
+        let second = store.submit(seal_job(2)).unwrap();
+        store
+            .update(first.id, |job| job.state = JobState::Running)
+            .unwrap();
+        store
+            .update(second.id, |job| job.state = JobState::Done)
+            .unwrap();
+        drop(store);
+
+        let mut store = JobStore::open(dir.path()).unwrap();
+        assert_eq!(store.get(first.id).unwrap().state, JobState::Queued);
+        assert_eq!(store.get(second.id).unwrap().state, JobState::Done);
+        assert_eq!(store.submit(seal_job(3)).unwrap().id, second.id + 1);
+    }
+
+    #[test]
+    fn test_same_sector_jobs_run_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = JobStore::open(dir.path()).unwrap();
+        let first = store.submit(seal_job(1)).unwrap();
+        store.submit(seal_job(1)).unwrap();
+        let other = store.submit(seal_job(2)).unwrap();
+
+        let none = HashSet::new();
+        assert_eq!(store.next_runnable(&none).unwrap().id, first.id);
+
+        let busy = HashSet::from([1]);
+        assert_eq!(store.next_runnable(&busy).unwrap().id, other.id);
+    }
+
+    #[test]
+    fn test_client_key() {
+        let kind: JobKind = serde_json::from_str(
+            r#"{"type": "add_piece", "sector_id": 1, "piece_path": "piece", "piece_size": 2032,
+                "key": "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8="}"#,
+        )
+        .unwrap();
+        let JobKind::AddPiece { key: Some(key), .. } = &kind else {
+            panic!("missing key: {kind:?}");
+        };
+        assert_eq!(key.decode().unwrap(), std::array::from_fn(|i| i as u8));
+        assert!(!format!("{kind:?}").contains(&key.0));
+
+        assert!(ClientKey("AAEC".to_string()).decode().is_err());
+    }
+
+    #[test]
+    fn test_client_key_not_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+
+        let mut store = JobStore::open(dir.path()).unwrap();
+        let job = store
+            .submit(JobKind::AddPiece {
+                sector_id: 1,
+                piece_path: "piece".to_string(),
+                piece_size: 2032,
+                key: Some(ClientKey(key.to_string())),
+                car: false,
+            })
+            .unwrap();
+        let queued = store.submit(seal_job(2)).unwrap();
+        assert_eq!(
+            store.get(job.id).unwrap().kind.client_key(),
+            Some(&ClientKey(key.to_string()))
+        );
+
+        let persisted = fs::read_to_string(dir.path().join(format!("{}.json", job.id))).unwrap();
+        assert!(!persisted.contains(key));
+        // As returned by the `job_status` and `list_jobs` calls.
+        let listed = serde_json::to_string(&store.list().collect::<Vec<_>>()).unwrap();
+        assert!(!listed.contains(key));
+        drop(store);
+
+        let store = JobStore::open(dir.path()).unwrap();
+        let job = store.get(job.id).unwrap();
+        assert_eq!(job.state, JobState::Failed);
+        assert!(job.kind.client_key().unwrap().decode().is_err());
+        assert_eq!(store.get(queued.id).unwrap().state, JobState::Queued);
+    }
+
+    #[test]
+    fn test_car_piece() {
+        let kind: JobKind = serde_json::from_str(
+            r#"{"type": "add_piece", "sector_id": 1, "piece_path": "piece", "piece_size": 2032}"#,
+        )
+        .unwrap();
+        assert!(matches!(kind, JobKind::AddPiece { car: false, .. }));
+        assert!(!serde_json::to_string(&kind).unwrap().contains("car"));
+
+        let kind: JobKind = serde_json::from_str(
+            r#"{"type": "add_piece", "sector_id": 1, "piece_path": "piece.car", "piece_size": 2032,
+                "car": true}"#,
+        )
+        .unwrap();
+        assert!(matches!(kind, JobKind::AddPiece { car: true, .. }));
+    }
+
+    #[test]
+    fn test_sector_transfer() {
+        let kind: JobKind = serde_json::from_str(
+            r#"{"type": "import_sector", "sector_id": 7, "archive_path": "sector-7.archive",
+                "comm_r": "bagboea4b5abc..."}"#,
+        )
+        .unwrap();
+        assert_eq!(kind.sector_id(), 7);
+        assert!(serde_json::from_str::<JobKind>(
+            r#"{"type": "import_sector", "sector_id": 7, "archive_path": "sector-7.archive"}"#
+        )
+        .is_err());
+
+        let kind = JobKind::ExportSector {
+            sector_id: 7,
+            output_path: "sector-7.archive".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&kind).unwrap()["type"],
+            "export_sector"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Long running prover.
+//!
+//! Accepts sealing and proving jobs over a local HTTP/JSON-RPC API (see
+//! [`rpc`]), keeps them in a queue persisted under `--data-dir` and runs them
+//! with a bounded number of jobs per resource hungry phase.
+//!
+//! When `--contract` is given, a [`watcher`] also proves every round opened
+//! by the verifier contract.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{ensure, Result};
+use clap::Parser;
+use proofs_core::api_version::ApiVersion;
+use seal::sector_registry;
+
+use crate::chain::{CosmWasmChain, TxConfig};
+use crate::daemon::{from_base64, Daemon, DaemonConfig};
+use crate::watcher::{Watcher, WatcherConfig};
+
+mod beacon;
+mod chain;
+mod daemon;
+mod jobs;
+mod phase;
+mod rpc;
+mod sectors;
+mod watcher;
+mod worker;
+
+#[derive(Parser)]
+#[command(about = "Seals sectors and generates proofs on request")]
+struct Args {
+    /// Address the JSON-RPC API listens on
+    #[arg(long, default_value = "127.0.0.1:9944")]
+    listen: String,
+
+    /// Directory holding the job queue and the sectors
+    #[arg(long)]
+    data_dir: PathBuf,
+
+    /// Sector size in bytes, one of the sector profiles (see EUENO_SECTOR_PROFILE)
+    #[arg(long)]
+    sector_size: u64,
+
+    /// Porep id, base64
+    #[arg(long)]
+    porep_id: String,
+
+    /// Api version, e.g. 1.1.0
+    #[arg(long, default_value = "1.1.0")]
+    api_version: ApiVersion,
+
+    /// Prover id, base64
+    #[arg(long)]
+    prover_id: String,
+
+    /// Maximum number of sectors in precommit phase 1 at once
+    #[arg(long, default_value_t = 1)]
+    pc1_limit: usize,
+
+    /// Maximum number of sectors in precommit phase 2 at once
+    #[arg(long, default_value_t = 1)]
+    pc2_limit: usize,
+
+    /// Maximum number of sectors in commit phase 2 at once
+    #[arg(long, default_value_t = 1)]
+    c2_limit: usize,
+
+    /// Drand chain endpoint tickets and seeds are drawn from, e.g.
+    /// https://api.drand.sh/<chain hash>
+    #[arg(long)]
+    beacon_url: Option<String>,
+
+    /// Verifier contract to submit proofs to, enables the round watcher
+    #[arg(long)]
+    contract: Option<String>,
+
+    /// LCD endpoint the contract is queried through
+    #[arg(long, default_value = "http://127.0.0.1:1317")]
+    lcd_url: String,
+
+    /// Tendermint RPC endpoint transactions are broadcast to
+    #[arg(long, default_value = "tcp://127.0.0.1:26657")]
+    node: String,
+
+    /// Chain CLI used to sign and broadcast transactions
+    #[arg(long, default_value = "oraid")]
+    chain_binary: String,
+
+    /// Key in the chain CLI keyring whose address hashes to the prover id
+    #[arg(long, default_value = "prover")]
+    key: String,
+
+    #[arg(long, default_value = "Oraichain-testnet")]
+    chain_id: String,
+
+    #[arg(long, default_value = "0.002orai")]
+    gas_prices: String,
+
+    /// Seconds between two polls of the current round
+    #[arg(long, default_value_t = 30)]
+    poll_interval: u64,
+
+    /// Seconds before a round expires after which it is given up
+    #[arg(long, default_value_t = 30)]
+    deadline_margin: u64,
+}
+
+fn main() -> Result<()> {
+    fil_logger::init();
+    let args = Args::parse();
+
+    ensure!(
+        sector_registry::global().get(args.sector_size).is_some(),
+        "unsupported sector size: {}",
+        args.sector_size
+    );
+
+    let daemon = Arc::new(Daemon::open(DaemonConfig {
+        data_dir: args.data_dir,
+        sector_size: args.sector_size,
+        porep_id: from_base64(&args.porep_id)?,
+        api_version: args.api_version,
+        prover_id: from_base64(&args.prover_id)?,
+        pc1_limit: args.pc1_limit,
+        pc2_limit: args.pc2_limit,
+        c2_limit: args.c2_limit,
+        beacon_url: args.beacon_url,
+    })?);
+
+    let dispatcher = Arc::clone(&daemon);
+    thread::spawn(move || dispatcher.run());
+
+    if let Some(contract) = &args.contract {
+        let chain = CosmWasmChain::new(
+            &args.lcd_url,
+            contract,
+            TxConfig {
+                binary: args.chain_binary,
+                from: args.key,
+                chain_id: args.chain_id,
+                node: args.node,
+                gas_prices: args.gas_prices,
+            },
+        );
+        let config = WatcherConfig {
+            poll_interval: Duration::from_secs(args.poll_interval),
+            retry_interval: Duration::from_secs(5),
+            deadline_margin: args.deadline_margin,
+        };
+        let watcher = Watcher::new(
+            chain,
+            Arc::clone(&daemon),
+            config,
+            &daemon.config.data_dir.join("watcher.json"),
+        )?;
+        thread::spawn(move || watcher.run());
+    }
+
+    rpc::serve(daemon, &args.listen)
+}
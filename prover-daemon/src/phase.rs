@@ -0,0 +1,84 @@
+//! Sealing phases and the per-phase concurrency limits.
+
+use std::sync::{Condvar, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    AddPiece,
+    PreCommit1,
+    PreCommit2,
+    Commit1,
+    Commit2,
+    Unseal,
+    /// Export or import of a sealed sector.
+    Transfer,
+}
+
+/// A counting semaphore guarding how many jobs may be in a phase at once.
+pub struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+pub struct Permit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Semaphore {
+            available: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a permit is available.
+    pub fn acquire(&self) -> Permit<'_> {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.released.wait(available).unwrap();
+        }
+        *available -= 1;
+
+        Permit { semaphore: self }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.semaphore.available.lock().unwrap() += 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+/// Concurrency limits for the resource hungry phases. The remaining phases
+/// are only bounded by the number of sectors being worked on.
+pub struct PhaseLimits {
+    pc1: Semaphore,
+    pc2: Semaphore,
+    c2: Semaphore,
+}
+
+impl PhaseLimits {
+    pub fn new(pc1: usize, pc2: usize, c2: usize) -> Self {
+        PhaseLimits {
+            pc1: Semaphore::new(pc1.max(1)),
+            pc2: Semaphore::new(pc2.max(1)),
+            c2: Semaphore::new(c2.max(1)),
+        }
+    }
+
+    /// Blocks until the job may enter `phase`. The phase is left once the
+    /// returned permit is dropped.
+    pub fn enter(&self, phase: Phase) -> Option<Permit<'_>> {
+        match phase {
+            Phase::PreCommit1 => Some(self.pc1.acquire()),
+            Phase::PreCommit2 => Some(self.pc2.acquire()),
+            Phase::Commit2 => Some(self.c2.acquire()),
+            Phase::AddPiece | Phase::Commit1 | Phase::Unseal | Phase::Transfer => None,
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! JSON-RPC 2.0 over HTTP.
+//!
+//! Every call is a `POST` whose body is a JSON-RPC request:
+//!
+//! * `info` - the sector size, porep id, prover id and api version in use.
+//! * `submit_job` - `params` is a job, e.g. `{"type": "seal_sector", "sector_id": 1, "ticket": "..."}`.
+//! * `job_status` - `{"id": 1}`.
+//! * `list_jobs` - optionally filtered by `{"state": "queued"}`.
+//! * `list_sectors` - the whole sector inventory.
+//! * `sector_info` - `{"sector_id": 1}`.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::daemon::Daemon;
+use crate::jobs::{JobKind, JobState};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JobStatusParams {
+    id: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct ListJobsParams {
+    state: Option<JobState>,
+}
+
+#[derive(Deserialize)]
+struct SectorInfoParams {
+    sector_id: u64,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|err| RpcError::new(INVALID_PARAMS, format!("invalid params: {err}")))
+}
+
+fn server_error(err: anyhow::Error) -> RpcError {
+    RpcError::new(SERVER_ERROR, format!("{err:#}"))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|err| server_error(err.into()))
+}
+
+fn dispatch(daemon: &Daemon, request: RpcRequest) -> Result<Value, RpcError> {
+    match request.method.as_str() {
+        "info" => Ok(daemon.info()),
+        "submit_job" => {
+            let kind: JobKind = params(request.params)?;
+            to_value(daemon.submit(kind).map_err(server_error)?)
+        }
+        "job_status" => {
+            let JobStatusParams { id } = params(request.params)?;
+            let job = daemon
+                .job(id)
+                .ok_or_else(|| RpcError::new(SERVER_ERROR, format!("unknown job id={id}")))?;
+            to_value(job)
+        }
+        "list_jobs" => {
+            let ListJobsParams { state } = if request.params.is_null() {
+                ListJobsParams::default()
+            } else {
+                params(request.params)?
+            };
+            to_value(daemon.jobs(state))
+        }
+        "list_sectors" => {
+            let sectors: Vec<_> = daemon.sectors().list().cloned().collect();
+            to_value(sectors)
+        }
+        "sector_info" => {
+            let SectorInfoParams { sector_id } = params(request.params)?;
+            let sector = daemon.sectors().get(sector_id).cloned().ok_or_else(|| {
+                RpcError::new(SERVER_ERROR, format!("unknown sector id={sector_id}"))
+            })?;
+            to_value(sector)
+        }
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method={method}"),
+        )),
+    }
+}
+
+fn handle(daemon: &Daemon, request: &mut Request) -> Value {
+    if *request.method() != Method::Post {
+        return json!({
+            "jsonrpc": "2.0",
+            "id": Value::Null,
+            "error": RpcError::new(INVALID_REQUEST, "expected a POST request"),
+        });
+    }
+
+    let mut body = String::new();
+    if let Err(err) = request.as_reader().read_to_string(&mut body) {
+        return json!({
+            "jsonrpc": "2.0",
+            "id": Value::Null,
+            "error": RpcError::new(PARSE_ERROR, format!("could not read body: {err}")),
+        });
+    }
+    let rpc_request: RpcRequest = match serde_json::from_str(&body) {
+        Ok(rpc_request) => rpc_request,
+        Err(err) => {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": Value::Null,
+                "error": RpcError::new(PARSE_ERROR, format!("invalid request: {err}")),
+            })
+        }
+    };
+
+    let id = rpc_request.id.clone();
+    match dispatch(daemon, rpc_request) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+/// Serves the API on `addr` until the process exits.
+pub fn serve(daemon: Arc<Daemon>, addr: &str) -> Result<()> {
+    let server = Server::http(addr)
+        .map_err(|err| anyhow::anyhow!("{err}"))
+        .with_context(|| format!("could not listen on {addr}"))?;
+    info!("listening on {addr}");
+
+    let content_type: Header = "Content-Type: application/json"
+        .parse()
+        .expect("valid header");
+    for mut request in server.incoming_requests() {
+        let body = handle(&daemon, &mut request).to_string();
+        let response = Response::from_string(body).with_header(content_type.clone());
+        if let Err(err) = request.respond(response) {
+            warn!("could not send response: {err}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Sector inventory.
+//!
+//! Each sector lives in `<data_dir>/sectors/<sector_id>/` next to its
+//! `sector.json` metadata file:
+//!
+//! * `staged` - the unsealed sector data, pieces appended by `add_piece`.
+//! * `sealed` - the replica written by the precommit phases.
+//! * `cache/` - the Merkle trees and labels needed to prove the replica.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use contract_auxiliaries::beacon::BeaconEntry;
+use log::warn;
+use seal::types::{CarIndex, KeyEnvelope, PieceInfo, SealPreCommitOutput};
+use serde::{Deserialize, Serialize};
+
+const SECTOR_FILE: &str = "sector.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SectorState {
+    /// Pieces can still be added.
+    Open,
+    /// Both precommit phases ran, the sector can be proven and unsealed.
+    Sealed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SectorInfo {
+    pub sector_id: u64,
+    pub state: SectorState,
+    pub pieces: Vec<PieceInfo>,
+    /// The data key envelopes of the encrypted pieces, by piece index.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub key_envelopes: BTreeMap<usize, KeyEnvelope>,
+    /// The roots and the block offsets of the CAR pieces, by piece index.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub car_indexes: BTreeMap<usize, CarIndex>,
+    pub ticket: Option<String>, // base64
+    /// The beacon round the ticket was drawn from, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket_entry: Option<BeaconEntry>,
+    pub pre_commit_output: Option<SealPreCommitOutput>,
+    /// The last challenge round a proof was generated for.
+    pub last_proven_round: Option<u64>,
+}
+
+impl SectorInfo {
+    fn new(sector_id: u64) -> Self {
+        SectorInfo {
+            sector_id,
+            state: SectorState::Open,
+            pieces: vec![],
+            key_envelopes: BTreeMap::new(),
+            car_indexes: BTreeMap::new(),
+            ticket: None,
+            ticket_entry: None,
+            pre_commit_output: None,
+            last_proven_round: None,
+        }
+    }
+}
+
+pub struct SectorStore {
+    dir: PathBuf,
+    sectors: BTreeMap<u64, SectorInfo>,
+}
+
+impl SectorStore {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("could not create sector dir={}", dir.display()))?;
+
+        let mut sectors = BTreeMap::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path().join(SECTOR_FILE);
+            if !path.exists() {
+                continue;
+            }
+            match fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| Ok(serde_json::from_slice::<SectorInfo>(&bytes)?))
+            {
+                Ok(sector) => {
+                    sectors.insert(sector.sector_id, sector);
+                }
+                Err(err) => warn!(
+                    "skipping unreadable sector file {}: {err:#}",
+                    path.display()
+                ),
+            }
+        }
+
+        Ok(SectorStore {
+            dir: dir.to_path_buf(),
+            sectors,
+        })
+    }
+
+    pub fn get(&self, sector_id: u64) -> Option<&SectorInfo> {
+        self.sectors.get(&sector_id)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &SectorInfo> {
+        self.sectors.values()
+    }
+
+    /// Returns the sector, creating an empty open one if it is unknown.
+    pub fn get_or_create(&mut self, sector_id: u64) -> Result<SectorInfo> {
+        if let Some(sector) = self.sectors.get(&sector_id) {
+            return Ok(sector.clone());
+        }
+        let sector = SectorInfo::new(sector_id);
+        fs::create_dir_all(self.cache_path(sector_id))?;
+        self.save(sector.clone())?;
+
+        Ok(sector)
+    }
+
+    pub fn save(&mut self, sector: SectorInfo) -> Result<()> {
+        let dir = self.sector_dir(sector.sector_id);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(SECTOR_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&sector)?)
+            .with_context(|| format!("could not write sector file={}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("could not write sector file={}", path.display()))?;
+        self.sectors.insert(sector.sector_id, sector);
+
+        Ok(())
+    }
+
+    /// Like `get`, but fails unless the sector has been sealed.
+    pub fn sealed(&self, sector_id: u64) -> Result<SectorInfo> {
+        let sector = self
+            .get(sector_id)
+            .with_context(|| format!("unknown sector id={sector_id}"))?;
+        ensure!(
+            sector.state == SectorState::Sealed,
+            "sector {sector_id} is not sealed"
+        );
+
+        Ok(sector.clone())
+    }
+
+    pub fn sector_dir(&self, sector_id: u64) -> PathBuf {
+        self.dir.join(sector_id.to_string())
+    }
+
+    pub fn staged_path(&self, sector_id: u64) -> PathBuf {
+        self.sector_dir(sector_id).join("staged")
+    }
+
+    pub fn sealed_path(&self, sector_id: u64) -> PathBuf {
+        self.sector_dir(sector_id).join("sealed")
+    }
+
+    pub fn cache_path(&self, sector_id: u64) -> PathBuf {
+        self.sector_dir(sector_id).join("cache")
+    }
+}
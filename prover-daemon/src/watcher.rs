@@ -0,0 +1,512 @@
+//! Proves automatically whenever a new round opens on chain.
+//!
+//! The contract accepts one proof per prover and round, so every round is
+//! mapped to one of the sealed sectors, rotating through them. A round is
+//! given up once its `time_expire` is closer than the configured margin,
+//! otherwise failing queries, proofs and broadcasts are retried.
+//!
+//! When the contract has a beacon, the seed is drawn from the beacon round of
+//! the latest block, and the proof carries the ticket and seed rounds.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use contract_auxiliaries::beacon::{Beacon, BeaconEntry, SealRandomness};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::beacon::draw_seed;
+use crate::chain::{sector_size_name, Chain, RoundInfo, SubmitProof};
+use crate::daemon::Daemon;
+use crate::jobs::{JobKind, JobState};
+use crate::sectors::SectorState;
+
+/// Generates the proofs the watcher submits.
+pub trait RoundProver {
+    fn porep_id(&self) -> [u8; 32];
+
+    /// Sectors that can be proven, in ascending order.
+    fn sealed_sectors(&self) -> Vec<u64>;
+
+    fn beacon_entry(&self, round: u64) -> Result<BeaconEntry>;
+
+    /// Proves `sector_id` for `round`, failing if that takes past `deadline`.
+    fn prove(
+        &self,
+        sector_id: u64,
+        round: i32,
+        seed: &RoundSeed,
+        deadline: Instant,
+    ) -> Result<SubmitProof>;
+}
+
+/// What the seed of a round's proof comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoundSeed {
+    /// Without a beacon on chain, the seed is derived from the round, see [`round_seed`].
+    Round([u8; 32]),
+    /// The beacon round the seed of the proven sector is drawn from.
+    Beacon(BeaconEntry),
+}
+
+pub struct WatcherConfig {
+    /// How often the chain is polled for a new round.
+    pub poll_interval: Duration,
+    /// Delay between retries of a failed proof or broadcast.
+    pub retry_interval: Duration,
+    /// Seconds before `time_expire` after which a round is given up.
+    pub deadline_margin: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct WatcherState {
+    /// The last round that was submitted or given up.
+    last_round: i32,
+}
+
+pub struct Watcher<C, P> {
+    chain: C,
+    prover: P,
+    config: WatcherConfig,
+    state_path: PathBuf,
+    state: WatcherState,
+    /// Proof generated for a round whose broadcast has not gone through yet.
+    pending: Option<(i32, SubmitProof)>,
+}
+
+/// The seed a round's proofs are generated with when the contract has no beacon.
+pub fn round_seed(porep_id: &[u8; 32], round: i32) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(porep_id)
+        .chain_update(round.to_be_bytes())
+        .finalize()
+        .into()
+}
+
+impl<C: Chain, P: RoundProver> Watcher<C, P> {
+    pub fn new(chain: C, prover: P, config: WatcherConfig, state_path: &Path) -> Result<Self> {
+        let state = match fs::read(state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("invalid watcher state={}", state_path.display()))?,
+            Err(_) => WatcherState::default(),
+        };
+
+        Ok(Watcher {
+            chain,
+            prover,
+            config,
+            state_path: state_path.to_path_buf(),
+            state,
+            pending: None,
+        })
+    }
+
+    /// Polls the chain forever.
+    pub fn run(mut self) {
+        loop {
+            let wait = match self.tick() {
+                Ok(()) => self.config.poll_interval,
+                Err(err) => {
+                    warn!("watcher: {err:#}");
+                    self.config.retry_interval
+                }
+            };
+            thread::sleep(wait);
+        }
+    }
+
+    /// Handles the open round, if it has not been handled yet.
+    pub fn tick(&mut self) -> Result<()> {
+        let round = match self.chain.open_round()? {
+            Some(round) if round.round > self.state.last_round => round,
+            _ => return Ok(()),
+        };
+
+        if round.porep_id != self.prover.porep_id() {
+            warn!("round {} uses another porep id, skipping", round.round);
+            return self.finish(round.round);
+        }
+
+        let deadline = match self.deadline(&round)? {
+            Some(deadline) => deadline,
+            None => {
+                warn!("round {} expires too soon, giving up", round.round);
+                return self.finish(round.round);
+            }
+        };
+
+        // A broadcast may have gone through even if it was reported as failed.
+        if self.chain.submitted(round.round)? {
+            info!("proof for round {} is already on chain", round.round);
+            return self.finish(round.round);
+        }
+
+        let msg = match self.pending.take() {
+            Some((pending_round, msg)) if pending_round == round.round => msg,
+            _ => {
+                let sectors = self.prover.sealed_sectors();
+                if sectors.is_empty() {
+                    info!("no sealed sector to prove round {}", round.round);
+                    return Ok(());
+                }
+                let sector_id = sectors[round.round as usize % sectors.len()];
+                let seed = self.seed(&round)?;
+                info!("proving sector {sector_id} for round {}", round.round);
+                self.prover.prove(sector_id, round.round, &seed, deadline)?
+            }
+        };
+
+        match self.chain.submit_proof(&msg) {
+            Ok(txhash) => {
+                info!("submitted proof for round {}: {txhash}", round.round);
+                self.finish(round.round)
+            }
+            Err(err) => {
+                self.pending = Some((round.round, msg));
+                Err(err.context(format!("broadcast for round {} failed", round.round)))
+            }
+        }
+    }
+
+    /// The beacon round of the latest block if the contract has a beacon, so
+    /// that the seed round is not after the round at submission.
+    fn seed(&self, round: &RoundInfo) -> Result<RoundSeed> {
+        match self.chain.beacon()? {
+            Some(config) => {
+                let beacon = Beacon::new(&config)?;
+                let beacon_round = beacon.round_at(self.chain.block_time()?);
+                Ok(RoundSeed::Beacon(self.prover.beacon_entry(beacon_round)?))
+            }
+            None => Ok(RoundSeed::Round(round_seed(&round.porep_id, round.round))),
+        }
+    }
+
+    /// The local time by which the proof has to be broadcast, `None` if it
+    /// already passed.
+    fn deadline(&self, round: &RoundInfo) -> Result<Option<Instant>> {
+        let now = self.chain.block_time()?;
+        let left = round
+            .time_expire
+            .saturating_sub(now)
+            .checked_sub(self.config.deadline_margin)
+            .filter(|left| *left > 0);
+
+        Ok(left.map(|left| Instant::now() + Duration::from_secs(left)))
+    }
+
+    fn finish(&mut self, round: i32) -> Result<()> {
+        self.pending = None;
+        self.state.last_round = round;
+        fs::write(&self.state_path, serde_json::to_vec(&self.state)?)
+            .with_context(|| format!("could not write {}", self.state_path.display()))
+    }
+}
+
+impl RoundProver for Arc<Daemon> {
+    fn porep_id(&self) -> [u8; 32] {
+        self.config.porep_id
+    }
+
+    fn sealed_sectors(&self) -> Vec<u64> {
+        self.sectors()
+            .list()
+            .filter(|sector| sector.state == SectorState::Sealed)
+            .map(|sector| sector.sector_id)
+            .collect()
+    }
+
+    fn beacon_entry(&self, round: u64) -> Result<BeaconEntry> {
+        self.beacon()?.entry(round)
+    }
+
+    fn prove(
+        &self,
+        sector_id: u64,
+        round: i32,
+        seed: &RoundSeed,
+        deadline: Instant,
+    ) -> Result<SubmitProof> {
+        let sector = self
+            .sectors()
+            .get(sector_id)
+            .cloned()
+            .with_context(|| format!("unknown sector id={sector_id}"))?;
+        let (seed, randomness) = match seed {
+            RoundSeed::Round(seed) => (*seed, None),
+            RoundSeed::Beacon(entry) => {
+                let ticket = sector.ticket_entry.clone().with_context(|| {
+                    format!("sector {sector_id} was sealed without a beacon ticket")
+                })?;
+                let seed = draw_seed(entry, &self.config.prover_id, sector_id);
+                let randomness = SealRandomness {
+                    ticket,
+                    seed: entry.clone(),
+                };
+                (seed, Some(randomness))
+            }
+        };
+        let job = self.submit(JobKind::GenerateProof {
+            sector_id,
+            round: round as u64,
+            seed: general_purpose::STANDARD.encode(seed),
+        })?;
+
+        let job = loop {
+            let job = self.job(job.id).context("proof job vanished")?;
+            match job.state {
+                JobState::Done => break job,
+                JobState::Failed => bail!("proof job {} failed: {:?}", job.id, job.error),
+                JobState::Queued | JobState::Running if Instant::now() >= deadline => {
+                    bail!("proof job {} missed the round deadline", job.id)
+                }
+                JobState::Queued | JobState::Running => thread::sleep(Duration::from_secs(1)),
+            }
+        };
+
+        let result = job.result.unwrap_or_default();
+
+        Ok(SubmitProof {
+            proof_raw: result["proof_raw"].as_str().unwrap_or_default().to_owned(),
+            public_inputs: result["public_inputs"].clone(),
+            porep_id: general_purpose::STANDARD.encode(self.config.porep_id),
+            sector_size: sector_size_name(self.config.sector_size)?.to_owned(),
+            api_version: format!("V{}", self.config.api_version).replace('.', "_"),
+            prover_id: general_purpose::STANDARD.encode(self.config.prover_id),
+            sector_id,
+            ticket: sector.ticket.unwrap_or_default(),
+            randomness,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use anyhow::anyhow;
+    use contract_auxiliaries::beacon::{BeaconConfig, BeaconScheme};
+    use serde_json::Value;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockChain {
+        round: RefCell<Option<RoundInfo>>,
+        time: RefCell<u64>,
+        beacon: RefCell<Option<BeaconConfig>>,
+        failing_submits: RefCell<usize>,
+        /// Broadcasts which go through but are reported as failed.
+        lost_submits: RefCell<usize>,
+        submitted: RefCell<Vec<SubmitProof>>,
+        submitted_rounds: RefCell<Vec<i32>>,
+    }
+
+    impl Chain for &MockChain {
+        fn open_round(&self) -> Result<Option<RoundInfo>> {
+            Ok(self.round.borrow().clone())
+        }
+
+        fn block_time(&self) -> Result<u64> {
+            Ok(*self.time.borrow())
+        }
+
+        fn beacon(&self) -> Result<Option<BeaconConfig>> {
+            Ok(self.beacon.borrow().clone())
+        }
+
+        fn submitted(&self, round: i32) -> Result<bool> {
+            Ok(self.submitted_rounds.borrow().contains(&round))
+        }
+
+        fn submit_proof(&self, msg: &SubmitProof) -> Result<String> {
+            if *self.failing_submits.borrow() > 0 {
+                *self.failing_submits.borrow_mut() -= 1;
+                return Err(anyhow!("connection refused"));
+            }
+            self.submitted.borrow_mut().push(msg.clone());
+            let round = self.round.borrow().as_ref().unwrap().round;
+            self.submitted_rounds.borrow_mut().push(round);
+            if *self.lost_submits.borrow() > 0 {
+                *self.lost_submits.borrow_mut() -= 1;
+                return Err(anyhow!("timed out waiting for the transaction"));
+            }
+            Ok("txhash".to_owned())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockProver {
+        proofs: RefCell<Vec<(u64, i32)>>,
+        seeds: RefCell<Vec<RoundSeed>>,
+    }
+
+    impl RoundProver for &MockProver {
+        fn porep_id(&self) -> [u8; 32] {
+            [1; 32]
+        }
+
+        fn sealed_sectors(&self) -> Vec<u64> {
+            vec![10, 11]
+        }
+
+        fn beacon_entry(&self, round: u64) -> Result<BeaconEntry> {
+            Ok(BeaconEntry {
+                round,
+                signature: vec![],
+                previous_signature: None,
+            })
+        }
+
+        fn prove(
+            &self,
+            sector_id: u64,
+            round: i32,
+            seed: &RoundSeed,
+            _: Instant,
+        ) -> Result<SubmitProof> {
+            self.proofs.borrow_mut().push((sector_id, round));
+            self.seeds.borrow_mut().push(seed.clone());
+            Ok(SubmitProof {
+                proof_raw: String::new(),
+                public_inputs: Value::Null,
+                porep_id: String::new(),
+                sector_size: "sector_size2_kib".to_owned(),
+                api_version: "V1_1_0".to_owned(),
+                prover_id: String::new(),
+                sector_id,
+                ticket: String::new(),
+                randomness: None,
+            })
+        }
+    }
+
+    fn open_round(chain: &MockChain, round: i32, time_expire: u64) {
+        *chain.round.borrow_mut() = Some(RoundInfo {
+            round,
+            time_expire,
+            porep_id: [1; 32],
+        });
+    }
+
+    fn watcher<'a>(
+        chain: &'a MockChain,
+        prover: &'a MockProver,
+        dir: &Path,
+    ) -> Watcher<&'a MockChain, &'a MockProver> {
+        let config = WatcherConfig {
+            poll_interval: Duration::ZERO,
+            retry_interval: Duration::ZERO,
+            deadline_margin: 10,
+        };
+        Watcher::new(chain, prover, config, &dir.join("watcher.json")).unwrap()
+    }
+
+    #[test]
+    fn test_submits_once_per_round() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chain, prover) = (MockChain::default(), MockProver::default());
+        let mut watcher = watcher(&chain, &prover, dir.path());
+
+        watcher.tick().unwrap();
+        assert!(chain.submitted.borrow().is_empty());
+
+        open_round(&chain, 1, 100);
+        watcher.tick().unwrap();
+        watcher.tick().unwrap();
+        open_round(&chain, 2, 200);
+        watcher.tick().unwrap();
+
+        assert_eq!(*prover.proofs.borrow(), vec![(11, 1), (10, 2)]);
+        assert_eq!(
+            prover.seeds.borrow()[1],
+            RoundSeed::Round(round_seed(&[1; 32], 2))
+        );
+        assert_eq!(chain.submitted.borrow().len(), 2);
+
+        // Handled rounds are remembered across restarts.
+        let mut watcher = self::watcher(&chain, &prover, dir.path());
+        watcher.tick().unwrap();
+        assert_eq!(chain.submitted.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_retries_broadcast_without_reproving() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chain, prover) = (MockChain::default(), MockProver::default());
+        let mut watcher = watcher(&chain, &prover, dir.path());
+
+        open_round(&chain, 1, 100);
+        *chain.failing_submits.borrow_mut() = 2;
+        assert!(watcher.tick().is_err());
+        assert!(watcher.tick().is_err());
+        watcher.tick().unwrap();
+
+        assert_eq!(prover.proofs.borrow().len(), 1);
+        assert_eq!(chain.submitted.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_skips_round_already_on_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chain, prover) = (MockChain::default(), MockProver::default());
+        let mut watcher = watcher(&chain, &prover, dir.path());
+
+        open_round(&chain, 1, 100);
+        *chain.lost_submits.borrow_mut() = 1;
+        assert!(watcher.tick().is_err());
+        watcher.tick().unwrap();
+
+        assert_eq!(prover.proofs.borrow().len(), 1);
+        assert_eq!(chain.submitted.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_gives_up_expiring_round() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chain, prover) = (MockChain::default(), MockProver::default());
+        let mut watcher = watcher(&chain, &prover, dir.path());
+
+        open_round(&chain, 1, 100);
+        *chain.time.borrow_mut() = 95;
+        watcher.tick().unwrap();
+        assert!(prover.proofs.borrow().is_empty());
+
+        open_round(&chain, 2, 200);
+        watcher.tick().unwrap();
+        assert_eq!(chain.submitted.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_draws_seed_from_beacon() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chain, prover) = (MockChain::default(), MockProver::default());
+        let mut watcher = watcher(&chain, &prover, dir.path());
+
+        // The drand mainnet.
+        *chain.beacon.borrow_mut() = Some(BeaconConfig {
+            public_key: hex::decode(concat!(
+                "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a",
+                "7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31"
+            ))
+            .unwrap(),
+            scheme: BeaconScheme::Chained,
+            genesis_time: 1_595_431_050,
+            period: 30,
+            seed_lookback: 2,
+            ticket_max_age: 4,
+            seed_max_age: 1,
+        });
+        *chain.time.borrow_mut() = 1_595_431_050 + 5 * 30 + 10;
+        open_round(&chain, 1, 1_595_432_000);
+        watcher.tick().unwrap();
+
+        let seeds = prover.seeds.borrow();
+        assert!(matches!(&seeds[..], [RoundSeed::Beacon(entry)] if entry.round == 6));
+    }
+}
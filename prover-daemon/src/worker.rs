@@ -0,0 +1,662 @@
+//! Runs a single job against the `seal` API.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek};
+
+use anyhow::{ensure, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use converter::{
+    format::{to_base64, Encoding},
+    groth16::convert_proof,
+};
+use proofs_core::{merkle::MerkleTreeTrait, sector::SectorId};
+use rand::thread_rng;
+use seal::pieces::{get_piece_alignment, sum_piece_bytes_with_alignment};
+use seal::types::{
+    cid_to_commitment_of, CommitmentKind, ProverId, UnpaddedByteIndex, UnpaddedBytesAmount,
+};
+use seal::with_shape;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::beacon::draw_ticket;
+use crate::daemon::{from_base64, Daemon};
+use crate::jobs::{ClientKey, Job, JobKind};
+use crate::phase::Phase;
+use crate::sectors::{SectorInfo, SectorState};
+
+pub fn run(daemon: &Daemon, job: &Job) -> Result<Value> {
+    let sector_size = daemon.config.sector_size;
+    match &job.kind {
+        JobKind::AddPiece {
+            sector_id,
+            piece_path,
+            piece_size,
+            key,
+            car,
+        } => add_piece(
+            daemon,
+            job.id,
+            *sector_id,
+            piece_path,
+            *piece_size,
+            key.as_ref(),
+            *car,
+        ),
+        JobKind::SealSector { sector_id, ticket } => {
+            with_shape!(
+                sector_size,
+                seal_sector,
+                daemon,
+                job.id,
+                *sector_id,
+                ticket.as_deref()
+            )
+        }
+        JobKind::GenerateProof {
+            sector_id,
+            round,
+            seed,
+        } => with_shape!(
+            sector_size,
+            generate_proof,
+            daemon,
+            job.id,
+            *sector_id,
+            *round,
+            seed
+        ),
+        JobKind::Unseal {
+            sector_id,
+            output_path,
+            offset,
+            num_bytes,
+        } => with_shape!(
+            sector_size,
+            unseal,
+            daemon,
+            job.id,
+            *sector_id,
+            output_path,
+            *offset,
+            *num_bytes
+        ),
+        JobKind::UnsealPiece {
+            sector_id,
+            piece_index,
+            output_path,
+            key,
+        } => with_shape!(
+            sector_size,
+            unseal_piece,
+            daemon,
+            job.id,
+            *sector_id,
+            *piece_index,
+            output_path,
+            key.as_ref()
+        ),
+        JobKind::UnsealBlock {
+            sector_id,
+            cid,
+            output_path,
+        } => with_shape!(
+            sector_size,
+            unseal_block,
+            daemon,
+            job.id,
+            *sector_id,
+            cid,
+            output_path
+        ),
+        JobKind::ExportSector {
+            sector_id,
+            output_path,
+        } => with_shape!(
+            sector_size,
+            export_sector,
+            daemon,
+            job.id,
+            *sector_id,
+            output_path
+        ),
+        JobKind::ImportSector {
+            sector_id,
+            archive_path,
+            comm_r,
+        } => with_shape!(
+            sector_size,
+            import_sector,
+            daemon,
+            job.id,
+            *sector_id,
+            archive_path,
+            comm_r
+        ),
+    }
+}
+
+fn add_piece(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    piece_path: &str,
+    piece_size: u64,
+    key: Option<&ClientKey>,
+    car: bool,
+) -> Result<Value> {
+    ensure!(
+        !(car && key.is_some()),
+        "a CAR piece can not be encrypted, its blocks could not be unsealed on their own"
+    );
+    let _permit = daemon.enter_phase(job_id, Phase::AddPiece)?;
+    let (mut sector, staged_path) = {
+        let mut sectors = daemon.sectors();
+        (
+            sectors.get_or_create(sector_id)?,
+            sectors.staged_path(sector_id),
+        )
+    };
+    ensure!(
+        sector.state == SectorState::Open,
+        "sector {sector_id} is already sealed"
+    );
+
+    let mut piece_file =
+        File::open(piece_path).with_context(|| format!("could not open piece={piece_path}"))?;
+    let staged_sector = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&staged_path)
+        .with_context(|| format!("could not open staged sector={}", staged_path.display()))?;
+    let piece_lengths: Vec<_> = sector.pieces.iter().map(|piece| piece.size).collect();
+
+    let (piece_info, written) = match key {
+        Some(key) => {
+            let plaintext_len = piece_file.metadata()?.len();
+            let (piece_info, written, envelope) = seal::add_encrypted_piece(
+                &key.decode()?,
+                piece_file,
+                staged_sector,
+                plaintext_len,
+                UnpaddedBytesAmount(piece_size),
+                &piece_lengths,
+                &mut thread_rng(),
+            )?;
+            sector.key_envelopes.insert(sector.pieces.len(), envelope);
+            (piece_info, written)
+        }
+        None if car => {
+            let index = seal::index_car(io::BufReader::new(&piece_file))?;
+            ensure!(
+                index.car_len <= piece_size,
+                "CAR of {} bytes does not fit in a piece of {piece_size} bytes",
+                index.car_len
+            );
+            piece_file.rewind()?;
+            let source = piece_file
+                .take(index.car_len)
+                .chain(io::repeat(0).take(piece_size - index.car_len));
+            sector.car_indexes.insert(sector.pieces.len(), index);
+            seal::add_piece(
+                source,
+                staged_sector,
+                UnpaddedBytesAmount(piece_size),
+                &piece_lengths,
+            )?
+        }
+        None => seal::add_piece(
+            piece_file,
+            staged_sector,
+            UnpaddedBytesAmount(piece_size),
+            &piece_lengths,
+        )?,
+    };
+    sector.pieces.push(piece_info.clone());
+    daemon.sectors().save(sector)?;
+
+    Ok(json!({
+        "piece_cid": piece_info.cid(),
+        "piece_info": piece_info,
+        "written": written.0,
+    }))
+}
+
+fn seal_sector<Tree: 'static + MerkleTreeTrait>(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    ticket: Option<&str>,
+) -> Result<Value> {
+    let (mut sector, staged_path, sealed_path, cache_path) = {
+        let sectors = daemon.sectors();
+        let sector = sectors
+            .get(sector_id)
+            .cloned()
+            .with_context(|| format!("unknown sector id={sector_id}"))?;
+        (
+            sector,
+            sectors.staged_path(sector_id),
+            sectors.sealed_path(sector_id),
+            sectors.cache_path(sector_id),
+        )
+    };
+    ensure!(
+        sector.state == SectorState::Open,
+        "sector {sector_id} is already sealed"
+    );
+    ensure!(
+        !sector.pieces.is_empty(),
+        "sector {sector_id} has no pieces"
+    );
+
+    let (ticket, ticket_entry) = match ticket {
+        Some(ticket) => (from_base64(ticket)?, None),
+        None => {
+            let entry = daemon.beacon()?.latest()?;
+            let ticket = draw_ticket(&entry, &daemon.config.prover_id, sector_id);
+            (ticket, Some(entry))
+        }
+    };
+
+    let config = daemon.config.porep_config();
+    File::create(&sealed_path)
+        .with_context(|| format!("could not create sealed sector={}", sealed_path.display()))?;
+
+    let phase1_output = {
+        let _permit = daemon.enter_phase(job_id, Phase::PreCommit1)?;
+        seal::seal_pre_commit_phase1::<_, _, _, Tree>(
+            &config,
+            &cache_path,
+            &staged_path,
+            &sealed_path,
+            daemon.config.prover_id,
+            SectorId::from(sector_id),
+            ticket,
+            &sector.pieces,
+        )?
+    };
+
+    let pre_commit_output = {
+        let _permit = daemon.enter_phase(job_id, Phase::PreCommit2)?;
+        seal::seal_pre_commit_phase2(&config, phase1_output, &cache_path, &sealed_path)?
+    };
+
+    sector.state = SectorState::Sealed;
+    sector.ticket = Some(general_purpose::STANDARD.encode(ticket));
+    sector.ticket_entry = ticket_entry;
+    sector.pre_commit_output = Some(pre_commit_output.clone());
+    daemon.sectors().save(sector)?;
+
+    Ok(json!({
+        "comm_r": pre_commit_output.comm_r,
+        "comm_d": pre_commit_output.comm_d,
+        "comm_r_cid": pre_commit_output.comm_r_cid(),
+        "comm_d_cid": pre_commit_output.comm_d_cid(),
+    }))
+}
+
+fn generate_proof<Tree: 'static + MerkleTreeTrait>(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    round: u64,
+    seed: &str,
+) -> Result<Value> {
+    let (mut sector, sealed_path, cache_path) = {
+        let sectors = daemon.sectors();
+        (
+            sectors.sealed(sector_id)?,
+            sectors.sealed_path(sector_id),
+            sectors.cache_path(sector_id),
+        )
+    };
+    let pre_commit_output = sector
+        .pre_commit_output
+        .clone()
+        .context("sealed sector without precommit output")?;
+    let ticket = from_base64(sector.ticket.as_deref().unwrap_or_default())?;
+    let seed = from_base64(seed)?;
+
+    let config = daemon.config.porep_config();
+    let prover_id = daemon.config.prover_id;
+    let (comm_r, comm_d) = (pre_commit_output.comm_r, pre_commit_output.comm_d);
+
+    let phase1_output = {
+        let _permit = daemon.enter_phase(job_id, Phase::Commit1)?;
+        seal::seal_commit_phase1::<_, Tree>(
+            &config,
+            cache_path,
+            sealed_path,
+            prover_id,
+            SectorId::from(sector_id),
+            ticket,
+            seed,
+            pre_commit_output,
+            &sector.pieces,
+        )?
+    };
+
+    let mut rng = thread_rng();
+    let (public_inputs, proofs) = {
+        let _permit = daemon.enter_phase(job_id, Phase::Commit2)?;
+        let commit_output = seal::seal_commit_phase2(
+            &config,
+            phase1_output,
+            prover_id,
+            SectorId::from(sector_id),
+            Some(&mut rng),
+        )?;
+        seal::generate_proof_and_public_inputs::<Tree, _>(
+            &config,
+            comm_r,
+            comm_d,
+            prover_id,
+            SectorId::from(sector_id),
+            ticket,
+            seed,
+            &commit_output.proof,
+            Some(&mut rng),
+        )?
+    };
+
+    // convert bellperson to ark-groth16
+    let proof = convert_proof(&proofs[0])?;
+
+    sector.last_proven_round = Some(round);
+    daemon.sectors().save(sector)?;
+
+    Ok(json!({
+        "round": round,
+        "proof_raw": to_base64(&proof, Encoding::Compressed)?,
+        "public_inputs": public_inputs,
+    }))
+}
+
+fn unseal<Tree: 'static + MerkleTreeTrait>(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    output_path: &str,
+    offset: u64,
+    num_bytes: u64,
+) -> Result<Value> {
+    let _permit = daemon.enter_phase(job_id, Phase::Unseal)?;
+    let (sector, sealed_path, cache_path) = {
+        let sectors = daemon.sectors();
+        (
+            sectors.sealed(sector_id)?,
+            sectors.sealed_path(sector_id),
+            sectors.cache_path(sector_id),
+        )
+    };
+    let pre_commit_output = sector
+        .pre_commit_output
+        .context("sealed sector without precommit output")?;
+    let ticket = from_base64(sector.ticket.as_deref().unwrap_or_default())?;
+
+    let sealed_sector = File::open(&sealed_path)
+        .with_context(|| format!("could not open sealed sector={}", sealed_path.display()))?;
+    let output = File::create(output_path)
+        .with_context(|| format!("could not create output={output_path}"))?;
+
+    let written = seal::unseal_range::<_, _, _, Tree>(
+        &daemon.config.porep_config(),
+        cache_path,
+        sealed_sector,
+        output,
+        daemon.config.prover_id,
+        SectorId::from(sector_id),
+        pre_commit_output.comm_d,
+        ticket,
+        UnpaddedByteIndex(offset),
+        UnpaddedBytesAmount(num_bytes),
+    )?;
+
+    Ok(json!({ "written": written.0 }))
+}
+
+fn unseal_piece<Tree: 'static + MerkleTreeTrait>(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    piece_index: usize,
+    output_path: &str,
+    key: Option<&ClientKey>,
+) -> Result<Value> {
+    let _permit = daemon.enter_phase(job_id, Phase::Unseal)?;
+    let (sector, sealed_path, cache_path) = {
+        let sectors = daemon.sectors();
+        (
+            sectors.sealed(sector_id)?,
+            sectors.sealed_path(sector_id),
+            sectors.cache_path(sector_id),
+        )
+    };
+    let pre_commit_output = sector
+        .pre_commit_output
+        .as_ref()
+        .context("sealed sector without precommit output")?;
+    let ticket = from_base64(sector.ticket.as_deref().unwrap_or_default())?;
+
+    let (offset, piece_size) = piece_offset(&sector, piece_index)?;
+
+    let sealed_sector = File::open(&sealed_path)
+        .with_context(|| format!("could not open sealed sector={}", sealed_path.display()))?;
+    let output = File::create(output_path)
+        .with_context(|| format!("could not create output={output_path}"))?;
+
+    let written = match sector.key_envelopes.get(&piece_index) {
+        Some(envelope) => {
+            let key = key.with_context(|| format!("piece {piece_index} is encrypted"))?;
+            seal::unseal_encrypted_piece::<_, _, _, Tree>(
+                &daemon.config.porep_config(),
+                cache_path,
+                sealed_sector,
+                output,
+                daemon.config.prover_id,
+                SectorId::from(sector_id),
+                pre_commit_output.comm_d,
+                ticket,
+                UnpaddedByteIndex::from(offset),
+                &key.decode()?,
+                envelope,
+            )?
+        }
+        None => {
+            seal::unseal_range::<_, _, _, Tree>(
+                &daemon.config.porep_config(),
+                cache_path,
+                sealed_sector,
+                output,
+                daemon.config.prover_id,
+                SectorId::from(sector_id),
+                pre_commit_output.comm_d,
+                ticket,
+                UnpaddedByteIndex::from(offset),
+                piece_size,
+            )?
+            .0
+        }
+    };
+
+    Ok(json!({ "written": written }))
+}
+
+/// The offset of a piece in the unsealed data of a sector, and its size.
+fn piece_offset(
+    sector: &SectorInfo,
+    piece_index: usize,
+) -> Result<(UnpaddedBytesAmount, UnpaddedBytesAmount)> {
+    let piece_lengths: Vec<_> = sector.pieces.iter().map(|piece| piece.size).collect();
+    ensure!(
+        piece_index < piece_lengths.len(),
+        "sector {} has no piece {piece_index}",
+        sector.sector_id
+    );
+    let written = sum_piece_bytes_with_alignment(&piece_lengths[..piece_index]);
+    let piece_size = piece_lengths[piece_index];
+
+    Ok((
+        written + get_piece_alignment(written, piece_size).left_bytes,
+        piece_size,
+    ))
+}
+
+fn unseal_block<Tree: 'static + MerkleTreeTrait>(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    cid: &str,
+    output_path: &str,
+) -> Result<Value> {
+    let _permit = daemon.enter_phase(job_id, Phase::Unseal)?;
+    let (sector, sealed_path, cache_path) = {
+        let sectors = daemon.sectors();
+        (
+            sectors.sealed(sector_id)?,
+            sectors.sealed_path(sector_id),
+            sectors.cache_path(sector_id),
+        )
+    };
+    let pre_commit_output = sector
+        .pre_commit_output
+        .as_ref()
+        .context("sealed sector without precommit output")?;
+    let ticket = from_base64(sector.ticket.as_deref().unwrap_or_default())?;
+
+    let (piece_index, block) = sector
+        .car_indexes
+        .iter()
+        .find_map(|(piece_index, index)| Some((*piece_index, index.block(cid)?)))
+        .with_context(|| format!("sector {sector_id} has no block {cid}"))?;
+    let (offset, _) = piece_offset(&sector, piece_index)?;
+
+    let sealed_sector = File::open(&sealed_path)
+        .with_context(|| format!("could not open sealed sector={}", sealed_path.display()))?;
+    let output = File::create(output_path)
+        .with_context(|| format!("could not create output={output_path}"))?;
+
+    let written = seal::unseal_car_block::<_, _, _, Tree>(
+        &daemon.config.porep_config(),
+        cache_path,
+        sealed_sector,
+        output,
+        daemon.config.prover_id,
+        SectorId::from(sector_id),
+        pre_commit_output.comm_d,
+        ticket,
+        UnpaddedByteIndex::from(offset),
+        block,
+    )?;
+
+    Ok(json!({ "piece_index": piece_index, "written": written }))
+}
+
+/// The metadata of an exported sector, kept in the manifest of its archive.
+#[derive(Serialize, Deserialize)]
+struct ExportedSector {
+    /// The replica can only be proven by the prover it was sealed for.
+    prover_id: ProverId,
+    sector: SectorInfo,
+}
+
+fn export_sector<Tree: 'static + MerkleTreeTrait>(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    output_path: &str,
+) -> Result<Value> {
+    let _permit = daemon.enter_phase(job_id, Phase::Transfer)?;
+    let (sector, sealed_path, cache_path) = {
+        let sectors = daemon.sectors();
+        (
+            sectors.sealed(sector_id)?,
+            sectors.sealed_path(sector_id),
+            sectors.cache_path(sector_id),
+        )
+    };
+    let metadata = serde_json::to_value(ExportedSector {
+        prover_id: daemon.config.prover_id,
+        sector,
+    })?;
+
+    let output = File::create(output_path)
+        .with_context(|| format!("could not create output={output_path}"))?;
+    let manifest = seal::export_sector::<Tree, _>(
+        &daemon.config.porep_config(),
+        &cache_path,
+        &sealed_path,
+        SectorId::from(sector_id),
+        metadata,
+        output,
+    )?;
+
+    Ok(json!({
+        "files": manifest.files.len(),
+        "written": manifest.files_len(),
+    }))
+}
+
+fn import_sector<Tree: 'static + MerkleTreeTrait>(
+    daemon: &Daemon,
+    job_id: u64,
+    sector_id: u64,
+    archive_path: &str,
+    comm_r: &str,
+) -> Result<Value> {
+    let _permit = daemon.enter_phase(job_id, Phase::Transfer)?;
+    let comm_r = cid_to_commitment_of(CommitmentKind::Sealed, comm_r)?;
+    let open_archive = || {
+        File::open(archive_path).with_context(|| format!("could not open archive={archive_path}"))
+    };
+
+    // The metadata is checked before any file is unpacked.
+    let manifest = seal::read_sector_archive_manifest(&mut open_archive()?)?;
+    let ExportedSector { prover_id, sector } = serde_json::from_value(manifest.metadata)
+        .context("archive was not exported by a daemon")?;
+    ensure!(
+        prover_id == daemon.config.prover_id,
+        "sector was sealed for another prover"
+    );
+    ensure!(
+        manifest.sector_id == sector_id && sector.sector_id == sector_id,
+        "archive is of sector {}",
+        manifest.sector_id
+    );
+    ensure!(
+        sector.state == SectorState::Sealed
+            && sector
+                .pre_commit_output
+                .as_ref()
+                .map(|output| output.comm_r)
+                == Some(comm_r),
+        "archive metadata does not match comm_r"
+    );
+
+    let (sealed_path, cache_path) = {
+        let sectors = daemon.sectors();
+        ensure!(
+            sectors.get(sector_id).is_none(),
+            "sector {sector_id} already exists"
+        );
+        (
+            sectors.sealed_path(sector_id),
+            sectors.cache_path(sector_id),
+        )
+    };
+    fs::create_dir_all(&cache_path)?;
+    let manifest = seal::import_sector::<Tree, _>(
+        &daemon.config.porep_config(),
+        open_archive()?,
+        &cache_path,
+        &sealed_path,
+        comm_r,
+    )?;
+    daemon.sectors().save(sector)?;
+
+    Ok(json!({
+        "files": manifest.files.len(),
+        "written": manifest.files_len(),
+    }))
+}
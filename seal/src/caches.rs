@@ -6,11 +6,19 @@ use bellperson::groth16::{self, prepare_verifying_key};
 use blstrs::Bls12;
 use lazy_static::lazy_static;
 use log::info;
-use proofs_core::{compound_proof::CompoundProof, merkle::MerkleTreeTrait};
-use proofs_porep::stacked::{StackedCompound, StackedDrg};
+use proofs_core::{compound_proof::CompoundProof, drgraph::BucketGraph, merkle::MerkleTreeTrait};
+use proofs_porep::{
+    drg::{DrgPoRep, DrgPoRepCompound},
+    stacked::{StackedCompound, StackedDrg},
+    update::{EmptySectorUpdate, EmptySectorUpdateCompound},
+};
 use rand::RngCore;
 
-use crate::{constants::DefaultPieceHasher, parameters::public_params, types::PoRepConfig};
+use crate::{
+    constants::{DefaultPieceHasher, DefaultTreeHasher},
+    parameters::{drg_public_params, public_params, update_public_params},
+    types::PoRepConfig,
+};
 
 type Bls12GrothParams = groth16::MappedParameters<Bls12>;
 pub type Bls12PreparedVerifyingKey = groth16::PreparedVerifyingKey<Bls12>;
@@ -147,3 +155,131 @@ pub fn generate_verifier_key<Tree: 'static + MerkleTreeTrait, R: RngCore>(
         _,
     >>::verifying_key(rng, &public_params)
 }
+
+pub fn get_empty_sector_update_params<Tree: 'static + MerkleTreeTrait, R: RngCore>(
+    porep_config: &PoRepConfig,
+    rng: Option<&mut R>,
+) -> Result<Arc<Bls12GrothParams>> {
+    let public_params = update_public_params::<Tree>(porep_config.padded_bytes_amount())?;
+
+    let parameters_generator = || {
+        <EmptySectorUpdateCompound<Tree, DefaultPieceHasher> as CompoundProof<
+            EmptySectorUpdate<'_, Tree, DefaultPieceHasher>,
+            _,
+        >>::groth_params(rng, &public_params)
+        .map_err(Into::into)
+    };
+
+    lookup_groth_params(
+        format!(
+            "UPDATE[{}]",
+            usize::from(porep_config.padded_bytes_amount())
+        ),
+        parameters_generator,
+    )
+}
+
+pub fn get_empty_sector_update_verifying_key<Tree: 'static + MerkleTreeTrait, R: RngCore>(
+    porep_config: &PoRepConfig,
+    rng: Option<&mut R>,
+) -> Result<Arc<Bls12PreparedVerifyingKey>> {
+    let public_params = update_public_params::<Tree>(porep_config.padded_bytes_amount())?;
+
+    let vk_generator = || {
+        let vk = <EmptySectorUpdateCompound<Tree, DefaultPieceHasher> as CompoundProof<
+            EmptySectorUpdate<'_, Tree, DefaultPieceHasher>,
+            _,
+        >>::verifying_key(rng, &public_params)?;
+        Ok(prepare_verifying_key(&vk))
+    };
+
+    lookup_verifying_key(
+        format!(
+            "UPDATE[{}]",
+            usize::from(porep_config.padded_bytes_amount())
+        ),
+        vk_generator,
+    )
+}
+
+pub fn generate_empty_sector_update_verifier_key<Tree: 'static + MerkleTreeTrait, R: RngCore>(
+    porep_config: &PoRepConfig,
+    rng: Option<&mut R>,
+) -> Result<Bls12VerifyingKey> {
+    let public_params = update_public_params::<Tree>(porep_config.padded_bytes_amount())?;
+    <EmptySectorUpdateCompound<Tree, DefaultPieceHasher> as CompoundProof<
+        EmptySectorUpdate<'_, Tree, DefaultPieceHasher>,
+        _,
+    >>::verifying_key(rng, &public_params)
+}
+
+type DrgGraph = BucketGraph<DefaultTreeHasher>;
+
+pub fn get_drg_params<R: RngCore>(
+    porep_config: &PoRepConfig,
+    rng: Option<&mut R>,
+) -> Result<Arc<Bls12GrothParams>> {
+    let public_params = drg_public_params(
+        porep_config.padded_bytes_amount(),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+
+    let parameters_generator = || {
+        <DrgPoRepCompound<DefaultTreeHasher, DrgGraph> as CompoundProof<
+            DrgPoRep<'_, DefaultTreeHasher, DrgGraph>,
+            _,
+        >>::groth_params(rng, &public_params)
+        .map_err(Into::into)
+    };
+
+    lookup_groth_params(
+        format!(
+            "DRG[{}]",
+            usize::from(porep_config.padded_bytes_amount())
+        ),
+        parameters_generator,
+    )
+}
+
+pub fn get_drg_verifying_key<R: RngCore>(
+    porep_config: &PoRepConfig,
+    rng: Option<&mut R>,
+) -> Result<Arc<Bls12PreparedVerifyingKey>> {
+    let public_params = drg_public_params(
+        porep_config.padded_bytes_amount(),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+
+    let vk_generator = || {
+        let vk = <DrgPoRepCompound<DefaultTreeHasher, DrgGraph> as CompoundProof<
+            DrgPoRep<'_, DefaultTreeHasher, DrgGraph>,
+            _,
+        >>::verifying_key(rng, &public_params)?;
+        Ok(prepare_verifying_key(&vk))
+    };
+
+    lookup_verifying_key(
+        format!(
+            "DRG[{}]",
+            usize::from(porep_config.padded_bytes_amount())
+        ),
+        vk_generator,
+    )
+}
+
+pub fn generate_drg_verifier_key<R: RngCore>(
+    porep_config: &PoRepConfig,
+    rng: Option<&mut R>,
+) -> Result<Bls12VerifyingKey> {
+    let public_params = drg_public_params(
+        porep_config.padded_bytes_amount(),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+    <DrgPoRepCompound<DefaultTreeHasher, DrgGraph> as CompoundProof<
+        DrgPoRep<'_, DefaultTreeHasher, DrgGraph>,
+        _,
+    >>::verifying_key(rng, &public_params)
+}
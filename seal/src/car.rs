@@ -0,0 +1,303 @@
+//! Ingestion of CARv1 archives as pieces.
+//!
+//! The header and every block of an archive are checked while it is read, the data of a block
+//! against the digest of its CID. The archive is added as is, so the offset of a block in the
+//! archive is its offset in the piece, and a block can be unsealed on its own by its CID.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+use log::info;
+use proofs_core::{merkle::MerkleTreeTrait, sector::SectorId};
+use sha2::{Digest, Sha256};
+
+use crate::types::{
+    base32_decode, base32_encode, read_varint, write_varint, CarBlock, CarIndex, Commitment,
+    PoRepConfig, ProverId, Ticket, UnpaddedByteIndex, UnpaddedBytesAmount,
+};
+use crate::unseal_range;
+
+/// Upper bound of the length of the header and of a block section, as other CAR readers.
+pub const MAX_CAR_SECTION_LEN: u64 = 8 << 20;
+
+const CAR_VERSION: u64 = 1;
+const CID_TAG: u64 = 42;
+
+const DAG_PB: u64 = 0x70;
+const IDENTITY: u64 = 0x00;
+const SHA2_256: u64 = 0x12;
+const BLAKE2B_256: u64 = 0xb220;
+
+/// The parts of a CID needed to check and name a block.
+struct BlockCid {
+    codec: u64,
+    hash: u64,
+    digest: Vec<u8>,
+}
+
+impl BlockCid {
+    /// Reads a binary CID, either a CIDv0 (a bare sha2-256 multihash) or a CIDv1.
+    fn read(bytes: &mut &[u8]) -> Result<Self> {
+        let (codec, hash) = if bytes.starts_with(&[SHA2_256 as u8, 32]) {
+            *bytes = &bytes[1..];
+            (DAG_PB, SHA2_256)
+        } else {
+            ensure!(read_varint(bytes)? == 1, "unsupported CID version");
+            (read_varint(bytes)?, read_varint(bytes)?)
+        };
+        let len = read_varint(bytes)? as usize;
+        ensure!(bytes.len() >= len, "truncated CID digest");
+        let digest = bytes[..len].to_vec();
+        *bytes = &bytes[len..];
+
+        Ok(BlockCid {
+            codec,
+            hash,
+            digest,
+        })
+    }
+
+    fn parse(cid: &str) -> Result<Self> {
+        let encoded = cid
+            .strip_prefix('b')
+            .with_context(|| format!("CID is not a base32 CIDv1: {cid}"))?;
+        let bytes = base32_decode(encoded)?;
+        let mut bytes = bytes.as_slice();
+        let block_cid = BlockCid::read(&mut bytes)?;
+        ensure!(bytes.is_empty(), "trailing bytes after CID {cid}");
+        Ok(block_cid)
+    }
+
+    /// The base32 CIDv1.
+    fn encode(&self) -> String {
+        let mut bytes = vec![];
+        write_varint(&mut bytes, 1);
+        write_varint(&mut bytes, self.codec);
+        write_varint(&mut bytes, self.hash);
+        write_varint(&mut bytes, self.digest.len() as u64);
+        bytes.extend_from_slice(&self.digest);
+        format!("b{}", base32_encode(&bytes))
+    }
+
+    fn verify(&self, data: &[u8]) -> Result<()> {
+        let digest = match self.hash {
+            IDENTITY => data.to_vec(),
+            SHA2_256 => Sha256::digest(data).to_vec(),
+            BLAKE2B_256 => blake2b_simd::Params::new()
+                .hash_length(32)
+                .hash(data)
+                .as_bytes()
+                .to_vec(),
+            hash => bail!("unsupported multihash {hash:#x}"),
+        };
+        ensure!(digest == self.digest, "block does not match its CID");
+        Ok(())
+    }
+}
+
+/// Checks `data` against the digest of the base32 CIDv1 `cid`.
+pub fn verify_car_block(cid: &str, data: &[u8]) -> Result<()> {
+    BlockCid::parse(cid)?.verify(data)
+}
+
+/// Reads a CARv1 archive from `source`, checking its header and its blocks, and returns its
+/// roots and the offsets of its blocks.
+pub fn index_car<R: Read>(source: R) -> Result<CarIndex> {
+    index_car_to(source, io::sink())
+}
+
+/// Like `index_car`, copying the archive to `copy` as it is read, so that the copy is exactly the
+/// archive that was checked.
+pub fn index_car_to<R: Read, W: Write>(source: R, copy: W) -> Result<CarIndex> {
+    let mut reader = CountingReader {
+        inner: source,
+        copy,
+        position: 0,
+    };
+
+    let header = read_section(&mut reader)?.context("empty CAR")?;
+    let roots = read_header(&header).context("invalid CAR header")?;
+
+    let mut blocks = vec![];
+    while let Some(section) = read_section(&mut reader)? {
+        let mut bytes = section.as_slice();
+        let cid = BlockCid::read(&mut bytes)
+            .with_context(|| format!("invalid CID of block {}", blocks.len()))?;
+        cid.verify(bytes)
+            .with_context(|| format!("invalid block {}", blocks.len()))?;
+
+        blocks.push(CarBlock {
+            cid: cid.encode(),
+            offset: reader.position - bytes.len() as u64,
+            len: bytes.len() as u64,
+        });
+    }
+
+    Ok(CarIndex {
+        roots,
+        blocks,
+        car_len: reader.position,
+    })
+}
+
+/// Unseals the block of a CAR piece whose first (unpadded) byte begins at `piece_offset`, checks
+/// it against its CID, and writes it to `unsealed_output`. See `unseal_range`.
+#[allow(clippy::too_many_arguments)]
+pub fn unseal_car_block<P, R, W, Tree>(
+    porep_config: &PoRepConfig,
+    cache_path: P,
+    sealed_sector: R,
+    mut unsealed_output: W,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    comm_d: Commitment,
+    ticket: Ticket,
+    piece_offset: UnpaddedByteIndex,
+    block: &CarBlock,
+) -> Result<u64>
+where
+    P: AsRef<Path>,
+    R: Read,
+    W: Write,
+    Tree: 'static + MerkleTreeTrait,
+{
+    info!("unseal_car_block:start: {sector_id:?} {}", block.cid);
+
+    let mut data = Vec::with_capacity(block.len as usize);
+    unseal_range::<_, _, _, Tree>(
+        porep_config,
+        cache_path,
+        sealed_sector,
+        &mut data,
+        prover_id,
+        sector_id,
+        comm_d,
+        ticket,
+        UnpaddedByteIndex(u64::from(piece_offset) + block.offset),
+        UnpaddedBytesAmount(block.len),
+    )?;
+    verify_car_block(&block.cid, &data)?;
+    unsealed_output.write_all(&data)?;
+
+    info!("unseal_car_block:finish: {sector_id:?} {}", block.cid);
+    Ok(block.len)
+}
+
+struct CountingReader<R, W> {
+    inner: R,
+    copy: W,
+    position: u64,
+}
+
+impl<R: Read, W: Write> Read for CountingReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.copy.write_all(&buf[..n])?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+/// Reads a section prefixed by its varint length, `None` at the end of the archive.
+fn read_section<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut len = 0;
+    for i in 0..9 {
+        let mut byte = [0];
+        if reader.read(&mut byte)? == 0 {
+            ensure!(i == 0, "truncated section length");
+            return Ok(None);
+        }
+        len |= u64::from(byte[0] & 0x7f) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            ensure!(
+                len > 0 && len <= MAX_CAR_SECTION_LEN,
+                "invalid section length {len}"
+            );
+            let mut section = vec![0; len as usize];
+            reader
+                .read_exact(&mut section)
+                .context("truncated section")?;
+            return Ok(Some(section));
+        }
+    }
+    bail!("invalid section length")
+}
+
+/// Decodes the DAG-CBOR header `{"roots": [CID, ...], "version": 1}`, and returns its roots.
+fn read_header(header: &[u8]) -> Result<Vec<String>> {
+    let mut bytes = header;
+    let (major, fields) = read_cbor_head(&mut bytes)?;
+    ensure!(major == 5, "header is not a map");
+
+    let (mut roots, mut version) = (None, None);
+    for _ in 0..fields {
+        match read_cbor_text(&mut bytes)?.as_str() {
+            "roots" => {
+                let (major, len) = read_cbor_head(&mut bytes)?;
+                ensure!(major == 4, "roots are not an array");
+                let mut cids = vec![];
+                for _ in 0..len {
+                    ensure!(
+                        read_cbor_head(&mut bytes)? == (6, CID_TAG),
+                        "root is not a CID"
+                    );
+                    let (major, len) = read_cbor_head(&mut bytes)?;
+                    ensure!(major == 2 && bytes.len() as u64 >= len, "invalid root CID");
+                    let (mut cid, rest) = bytes.split_at(len as usize);
+                    bytes = rest;
+                    // CIDs are prefixed with the identity multibase in DAG-CBOR.
+                    ensure!(cid.first() == Some(&0), "invalid root CID multibase");
+                    cid = &cid[1..];
+                    cids.push(BlockCid::read(&mut cid)?.encode());
+                    ensure!(cid.is_empty(), "trailing bytes after root CID");
+                }
+                roots = Some(cids);
+            }
+            "version" => {
+                let (major, value) = read_cbor_head(&mut bytes)?;
+                ensure!(major == 0, "version is not an integer");
+                version = Some(value);
+            }
+            field => bail!("unexpected header field {field:?}"),
+        }
+    }
+    ensure!(bytes.is_empty(), "trailing bytes after header");
+    ensure!(
+        version == Some(CAR_VERSION),
+        "unsupported CAR version {version:?}"
+    );
+
+    roots.context("header has no roots")
+}
+
+/// Reads the major type and the argument of a CBOR item.
+fn read_cbor_head(bytes: &mut &[u8]) -> Result<(u8, u64)> {
+    let (&initial, rest) = bytes.split_first().context("truncated CBOR item")?;
+    let len = match initial & 0x1f {
+        info @ 0..=23 => {
+            *bytes = rest;
+            return Ok((initial >> 5, u64::from(info)));
+        }
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        info => bail!("unsupported CBOR argument {info}"),
+    };
+    ensure!(rest.len() >= len, "truncated CBOR item");
+    let value = rest[..len]
+        .iter()
+        .fold(0, |value, byte| (value << 8) | u64::from(*byte));
+    *bytes = &rest[len..];
+
+    Ok((initial >> 5, value))
+}
+
+fn read_cbor_text(bytes: &mut &[u8]) -> Result<String> {
+    let (major, len) = read_cbor_head(bytes)?;
+    ensure!(major == 3 && bytes.len() as u64 >= len, "invalid CBOR text");
+    let (text, rest) = bytes.split_at(len as usize);
+    *bytes = rest;
+    Ok(String::from_utf8(text.to_vec())?)
+}
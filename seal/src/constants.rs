@@ -10,39 +10,16 @@ use proofs_core::{
     merkle::{BinaryMerkleTree, DiskTree, LCTree},
     util::NODE_SIZE
 };
+pub use sector_registry::{self, SectorProfile, TreeShape};
 use typenum::{U8, U0, U2};
 
-pub const SECTOR_SIZE_2_KIB: u64 = 1 << 11;
-pub const SECTOR_SIZE_4_KIB: u64 = 1 << 12;
-pub const SECTOR_SIZE_16_KIB: u64 = 1 << 14;
-pub const SECTOR_SIZE_32_KIB: u64 = 1 << 15;
-pub const SECTOR_SIZE_8_MIB: u64 = 1 << 23;
-pub const SECTOR_SIZE_16_MIB: u64 = 1 << 24;
-pub const SECTOR_SIZE_512_MIB: u64 = 1 << 29;
-pub const SECTOR_SIZE_1_GIB: u64 = 1 << 30;
-pub const SECTOR_SIZE_32_GIB: u64 = 1 << 35;
-pub const SECTOR_SIZE_64_GIB: u64 = 1 << 36;
+/// The largest sector size that may be sealed with the single layer DRG PoRep, 32KiB.
+pub const DRG_MAX_SECTOR_SIZE: u64 = 1 << 15;
 
 pub struct PorepMinimumChallenges(RwLock<HashMap<u64, usize>>);
 impl PorepMinimumChallenges {
     fn new() -> Self {
-        Self(RwLock::new(
-            [
-                (SECTOR_SIZE_2_KIB, 2),
-                (SECTOR_SIZE_4_KIB, 2),
-                (SECTOR_SIZE_16_KIB, 2),
-                (SECTOR_SIZE_32_KIB, 2),
-                (SECTOR_SIZE_8_MIB, 2),
-                (SECTOR_SIZE_16_MIB, 2),
-                (SECTOR_SIZE_512_MIB, 2),
-                (SECTOR_SIZE_1_GIB, 2),
-                (SECTOR_SIZE_32_GIB, 176),
-                (SECTOR_SIZE_64_GIB, 176),
-            ]
-            .iter()
-            .copied()
-            .collect(),
-        ))
+        Self(RwLock::new(from_registry(|profile| profile.minimum_challenges)))
     }
 
     pub fn from_sector_size(&self, sector_size: u64) -> usize {
@@ -58,62 +35,26 @@ impl PorepMinimumChallenges {
     }
 }
 
-lazy_static! {
-    pub static ref POREP_MINIMUM_CHALLENGES: PorepMinimumChallenges = PorepMinimumChallenges::new();
-    pub static ref POREP_PARTITIONS: RwLock<HashMap<u64, u8>> = RwLock::new(
-        [
-            (SECTOR_SIZE_2_KIB, 1),
-            (SECTOR_SIZE_4_KIB, 1),
-            (SECTOR_SIZE_16_KIB, 1),
-            (SECTOR_SIZE_32_KIB, 1),
-            (SECTOR_SIZE_8_MIB, 1),
-            (SECTOR_SIZE_16_MIB, 1),
-            (SECTOR_SIZE_512_MIB, 1),
-            (SECTOR_SIZE_1_GIB, 1),
-            (SECTOR_SIZE_32_GIB, 10),
-            (SECTOR_SIZE_64_GIB, 10),
-        ]
-        .iter()
-        .copied()
-        .collect()
-    );
-    pub static ref LAYERS: RwLock<HashMap<u64, usize>> = RwLock::new(
-        [
-            (SECTOR_SIZE_2_KIB, 2),
-            (SECTOR_SIZE_4_KIB, 2),
-            (SECTOR_SIZE_16_KIB, 2),
-            (SECTOR_SIZE_32_KIB, 2),
-            (SECTOR_SIZE_8_MIB, 2),
-            (SECTOR_SIZE_16_MIB, 2),
-            (SECTOR_SIZE_512_MIB, 2),
-            (SECTOR_SIZE_1_GIB, 2),
-            (SECTOR_SIZE_32_GIB, 11),
-            (SECTOR_SIZE_64_GIB, 11),
-        ]
+/// Maps every sector size of the registry to a value of its profile.
+fn from_registry<T>(value: impl Fn(&SectorProfile) -> T) -> HashMap<u64, T> {
+    sector_registry::global()
+        .profiles()
         .iter()
-        .copied()
+        .map(|profile| (profile.sector_size, value(profile)))
         .collect()
-    );
+}
+
+lazy_static! {
+    pub static ref POREP_MINIMUM_CHALLENGES: PorepMinimumChallenges = PorepMinimumChallenges::new();
+    pub static ref POREP_PARTITIONS: RwLock<HashMap<u64, u8>> =
+        RwLock::new(from_registry(|profile| profile.partitions));
+    pub static ref LAYERS: RwLock<HashMap<u64, usize>> =
+        RwLock::new(from_registry(|profile| profile.layers));
     // These numbers must match those used for Window PoSt scheduling in the miner actor.
     // Please coordinate changes with actor code.
     // https://github.com/filecoin-project/specs-actors/blob/master/actors/abi/sector.go
-    pub static ref WINDOW_POST_SECTOR_COUNT: RwLock<HashMap<u64, usize>> = RwLock::new(
-        [
-            (SECTOR_SIZE_2_KIB, 2),
-            (SECTOR_SIZE_4_KIB, 2),
-            (SECTOR_SIZE_16_KIB, 2),
-            (SECTOR_SIZE_32_KIB, 2),
-            (SECTOR_SIZE_8_MIB, 2),
-            (SECTOR_SIZE_16_MIB, 2),
-            (SECTOR_SIZE_512_MIB, 2),
-            (SECTOR_SIZE_1_GIB, 2),
-            (SECTOR_SIZE_32_GIB, 2349), // this gives 125,279,217 constraints, fitting in a single partition
-            (SECTOR_SIZE_64_GIB, 2300), // this gives 129,887,900 constraints, fitting in a single partition
-        ]
-        .iter()
-        .copied()
-        .collect()
-    );
+    pub static ref WINDOW_POST_SECTOR_COUNT: RwLock<HashMap<u64, usize>> =
+        RwLock::new(from_registry(|profile| profile.window_post_sector_count));
 }
 
 /// The size of a single snark proof.
@@ -125,6 +66,9 @@ pub const MINIMUM_RESERVED_LEAVES_FOR_PIECE_IN_SECTOR: u64 = 4;
 pub const MINIMUM_RESERVED_BYTES_FOR_PIECE_IN_FULLY_ALIGNED_SECTOR: u64 =
     (MINIMUM_RESERVED_LEAVES_FOR_PIECE_IN_SECTOR * NODE_SIZE as u64) - 1;
 
+/// Unpadded bytes of a piece padded at once by `add_piece`, a whole number of 127 byte blocks.
+pub const ADD_PIECE_CHUNK_BYTES: usize = 127 * 8192;
+
 /// The hasher used for creating comm_d.
 pub type DefaultPieceHasher = Sha256Hasher;
 pub type DefaultPieceDomain = <DefaultPieceHasher as Hasher>::Domain;
@@ -160,6 +104,7 @@ pub type SectorShape32GiB = SectorShapeSub8;
 pub type SectorShape32KiB = SectorShapeTop2;
 pub type SectorShape64GiB = SectorShapeTop2;
 /// Calls a function with the type hint of the sector shape matching the provided sector.
+/// The shape is looked up in the sector registry.
 /// Panics if provided with an unknown sector size.
 #[macro_export]
 macro_rules! with_shape {
@@ -167,41 +112,44 @@ macro_rules! with_shape {
         with_shape!($size, $f,)
     };
     ($size:expr, $f:ident, $($args:expr,)*) => {
-        match $size {
-            _x if $size == $crate::constants::SECTOR_SIZE_2_KIB => {
-              $f::<$crate::constants::SectorShape2KiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_4_KIB => {
-              $f::<$crate::constants::SectorShape4KiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_16_KIB => {
-              $f::<$crate::constants::SectorShape16KiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_32_KIB => {
-              $f::<$crate::constants::SectorShape32KiB>($($args),*)
-            },
-            _xx if $size == $crate::constants::SECTOR_SIZE_8_MIB => {
-              $f::<$crate::constants::SectorShape8MiB>($($args),*)
-            },
-            _xx if $size == $crate::constants::SECTOR_SIZE_16_MIB => {
-              $f::<$crate::constants::SectorShape16MiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_512_MIB => {
-              $f::<$crate::constants::SectorShape512MiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_1_GIB => {
-              $f::<$crate::constants::SectorShape1GiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_32_GIB => {
-              $f::<$crate::constants::SectorShape32GiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_64_GIB => {
-              $f::<$crate::constants::SectorShape64GiB>($($args),*)
-            },
-            _ => panic!("unsupported sector size: {}", $size),
+        match $crate::sector_registry::global().get($size).map(|profile| profile.shape) {
+            Some($crate::TreeShape::Base) => $f::<$crate::SectorShapeBase>($($args),*),
+            Some($crate::TreeShape::Sub2) => $f::<$crate::SectorShapeSub2>($($args),*),
+            Some($crate::TreeShape::Sub8) => $f::<$crate::SectorShapeSub8>($($args),*),
+            Some($crate::TreeShape::Top2) => $f::<$crate::SectorShapeTop2>($($args),*),
+            None => panic!("unsupported sector size: {}", $size),
         }
     };
     ($size:expr, $f:ident, $($args:expr),*) => {
         with_shape!($size, $f, $($args,)*)
     };
 }
+
+/// Shape of the sector profile registered for `sector_size`.
+pub fn sector_shape(sector_size: u64) -> anyhow::Result<TreeShape> {
+    sector_registry::global()
+        .get(sector_size)
+        .map(|profile| profile.shape)
+        .ok_or_else(|| anyhow::anyhow!("unsupported sector size: {}", sector_size))
+}
+
+/// Like `with_shape!`, for functions returning an `anyhow::Result`, but returns an error instead of
+/// panicking if provided with an unknown sector size.
+#[macro_export]
+macro_rules! try_with_shape {
+    ($size:expr, $f:ident) => {
+        try_with_shape!($size, $f,)
+    };
+    ($size:expr, $f:ident, $($args:expr,)*) => {
+        match $crate::sector_shape($size) {
+            Ok($crate::TreeShape::Base) => $f::<$crate::SectorShapeBase>($($args),*),
+            Ok($crate::TreeShape::Sub2) => $f::<$crate::SectorShapeSub2>($($args),*),
+            Ok($crate::TreeShape::Sub8) => $f::<$crate::SectorShapeSub8>($($args),*),
+            Ok($crate::TreeShape::Top2) => $f::<$crate::SectorShapeTop2>($($args),*),
+            Err(err) => Err(err),
+        }
+    };
+    ($size:expr, $f:ident, $($args:expr),*) => {
+        try_with_shape!($size, $f, $($args,)*)
+    };
+}
@@ -0,0 +1,388 @@
+//! Sealing with the single layer DRG PoRep of `proofs_porep::drg`, for the small object tier.
+//!
+//! Both the data and the replica are committed with binary trees of `DefaultTreeHasher`, and the
+//! commitments are public inputs of the proofs.
+
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use blstrs::Scalar as Fr;
+use log::info;
+use memmap2::MmapOptions;
+use merkletree::{
+    merkle::get_merkle_tree_len,
+    store::{DiskStore, ReplicaConfig, Store, StoreConfig},
+};
+use proofs_core::{
+    cache_key::CacheKey,
+    compound_proof::{self, CompoundProof},
+    drgraph::{BucketGraph, Graph},
+    merkle::{create_base_merkle_tree, create_lc_tree, BinaryMerkleTree, LCTree},
+    multi_proof::MultiProof,
+    proof::NoRequirements,
+    sector::SectorId,
+    util::default_rows_to_discard,
+};
+use proofs_porep::{
+    drg::{self, derive_challenges, DrgPoRep, DrgPoRepCompound},
+    stacked::generate_replica_id,
+    PoRep,
+};
+use rand::RngCore;
+use typenum::{U0, U2};
+
+use crate::{
+    caches::{get_drg_params, get_drg_verifying_key},
+    constants::{DefaultTreeDomain, DefaultTreeHasher},
+    parameters::{drg_public_params, drg_setup_params},
+    types::{
+        Commitment, PoRepConfig, ProverId, SealCommitOutput, SealPreCommitOutput, Ticket,
+        BINARY_ARITY,
+    },
+    util::{as_safe_commitment, commitment_from_fr},
+};
+
+type DrgGraph = BucketGraph<DefaultTreeHasher>;
+type DrgTreeR = LCTree<DefaultTreeHasher, U2, U0, U0>;
+
+fn drg_compound_public_params<'a>(
+    porep_config: &PoRepConfig,
+) -> Result<compound_proof::PublicParams<'a, DrgPoRep<'a, DefaultTreeHasher, DrgGraph>>> {
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: drg_setup_params(
+            porep_config.padded_bytes_amount(),
+            porep_config.porep_id,
+            porep_config.api_version,
+        )?,
+        partitions: Some(1),
+        priority: false,
+    };
+
+    <DrgPoRepCompound<DefaultTreeHasher, DrgGraph> as CompoundProof<
+        DrgPoRep<'a, DefaultTreeHasher, DrgGraph>,
+        _,
+    >>::setup(&compound_setup_params)
+}
+
+/// The config of the tree of the data, `tree_r` is stored alongside with the same rows discarded.
+fn drg_tree_config(cache_path: &Path, nodes: usize, key: CacheKey) -> Result<StoreConfig> {
+    let mut config = StoreConfig::new(
+        cache_path,
+        key.to_string(),
+        default_rows_to_discard(nodes, BINARY_ARITY),
+    );
+    config.size = Some(get_merkle_tree_len(nodes, BINARY_ARITY)?);
+    Ok(config)
+}
+
+fn drg_public_inputs(
+    porep_config: &PoRepConfig,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<drg::PublicInputs<DefaultTreeDomain>> {
+    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
+
+    let comm_r: DefaultTreeDomain = as_safe_commitment(&comm_r, "comm_r")?;
+    let comm_d: DefaultTreeDomain = as_safe_commitment(&comm_d, "comm_d")?;
+    let replica_id = generate_replica_id::<DefaultTreeHasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
+        comm_d,
+        &porep_config.porep_id,
+    );
+
+    let setup_params = drg_setup_params(
+        porep_config.padded_bytes_amount(),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+    let challenges = derive_challenges(
+        &replica_id,
+        &seed,
+        setup_params.drg.nodes,
+        setup_params.challenges_count,
+    );
+
+    Ok(drg::PublicInputs {
+        replica_id: Some(replica_id),
+        challenges,
+        tau: Some(drg::Tau::new(comm_d, comm_r)),
+    })
+}
+
+/// Seals the fr32 padded sector data at `in_path` into `out_path` with the DRG PoRep, storing its
+/// trees in `cache_path`.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config, of at most `DRG_MAX_SECTOR_SIZE` bytes.
+/// * `cache_path` - directory to store the trees in.
+/// * `in_path` - path of the sector data, as written by `add_piece`.
+/// * `out_path` - path of the replica to create.
+/// * `prover_id`, `sector_id`, `ticket` - the values the replica id is derived from.
+pub fn seal_drg(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    in_path: &Path,
+    out_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+) -> Result<SealPreCommitOutput> {
+    info!("seal_drg:start: {:?}", sector_id);
+
+    ensure!(
+        fs::metadata(cache_path)?.is_dir(),
+        "cache_path must be a directory"
+    );
+    let setup_params = drg_setup_params(
+        porep_config.padded_bytes_amount(),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+    let nodes = setup_params.drg.nodes;
+    let sector_bytes = u64::from(porep_config.padded_bytes_amount());
+
+    fs::copy(in_path, out_path)
+        .with_context(|| format!("could not copy {:?} to {:?}", in_path, out_path))?;
+    let f_data = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(out_path)
+        .with_context(|| format!("could not open out_path={:?}", out_path))?;
+    ensure!(
+        f_data.metadata()?.len() == sector_bytes,
+        "sector data must be exactly one sector"
+    );
+    let mut data = unsafe { MmapOptions::new().map_mut(&f_data) }
+        .with_context(|| format!("could not mmap out_path={:?}", out_path))?;
+
+    let config = drg_tree_config(cache_path, nodes, CacheKey::CommDTree)?;
+    let tree_d = create_base_merkle_tree::<BinaryMerkleTree<DefaultTreeHasher>>(
+        Some(config.clone()),
+        nodes,
+        &data,
+    )?;
+    let replica_id = generate_replica_id::<DefaultTreeHasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
+        tree_d.root(),
+        &porep_config.porep_id,
+    );
+
+    let public_params = drg_public_params(
+        porep_config.padded_bytes_amount(),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+    let (tau, _) = DrgPoRep::<DefaultTreeHasher, DrgGraph>::replicate(
+        &public_params,
+        &replica_id,
+        (&mut data[..]).into(),
+        Some(tree_d),
+        config,
+        PathBuf::from(out_path),
+    )?;
+    data.flush()?;
+
+    info!("seal_drg:finish: {:?}", sector_id);
+    Ok(SealPreCommitOutput {
+        comm_r: commitment_from_fr(tau.comm_r.into()),
+        comm_d: commitment_from_fr(tau.comm_d.into()),
+    })
+}
+
+/// Proves the replica sealed by `seal_drg`, at the challenges drawn from `seed`.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_drg_proof<R: RngCore>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    replica_path: &Path,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    rng: Option<&mut R>,
+) -> Result<SealCommitOutput> {
+    info!("generate_drg_proof:start: {:?}", sector_id);
+
+    let public_inputs = drg_public_inputs(
+        porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+    )?;
+    let compound_public_params = drg_compound_public_params(porep_config)?;
+    let nodes = compound_public_params.vanilla_params.graph.size();
+
+    let tree_d_config = drg_tree_config(cache_path, nodes, CacheKey::CommDTree)?;
+    let tree_d_size = tree_d_config.size.context("config size failure")?;
+    let tree_d_store: DiskStore<DefaultTreeDomain> =
+        DiskStore::new_from_disk(tree_d_size, BINARY_ARITY, &tree_d_config)?;
+    let tree_d = BinaryMerkleTree::<DefaultTreeHasher>::from_data_store(tree_d_store, nodes)?;
+
+    let tree_r_config = drg_tree_config(cache_path, nodes, CacheKey::CommRLastTree)?;
+    let tree_r = create_lc_tree::<DrgTreeR>(
+        tree_r_config.size.context("config size failure")?,
+        std::slice::from_ref(&tree_r_config),
+        &ReplicaConfig {
+            path: PathBuf::from(replica_path),
+            offsets: vec![0],
+        },
+    )?;
+    ensure!(
+        commitment_from_fr(tree_r.root().into()) == comm_r,
+        "tree_r does not match comm_r"
+    );
+
+    let private_inputs = drg::PrivateInputs::<DefaultTreeHasher> {
+        tree_d: &tree_d,
+        tree_r: &tree_r,
+        tree_r_config_rows_to_discard: tree_r_config.rows_to_discard,
+    };
+
+    let groth_params = get_drg_params(porep_config, rng)?;
+    let proof = DrgPoRepCompound::<DefaultTreeHasher, DrgGraph>::prove(
+        &compound_public_params,
+        &public_inputs,
+        &private_inputs,
+        &groth_params,
+    )?;
+
+    let mut buf = Vec::new();
+    proof.write(&mut buf)?;
+
+    info!("generate_drg_proof:finish: {:?}", sector_id);
+    Ok(SealCommitOutput { proof: buf })
+}
+
+/// Returns the public inputs of the proof of `generate_drg_proof`.
+pub fn get_drg_inputs(
+    porep_config: &PoRepConfig,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<Vec<Fr>> {
+    let public_inputs = drg_public_inputs(
+        porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+    )?;
+    let compound_public_params = drg_compound_public_params(porep_config)?;
+
+    DrgPoRepCompound::<DefaultTreeHasher, DrgGraph>::generate_public_inputs(
+        &public_inputs,
+        &compound_public_params.vanilla_params,
+        None,
+    )
+}
+
+/// Verifies a proof of `generate_drg_proof`.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_drg_proof<R: RngCore>(
+    porep_config: &PoRepConfig,
+    proof: &[u8],
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    rng: Option<&mut R>,
+) -> Result<bool> {
+    info!("verify_drg_proof:start: {:?}", sector_id);
+    ensure!(!proof.is_empty(), "Invalid proof bytes (empty vector)");
+
+    let public_inputs = drg_public_inputs(
+        porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+    )?;
+    let compound_public_params = drg_compound_public_params(porep_config)?;
+
+    let verifying_key = get_drg_verifying_key(porep_config, rng)?;
+    let proof = MultiProof::new_from_reader(Some(1), proof, &verifying_key)?;
+
+    let result = DrgPoRepCompound::<DefaultTreeHasher, DrgGraph>::verify(
+        &compound_public_params,
+        &public_inputs,
+        &proof,
+        &NoRequirements,
+    );
+
+    info!("verify_drg_proof:finish: {:?}", sector_id);
+    result
+}
+
+/// Decodes the replica sealed by `seal_drg` at `sealed_path`, writing the fr32 padded sector
+/// data to `out_path`.
+pub fn unseal_drg(
+    porep_config: &PoRepConfig,
+    sealed_path: &Path,
+    out_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    comm_d: Commitment,
+) -> Result<()> {
+    info!("unseal_drg:start: {:?}", sector_id);
+
+    let comm_d: DefaultTreeDomain = as_safe_commitment(&comm_d, "comm_d")?;
+    let replica_id = generate_replica_id::<DefaultTreeHasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
+        comm_d,
+        &porep_config.porep_id,
+    );
+    let public_params = drg_public_params(
+        porep_config.padded_bytes_amount(),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+
+    fs::copy(sealed_path, out_path)
+        .with_context(|| format!("could not copy {:?} to {:?}", sealed_path, out_path))?;
+    let f_out = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(out_path)
+        .with_context(|| format!("could not open out_path={:?}", out_path))?;
+    let mut data = unsafe { MmapOptions::new().map_mut(&f_out) }
+        .with_context(|| format!("could not mmap out_path={:?}", out_path))?;
+
+    DrgPoRep::<DefaultTreeHasher, DrgGraph>::extract_all(
+        &public_params,
+        &replica_id,
+        &mut data,
+        None,
+    )?;
+    data.flush()?;
+
+    info!("unseal_drg:finish: {:?}", sector_id);
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! Client-side encryption of pieces.
+//!
+//! A piece is encrypted with a fresh data key before it is padded, so its `comm_p` commits to
+//! the ciphertext and neither the staged nor the sealed sector reveal the plaintext. The data key
+//! is wrapped with the key of the client into a `KeyEnvelope`, to be kept with the sector.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use anyhow::{ensure, Result};
+use log::info;
+use proofs_core::{
+    crypto::aes::{stream_ciphertext_len, unwrap_key, wrap_key, DecryptWriter, EncryptReader},
+    merkle::MerkleTreeTrait,
+    sector::SectorId,
+};
+use rand::{Rng, RngCore};
+
+use crate::types::{
+    Commitment, KeyEnvelope, PieceInfo, PoRepConfig, ProverId, Ticket, UnpaddedByteIndex,
+    UnpaddedBytesAmount,
+};
+use crate::{add_piece, unseal_range};
+
+/// Encrypts the `plaintext_len` bytes read from `source` and adds them as a piece of
+/// `piece_size` bytes, the ciphertext being followed by zeros up to the size of the piece.
+/// Returns the piece, the number of bytes written, and the envelope of the data key wrapped
+/// with `key`.
+///
+/// # Arguments
+///
+/// * `key` - the 32 bytes key-encryption key of the client.
+/// * `source` - a readable source of the plaintext bytes.
+/// * `target` - a writer where we will write the processed piece bytes.
+/// * `plaintext_len` - the number of bytes which can be read from source before EOF.
+/// * `piece_size` - the number of unpadded bytes of the piece, which must fit the ciphertext.
+/// * `piece_lengths` - the number of bytes for each previous piece in the sector.
+/// * `rng` - the source of the data key and of the nonces.
+pub fn add_encrypted_piece<R, W>(
+    key: &[u8],
+    source: R,
+    target: W,
+    plaintext_len: u64,
+    piece_size: UnpaddedBytesAmount,
+    piece_lengths: &[UnpaddedBytesAmount],
+    rng: &mut impl RngCore,
+) -> Result<(PieceInfo, UnpaddedBytesAmount, KeyEnvelope)>
+where
+    R: Read,
+    W: Write,
+{
+    let ciphertext_len = stream_ciphertext_len(plaintext_len);
+    ensure!(
+        ciphertext_len <= u64::from(piece_size),
+        "piece_size ({:?}) is too small for {} encrypted bytes",
+        piece_size,
+        ciphertext_len,
+    );
+
+    let data_key: [u8; 32] = rng.gen();
+    let nonce = rng.gen();
+    let envelope = KeyEnvelope {
+        wrapped_key: wrap_key(key, &data_key, rng)?,
+        nonce,
+        plaintext_len,
+    };
+
+    let ciphertext = EncryptReader::new(&data_key, &nonce, source, plaintext_len)?
+        .chain(io::repeat(0).take(u64::from(piece_size) - ciphertext_len));
+    let (piece_info, written) = add_piece(ciphertext, target, piece_size, piece_lengths)?;
+
+    Ok((piece_info, written, envelope))
+}
+
+/// Decrypts the piece read from `source`, as written by `add_encrypted_piece`, to `target`.
+/// Returns the number of plaintext bytes written.
+pub fn decrypt_piece<R, W>(key: &[u8], envelope: &KeyEnvelope, source: R, target: W) -> Result<u64>
+where
+    R: Read,
+    W: Write,
+{
+    let data_key = unwrap_key(key, &envelope.wrapped_key)?;
+    let mut writer = DecryptWriter::new(&data_key, &envelope.nonce, target)?;
+
+    let ciphertext_len = stream_ciphertext_len(envelope.plaintext_len);
+    let n = io::copy(&mut source.take(ciphertext_len), &mut writer)?;
+    ensure!(n == ciphertext_len, "encrypted piece is truncated");
+    writer.finish()?;
+
+    Ok(envelope.plaintext_len)
+}
+
+/// Unseals the encrypted piece whose first (unpadded) byte begins at `offset`, and writes its
+/// plaintext to `unsealed_output`. See `unseal_range`.
+#[allow(clippy::too_many_arguments)]
+pub fn unseal_encrypted_piece<P, R, W, Tree>(
+    porep_config: &PoRepConfig,
+    cache_path: P,
+    sealed_sector: R,
+    unsealed_output: W,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    comm_d: Commitment,
+    ticket: Ticket,
+    offset: UnpaddedByteIndex,
+    key: &[u8],
+    envelope: &KeyEnvelope,
+) -> Result<u64>
+where
+    P: AsRef<Path>,
+    R: Read,
+    W: Write,
+    Tree: 'static + MerkleTreeTrait,
+{
+    info!("unseal_encrypted_piece:start: {sector_id:?}");
+
+    let data_key = unwrap_key(key, &envelope.wrapped_key)?;
+    let mut writer = DecryptWriter::new(&data_key, &envelope.nonce, unsealed_output)?;
+
+    unseal_range::<_, _, _, Tree>(
+        porep_config,
+        cache_path,
+        sealed_sector,
+        &mut writer,
+        prover_id,
+        sector_id,
+        comm_d,
+        ticket,
+        offset,
+        UnpaddedBytesAmount(stream_ciphertext_len(envelope.plaintext_len)),
+    )?;
+    writer.finish()?;
+
+    info!("unseal_encrypted_piece:finish: {sector_id:?}");
+    Ok(envelope.plaintext_len)
+}
@@ -0,0 +1,110 @@
+//! Reed-Solomon erasure coding of files across sectors.
+//!
+//! A file is split in `k` data shards and extended with `m` parity shards, each of them added as
+//! a piece with its own `comm_p`. Placed in `k + m` different sectors, the file can be recovered
+//! from the shards of any `k` of them.
+
+use std::io::Read;
+
+use anyhow::{ensure, Result};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+use crate::constants::MINIMUM_RESERVED_BYTES_FOR_PIECE_IN_FULLY_ALIGNED_SECTOR as MINIMUM_PIECE_SIZE;
+use crate::generate_piece_commitment;
+use crate::types::{ErasureLayout, UnpaddedBytesAmount};
+
+/// The smallest piece size holding `shard_size` bytes.
+fn shard_piece_size(shard_size: u64) -> UnpaddedBytesAmount {
+    let mut piece_size = MINIMUM_PIECE_SIZE;
+    while piece_size < shard_size {
+        piece_size *= 2;
+    }
+
+    UnpaddedBytesAmount(piece_size)
+}
+
+/// Splits the `file_size` bytes read from `source` in `data_shards` shards, and computes
+/// `parity_shards` more. Every shard is as large as the piece it is added as, so it can be passed
+/// as is to `add_piece` with the `piece_size` of the layout. The whole file is held in memory.
+pub fn erasure_encode<R: Read>(
+    source: R,
+    file_size: u64,
+    data_shards: usize,
+    parity_shards: usize,
+) -> Result<(ErasureLayout, Vec<Vec<u8>>)> {
+    ensure!(file_size > 0, "cannot encode an empty file");
+    let codec = ReedSolomon::new(data_shards, parity_shards)?;
+
+    let piece_size = shard_piece_size(file_size.div_ceil(data_shards as u64));
+    let shard_len = u64::from(piece_size) as usize;
+
+    let mut data = Vec::with_capacity(shard_len * data_shards);
+    source.take(file_size).read_to_end(&mut data)?;
+    ensure!(
+        data.len() as u64 == file_size,
+        "read {} bytes of a {} bytes file",
+        data.len(),
+        file_size,
+    );
+    data.resize(shard_len * data_shards, 0);
+
+    let mut shards: Vec<Vec<u8>> = data.chunks(shard_len).map(|c| c.to_vec()).collect();
+    shards.resize(data_shards + parity_shards, vec![0; shard_len]);
+    codec.encode(&mut shards)?;
+
+    let pieces = shards
+        .iter()
+        .map(|shard| generate_piece_commitment(shard.as_slice(), piece_size))
+        .collect::<Result<_>>()?;
+
+    Ok((
+        ErasureLayout {
+            data_shards,
+            parity_shards,
+            file_size,
+            piece_size,
+            pieces,
+        },
+        shards,
+    ))
+}
+
+/// Recovers a file from its shards, `shards[i]` being the i-th shard or `None` if it is lost.
+/// Every given shard is checked against its `comm_p`, and at least `data_shards` are needed.
+pub fn erasure_decode(layout: &ErasureLayout, mut shards: Vec<Option<Vec<u8>>>) -> Result<Vec<u8>> {
+    ensure!(
+        shards.len() == layout.total_shards(),
+        "{} shards given for {} shards",
+        shards.len(),
+        layout.total_shards(),
+    );
+    for (i, shard) in shards.iter().enumerate() {
+        if let Some(shard) = shard {
+            let piece_info = generate_piece_commitment(shard.as_slice(), layout.piece_size)?;
+            ensure!(
+                piece_info == layout.pieces[i],
+                "shard {} does not match its comm_p",
+                i
+            );
+        }
+    }
+    let available = shards.iter().filter(|shard| shard.is_some()).count();
+    ensure!(
+        available >= layout.data_shards,
+        "{} shards available, {} required",
+        available,
+        layout.data_shards,
+    );
+
+    let codec = ReedSolomon::new(layout.data_shards, layout.parity_shards)?;
+    codec.reconstruct_data(&mut shards)?;
+
+    let mut data: Vec<u8> = shards
+        .into_iter()
+        .take(layout.data_shards)
+        .flat_map(|shard| shard.expect("reconstructed data shard"))
+        .collect();
+    data.truncate(layout.file_size as usize);
+
+    Ok(data)
+}
@@ -1,15 +1,16 @@
 use std::{
     fs::OpenOptions,
-    io::{BufReader, Read, Seek, Write},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
     path::Path,
 };
 
 use crate::{
-    add_piece, clear_cache, generate_piece_commitment, get_seal_inputs, seal_commit_phase1,
-    seal_commit_phase2, seal_pre_commit_phase1, seal_pre_commit_phase2,
+    add_piece, clear_cache, generate_piece_commitment, get_seal_inputs, index_car_to,
+    seal_commit_phase1, seal_commit_phase2, seal_pre_commit_phase1, seal_pre_commit_phase2,
     types::{
-        Commitment, PaddedBytesAmount, PieceInfo, PoRepConfig, ProverId, SealCommitOutput,
-        SealPreCommitOutput, SealPreCommitPhase1Output, UnpaddedBytesAmount, Ticket,
+        CarIndex, Commitment, PaddedBytesAmount, PieceInfo, PoRepConfig, ProverId,
+        SealCommitOutput, SealPreCommitOutput, SealPreCommitPhase1Output, Ticket,
+        UnpaddedBytesAmount,
     },
     validate_cache_for_commit, validate_cache_for_precommit_phase2, verify_seal,
 };
@@ -39,6 +40,43 @@ fn generate_piece_file(path: &Path, sector_size: u64) -> Result<NamedTempFile> {
     Ok(piece_file)
 }
 
+/// Generates the piece of a sector from the CARv1 archive at `path`. The archive is streamed
+/// into the piece as it is checked and indexed, and followed by zeros.
+pub fn generate_car_piece_file(path: &Path, sector_size: u64) -> Result<(NamedTempFile, CarIndex)> {
+    let file = OpenOptions::new().read(true).open(path)?;
+    let number_of_bytes_in_piece = UnpaddedBytesAmount::from(PaddedBytesAmount(sector_size));
+    let fits = |car_len: u64| {
+        ensure!(
+            car_len <= number_of_bytes_in_piece.0,
+            "CAR of {} bytes does not fit in a piece of {:?}",
+            car_len,
+            number_of_bytes_in_piece,
+        );
+        Ok(())
+    };
+    fits(file.metadata()?.len())?;
+
+    // An archive growing while it is read is cut one byte past the piece, so that it either fails
+    // to be indexed or does not fit.
+    let mut piece_file = NamedTempFile::new()?;
+    let car = BufReader::new(file).take(number_of_bytes_in_piece.0 + 1);
+    let index = {
+        let mut copy = BufWriter::new(&mut piece_file);
+        let index = index_car_to(car, &mut copy)?;
+        copy.flush()?;
+        index
+    };
+    fits(index.car_len)?;
+    io::copy(
+        &mut io::repeat(0).take(number_of_bytes_in_piece.0 - index.car_len),
+        &mut piece_file,
+    )?;
+    piece_file.as_file_mut().sync_all()?;
+    piece_file.as_file_mut().rewind()?;
+
+    Ok((piece_file, index))
+}
+
 pub fn porep_config(sector_size: u64, porep_id: [u8; 32], api_version: ApiVersion) -> PoRepConfig {
     PoRepConfig::new_groth16(sector_size, porep_id, api_version)
 }
@@ -323,7 +361,7 @@ mod test {
     use std::path::Path;
 
     use crate::{
-        constants::{DefaultTreeDomain, SectorShape2KiB, SECTOR_SIZE_2_KIB},
+        constants::{sector_registry, DefaultTreeDomain, SectorShape2KiB},
         file_processor::create_seal,
     };
     use anyhow::Result;
@@ -378,8 +416,12 @@ mod test {
         ];
 
         let path = Path::new("file_2kiB.txt");
+        let sector_size = sector_registry::global()
+            .by_name("sector_size2_kib")
+            .unwrap()
+            .sector_size;
         for (porep_id, api_version) in test_inputs {
-            seal_lifecycle::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, &porep_id, api_version, path)?;
+            seal_lifecycle::<SectorShape2KiB>(sector_size, &porep_id, api_version, path)?;
         }
 
         Ok(())
@@ -0,0 +1,102 @@
+//! Finalization of sealed sectors.
+//!
+//! Proving a sector against its replica only takes `p_aux` and `tree_r_last`, of which only the
+//! rows above the `rows_to_discard` lowest ones are stored, the others being recomputed from the
+//! replica. `finalize_sector` deletes the rest of the cache, and `regenerate_tree_r_last` rebuilds
+//! `tree_r_last` from the replica when its stored rows are needed again but missing.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use log::{info, trace};
+use proofs_core::{
+    cache_key::CacheKey,
+    merkle::{get_base_tree_count, MerkleTreeTrait},
+    util::NODE_SIZE,
+};
+use proofs_porep::update::{EmptySectorUpdate, TreeRLast};
+
+use crate::constants::DefaultPieceHasher;
+use crate::sector_archive::{open_tree_r_last, read_p_aux, store_files, verify_sector_cache};
+use crate::types::PoRepConfig;
+use crate::update::{mmap_read, sector_tree_r_last_config, tree_r_last_config};
+
+/// Deletes every file of the cache of a sealed sector but `p_aux` and the stored rows of
+/// `tree_r_last`, after checking that they match the replica at `replica_path`.
+pub fn finalize_sector<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    replica_path: &Path,
+) -> Result<()> {
+    info!("finalize_sector:start");
+
+    verify_sector_cache::<Tree>(porep_config, cache_path, replica_path)?;
+
+    // Without t_aux, tree_r_last is found and rebuilt with the default config.
+    let config = sector_tree_r_last_config::<Tree>(cache_path, porep_config.sector_size)?;
+    let default_config = tree_r_last_config::<Tree>(cache_path, porep_config.sector_size)?;
+    ensure!(
+        config.size == default_config.size
+            && config.rows_to_discard == default_config.rows_to_discard,
+        "tree_r_last does not have the default shape, t_aux can not be deleted"
+    );
+
+    let mut keep: Vec<PathBuf> = store_files(&config, get_base_tree_count::<Tree>())?;
+    keep.push(cache_path.join(CacheKey::PAux.to_string()));
+    for entry in fs::read_dir(cache_path)? {
+        let path = entry?.path();
+        if path.is_file() && !keep.contains(&path) {
+            trace!("finalize_sector: removing {:?}", path);
+            fs::remove_file(&path).with_context(|| format!("could not remove {:?}", path))?;
+        }
+    }
+
+    info!("finalize_sector:finish");
+    Ok(())
+}
+
+/// Rebuilds `tree_r_last` of a sealed sector from the replica at `replica_path`, replacing its
+/// stored rows once the root is checked against the `comm_r_last` of `p_aux`.
+pub fn regenerate_tree_r_last<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    replica_path: &Path,
+) -> Result<TreeRLast<Tree>> {
+    info!("regenerate_tree_r_last:start");
+
+    let p_aux = read_p_aux::<Tree>(cache_path)?;
+    let config = sector_tree_r_last_config::<Tree>(cache_path, porep_config.sector_size)?;
+    let replica = mmap_read(replica_path)?;
+    ensure!(
+        replica.len() as u64 == u64::from(porep_config.padded_bytes_amount()),
+        "replica must be exactly one sector"
+    );
+
+    // The tree is built aside, so that stored rows are only ever replaced by checked ones.
+    let build_dir = tempfile::tempdir_in(cache_path)?;
+    let mut build_config = config.clone();
+    build_config.path = build_dir.path().to_path_buf();
+    let tree_count = get_base_tree_count::<Tree>();
+    let tree_r_last = EmptySectorUpdate::<Tree, DefaultPieceHasher>::generate_tree_r_last(
+        &replica,
+        replica.len() / NODE_SIZE / tree_count,
+        tree_count,
+        build_config.clone(),
+        replica_path.to_path_buf(),
+    )?;
+    ensure!(
+        tree_r_last.root() == p_aux.comm_r_last,
+        "tree_r_last rebuilt from the replica does not match comm_r_last"
+    );
+    drop(tree_r_last);
+
+    for path in store_files(&build_config, tree_count)? {
+        let name = path.file_name().context("invalid store path")?;
+        fs::rename(&path, cache_path.join(name))
+            .with_context(|| format!("could not move {:?} to the cache", path))?;
+    }
+
+    info!("regenerate_tree_r_last:finish");
+    open_tree_r_last::<Tree>(&config, replica_path)
+}
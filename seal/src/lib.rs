@@ -1,10 +1,18 @@
 mod caches;
+mod car;
 mod commitment_reader;
 mod constants;
+mod drg;
+mod encryption;
+mod erasure;
 mod file_processor;
+mod finalize;
 mod parameters;
-mod pieces;
-mod types;
+mod piece_inclusion;
+pub mod pieces;
+mod sector_archive;
+pub mod types;
+mod update;
 mod util;
 
 #[cfg(test)]
@@ -12,10 +20,18 @@ mod test;
 
 use bellperson::groth16::{Proof, VerifyingKey};
 pub use caches::*;
+pub use car::*;
 pub use constants::*;
+pub use drg::*;
+pub use encryption::*;
+pub use erasure::*;
 pub use file_processor::*;
+pub use finalize::*;
+pub use piece_inclusion::*;
+pub use sector_archive::*;
+pub use update::*;
 
-use fr32::Fr32Reader;
+use fr32::{pad_parallel, unpad_parallel, Fr32Reader};
 use hashers::sha256::Sha256Hasher;
 use proofs_core::drgraph::Graph;
 use proofs_core::merkle::get_base_tree_count;
@@ -48,20 +64,23 @@ use proofs_porep::stacked::{
     self, generate_replica_id, ChallengeRequirements, PersistentAux, StackedCompound, StackedDrg,
     Tau, TemporaryAux, TemporaryAuxCache,
 };
+use proofs_porep::PoRep;
 use rayon::prelude::*;
 use types::{
     Commitment, PieceInfo, PoRepConfig, ProverId, SealCommitOutput, SealCommitPhase1Output,
-    SealPreCommitOutput, SealPreCommitPhase1Output, Ticket, UnpaddedBytesAmount,
+    SealPreCommitOutput, SealPreCommitPhase1Output, Ticket, UnpaddedByteIndex,
+    UnpaddedBytesAmount,
 };
 
 use crate::caches::{get_stacked_params, get_stacked_verifying_key};
 use crate::commitment_reader::CommitmentReader;
 use crate::constants::{
     DefaultBinaryTree, DefaultOctTree, DefaultPieceDomain, DefaultPieceHasher,
+    ADD_PIECE_CHUNK_BYTES,
     MINIMUM_RESERVED_BYTES_FOR_PIECE_IN_FULLY_ALIGNED_SECTOR as MINIMUM_PIECE_SIZE,
     POREP_MINIMUM_CHALLENGES, SINGLE_PARTITION_PROOF_LEN,
 };
-use crate::parameters::setup_params;
+use crate::parameters::{public_params, setup_params};
 use crate::pieces::{get_piece_alignment, sum_piece_bytes_with_alignment, verify_pieces};
 use crate::types::{PaddedBytesAmount, BINARY_ARITY};
 use crate::util::{
@@ -939,21 +958,36 @@ where
     let result = measure_op(Operation::AddPiece, || {
         ensure_piece_size(piece_size)?;
 
-        let source = BufReader::new(source);
+        let mut source = BufReader::new(source);
         let mut target = BufWriter::new(target);
 
         let written_bytes = sum_piece_bytes_with_alignment(piece_lengths);
         let piece_alignment = get_piece_alignment(written_bytes, piece_size);
-        let fr32_reader = Fr32Reader::new(source);
 
         // write left alignment
         for _ in 0..usize::from(PaddedBytesAmount::from(piece_alignment.left_bytes)) {
             target.write_all(&[0u8][..])?;
         }
 
-        let mut commitment_reader = CommitmentReader::new(fr32_reader);
-        let n = io::copy(&mut commitment_reader, &mut target)
-            .context("failed to write and preprocess bytes")?;
+        // Pad the source in chunks of whole blocks, each of them in parallel.
+        let mut chunk = Vec::with_capacity(ADD_PIECE_CHUNK_BYTES);
+        let mut commitment_reader = CommitmentReader::new(io::Cursor::new(Vec::new()));
+        let mut n = 0;
+        loop {
+            chunk.clear();
+            source
+                .by_ref()
+                .take(ADD_PIECE_CHUNK_BYTES as u64)
+                .read_to_end(&mut chunk)
+                .context("failed to read piece bytes")?;
+            if chunk.is_empty() {
+                break;
+            }
+
+            *commitment_reader.get_mut() = io::Cursor::new(pad_parallel(&chunk));
+            n += io::copy(&mut commitment_reader, &mut target)
+                .context("failed to write and preprocess bytes")?;
+        }
 
         ensure!(n != 0, "add_piece: read 0 bytes before EOF from source");
         let n = PaddedBytesAmount(n);
@@ -979,6 +1013,128 @@ where
     result
 }
 
+/// Unseals the sector read from `sealed_sector` and returns the bytes for a
+/// piece whose first (unpadded) byte begins at `offset` and which ends before
+/// `offset` plus `num_bytes`, exclusive. Note that the entire sector is
+/// unsealed each time this function is called.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the sector size.
+/// * `cache_path` - path to the directory in which the sector data's Merkle Tree is written.
+/// * `sealed_sector` - a byte source from which we read sealed sector data.
+/// * `unsealed_output` - a byte sink to which we write unsealed, un-bit-padded sector bytes.
+/// * `prover_id` - the prover-id that sealed the sector.
+/// * `sector_id` - the sector-id of the sealed sector.
+/// * `comm_d` - the commitment to the sector's data.
+/// * `ticket` - the ticket that was used to generate the sector's replica-id.
+/// * `offset` - the byte index in the unsealed sector of the first byte that we want to read.
+/// * `num_bytes` - the number of bytes that we want to read.
+#[allow(clippy::too_many_arguments)]
+pub fn unseal_range<P, R, W, Tree>(
+    porep_config: &PoRepConfig,
+    cache_path: P,
+    mut sealed_sector: R,
+    unsealed_output: W,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    comm_d: Commitment,
+    ticket: Ticket,
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+) -> Result<UnpaddedBytesAmount>
+where
+    P: AsRef<Path>,
+    R: Read,
+    W: Write,
+    Tree: 'static + MerkleTreeTrait,
+{
+    info!("unseal_range:start: {sector_id:?}");
+    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
+
+    let comm_d = as_safe_commitment::<DefaultPieceDomain, _>(&comm_d, "comm_d")?;
+
+    let replica_id = generate_replica_id::<Tree::Hasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
+        comm_d,
+        &porep_config.porep_id,
+    );
+
+    let mut data = Vec::new();
+    sealed_sector.read_to_end(&mut data)?;
+
+    let res = unseal_range_inner::<_, _, Tree>(
+        porep_config,
+        cache_path,
+        &mut data,
+        unsealed_output,
+        replica_id,
+        offset,
+        num_bytes,
+    )?;
+
+    info!("unseal_range:finish: {sector_id:?}");
+    Ok(res)
+}
+
+fn unseal_range_inner<P, W, Tree>(
+    porep_config: &PoRepConfig,
+    cache_path: P,
+    data: &mut [u8],
+    mut unsealed_output: W,
+    replica_id: <Tree::Hasher as Hasher>::Domain,
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+) -> Result<UnpaddedBytesAmount>
+where
+    P: AsRef<Path>,
+    W: Write,
+    Tree: 'static + MerkleTreeTrait,
+{
+    trace!("unseal_range_inner:start");
+
+    let offset_padded: PaddedBytesAmount = UnpaddedBytesAmount::from(offset).into();
+    let num_bytes_padded: PaddedBytesAmount = num_bytes.into();
+    let start: usize = offset_padded.into();
+    let end = start + usize::from(num_bytes_padded);
+    ensure!(
+        end <= usize::from(porep_config.padded_bytes_amount()),
+        "unseal range exceeds sector size"
+    );
+    ensure!(
+        end <= data.len(),
+        "sealed sector is shorter than the requested range"
+    );
+
+    let base_tree_size = get_base_tree_size::<DefaultBinaryTree>(porep_config.sector_size)?;
+    let base_tree_leafs = get_base_tree_leafs::<DefaultBinaryTree>(base_tree_size)?;
+    let config = StoreConfig::new(
+        cache_path.as_ref(),
+        CacheKey::CommDTree.to_string(),
+        default_rows_to_discard(base_tree_leafs, BINARY_ARITY),
+    );
+    let pp = public_params::<Tree>(
+        porep_config.padded_bytes_amount(),
+        1,
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+
+    StackedDrg::<Tree, DefaultPieceHasher>::extract_all(&pp, &replica_id, data, Some(config))?;
+    let unsealed = &data[start..end];
+
+    // The byte at index 0 of `unsealed` is the byte at index `offset_padded`
+    // of the sealed sector, so the unpadded read starts at bit offset 0.
+    let unpadded =
+        unpad_parallel(unsealed, 0, num_bytes.into()).context("unpad_parallel failed")?;
+    unsealed_output.write_all(&unpadded)?;
+
+    trace!("unseal_range_inner:finish");
+    Ok(UnpaddedBytesAmount(unpadded.len() as u64))
+}
+
 // Verifies if a DiskStore specified by a config (or set of 'required_configs' is consistent).
 fn verify_store(config: &StoreConfig, arity: usize, required_configs: usize) -> Result<()> {
     let store_path = StoreConfig::data_path(&config.path, &config.id);
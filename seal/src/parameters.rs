@@ -1,11 +1,17 @@
 use crate::{
-    constants::{DefaultPieceHasher, DRG_DEGREE, EXP_DEGREE, LAYERS},
+    constants::{
+        DefaultPieceHasher, DefaultTreeHasher, DRG_DEGREE, DRG_MAX_SECTOR_SIZE, EXP_DEGREE, LAYERS,
+    },
     types::{MerkleTreeTrait, PaddedBytesAmount},
     POREP_MINIMUM_CHALLENGES,
 };
 use anyhow::{ensure, Result};
-use proofs_core::{api_version::ApiVersion, proof::ProofScheme};
-use proofs_porep::stacked::{self, LayerChallenges, StackedDrg};
+use proofs_core::{api_version::ApiVersion, drgraph::BucketGraph, proof::ProofScheme};
+use proofs_porep::{
+    drg::{self, DrgPoRep},
+    stacked::{self, LayerChallenges, StackedDrg},
+    update::{self, EmptySectorUpdate},
+};
 
 pub fn public_params<Tree: 'static + MerkleTreeTrait>(
     sector_bytes: PaddedBytesAmount,
@@ -73,4 +79,71 @@ fn select_challenges(
     guess
 }
 
+pub fn update_public_params<Tree: 'static + MerkleTreeTrait>(
+    sector_bytes: PaddedBytesAmount,
+) -> Result<update::PublicParams> {
+    EmptySectorUpdate::<Tree, DefaultPieceHasher>::setup(&update_setup_params(sector_bytes)?)
+}
+
+/// An update samples as many nodes as the PoRep of the same sector size.
+pub fn update_setup_params(sector_bytes: PaddedBytesAmount) -> Result<update::SetupParams> {
+    let sector_bytes = u64::from(sector_bytes);
+
+    ensure!(
+        sector_bytes % 32 == 0,
+        "sector_bytes ({}) must be a multiple of 32",
+        sector_bytes,
+    );
 
+    Ok(update::SetupParams {
+        nodes: (sector_bytes / 32) as usize,
+        challenges_count: POREP_MINIMUM_CHALLENGES.from_sector_size(sector_bytes),
+    })
+}
+
+pub type DrgPublicParams = drg::PublicParams<DefaultTreeHasher, BucketGraph<DefaultTreeHasher>>;
+
+pub fn drg_public_params(
+    sector_bytes: PaddedBytesAmount,
+    porep_id: [u8; 32],
+    api_version: ApiVersion,
+) -> Result<DrgPublicParams> {
+    DrgPoRep::<DefaultTreeHasher, BucketGraph<DefaultTreeHasher>>::setup(&drg_setup_params(
+        sector_bytes,
+        porep_id,
+        api_version,
+    )?)
+}
+
+/// The DRG PoRep is single layer, so it is only used for the small sector sizes, and challenges
+/// as many nodes as the stacked PoRep of the same size. Its commitments are public inputs.
+pub fn drg_setup_params(
+    sector_bytes: PaddedBytesAmount,
+    porep_id: [u8; 32],
+    api_version: ApiVersion,
+) -> Result<drg::SetupParams> {
+    let sector_bytes = u64::from(sector_bytes);
+
+    ensure!(
+        sector_bytes <= DRG_MAX_SECTOR_SIZE,
+        "sector_bytes ({}) is too large for the DRG PoRep",
+        sector_bytes,
+    );
+    ensure!(
+        sector_bytes % 32 == 0,
+        "sector_bytes ({}) must be a multiple of 32",
+        sector_bytes,
+    );
+
+    Ok(drg::SetupParams {
+        drg: drg::DrgParams {
+            nodes: (sector_bytes / 32) as usize,
+            degree: DRG_DEGREE,
+            expansion_degree: 0,
+            porep_id,
+        },
+        private: false,
+        challenges_count: POREP_MINIMUM_CHALLENGES.from_sector_size(sector_bytes),
+        api_version,
+    })
+}
@@ -0,0 +1,130 @@
+use anyhow::{ensure, Context, Result};
+use hashers::{Domain, Hasher};
+use proofs_core::{
+    merkle::{BinaryMerkleTree, MerkleProofTrait, MerkleTreeTrait},
+    pieces::PieceSpec,
+    util::NODE_SIZE,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::DefaultPieceHasher,
+    pieces::{get_piece_alignment, piece_hash},
+    types::{Commitment, PaddedBytesAmount, PieceInfo, UnpaddedBytesAmount},
+};
+
+/// Proof that a piece is a subtree of the data tree committed to by `comm_d`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceInclusionProof {
+    /// Index of the first leaf of the piece in the data tree.
+    pub position: u64,
+    /// Number of leaves spanned by the piece, a power of two.
+    pub number_of_leaves: u64,
+    /// Siblings on the path from the root of the piece up to `comm_d`, bottom up.
+    pub siblings: Vec<Commitment>,
+}
+
+/// Returns where each of the pieces, in the order they were added, lies in the data tree.
+pub fn piece_specs(piece_infos: &[PieceInfo]) -> Vec<PieceSpec> {
+    let mut written = UnpaddedBytesAmount(0);
+
+    piece_infos
+        .iter()
+        .map(|piece_info| {
+            let alignment = get_piece_alignment(written, piece_info.size);
+            let position = PaddedBytesAmount::from(written + alignment.left_bytes);
+            let length = PaddedBytesAmount::from(piece_info.size + alignment.right_bytes);
+            written = written + alignment.sum(piece_info.size);
+
+            PieceSpec {
+                comm_p: piece_info.commitment,
+                position: u64::from(position) as usize / NODE_SIZE,
+                number_of_leaves: u64::from(length) as usize / NODE_SIZE,
+            }
+        })
+        .collect()
+}
+
+/// Proves that the piece described by `piece_spec` is part of `tree_d`.
+pub fn generate_piece_inclusion_proof(
+    tree_d: &BinaryMerkleTree<DefaultPieceHasher>,
+    piece_spec: &PieceSpec,
+) -> Result<PieceInclusionProof> {
+    let (_, proof_length) = piece_spec.compute_packing(tree_d.leaves())?;
+
+    // The path from the first leaf of the piece goes through the root of the piece.
+    let proof = tree_d.gen_proof(piece_spec.position)?;
+    let path = proof.path();
+    let piece_height = path
+        .len()
+        .checked_sub(proof_length)
+        .context("piece is larger than the data tree")?;
+
+    let comm_p = path[..piece_height]
+        .iter()
+        .fold(proof.leaf(), |node, (siblings, index)| {
+            hash_node(node.as_ref(), siblings[0].as_ref(), *index)
+        });
+    ensure!(
+        AsRef::<[u8]>::as_ref(&comm_p) == piece_spec.comm_p,
+        "comm_p does not match the data tree at position {}",
+        piece_spec.position
+    );
+
+    let siblings = path[piece_height..]
+        .iter()
+        .map(|(siblings, _)| {
+            let mut sibling = Commitment::default();
+            siblings[0].write_bytes(&mut sibling)?;
+            Ok(sibling)
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(PieceInclusionProof {
+        position: piece_spec.position as u64,
+        number_of_leaves: piece_spec.number_of_leaves as u64,
+        siblings,
+    })
+}
+
+/// Verifies that `comm_p` is included in `comm_d` at the place given by `piece_spec`.
+pub fn verify_piece_inclusion_proof(
+    comm_d: &Commitment,
+    comm_p: &Commitment,
+    piece_spec: &PieceSpec,
+    proof: &PieceInclusionProof,
+) -> Result<bool> {
+    ensure!(
+        &piece_spec.comm_p == comm_p,
+        "comm_p does not match piece spec"
+    );
+    ensure!(
+        piece_spec.position as u64 == proof.position
+            && piece_spec.number_of_leaves as u64 == proof.number_of_leaves,
+        "proof is for another piece placement"
+    );
+    if !proof.number_of_leaves.is_power_of_two()
+        || !proof.position.is_multiple_of(proof.number_of_leaves)
+    {
+        return Ok(false);
+    }
+
+    let mut index = proof.position / proof.number_of_leaves;
+    let mut node = *comm_p;
+    for sibling in &proof.siblings {
+        let hash = hash_node(&node, sibling, (index & 1) as usize);
+        node.copy_from_slice(hash.as_ref());
+        index >>= 1;
+    }
+
+    Ok(index == 0 && &node == comm_d)
+}
+
+/// Hashes `node` with its `sibling`, `index` being the position of `node` in the pair.
+fn hash_node(node: &[u8], sibling: &[u8], index: usize) -> <DefaultPieceHasher as Hasher>::Domain {
+    if index == 0 {
+        piece_hash(node, sibling)
+    } else {
+        piece_hash(sibling, node)
+    }
+}
@@ -0,0 +1,419 @@
+//! Export and import of sealed sectors, to move them between machines.
+//!
+//! An archive starts with `SECTOR_ARCHIVE_MAGIC`, the length of its JSON manifest as a little
+//! endian `u64` and the manifest, followed by the files the manifest lists, in order: the
+//! replica, `p_aux`, `t_aux` unless the sector was finalized, and the stores of the cache which
+//! are present (the labels, `tree_d`, `tree_c` and the split files of `tree_r_last`). An imported
+//! archive is checked against the digests of its manifest, then as a sector against the expected
+//! `comm_r`.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use bincode::deserialize;
+use hashers::{HashFunction, Hasher};
+use log::{info, warn};
+use merkletree::store::StoreConfig;
+use proofs_core::{
+    cache_key::CacheKey,
+    merkle::MerkleTreeTrait,
+    merkle::{create_lc_tree, get_base_tree_count, split_config, split_config_and_replica},
+    sector::SectorId,
+};
+use proofs_porep::{
+    stacked::{PersistentAux, TemporaryAux},
+    update::TreeRLast,
+};
+use sha2::{Digest, Sha256};
+use typenum::Unsigned;
+
+use crate::constants::DefaultPieceHasher;
+use crate::finalize::regenerate_tree_r_last;
+use crate::types::{ArchivedFile, Commitment, PoRepConfig, SectorArchiveManifest, BINARY_ARITY};
+use crate::update::sector_tree_r_last_config;
+use crate::util::{commitment_from_fr, get_base_tree_leafs};
+use crate::{verify_level_cache_store, verify_store};
+
+pub const SECTOR_ARCHIVE_MAGIC: &[u8; 8] = b"SECTARC\0";
+pub const SECTOR_ARCHIVE_VERSION: u32 = 1;
+/// The name of the replica in the manifest of an archive.
+pub const SECTOR_ARCHIVE_REPLICA: &str = "sealed";
+
+const MAX_MANIFEST_LEN: u64 = 1 << 20;
+const COPY_BUFFER_LEN: usize = 1 << 20;
+
+type TreeDomain<Tree> = <<Tree as MerkleTreeTrait>::Hasher as Hasher>::Domain;
+
+/// Writes the sealed replica at `replica_path` and the cache at `cache_path` needed to prove it
+/// to `output`, after checking that they are consistent. `metadata` is stored as is in the
+/// manifest, for the importer to restore the sector with.
+pub fn export_sector<Tree: 'static + MerkleTreeTrait, W: Write>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    replica_path: &Path,
+    sector_id: SectorId,
+    metadata: serde_json::Value,
+    output: W,
+) -> Result<SectorArchiveManifest> {
+    info!("export_sector:start: {sector_id:?}");
+
+    let comm_r = verify_sector_cache::<Tree>(porep_config, cache_path, replica_path)?;
+    let paths = sector_files::<Tree>(porep_config, cache_path, replica_path)?;
+    let files = paths
+        .iter()
+        .map(|(name, path)| {
+            let file = File::open(path).with_context(|| format!("could not open {:?}", path))?;
+            let (len, sha256) = copy_with_digest(file, io::sink())?;
+            Ok(ArchivedFile {
+                name: name.clone(),
+                len,
+                sha256,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let manifest = SectorArchiveManifest {
+        version: SECTOR_ARCHIVE_VERSION,
+        sector_id: sector_id.into(),
+        sector_size: porep_config.sector_size.into(),
+        comm_r,
+        files,
+        metadata,
+    };
+    let manifest_bytes = serde_json::to_vec(&manifest)?;
+
+    let mut output = BufWriter::new(output);
+    output.write_all(SECTOR_ARCHIVE_MAGIC)?;
+    output.write_all(&(manifest_bytes.len() as u64).to_le_bytes())?;
+    output.write_all(&manifest_bytes)?;
+    for (file, (_, path)) in manifest.files.iter().zip(&paths) {
+        let source = File::open(path).with_context(|| format!("could not open {:?}", path))?;
+        let (len, sha256) = copy_with_digest(source, &mut output)?;
+        ensure!(
+            len == file.len && sha256 == file.sha256,
+            "{:?} changed while the sector was exported",
+            path
+        );
+    }
+    output.flush()?;
+
+    info!("export_sector:finish: {sector_id:?}");
+    Ok(manifest)
+}
+
+/// Reads the manifest at the start of a sector archive.
+pub fn read_sector_archive_manifest<R: Read>(archive: &mut R) -> Result<SectorArchiveManifest> {
+    let mut magic = [0; 8];
+    archive
+        .read_exact(&mut magic)
+        .context("could not read sector archive")?;
+    ensure!(&magic == SECTOR_ARCHIVE_MAGIC, "not a sector archive");
+
+    let mut len = [0; 8];
+    archive.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    ensure!(len <= MAX_MANIFEST_LEN, "invalid manifest length {len}");
+
+    let mut manifest_bytes = vec![0; len as usize];
+    archive
+        .read_exact(&mut manifest_bytes)
+        .context("truncated manifest")?;
+    let manifest: SectorArchiveManifest =
+        serde_json::from_slice(&manifest_bytes).context("invalid manifest")?;
+    ensure!(
+        manifest.version == SECTOR_ARCHIVE_VERSION,
+        "unsupported sector archive version {}",
+        manifest.version
+    );
+
+    Ok(manifest)
+}
+
+/// Unpacks a sector archive, the replica to `replica_path` and the cache into `cache_path`, and
+/// checks every file against its digest and the stores and `p_aux` against `comm_r`. The replica
+/// itself is checked by rebuilding `tree_r_last` from it. Nothing is left behind if a check fails.
+pub fn import_sector<Tree: 'static + MerkleTreeTrait, R: Read>(
+    porep_config: &PoRepConfig,
+    archive: R,
+    cache_path: &Path,
+    replica_path: &Path,
+    comm_r: Commitment,
+) -> Result<SectorArchiveManifest> {
+    let mut archive = BufReader::new(archive);
+    let manifest = read_sector_archive_manifest(&mut archive)?;
+    info!("import_sector:start: {}", manifest.sector_id);
+
+    ensure!(
+        manifest.sector_size == u64::from(porep_config.sector_size),
+        "archive of a sector of {} bytes, expected {}",
+        manifest.sector_size,
+        u64::from(porep_config.sector_size)
+    );
+    ensure!(
+        manifest.comm_r == comm_r,
+        "archive of sector {} does not have the expected comm_r",
+        manifest.sector_id
+    );
+    ensure!(
+        fs::metadata(cache_path)?.is_dir(),
+        "cache_path must be a directory"
+    );
+    check_file_names(&manifest)?;
+    ensure!(
+        manifest
+            .files
+            .iter()
+            .any(|file| file.name == SECTOR_ARCHIVE_REPLICA && file.len == manifest.sector_size),
+        "replica does not have the size of the sector"
+    );
+
+    let mut written = vec![];
+    let result = import_files(
+        &manifest,
+        &mut archive,
+        cache_path,
+        replica_path,
+        &mut written,
+    )
+    .and_then(|_| {
+        let actual = verify_sector_cache::<Tree>(porep_config, cache_path, replica_path)?;
+        ensure!(actual == comm_r, "imported sector does not match comm_r");
+        // The stored rows of tree_r_last match any replica, they are only checked against
+        // comm_r_last once rebuilt from it.
+        regenerate_tree_r_last::<Tree>(porep_config, cache_path, replica_path)?;
+        Ok(())
+    });
+    if let Err(err) = result {
+        for path in written {
+            if let Err(err) = fs::remove_file(&path) {
+                warn!("could not remove {:?}: {}", path, err);
+            }
+        }
+        return Err(err);
+    }
+
+    info!("import_sector:finish: {}", manifest.sector_id);
+    Ok(manifest)
+}
+
+fn check_file_names(manifest: &SectorArchiveManifest) -> Result<()> {
+    let mut names = vec![];
+    for file in &manifest.files {
+        let name = file.name.as_str();
+        ensure!(
+            !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\']),
+            "invalid file name {name:?} in archive"
+        );
+        ensure!(
+            !names.contains(&name),
+            "file {name:?} appears twice in archive"
+        );
+        names.push(name);
+    }
+    for required in [
+        SECTOR_ARCHIVE_REPLICA.to_string(),
+        CacheKey::PAux.to_string(),
+    ] {
+        ensure!(
+            names.contains(&required.as_str()),
+            "archive has no {required}"
+        );
+    }
+
+    Ok(())
+}
+
+fn import_files<R: Read>(
+    manifest: &SectorArchiveManifest,
+    archive: &mut R,
+    cache_path: &Path,
+    replica_path: &Path,
+    written: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for file in &manifest.files {
+        let path = if file.name == SECTOR_ARCHIVE_REPLICA {
+            replica_path.to_path_buf()
+        } else {
+            cache_path.join(&file.name)
+        };
+        ensure!(!path.exists(), "{:?} already exists", path);
+
+        let output = File::create(&path).with_context(|| format!("could not create {:?}", path))?;
+        written.push(path);
+        let (len, sha256) = copy_with_digest(archive.take(file.len), BufWriter::new(output))?;
+        ensure!(len == file.len, "archive is truncated in {}", file.name);
+        ensure!(sha256 == file.sha256, "digest mismatch for {}", file.name);
+    }
+    ensure!(
+        archive.read(&mut [0])? == 0,
+        "trailing data after the files of the archive"
+    );
+
+    Ok(())
+}
+
+/// Copies `source` to `output`, returning the number of bytes copied and their hex sha2-256.
+fn copy_with_digest<R: Read, W: Write>(mut source: R, mut output: W) -> Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; COPY_BUFFER_LEN];
+    let mut len = 0;
+    loop {
+        let n = match source.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        hasher.update(&buffer[..n]);
+        output.write_all(&buffer[..n])?;
+        len += n as u64;
+    }
+    output.flush()?;
+
+    Ok((len, hex::encode(hasher.finalize())))
+}
+
+pub(crate) fn read_t_aux<Tree: MerkleTreeTrait>(
+    cache_path: &Path,
+) -> Result<TemporaryAux<Tree, DefaultPieceHasher>> {
+    let t_aux_path = cache_path.join(CacheKey::TAux.to_string());
+    let t_aux_bytes = fs::read(&t_aux_path)
+        .with_context(|| format!("could not read file t_aux={:?}", t_aux_path))?;
+
+    let mut t_aux: TemporaryAux<Tree, DefaultPieceHasher> = deserialize(&t_aux_bytes)?;
+    t_aux.set_cache_path(cache_path);
+    Ok(t_aux)
+}
+
+pub(crate) fn read_p_aux<Tree: MerkleTreeTrait>(
+    cache_path: &Path,
+) -> Result<PersistentAux<TreeDomain<Tree>>> {
+    let p_aux_path = cache_path.join(CacheKey::PAux.to_string());
+    let p_aux_bytes = fs::read(&p_aux_path)
+        .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?;
+
+    Ok(deserialize(&p_aux_bytes)?)
+}
+
+/// The files of a store, split or not, which are present.
+pub(crate) fn store_files(config: &StoreConfig, count: usize) -> Result<Vec<PathBuf>> {
+    let path = StoreConfig::data_path(&config.path, &config.id);
+    if path.exists() {
+        return Ok(vec![path]);
+    }
+
+    Ok(split_config(config.clone(), count)?
+        .iter()
+        .map(|config| StoreConfig::data_path(&config.path, &config.id))
+        .filter(|path| path.exists())
+        .collect())
+}
+
+/// The files of an exported sector, by their name in the archive.
+fn sector_files<Tree: MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    replica_path: &Path,
+) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = vec![
+        (
+            SECTOR_ARCHIVE_REPLICA.to_string(),
+            replica_path.to_path_buf(),
+        ),
+        (
+            CacheKey::PAux.to_string(),
+            cache_path.join(CacheKey::PAux.to_string()),
+        ),
+    ];
+
+    let mut configs = vec![];
+    let t_aux_path = cache_path.join(CacheKey::TAux.to_string());
+    if t_aux_path.exists() {
+        files.push((CacheKey::TAux.to_string(), t_aux_path));
+        let t_aux = read_t_aux::<Tree>(cache_path)?;
+        configs.extend(t_aux.labels.labels);
+        configs.extend([t_aux.tree_d_config, t_aux.tree_c_config]);
+    }
+    configs.push(sector_tree_r_last_config::<Tree>(
+        cache_path,
+        porep_config.sector_size,
+    )?);
+    for config in &configs {
+        for path in store_files(config, get_base_tree_count::<Tree>())? {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .with_context(|| format!("invalid store path {:?}", path))?
+                .to_string();
+            files.push((name, path));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Opens the `tree_r_last` persisted with `config` over the replica at `replica_path`.
+pub(crate) fn open_tree_r_last<Tree: MerkleTreeTrait>(
+    config: &StoreConfig,
+    replica_path: &Path,
+) -> Result<TreeRLast<Tree>> {
+    // The size stored in the config is the base tree size.
+    let size = config.size.context("tree_r_last config has no size")?;
+    let (configs, replica_config) = split_config_and_replica(
+        config.clone(),
+        replica_path.to_path_buf(),
+        get_base_tree_leafs::<Tree>(size)?,
+        get_base_tree_count::<Tree>(),
+    )?;
+
+    create_lc_tree::<Tree>(size, &configs, &replica_config)
+}
+
+/// Checks the stores of the cache of a sealed sector which are present, and that the stored rows
+/// of `tree_r_last` are the ones `p_aux` commits to. The replica is not hashed, see
+/// `regenerate_tree_r_last`. Returns the `comm_r` of `p_aux`.
+pub(crate) fn verify_sector_cache<Tree: MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    replica_path: &Path,
+) -> Result<Commitment> {
+    ensure!(
+        fs::metadata(replica_path)
+            .with_context(|| format!("Missing replica: {}", replica_path.display()))?
+            .len()
+            > 0,
+        "Replica {} exists, but is empty!",
+        replica_path.display()
+    );
+
+    let p_aux = read_p_aux::<Tree>(cache_path)?;
+    let tree_count = get_base_tree_count::<Tree>();
+
+    // A finalized sector has no t_aux, nor any of the stores it describes but tree_r_last.
+    if cache_path.join(CacheKey::TAux.to_string()).exists() {
+        let t_aux = read_t_aux::<Tree>(cache_path)?;
+        for config in t_aux.labels.labels.iter().chain([&t_aux.tree_d_config]) {
+            if !store_files(config, tree_count)?.is_empty() {
+                verify_store(config, BINARY_ARITY, tree_count)?;
+            }
+        }
+        if !store_files(&t_aux.tree_c_config, tree_count)?.is_empty() {
+            verify_store(&t_aux.tree_c_config, Tree::Arity::to_usize(), tree_count)?;
+        }
+    }
+
+    let tree_r_last_config =
+        sector_tree_r_last_config::<Tree>(cache_path, porep_config.sector_size)?;
+    verify_level_cache_store::<Tree>(&tree_r_last_config)?;
+
+    let tree_r_last = open_tree_r_last::<Tree>(&tree_r_last_config, replica_path)?;
+    ensure!(
+        tree_r_last.root() == p_aux.comm_r_last,
+        "tree_r_last does not match comm_r_last of p_aux"
+    );
+
+    let comm_r = <Tree::Hasher as Hasher>::Function::hash2(&p_aux.comm_c, &p_aux.comm_r_last);
+    Ok(commitment_from_fr(comm_r.into()))
+}
@@ -1,30 +1,38 @@
 use std::{
-    io::{Seek, Write},
+    io::{Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
 use crate::{
     add_piece, clear_cache,
     constants::{
-        DefaultTreeDomain, SectorShape16KiB, SectorShape2KiB, SectorShape32GiB, SectorShape32KiB,
-        SectorShape4KiB, SectorShape512MiB, SectorShape64GiB, SECTOR_SIZE_16_KIB,
-        SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB, SECTOR_SIZE_32_KIB, SECTOR_SIZE_4_KIB,
-        SECTOR_SIZE_512_MIB, SECTOR_SIZE_64_GIB,
+        sector_registry, DefaultTreeDomain, SectorShape16KiB, SectorShape2KiB, SectorShape32GiB, SectorShape32KiB,
+        SectorShape4KiB, SectorShape512MiB, SectorShape64GiB,
     },
-    generate_piece_commitment, get_seal_inputs,
-    pieces::compute_comm_d,
-    seal_commit_phase1, seal_commit_phase2, seal_pre_commit_phase1, seal_pre_commit_phase2,
+    add_encrypted_piece, decode_from, decrypt_piece, encode_into, erasure_decode, erasure_encode,
+    generate_drg_proof, generate_empty_sector_update_proof, generate_piece_commitment, generate_piece_inclusion_proof, get_seal_inputs,
+    export_sector, finalize_sector, generate_car_piece_file, import_sector, index_car, piece_specs,
+    read_sector_archive_manifest, regenerate_tree_r_last, SECTOR_ARCHIVE_MAGIC,
+    SECTOR_ARCHIVE_REPLICA,
+    pieces::{compute_comm_d, EmptySource},
+    seal_commit_phase1, seal_commit_phase2, seal_drg, seal_pre_commit_phase1,
+    seal_pre_commit_phase2,
     types::{
-        Commitment, PaddedBytesAmount, PieceInfo, PoRepConfig, ProverId, SealCommitOutput,
-        SealPreCommitOutput, SealPreCommitPhase1Output, UnpaddedBytesAmount,
+        cid_to_commitment, cid_to_commitment_of, commitment_to_cid, Commitment, CommitmentKind,
+        FilePlacement, PaddedBytesAmount, PieceInfo, PoRepConfig, ProverId,
+        SealCommitOutput, SealPreCommitOutput, SealPreCommitPhase1Output, ShardPlacement,
+        UnpaddedByteIndex, UnpaddedBytesAmount,
     },
-    validate_cache_for_commit, validate_cache_for_precommit_phase2, verify_seal,
+    unseal_drg, unseal_range, validate_cache_for_commit, validate_cache_for_precommit_phase2,
+    verify_drg_proof, verify_empty_sector_update_proof, verify_piece_inclusion_proof,
+    verify_car_block, verify_seal, DefaultPieceDomain, DefaultPieceHasher,
 };
 use anyhow::{ensure, Result};
 use blstrs::Scalar as Fr;
 use ff::Field;
 use log::info;
-use proofs_core::{api_version::ApiVersion, merkle::MerkleTreeTrait, sector::SectorId, TEST_SEED, is_legacy_porep_id};
+use hashers::Domain;
+use proofs_core::{api_version::ApiVersion, merkle::{get_base_tree_count, BinaryMerkleTree, MerkleTreeTrait}, sector::SectorId, TEST_SEED, is_legacy_porep_id};
 use rand::{random, Rng, RngCore, SeedableRng};
 use rand_xorshift::XorShiftRng;
 use tempfile::{tempdir, NamedTempFile, TempDir};
@@ -44,6 +52,14 @@ fn generate_piece_file(sector_size: u64) -> Result<(NamedTempFile, Vec<u8>)> {
     Ok((piece_file, piece_bytes))
 }
 
+/// Size of the sector profile named `name`, e.g. `sector_size2_kib`.
+fn sector_size(name: &str) -> u64 {
+    sector_registry::global()
+        .by_name(name)
+        .expect("unknown sector profile")
+        .sector_size
+}
+
 fn porep_config(sector_size: u64, porep_id: [u8; 32], api_version: ApiVersion) -> PoRepConfig {
     PoRepConfig::new_groth16(sector_size, porep_id, api_version)
 }
@@ -146,15 +162,15 @@ fn generate_proof<Tree: 'static + MerkleTreeTrait, R: RngCore>(
 #[allow(clippy::too_many_arguments)]
 fn unseal<Tree: 'static + MerkleTreeTrait, R: RngCore>(
     config: &PoRepConfig,
-    _cache_dir_path: &Path,
-    _sealed_sector_file: &NamedTempFile,
+    cache_dir_path: &Path,
+    sealed_sector_file: &NamedTempFile,
     prover_id: ProverId,
     sector_id: SectorId,
     ticket: [u8; 32],
     seed: [u8; 32],
     pre_commit_output: &SealPreCommitOutput,
     piece_infos: &[PieceInfo],
-    _piece_bytes: &[u8],
+    piece_bytes: &[u8],
     commit_output: &SealCommitOutput,
     rng: Option<&mut R>,
 ) -> Result<()> {
@@ -168,6 +184,30 @@ fn unseal<Tree: 'static + MerkleTreeTrait, R: RngCore>(
         "Computed and expected comm_d don't match."
     );
 
+    let mut unseal_file = NamedTempFile::new()?;
+    let _ = unseal_range::<_, _, _, Tree>(
+        config,
+        cache_dir_path,
+        sealed_sector_file,
+        &unseal_file,
+        prover_id,
+        sector_id,
+        comm_d,
+        ticket,
+        UnpaddedByteIndex(508),
+        UnpaddedBytesAmount(508),
+    )?;
+
+    unseal_file.seek(SeekFrom::Start(0))?;
+
+    let mut contents = vec![];
+    assert!(
+        unseal_file.read_to_end(&mut contents).is_ok(),
+        "failed to populate buffer with unsealed bytes"
+    );
+    assert_eq!(contents.len(), 508);
+    assert_eq!(&piece_bytes[508..508 + 508], &contents[..]);
+
     let verified = verify_seal::<Tree, R>(
         config,
         comm_r,
@@ -315,6 +355,196 @@ fn seal_lifecycle<Tree: 'static + MerkleTreeTrait>(
     Ok(())
 }
 
+fn drg_lifecycle(sector_size: u64, porep_id: &[u8; 32], api_version: ApiVersion) -> Result<()> {
+    fil_logger::maybe_init();
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = porep_config(sector_size, *porep_id, api_version);
+    let number_of_bytes_in_piece = config.unpadded_bytes_amount();
+    let prover_id = rng.gen();
+    let sector_id = rng.gen::<u64>().into();
+    let ticket = rng.gen();
+    let seed = rng.gen();
+
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let mut staged_sector_file = NamedTempFile::new()?;
+    add_piece(
+        &mut piece_file,
+        &mut staged_sector_file,
+        number_of_bytes_in_piece,
+        &[],
+    )?;
+
+    let sealed_sector_file = NamedTempFile::new()?;
+    let cache_dir = tempdir()?;
+    let output = seal_drg(
+        &config,
+        cache_dir.path(),
+        staged_sector_file.path(),
+        sealed_sector_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+    )?;
+
+    let proof = generate_drg_proof(
+        &config,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+        output.comm_r,
+        output.comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        Some(&mut rng),
+    )?;
+    let verify = |seed, rng: &mut XorShiftRng| {
+        verify_drg_proof(
+            &config,
+            &proof.proof,
+            output.comm_r,
+            output.comm_d,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            Some(rng),
+        )
+    };
+    assert!(verify(seed, &mut rng)?);
+    assert!(!verify(ticket, &mut rng)?);
+
+    let unsealed_file = NamedTempFile::new()?;
+    unseal_drg(
+        &config,
+        sealed_sector_file.path(),
+        unsealed_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        output.comm_d,
+    )?;
+    assert_eq!(
+        std::fs::read(unsealed_file.path())?,
+        std::fs::read(staged_sector_file.path())?
+    );
+
+    Ok(())
+}
+
+fn empty_sector_update<Tree: 'static + MerkleTreeTrait>(
+    sector_size: u64,
+    porep_id: &[u8; 32],
+    api_version: ApiVersion,
+) -> Result<()> {
+    fil_logger::maybe_init();
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = porep_config(sector_size, *porep_id, api_version);
+    let number_of_bytes_in_piece = config.unpadded_bytes_amount();
+    let prover_id = rng.gen();
+    let sector_id = rng.gen::<u64>().into();
+    let ticket = rng.gen();
+
+    // Seal a committed capacity sector ahead of time.
+    let mut staged_cc_file = NamedTempFile::new()?;
+    let (cc_piece_info, _) = add_piece(
+        EmptySource::new(number_of_bytes_in_piece.into()),
+        &mut staged_cc_file,
+        number_of_bytes_in_piece,
+        &[],
+    )?;
+    let sector_key_file = NamedTempFile::new()?;
+    let sector_key_cache_dir = tempdir()?;
+    let phase1_output = seal_pre_commit_phase1::<_, _, _, Tree>(
+        &config,
+        sector_key_cache_dir.path(),
+        staged_cc_file.path(),
+        sector_key_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        &[cc_piece_info],
+    )?;
+    let pre_commit_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output,
+        sector_key_cache_dir.path(),
+        sector_key_file.path(),
+    )?;
+
+    // Encode new data into it.
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let piece_info = generate_piece_commitment(piece_file.as_file_mut(), number_of_bytes_in_piece)?;
+    piece_file.as_file_mut().rewind()?;
+    let mut staged_sector_file = NamedTempFile::new()?;
+    add_piece(
+        &mut piece_file,
+        &mut staged_sector_file,
+        number_of_bytes_in_piece,
+        &[],
+    )?;
+
+    let replica_file = NamedTempFile::new()?;
+    let replica_cache_dir = tempdir()?;
+    let encoded = encode_into::<Tree>(
+        &config,
+        replica_file.path(),
+        replica_cache_dir.path(),
+        sector_key_file.path(),
+        sector_key_cache_dir.path(),
+        staged_sector_file.path(),
+        &[piece_info],
+    )?;
+    assert_ne!(encoded.comm_r_new, pre_commit_output.comm_r);
+
+    let proof = generate_empty_sector_update_proof::<Tree, _>(
+        &config,
+        pre_commit_output.comm_r,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+        sector_key_file.path(),
+        sector_key_cache_dir.path(),
+        replica_file.path(),
+        replica_cache_dir.path(),
+        Some(&mut rng),
+    )?;
+    assert!(verify_empty_sector_update_proof::<Tree, _>(
+        &config,
+        &proof.proof,
+        pre_commit_output.comm_r,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+        Some(&mut rng),
+    )?);
+    assert!(!verify_empty_sector_update_proof::<Tree, _>(
+        &config,
+        &proof.proof,
+        pre_commit_output.comm_r,
+        encoded.comm_r_new,
+        pre_commit_output.comm_d,
+        Some(&mut rng),
+    )?);
+
+    // The data is recovered from the replica and the sector key.
+    let decoded_file = NamedTempFile::new()?;
+    decode_from::<Tree>(
+        &config,
+        decoded_file.path(),
+        replica_file.path(),
+        sector_key_file.path(),
+        sector_key_cache_dir.path(),
+        encoded.comm_d_new,
+    )?;
+    assert_eq!(
+        std::fs::read(decoded_file.path())?,
+        std::fs::read(staged_sector_file.path())?
+    );
+
+    Ok(())
+}
+
 // Use a fixed PoRep ID, so that the parents cache can be re-used between some tests.
 // Note however, that parents caches cannot be shared when testing the differences
 // between API v1 and v2 behaviour (since the parent caches will be different for the
@@ -322,11 +552,218 @@ fn seal_lifecycle<Tree: 'static + MerkleTreeTrait>(
 const ARBITRARY_POREP_ID_V1_0_0: [u8; 32] = [127; 32];
 const ARBITRARY_POREP_ID_V1_1_0: [u8; 32] = [128; 32];
 
+#[test]
+fn test_piece_inclusion_proof() -> Result<()> {
+    let sector_size = sector_size("sector_size2_kib");
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+    // The second piece gets aligned after some padding, the last one fills the sector.
+    let mut staged_sector = Vec::new();
+    let mut piece_infos = Vec::new();
+    for piece_size in [127, 508, 1016] {
+        let piece_bytes: Vec<u8> = (0..piece_size).map(|_| rng.gen()).collect();
+        let piece_lengths: Vec<_> = piece_infos.iter().map(|p: &PieceInfo| p.size).collect();
+        let (piece_info, _) = add_piece(
+            &piece_bytes[..],
+            &mut staged_sector,
+            UnpaddedBytesAmount(piece_size),
+            &piece_lengths,
+        )?;
+        piece_infos.push(piece_info);
+    }
+    assert_eq!(staged_sector.len() as u64, sector_size);
+    let tree_d = BinaryMerkleTree::<DefaultPieceHasher>::try_from_iter(
+        staged_sector
+            .chunks(32)
+            .map(DefaultPieceDomain::try_from_bytes),
+    )?;
+    let mut comm_d = Commitment::default();
+    tree_d.root().write_bytes(&mut comm_d)?;
+    assert_eq!(comm_d, compute_comm_d(sector_size.into(), &piece_infos)?);
+
+    let specs = piece_specs(&piece_infos);
+    assert_eq!((specs[1].position, specs[1].number_of_leaves), (16, 16));
+    assert_eq!((specs[2].position, specs[2].number_of_leaves), (32, 32));
+
+    for spec in &specs {
+        let proof = generate_piece_inclusion_proof(&tree_d, spec)?;
+        assert!(verify_piece_inclusion_proof(&comm_d, &spec.comm_p, spec, &proof)?);
+
+        let mut bad_proof = proof.clone();
+        bad_proof.siblings[0][0] ^= 1;
+        assert!(!verify_piece_inclusion_proof(&comm_d, &spec.comm_p, spec, &bad_proof)?);
+    }
+
+    // A piece can not be proven at another position.
+    let mut moved = specs[0].clone();
+    moved.position = 4;
+    assert!(generate_piece_inclusion_proof(&tree_d, &moved).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_encrypted_piece() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let key: [u8; 32] = rng.gen();
+    let piece_size = UnpaddedBytesAmount::from(PaddedBytesAmount(sector_size("sector_size2_kib")));
+
+    let plaintext: Vec<u8> = (0..1000).map(|_| rng.gen()).collect();
+    let mut staged_sector = Vec::new();
+    let (piece_info, _, envelope) = add_encrypted_piece(
+        &key,
+        &plaintext[..],
+        &mut staged_sector,
+        plaintext.len() as u64,
+        piece_size,
+        &[],
+        &mut rng,
+    )?;
+
+    // The piece commits to the ciphertext, and the plaintext appears nowhere.
+    let piece_bytes = fr32::unpad_parallel(&staged_sector, 0, usize::from(piece_size))?;
+    assert_eq!(
+        generate_piece_commitment(&piece_bytes[..], piece_size)?,
+        piece_info
+    );
+    assert!(!piece_bytes
+        .windows(32)
+        .any(|window| window == &plaintext[..32]));
+
+    let mut decrypted = Vec::new();
+    decrypt_piece(&key, &envelope, &piece_bytes[..], &mut decrypted)?;
+    assert_eq!(decrypted, plaintext);
+
+    let other_key: [u8; 32] = rng.gen();
+    assert!(decrypt_piece(&other_key, &envelope, &piece_bytes[..], Vec::new()).is_err());
+
+    // A plaintext whose ciphertext does not fit the piece is rejected.
+    let plaintext = vec![0u8; usize::from(piece_size)];
+    assert!(add_encrypted_piece(
+        &key,
+        &plaintext[..],
+        Vec::new(),
+        plaintext.len() as u64,
+        piece_size,
+        &[],
+        &mut rng,
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_erasure_coding() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let file: Vec<u8> = (0..3000).map(|_| rng.gen()).collect();
+
+    let (layout, shards) = erasure_encode(&file[..], file.len() as u64, 4, 2)?;
+    assert_eq!(layout.piece_size, UnpaddedBytesAmount(1016));
+    assert_eq!(layout.pieces.len(), 6);
+
+    // Every shard is a piece on its own.
+    for (shard, piece_info) in shards.iter().zip(&layout.pieces) {
+        let mut staged_sector = Vec::new();
+        let (added, _) = add_piece(&shard[..], &mut staged_sector, layout.piece_size, &[])?;
+        assert_eq!(&added, piece_info);
+    }
+
+    let placements: Vec<_> = (0..6)
+        .map(|i| ShardPlacement {
+            prover_id: [i % 3; 32],
+            sector_id: u64::from(i),
+        })
+        .collect();
+    assert!(FilePlacement::new(layout.clone(), placements[..5].to_vec()).is_err());
+    let mut same_sector = placements.clone();
+    same_sector[5] = same_sector[0].clone();
+    assert!(FilePlacement::new(layout.clone(), same_sector).is_err());
+    FilePlacement::new(layout.clone(), placements)?;
+
+    // Any 4 shards recover the file.
+    let mut available: Vec<_> = shards.iter().cloned().map(Some).collect();
+    available[0] = None;
+    available[3] = None;
+    assert_eq!(erasure_decode(&layout, available.clone())?, file);
+
+    available[5] = None;
+    assert!(erasure_decode(&layout, available).is_err());
+
+    let mut corrupted: Vec<_> = shards.into_iter().map(Some).collect();
+    corrupted[1].as_mut().expect("shard failure")[0] ^= 1;
+    assert!(erasure_decode(&layout, corrupted).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_commitment_cid() -> Result<()> {
+    // The piece CID of 2KiB of zeros.
+    let zeros = [0u8; 2032];
+    let piece_info = generate_piece_commitment(&zeros[..], UnpaddedBytesAmount(2032))?;
+    let piece_cid = "baga6ea4seaqpy7usqklokfx2vxuynmupslkeutzexe2uqurdg5vhtebhxqmpqmy";
+    assert_eq!(piece_info.cid(), piece_cid);
+    assert_eq!(
+        cid_to_commitment(piece_cid)?,
+        (CommitmentKind::Unsealed, piece_info.commitment)
+    );
+
+    let comm_r: Commitment = [0; 32];
+    let sealed_cid = "bagboea4b5abcaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    assert_eq!(
+        commitment_to_cid(CommitmentKind::Sealed, &comm_r),
+        sealed_cid
+    );
+    assert_eq!(
+        cid_to_commitment_of(CommitmentKind::Sealed, sealed_cid)?,
+        comm_r
+    );
+    assert!(cid_to_commitment_of(CommitmentKind::Unsealed, sealed_cid).is_err());
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    for kind in [CommitmentKind::Unsealed, CommitmentKind::Sealed] {
+        let commitment: Commitment = rng.gen();
+        let cid = commitment_to_cid(kind, &commitment);
+        assert_eq!(cid_to_commitment(&cid)?, (kind, commitment));
+    }
+
+    // A CIDv0, a truncated CID and a sealed codec with an unsealed multihash.
+    assert!(cid_to_commitment("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG").is_err());
+    assert!(cid_to_commitment(&piece_cid[..piece_cid.len() - 2]).is_err());
+    assert!(
+        cid_to_commitment("bagboea4seaqpy7usqklokfx2vxuynmupslkeutzexe2uqurdg5vhtebhxqmpqmy")
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_drg_lifecycle_2kib() -> Result<()> {
+    drg_lifecycle(
+        sector_size("sector_size2_kib"),
+        &ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_1_0,
+    )
+}
+
+#[test]
+#[ignore]
+fn test_empty_sector_update_2kib() -> Result<()> {
+    empty_sector_update::<SectorShape2KiB>(
+        sector_size("sector_size2_kib"),
+        &ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_1_0,
+    )
+}
+
 #[test]
 #[ignore]
 fn test_seal_lifecycle_2kib_sub_8_2_v1() -> Result<()> {
     seal_lifecycle::<SectorShape2KiB>(
-        SECTOR_SIZE_2_KIB,
+        sector_size("sector_size2_kib"),
         &ARBITRARY_POREP_ID_V1_0_0,
         ApiVersion::V1_0_0,
     )
@@ -336,7 +773,7 @@ fn test_seal_lifecycle_2kib_sub_8_2_v1() -> Result<()> {
 #[ignore]
 fn test_seal_lifecycle_2kib_sub_8_2_v1_1() -> Result<()> {
     seal_lifecycle::<SectorShape2KiB>(
-        SECTOR_SIZE_2_KIB,
+        sector_size("sector_size2_kib"),
         &ARBITRARY_POREP_ID_V1_1_0,
         ApiVersion::V1_1_0,
     )
@@ -346,7 +783,7 @@ fn test_seal_lifecycle_2kib_sub_8_2_v1_1() -> Result<()> {
 #[ignore]
 fn test_seal_lifecycle_4kib_sub_8_2_v1() -> Result<()> {
     seal_lifecycle::<SectorShape4KiB>(
-        SECTOR_SIZE_4_KIB,
+        sector_size("sector_size4_kib"),
         &ARBITRARY_POREP_ID_V1_0_0,
         ApiVersion::V1_0_0,
     )
@@ -356,7 +793,7 @@ fn test_seal_lifecycle_4kib_sub_8_2_v1() -> Result<()> {
 #[ignore]
 fn test_seal_lifecycle_4kib_sub_8_2_v1_1() -> Result<()> {
     seal_lifecycle::<SectorShape4KiB>(
-        SECTOR_SIZE_4_KIB,
+        sector_size("sector_size4_kib"),
         &ARBITRARY_POREP_ID_V1_1_0,
         ApiVersion::V1_1_0,
     )
@@ -366,7 +803,7 @@ fn test_seal_lifecycle_4kib_sub_8_2_v1_1() -> Result<()> {
 #[ignore]
 fn test_seal_lifecycle_16kib_sub_8_2_v1() -> Result<()> {
     seal_lifecycle::<SectorShape16KiB>(
-        SECTOR_SIZE_16_KIB,
+        sector_size("sector_size16_kib"),
         &ARBITRARY_POREP_ID_V1_0_0,
         ApiVersion::V1_0_0,
     )
@@ -376,7 +813,7 @@ fn test_seal_lifecycle_16kib_sub_8_2_v1() -> Result<()> {
 #[ignore]
 fn test_seal_lifecycle_16kib_sub_8_2_v1_1() -> Result<()> {
     seal_lifecycle::<SectorShape16KiB>(
-        SECTOR_SIZE_16_KIB,
+        sector_size("sector_size16_kib"),
         &ARBITRARY_POREP_ID_V1_1_0,
         ApiVersion::V1_1_0,
     )
@@ -386,7 +823,7 @@ fn test_seal_lifecycle_16kib_sub_8_2_v1_1() -> Result<()> {
 #[ignore]
 fn test_seal_lifecycle_32kib_top_8_8_2_v1() -> Result<()> {
     seal_lifecycle::<SectorShape32KiB>(
-        SECTOR_SIZE_32_KIB,
+        sector_size("sector_size32_kib"),
         &ARBITRARY_POREP_ID_V1_0_0,
         ApiVersion::V1_0_0,
     )
@@ -396,7 +833,7 @@ fn test_seal_lifecycle_32kib_top_8_8_2_v1() -> Result<()> {
 #[ignore]
 fn test_seal_lifecycle_32kib_top_8_8_2_v1_1() -> Result<()> {
     seal_lifecycle::<SectorShape32KiB>(
-        SECTOR_SIZE_32_KIB,
+        sector_size("sector_size32_kib"),
         &ARBITRARY_POREP_ID_V1_1_0,
         ApiVersion::V1_1_0,
     )
@@ -413,7 +850,7 @@ fn test_seal_lifecycle_512mib_porep_id_v1_top_8_0_0_api_v1() -> Result<()> {
     let mut porep_id = [0u8; 32];
     porep_id[..8].copy_from_slice(&porep_id_v1.to_le_bytes());
     assert!(is_legacy_porep_id(porep_id));
-    seal_lifecycle::<SectorShape512MiB>(SECTOR_SIZE_512_MIB, &porep_id, ApiVersion::V1_0_0)
+    seal_lifecycle::<SectorShape512MiB>(sector_size("sector_size512_mib"), &porep_id, ApiVersion::V1_0_0)
 }
 
 #[ignore]
@@ -424,7 +861,7 @@ fn test_seal_lifecycle_512mib_porep_id_v1_top_8_0_0_api_v1_1() -> Result<()> {
     let mut porep_id = [0u8; 32];
     porep_id[..8].copy_from_slice(&porep_id_v1_1.to_le_bytes());
     assert!(!is_legacy_porep_id(porep_id));
-    seal_lifecycle::<SectorShape512MiB>(SECTOR_SIZE_512_MIB, &porep_id, ApiVersion::V1_1_0)
+    seal_lifecycle::<SectorShape512MiB>(sector_size("sector_size512_mib"), &porep_id, ApiVersion::V1_1_0)
 }
 
 #[ignore]
@@ -435,7 +872,7 @@ fn test_seal_lifecycle_32gib_porep_id_v1_top_8_8_0_api_v1() -> Result<()> {
     let mut porep_id = [0u8; 32];
     porep_id[..8].copy_from_slice(&porep_id_v1.to_le_bytes());
     assert!(is_legacy_porep_id(porep_id));
-    seal_lifecycle::<SectorShape32GiB>(SECTOR_SIZE_32_GIB, &porep_id, ApiVersion::V1_0_0)
+    seal_lifecycle::<SectorShape32GiB>(sector_size("sector_size32_gib"), &porep_id, ApiVersion::V1_0_0)
 }
 
 #[ignore]
@@ -446,7 +883,7 @@ fn test_seal_lifecycle_32gib_porep_id_v1_1_top_8_8_0_api_v1_1() -> Result<()> {
     let mut porep_id = [0u8; 32];
     porep_id[..8].copy_from_slice(&porep_id_v1_1.to_le_bytes());
     assert!(!is_legacy_porep_id(porep_id));
-    seal_lifecycle::<SectorShape32GiB>(SECTOR_SIZE_32_GIB, &porep_id, ApiVersion::V1_1_0)
+    seal_lifecycle::<SectorShape32GiB>(sector_size("sector_size32_gib"), &porep_id, ApiVersion::V1_1_0)
 }
 
 #[ignore]
@@ -457,7 +894,7 @@ fn test_seal_lifecycle_64gib_porep_id_v1_top_8_8_2_api_v1() -> Result<()> {
     let mut porep_id = [0u8; 32];
     porep_id[..8].copy_from_slice(&porep_id_v1.to_le_bytes());
     assert!(is_legacy_porep_id(porep_id));
-    seal_lifecycle::<SectorShape64GiB>(SECTOR_SIZE_64_GIB, &porep_id, ApiVersion::V1_0_0)
+    seal_lifecycle::<SectorShape64GiB>(sector_size("sector_size64_gib"), &porep_id, ApiVersion::V1_0_0)
 }
 
 #[ignore]
@@ -468,5 +905,287 @@ fn test_seal_lifecycle_64gib_porep_id_v1_1_top_8_8_2_api_v1_1() -> Result<()> {
     let mut porep_id = [0u8; 32];
     porep_id[..8].copy_from_slice(&porep_id_v1_1.to_le_bytes());
     assert!(!is_legacy_porep_id(porep_id));
-    seal_lifecycle::<SectorShape64GiB>(SECTOR_SIZE_64_GIB, &porep_id, ApiVersion::V1_1_0)
+    seal_lifecycle::<SectorShape64GiB>(sector_size("sector_size64_gib"), &porep_id, ApiVersion::V1_1_0)
+}
+
+/// A CARv1 archive of `blocks`, given with their binary CIDs.
+fn car_archive(version: u8, roots: &[&[u8]], blocks: &[(Vec<u8>, &[u8])]) -> Vec<u8> {
+    fn write_section(car: &mut Vec<u8>, section: &[u8]) {
+        assert!(section.len() < 1 << 14);
+        if section.len() < 0x80 {
+            car.push(section.len() as u8);
+        } else {
+            car.extend([section.len() as u8 | 0x80, (section.len() >> 7) as u8]);
+        }
+        car.extend_from_slice(section);
+    }
+
+    let mut header = vec![0xa2, 0x65];
+    header.extend_from_slice(b"roots");
+    header.push(0x80 | roots.len() as u8);
+    for root in roots {
+        header.extend([0xd8, 42, 0x58, root.len() as u8 + 1, 0]);
+        header.extend_from_slice(root);
+    }
+    header.push(0x67);
+    header.extend_from_slice(b"version");
+    header.push(version);
+
+    let mut car = vec![];
+    write_section(&mut car, &header);
+    for (cid, data) in blocks {
+        write_section(&mut car, &[cid.as_slice(), data].concat());
+    }
+    car
+}
+
+#[test]
+fn test_car_ingestion() -> Result<()> {
+    use sha2::{Digest, Sha256};
+
+    let raw_cid = |data: &[u8]| [&[1, 0x55, 0x12, 32][..], &Sha256::digest(data)].concat();
+    let dag_pb_cid_v0 = |data: &[u8]| [&[0x12, 32][..], &Sha256::digest(data)].concat();
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let large: Vec<u8> = (0..300).map(|_| rng.gen()).collect();
+    let blocks: Vec<(Vec<u8>, &[u8])> = vec![
+        (raw_cid(b"hello world"), b"hello world"),
+        (dag_pb_cid_v0(&large), &large),
+        // An identity CID inlines its data.
+        ([&[1, 0x55, 0, 3][..], b"abc"].concat(), b"abc"),
+    ];
+    let car = car_archive(1, &[&blocks[1].0], &blocks);
+
+    let index = index_car(&car[..])?;
+    assert_eq!(index.car_len, car.len() as u64);
+    assert_eq!(index.blocks.len(), 3);
+    assert_eq!(
+        index.blocks[0].cid,
+        "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"
+    );
+    // The CIDv0 root is named by its CIDv1.
+    assert_eq!(index.roots, vec![index.blocks[1].cid.clone()]);
+    assert!(index.roots[0].starts_with("bafybei"));
+    for (block, (_, data)) in index.blocks.iter().zip(&blocks) {
+        let range = block.offset as usize..(block.offset + block.len) as usize;
+        assert_eq!(&car[range], *data);
+        verify_car_block(&block.cid, data)?;
+        assert_eq!(index.block(&block.cid), Some(block));
+    }
+    assert!(verify_car_block(&index.blocks[0].cid, b"hello world!").is_err());
+
+    // The archive is streamed into the piece, followed by zeros.
+    let mut car_file = NamedTempFile::new()?;
+    car_file.write_all(&car)?;
+    let (mut piece_file, piece_index) =
+        generate_car_piece_file(car_file.path(), sector_size("sector_size2_kib"))?;
+    assert_eq!(piece_index, index);
+    let mut piece = Vec::new();
+    piece_file.read_to_end(&mut piece)?;
+    assert_eq!(piece.len(), 2032);
+    assert_eq!(&piece[..car.len()], &car[..]);
+    assert!(piece[car.len()..].iter().all(|byte| *byte == 0));
+    let huge: Vec<u8> = (0..2100).map(|_| rng.gen()).collect();
+    let huge_cid = raw_cid(&huge);
+    let mut car_file = NamedTempFile::new()?;
+    car_file.write_all(&car_archive(1, &[&huge_cid], &[(huge_cid.clone(), &huge)]))?;
+    let err = generate_car_piece_file(car_file.path(), sector_size("sector_size2_kib"))
+        .expect_err("CAR larger than the piece");
+    assert!(err.to_string().contains("does not fit"));
+
+    // A corrupted block, an unsupported version and a truncated archive are rejected.
+    let mut corrupted = car.clone();
+    let offset = index.blocks[1].offset as usize;
+    corrupted[offset] ^= 1;
+    assert!(index_car(&corrupted[..]).is_err());
+    assert!(index_car(&car_archive(2, &[&blocks[1].0], &blocks)[..]).is_err());
+    assert!(index_car(&car[..car.len() - 1]).is_err());
+
+    Ok(())
+}
+
+fn sector_archive<Tree: 'static + MerkleTreeTrait>(
+    sector_size: u64,
+    porep_id: &[u8; 32],
+    api_version: ApiVersion,
+) -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = porep_config(sector_size, *porep_id, api_version);
+    let prover_id = rng.gen();
+    let (sector_id, replica, comm_r, cache_dir) =
+        create_seal::<_, Tree>(&mut rng, sector_size, prover_id, true, porep_id, api_version)?;
+
+    let metadata = serde_json::json!({ "ticket": "moved" });
+    let mut archive = vec![];
+    let manifest = export_sector::<Tree, _>(
+        &config,
+        cache_dir.path(),
+        replica.path(),
+        sector_id,
+        metadata.clone(),
+        &mut archive,
+    )?;
+    assert_eq!(manifest.comm_r, comm_r);
+    assert_eq!(manifest.metadata, metadata);
+    assert_eq!(read_sector_archive_manifest(&mut &archive[..])?, manifest);
+
+    // A corrupted file, or another comm_r, is rejected without leaving anything behind.
+    let target = tempdir()?;
+    let replica_path = target.path().join("sealed");
+    let mut corrupted = archive.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 1;
+    for (archive, comm_r) in [(&corrupted, comm_r), (&archive, [1; 32])] {
+        assert!(import_sector::<Tree, _>(
+            &config,
+            &archive[..],
+            target.path(),
+            &replica_path,
+            comm_r,
+        )
+        .is_err());
+        assert_eq!(std::fs::read_dir(target.path())?.count(), 0);
+    }
+
+    // Neither is a replica tampered with, listed with its own digest, the stored rows of
+    // tree_r_last being consistent with any replica.
+    let manifest_len = u64::from_le_bytes(archive[8..16].try_into()?) as usize;
+    let mut files = archive[16 + manifest_len..].to_vec();
+    let mut tampered_manifest = manifest.clone();
+    let replica_file = tampered_manifest
+        .files
+        .iter()
+        .position(|file| file.name == SECTOR_ARCHIVE_REPLICA)
+        .expect("archive has a replica");
+    let start = tampered_manifest.files[..replica_file]
+        .iter()
+        .map(|file| file.len as usize)
+        .sum::<usize>();
+    let end = start + tampered_manifest.files[replica_file].len as usize;
+    files[start] ^= 1;
+    tampered_manifest.files[replica_file].sha256 =
+        hex::encode(<sha2::Sha256 as sha2::Digest>::digest(&files[start..end]));
+    let manifest_bytes = serde_json::to_vec(&tampered_manifest)?;
+    let mut tampered = SECTOR_ARCHIVE_MAGIC.to_vec();
+    tampered.extend_from_slice(&(manifest_bytes.len() as u64).to_le_bytes());
+    tampered.extend_from_slice(&manifest_bytes);
+    tampered.extend_from_slice(&files);
+    assert!(import_sector::<Tree, _>(
+        &config,
+        &tampered[..],
+        target.path(),
+        &replica_path,
+        comm_r,
+    )
+    .is_err());
+    assert_eq!(std::fs::read_dir(target.path())?.count(), 0);
+
+    let imported = import_sector::<Tree, _>(
+        &config,
+        &archive[..],
+        target.path(),
+        &replica_path,
+        comm_r,
+    )?;
+    assert_eq!(imported, manifest);
+    assert_eq!(std::fs::read(&replica_path)?, std::fs::read(replica.path())?);
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_sector_archive_2kib() -> Result<()> {
+    sector_archive::<SectorShape2KiB>(
+        sector_size("sector_size2_kib"),
+        &ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_1_0,
+    )
+}
+
+fn finalize<Tree: 'static + MerkleTreeTrait>(
+    sector_size: u64,
+    porep_id: &[u8; 32],
+    api_version: ApiVersion,
+) -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = porep_config(sector_size, *porep_id, api_version);
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let replica = NamedTempFile::new()?;
+    let cache_dir = tempdir()?;
+
+    let (_, phase1_output) = run_seal_pre_commit_phase1::<Tree>(
+        &config,
+        rng.gen(),
+        rng.gen::<u64>().into(),
+        rng.gen(),
+        &cache_dir,
+        &mut piece_file,
+        &replica,
+    )?;
+    let pre_commit_output =
+        seal_pre_commit_phase2(&config, phase1_output, cache_dir.path(), replica.path())?;
+
+    let cache_files = || -> Result<Vec<String>> {
+        let mut names = std::fs::read_dir(cache_dir.path())?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>>>()?;
+        names.sort();
+        Ok(names)
+    };
+    finalize_sector::<Tree>(&config, cache_dir.path(), replica.path())?;
+    let finalized = cache_files()?;
+    assert_eq!(finalized.len(), 1 + get_base_tree_count::<Tree>());
+    assert!(finalized.iter().all(|name| name == "p_aux" || name.contains("tree-r-last")));
+
+    // A finalized sector can still be exported.
+    let mut archive = vec![];
+    let manifest = export_sector::<Tree, _>(
+        &config,
+        cache_dir.path(),
+        replica.path(),
+        0.into(),
+        serde_json::Value::Null,
+        &mut archive,
+    )?;
+    assert_eq!(manifest.comm_r, pre_commit_output.comm_r);
+    assert_eq!(manifest.files.len(), finalized.len() + 1);
+
+    // The stored rows of tree_r_last are rebuilt from the replica, but not from another one.
+    let tree_r_last = finalized
+        .iter()
+        .filter(|name| name.contains("tree-r-last"))
+        .map(|name| cache_dir.path().join(name));
+    let stored = tree_r_last
+        .clone()
+        .map(std::fs::read)
+        .collect::<std::io::Result<Vec<_>>>()?;
+    tree_r_last.clone().try_for_each(std::fs::remove_file)?;
+    regenerate_tree_r_last::<Tree>(&config, cache_dir.path(), replica.path())?;
+    assert_eq!(cache_files()?, finalized);
+    assert_eq!(
+        tree_r_last
+            .clone()
+            .map(std::fs::read)
+            .collect::<std::io::Result<Vec<_>>>()?,
+        stored
+    );
+
+    let mut replica_bytes = std::fs::read(replica.path())?;
+    replica_bytes[0] ^= 1;
+    std::fs::write(replica.path(), &replica_bytes)?;
+    assert!(regenerate_tree_r_last::<Tree>(&config, cache_dir.path(), replica.path()).is_err());
+    assert_eq!(cache_files()?, finalized);
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_finalize_2kib() -> Result<()> {
+    finalize::<SectorShape2KiB>(
+        sector_size("sector_size2_kib"),
+        &ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_1_0,
+    )
 }
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// A block of a CAR piece.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CarBlock {
+    /// The CIDv1 of the block, base32 encoded. CIDv0 blocks are given their CIDv1.
+    pub cid: String,
+    /// Offset of the data of the block from the start of the piece, in unpadded bytes.
+    pub offset: u64,
+    /// Number of bytes of the data of the block.
+    pub len: u64,
+}
+
+/// The roots and the blocks of a CARv1 archive added as a piece.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CarIndex {
+    /// The root CIDs of the archive, base32 encoded.
+    pub roots: Vec<String>,
+    pub blocks: Vec<CarBlock>,
+    /// Number of bytes of the archive.
+    pub car_len: u64,
+}
+
+impl CarIndex {
+    /// The block with the given CIDv1.
+    pub fn block(&self, cid: &str) -> Option<&CarBlock> {
+        self.blocks.iter().find(|block| block.cid == cid)
+    }
+}
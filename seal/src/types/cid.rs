@@ -0,0 +1,173 @@
+//! CIDv1 of commitments, as used by the Filecoin tooling: `comm_p` and `comm_d` with the
+//! `fil-commitment-unsealed` codec and a `sha2-256-trunc254-padded` multihash, `comm_r` with the
+//! `fil-commitment-sealed` codec and a `poseidon-bls12_381-a2-fc1` multihash. They are encoded
+//! with the lowercase base32 multibase.
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::types::{Commitment, PieceInfo, SealPreCommitOutput};
+
+pub const FIL_COMMITMENT_UNSEALED: u64 = 0xf101;
+pub const FIL_COMMITMENT_SEALED: u64 = 0xf102;
+pub const SHA2_256_TRUNC254_PADDED: u64 = 0x1012;
+pub const POSEIDON_BLS12_381_A2_FC1: u64 = 0xb401;
+
+const CID_VERSION: u64 = 1;
+const BASE32_PREFIX: char = 'b';
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// What a commitment commits to, which selects the codec and the multihash of its CID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitmentKind {
+    /// `comm_p` or `comm_d`.
+    Unsealed,
+    /// `comm_r`.
+    Sealed,
+}
+
+impl CommitmentKind {
+    pub fn codec(self) -> u64 {
+        match self {
+            CommitmentKind::Unsealed => FIL_COMMITMENT_UNSEALED,
+            CommitmentKind::Sealed => FIL_COMMITMENT_SEALED,
+        }
+    }
+
+    pub fn multihash(self) -> u64 {
+        match self {
+            CommitmentKind::Unsealed => SHA2_256_TRUNC254_PADDED,
+            CommitmentKind::Sealed => POSEIDON_BLS12_381_A2_FC1,
+        }
+    }
+}
+
+/// Encodes `commitment` as a base32 CID.
+pub fn commitment_to_cid(kind: CommitmentKind, commitment: &Commitment) -> String {
+    let mut bytes = vec![];
+    write_varint(&mut bytes, CID_VERSION);
+    write_varint(&mut bytes, kind.codec());
+    write_varint(&mut bytes, kind.multihash());
+    write_varint(&mut bytes, commitment.len() as u64);
+    bytes.extend_from_slice(commitment);
+
+    let mut cid = String::from(BASE32_PREFIX);
+    cid.push_str(&base32_encode(&bytes));
+    cid
+}
+
+/// Decodes a base32 CID of a commitment, checking that its multihash matches its codec.
+pub fn cid_to_commitment(cid: &str) -> Result<(CommitmentKind, Commitment)> {
+    let encoded = cid
+        .strip_prefix(BASE32_PREFIX)
+        .with_context(|| format!("CID is not base32 encoded: {cid}"))?;
+    let bytes = base32_decode(encoded).with_context(|| format!("invalid CID: {cid}"))?;
+    let mut bytes = bytes.as_slice();
+
+    ensure!(
+        read_varint(&mut bytes)? == CID_VERSION,
+        "not a CIDv1: {cid}"
+    );
+    let kind = match read_varint(&mut bytes)? {
+        FIL_COMMITMENT_UNSEALED => CommitmentKind::Unsealed,
+        FIL_COMMITMENT_SEALED => CommitmentKind::Sealed,
+        codec => bail!("unexpected codec {codec:#x} of CID {cid}"),
+    };
+    let multihash = read_varint(&mut bytes)?;
+    ensure!(
+        multihash == kind.multihash(),
+        "unexpected multihash {multihash:#x} for codec {:#x}",
+        kind.codec(),
+    );
+    ensure!(
+        read_varint(&mut bytes)? == 32 && bytes.len() == 32,
+        "commitment of CID {cid} is not 32 bytes"
+    );
+
+    let mut commitment = [0; 32];
+    commitment.copy_from_slice(bytes);
+    Ok((kind, commitment))
+}
+
+/// Decodes a base32 CID of a commitment of the given kind.
+pub fn cid_to_commitment_of(kind: CommitmentKind, cid: &str) -> Result<Commitment> {
+    let (actual, commitment) = cid_to_commitment(cid)?;
+    ensure!(actual == kind, "expected a {kind:?} CID, got {actual:?}");
+    Ok(commitment)
+}
+
+impl PieceInfo {
+    /// The piece CID of `comm_p`.
+    pub fn cid(&self) -> String {
+        commitment_to_cid(CommitmentKind::Unsealed, &self.commitment)
+    }
+}
+
+impl SealPreCommitOutput {
+    pub fn comm_r_cid(&self) -> String {
+        commitment_to_cid(CommitmentKind::Sealed, &self.comm_r)
+    }
+
+    pub fn comm_d_cid(&self) -> String {
+        commitment_to_cid(CommitmentKind::Unsealed, &self.comm_d)
+    }
+}
+
+pub(crate) fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+pub(crate) fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0;
+    for (i, byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Ok(value);
+        }
+    }
+    bail!("truncated varint")
+}
+
+/// RFC 4648 base32, lowercase and without padding.
+pub(crate) fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for byte in bytes {
+        buffer = (buffer << 8) | u16::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    encoded
+}
+
+pub(crate) fn base32_decode(encoded: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .with_context(|| format!("invalid base32 character {:?}", c as char))?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    ensure!(
+        bits < 5 && buffer & ((1 << bits) - 1) == 0,
+        "invalid base32 padding"
+    );
+    Ok(bytes)
+}
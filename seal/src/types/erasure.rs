@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{PieceInfo, ProverId, UnpaddedBytesAmount};
+
+/// How a file is split in shards by `erasure_encode`, each shard being a piece of `piece_size`
+/// bytes. Any `data_shards` of the shards are enough to recover the file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErasureLayout {
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    /// Number of bytes of the file, the last data shard being padded with zeros.
+    pub file_size: u64,
+    pub piece_size: UnpaddedBytesAmount,
+    /// The pieces of the data shards, then of the parity shards.
+    pub pieces: Vec<PieceInfo>,
+}
+
+impl ErasureLayout {
+    pub fn total_shards(&self) -> usize {
+        self.data_shards + self.parity_shards
+    }
+}
+
+/// The sector a shard was added to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ShardPlacement {
+    pub prover_id: ProverId,
+    pub sector_id: u64,
+}
+
+/// Where the shards of a file are stored, `placements[i]` holding the shard of `layout.pieces[i]`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilePlacement {
+    pub layout: ErasureLayout,
+    pub placements: Vec<ShardPlacement>,
+}
+
+impl FilePlacement {
+    /// Checks that every shard is placed, each in its own sector, as two shards in the same
+    /// sector would be lost together.
+    pub fn new(layout: ErasureLayout, placements: Vec<ShardPlacement>) -> Result<Self> {
+        ensure!(
+            placements.len() == layout.total_shards(),
+            "{} placements for {} shards",
+            placements.len(),
+            layout.total_shards(),
+        );
+        let sectors: HashSet<_> = placements.iter().collect();
+        ensure!(
+            sectors.len() == placements.len(),
+            "several shards are placed in the same sector"
+        );
+
+        Ok(FilePlacement { layout, placements })
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt::{self, Debug, Formatter};
+
+use proofs_core::crypto::aes::STREAM_NONCE_SIZE;
+use serde::{Deserialize, Serialize};
+
+/// What it takes, besides the key of the client, to decrypt a piece added with
+/// `add_encrypted_piece`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyEnvelope {
+    /// The data key of the piece, wrapped with the key of the client.
+    pub wrapped_key: Vec<u8>,
+    /// The nonce prefix of the encrypted stream.
+    pub nonce: [u8; STREAM_NONCE_SIZE],
+    /// Number of bytes of the piece before encryption.
+    pub plaintext_len: u64,
+}
+
+impl Debug for KeyEnvelope {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("KeyEnvelope")
+            .field("wrapped_key", &hex::encode(&self.wrapped_key))
+            .field("nonce", &hex::encode(self.nonce))
+            .field("plaintext_len", &self.plaintext_len)
+            .finish()
+    }
+}
@@ -1,11 +1,21 @@
 mod byte_amounts;
+mod car;
+mod cid;
+mod erasure;
+mod key_envelope;
 mod piece_info;
 mod porep_config;
+mod sector_archive;
 mod sector_size;
 
 pub use byte_amounts::*;
+pub use car::*;
+pub use cid::*;
+pub use erasure::*;
+pub use key_envelope::*;
 pub use piece_info::*;
 pub use porep_config::*;
+pub use sector_archive::*;
 pub use sector_size::*;
 
 pub use merkletree::store::StoreConfig;
@@ -47,11 +57,24 @@ pub struct SealCommitPhase1Output<Tree: MerkleTreeTrait> {
     pub ticket: Ticket,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SealCommitOutput {
     pub proof: Vec<u8>
 }
 
+/// The commitments of a sector after new data was encoded into it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmptySectorUpdateEncoded {
+    pub comm_r_new: Commitment,
+    pub comm_r_last_new: Commitment,
+    pub comm_d_new: Commitment,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmptySectorUpdateProof {
+    pub proof: Vec<u8>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SealPreCommitPhase1Output<Tree: MerkleTreeTrait> {
     #[serde(bound(
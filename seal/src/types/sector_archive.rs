@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::Commitment;
+
+/// A file packaged in a sector archive.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    /// The name of the file in the cache directory, or `SECTOR_ARCHIVE_REPLICA` for the replica.
+    pub name: String,
+    /// Number of bytes of the file.
+    pub len: u64,
+    /// The hex encoded sha2-256 digest of the file.
+    pub sha256: String,
+}
+
+/// Describes a sector archive, and is written at its start, before the files in the same order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectorArchiveManifest {
+    pub version: u32,
+    pub sector_id: u64,
+    pub sector_size: u64,
+    pub comm_r: Commitment,
+    pub files: Vec<ArchivedFile>,
+    /// Whatever the exporter needs to restore the sector, e.g. its pieces and its ticket.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+impl SectorArchiveManifest {
+    /// Number of bytes of the files of the archive.
+    pub fn files_len(&self) -> u64 {
+        self.files.iter().map(|file| file.len).sum()
+    }
+}
@@ -0,0 +1,406 @@
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use bincode::serialize;
+use blstrs::Scalar as Fr;
+use hashers::{HashFunction, Hasher};
+use log::{info, trace};
+use memmap2::{Mmap, MmapMut, MmapOptions};
+use merkletree::store::{DiskStore, Store, StoreConfig};
+use proofs_core::{
+    cache_key::CacheKey,
+    compound_proof::{self, CompoundProof},
+    merkle::{create_base_merkle_tree, get_base_tree_count, BinaryMerkleTree, MerkleTreeTrait},
+    multi_proof::MultiProof,
+    proof::NoRequirements,
+    util::default_rows_to_discard,
+};
+use proofs_porep::{
+    stacked::PersistentAux,
+    update::{self, EmptySectorUpdate, EmptySectorUpdateCompound},
+};
+use rand::RngCore;
+use typenum::Unsigned;
+
+use crate::{
+    caches::{get_empty_sector_update_params, get_empty_sector_update_verifying_key},
+    constants::{DefaultBinaryTree, DefaultPieceDomain, DefaultPieceHasher},
+    finalize::regenerate_tree_r_last,
+    parameters::update_setup_params,
+    pieces::verify_pieces,
+    sector_archive::{read_p_aux, read_t_aux},
+    types::{
+        Commitment, EmptySectorUpdateEncoded, EmptySectorUpdateProof, PieceInfo, PoRepConfig,
+        SectorSize, BINARY_ARITY,
+    },
+    util::{as_safe_commitment, commitment_from_fr, get_base_tree_leafs, get_base_tree_size},
+    verify_level_cache_store,
+};
+
+type TreeDomain<Tree> = <<Tree as MerkleTreeTrait>::Hasher as Hasher>::Domain;
+
+pub(crate) fn mmap_read(path: &Path) -> Result<Mmap> {
+    let file = File::open(path).with_context(|| format!("could not open {:?}", path))?;
+    unsafe { MmapOptions::new().map(&file) }.with_context(|| format!("could not mmap {:?}", path))
+}
+
+fn mmap_write(path: &Path, len: u64) -> Result<MmapMut> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("could not open {:?}", path))?;
+    file.set_len(len)?;
+    unsafe { MmapOptions::new().map_mut(&file) }
+        .with_context(|| format!("could not mmap {:?}", path))
+}
+
+/// The `tree_r_last` config the original sealing recorded in `t_aux`, or the default one once the
+/// sector was finalized and `t_aux` deleted.
+pub(crate) fn sector_tree_r_last_config<Tree: MerkleTreeTrait>(
+    cache_path: &Path,
+    sector_size: SectorSize,
+) -> Result<StoreConfig> {
+    if !cache_path.join(CacheKey::TAux.to_string()).exists() {
+        return tree_r_last_config::<Tree>(cache_path, sector_size);
+    }
+
+    Ok(read_t_aux::<Tree>(cache_path)?.tree_r_last_config)
+}
+
+pub(crate) fn tree_r_last_config<Tree: MerkleTreeTrait>(
+    cache_path: &Path,
+    sector_size: SectorSize,
+) -> Result<StoreConfig> {
+    let base_tree_size = get_base_tree_size::<Tree>(sector_size)?;
+    let base_tree_leafs = get_base_tree_leafs::<Tree>(base_tree_size)?;
+
+    let mut config = StoreConfig::new(
+        cache_path,
+        CacheKey::CommRLastTree.to_string(),
+        default_rows_to_discard(base_tree_leafs, Tree::Arity::to_usize()),
+    );
+    config.size = Some(base_tree_size);
+    Ok(config)
+}
+
+fn tree_d_config(cache_path: &Path, sector_size: SectorSize) -> Result<StoreConfig> {
+    let base_tree_size = get_base_tree_size::<DefaultBinaryTree>(sector_size)?;
+    let base_tree_leafs = get_base_tree_leafs::<DefaultBinaryTree>(base_tree_size)?;
+
+    let mut config = StoreConfig::new(
+        cache_path,
+        CacheKey::CommDTree.to_string(),
+        default_rows_to_discard(base_tree_leafs, BINARY_ARITY),
+    );
+    config.size = Some(base_tree_size);
+    Ok(config)
+}
+
+fn update_public_inputs<Tree: MerkleTreeTrait>(
+    comm_r_old: Commitment,
+    comm_d_new: Commitment,
+    comm_r_new: Commitment,
+) -> Result<update::PublicInputs<TreeDomain<Tree>, DefaultPieceDomain>> {
+    ensure!(
+        comm_r_old != [0; 32],
+        "Invalid all zero commitment (comm_r_old)"
+    );
+    ensure!(
+        comm_d_new != [0; 32],
+        "Invalid all zero commitment (comm_d_new)"
+    );
+    ensure!(
+        comm_r_new != [0; 32],
+        "Invalid all zero commitment (comm_r_new)"
+    );
+
+    Ok(update::PublicInputs {
+        comm_r_old: as_safe_commitment(&comm_r_old, "comm_r_old")?,
+        comm_d_new: as_safe_commitment(&comm_d_new, "comm_d_new")?,
+        comm_r_new: as_safe_commitment(&comm_r_new, "comm_r_new")?,
+    })
+}
+
+fn update_compound_public_params<'a, Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+) -> Result<compound_proof::PublicParams<'a, EmptySectorUpdate<'a, Tree, DefaultPieceHasher>>> {
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: update_setup_params(porep_config.padded_bytes_amount())?,
+        partitions: Some(1),
+        priority: false,
+    };
+
+    <EmptySectorUpdateCompound<Tree, DefaultPieceHasher> as CompoundProof<
+        EmptySectorUpdate<'a, Tree, DefaultPieceHasher>,
+        _,
+    >>::setup(&compound_setup_params)
+}
+
+/// Encodes the sector data at `staged_data_path` into the sealed sector (the "sector key")
+/// at `sector_key_path`, writing the new replica to `new_replica_path` and its `tree_r_last`
+/// and `p_aux` to `new_cache_path`.
+///
+/// The sector key is typically a committed capacity sector, sealed ahead of time with the zero
+/// pieces of `pieces::EmptySource`, and is left untouched so the data can be decoded again.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `new_replica_path` - path of the replica to create.
+/// * `new_cache_path` - directory to store the trees and `p_aux` of the new replica in.
+/// * `sector_key_path` - path of the replica of the original sealing.
+/// * `sector_key_cache_path` - cache directory of the original sealing.
+/// * `staged_data_path` - path of the fr32 padded sector data, as written by `add_piece`.
+/// * `piece_infos` - the pieces of the new data.
+pub fn encode_into<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    new_replica_path: &Path,
+    new_cache_path: &Path,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    staged_data_path: &Path,
+    piece_infos: &[PieceInfo],
+) -> Result<EmptySectorUpdateEncoded> {
+    info!("encode_into:start");
+
+    ensure!(
+        fs::metadata(new_cache_path)?.is_dir(),
+        "new_cache_path must be a directory"
+    );
+    let sector_bytes = u64::from(porep_config.padded_bytes_amount());
+    let nodes = sector_bytes as usize / 32;
+
+    let p_aux = read_p_aux::<Tree>(sector_key_cache_path)?;
+    let comm_r_old = <Tree::Hasher as Hasher>::Function::hash2(&p_aux.comm_c, &p_aux.comm_r_last);
+
+    let data = mmap_read(staged_data_path)?;
+    ensure!(
+        data.len() as u64 == sector_bytes,
+        "staged data must be exactly one sector"
+    );
+
+    trace!("building merkle tree for the new data");
+    let tree_d_config = tree_d_config(new_cache_path, porep_config.sector_size)?;
+    let tree_d = create_base_merkle_tree::<BinaryMerkleTree<DefaultPieceHasher>>(
+        Some(tree_d_config),
+        nodes,
+        &data,
+    )?;
+    let comm_d_new = tree_d.root();
+    drop(tree_d);
+
+    ensure!(
+        verify_pieces(
+            &commitment_from_fr(comm_d_new.into()),
+            piece_infos,
+            porep_config.sector_size
+        )?,
+        "pieces and comm_d do not match"
+    );
+
+    trace!("encoding the new data");
+    fs::copy(sector_key_path, new_replica_path).with_context(|| {
+        format!(
+            "could not copy {:?} to {:?}",
+            sector_key_path, new_replica_path
+        )
+    })?;
+    let mut replica = mmap_write(new_replica_path, sector_bytes)?;
+    EmptySectorUpdate::<Tree, DefaultPieceHasher>::encode(
+        &comm_d_new,
+        &comm_r_old,
+        &mut replica,
+        &data,
+    )?;
+    replica.flush()?;
+
+    trace!("building tree_r_last for the new replica");
+    let tree_count = get_base_tree_count::<Tree>();
+    let tree_r_last = EmptySectorUpdate::<Tree, DefaultPieceHasher>::generate_tree_r_last(
+        &replica,
+        nodes / tree_count,
+        tree_count,
+        tree_r_last_config::<Tree>(new_cache_path, porep_config.sector_size)?,
+        new_replica_path.to_path_buf(),
+    )?;
+    let comm_r_last_new = tree_r_last.root();
+    let comm_r_new = <Tree::Hasher as Hasher>::Function::hash2(&p_aux.comm_c, &comm_r_last_new);
+
+    let p_aux_new = PersistentAux {
+        comm_c: p_aux.comm_c,
+        comm_r_last: comm_r_last_new,
+    };
+    let p_aux_path = new_cache_path.join(CacheKey::PAux.to_string());
+    fs::write(&p_aux_path, serialize(&p_aux_new)?)
+        .with_context(|| format!("could not write to file p_aux={:?}", p_aux_path))?;
+
+    info!("encode_into:finish");
+    Ok(EmptySectorUpdateEncoded {
+        comm_r_new: commitment_from_fr(comm_r_new.into()),
+        comm_r_last_new: commitment_from_fr(comm_r_last_new.into()),
+        comm_d_new: commitment_from_fr(comm_d_new.into()),
+    })
+}
+
+/// Decodes the data encoded into `replica_path` by `encode_into` and writes it, fr32 padded, to
+/// `out_data_path`.
+pub fn decode_from<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    out_data_path: &Path,
+    replica_path: &Path,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    comm_d_new: Commitment,
+) -> Result<()> {
+    info!("decode_from:start");
+
+    let sector_bytes = u64::from(porep_config.padded_bytes_amount());
+    let p_aux = read_p_aux::<Tree>(sector_key_cache_path)?;
+    let comm_r_old = <Tree::Hasher as Hasher>::Function::hash2(&p_aux.comm_c, &p_aux.comm_r_last);
+    let comm_d_new: DefaultPieceDomain = as_safe_commitment(&comm_d_new, "comm_d_new")?;
+
+    let replica = mmap_read(replica_path)?;
+    let sector_key = mmap_read(sector_key_path)?;
+    ensure!(
+        replica.len() as u64 == sector_bytes && sector_key.len() as u64 == sector_bytes,
+        "replicas must be exactly one sector"
+    );
+
+    let mut data = mmap_write(out_data_path, sector_bytes)?;
+    EmptySectorUpdate::<Tree, DefaultPieceHasher>::decode(
+        &comm_d_new,
+        &comm_r_old,
+        &replica,
+        &sector_key,
+        &mut data,
+    )?;
+    data.flush()?;
+
+    info!("decode_from:finish");
+    Ok(())
+}
+
+/// Proves that the replica at `replica_path` encodes the data of `comm_d_new` into the sealed
+/// sector of `comm_r_old`.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_empty_sector_update_proof<Tree: 'static + MerkleTreeTrait, R: RngCore>(
+    porep_config: &PoRepConfig,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+    rng: Option<&mut R>,
+) -> Result<EmptySectorUpdateProof> {
+    info!("generate_empty_sector_update_proof:start");
+
+    let public_inputs = update_public_inputs::<Tree>(comm_r_old, comm_d_new, comm_r_new)?;
+    let p_aux = read_p_aux::<Tree>(replica_cache_path)?;
+
+    let tree_r_old_config =
+        sector_tree_r_last_config::<Tree>(sector_key_cache_path, porep_config.sector_size)?;
+    // Missing stored rows of tree_r_last are rebuilt from the sector key.
+    let tree_r_old = if verify_level_cache_store::<Tree>(&tree_r_old_config).is_ok() {
+        EmptySectorUpdate::<Tree, DefaultPieceHasher>::open_tree_r_last(
+            &tree_r_old_config,
+            PathBuf::from(sector_key_path),
+        )?
+    } else {
+        regenerate_tree_r_last::<Tree>(porep_config, sector_key_cache_path, sector_key_path)?
+    };
+    let tree_r_new_config =
+        tree_r_last_config::<Tree>(replica_cache_path, porep_config.sector_size)?;
+    ensure!(
+        tree_r_old_config.rows_to_discard == tree_r_new_config.rows_to_discard,
+        "tree_r_last of the sector key discards a different number of rows"
+    );
+    let tree_r_new = EmptySectorUpdate::<Tree, DefaultPieceHasher>::open_tree_r_last(
+        &tree_r_new_config,
+        PathBuf::from(replica_path),
+    )?;
+
+    let tree_d_config = tree_d_config(replica_cache_path, porep_config.sector_size)?;
+    let tree_d_size = tree_d_config.size.context("config size failure")?;
+    let tree_d_store: DiskStore<DefaultPieceDomain> =
+        DiskStore::new_from_disk(tree_d_size, BINARY_ARITY, &tree_d_config)?;
+    let tree_d_new = BinaryMerkleTree::<DefaultPieceHasher>::from_data_store(
+        tree_d_store,
+        get_base_tree_leafs::<DefaultBinaryTree>(tree_d_size)?,
+    )?;
+
+    let private_inputs = update::PrivateInputs::<Tree, DefaultPieceHasher> {
+        comm_c: p_aux.comm_c,
+        tree_d_new: &tree_d_new,
+        tree_r_old: &tree_r_old,
+        tree_r_new: &tree_r_new,
+        tree_r_rows_to_discard: tree_r_new_config.rows_to_discard,
+    };
+
+    let compound_public_params = update_compound_public_params::<Tree>(porep_config)?;
+    let groth_params = get_empty_sector_update_params::<Tree, R>(porep_config, rng)?;
+    let proof = EmptySectorUpdateCompound::<Tree, DefaultPieceHasher>::prove(
+        &compound_public_params,
+        &public_inputs,
+        &private_inputs,
+        &groth_params,
+    )?;
+
+    let mut buf = Vec::new();
+    proof.write(&mut buf)?;
+
+    info!("generate_empty_sector_update_proof:finish");
+    Ok(EmptySectorUpdateProof { proof: buf })
+}
+
+/// Returns the public inputs of the proof that `comm_r_new` encodes the data of `comm_d_new`
+/// into the sealed sector of `comm_r_old`.
+pub fn get_empty_sector_update_inputs<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<Vec<Fr>> {
+    let public_inputs = update_public_inputs::<Tree>(comm_r_old, comm_d_new, comm_r_new)?;
+    let compound_public_params = update_compound_public_params::<Tree>(porep_config)?;
+
+    EmptySectorUpdateCompound::<Tree, DefaultPieceHasher>::generate_public_inputs(
+        &public_inputs,
+        &compound_public_params.vanilla_params,
+        Some(0),
+    )
+}
+
+/// Verifies a proof of `generate_empty_sector_update_proof`.
+pub fn verify_empty_sector_update_proof<Tree: 'static + MerkleTreeTrait, R: RngCore>(
+    porep_config: &PoRepConfig,
+    proof: &[u8],
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    rng: Option<&mut R>,
+) -> Result<bool> {
+    info!("verify_empty_sector_update_proof:start");
+    ensure!(!proof.is_empty(), "Invalid proof bytes (empty vector)");
+
+    let public_inputs = update_public_inputs::<Tree>(comm_r_old, comm_d_new, comm_r_new)?;
+    let compound_public_params = update_compound_public_params::<Tree>(porep_config)?;
+
+    let verifying_key = get_empty_sector_update_verifying_key::<Tree, R>(porep_config, rng)?;
+    let proof = MultiProof::new_from_reader(Some(1), proof, &verifying_key)?;
+
+    let result = EmptySectorUpdateCompound::<Tree, DefaultPieceHasher>::verify(
+        &compound_public_params,
+        &public_inputs,
+        &proof,
+        &NoRequirements,
+    );
+
+    info!("verify_empty_sector_update_proof:finish");
+    result
+}
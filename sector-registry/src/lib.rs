@@ -0,0 +1,314 @@
+//! Sector sizes supported by the prover and the verifier.
+//!
+//! Each size is described by a [`SectorProfile`]: its tree shape, the number
+//! of layers, partitions and challenges, and the digests of its parent caches.
+//! The built-in profiles live in `profiles/default.json`. A replacement set can
+//! be loaded from a JSON or TOML file, and [`global`] picks it up from the
+//! `EUENO_SECTOR_PROFILE` environment variable.
+
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Environment variable pointing to the profile file used by [`global`].
+pub const PROFILE_ENV: &str = "EUENO_SECTOR_PROFILE";
+
+const BUILTIN_PROFILES: &str = include_str!("../profiles/default.json");
+
+/// Size of a single node in bytes.
+const NODE_SIZE: u64 = 32;
+
+/// Arity of the base trees of the replica tree.
+const BASE_ARITY: u64 = 8;
+
+static GLOBAL: Lazy<Registry> = Lazy::new(|| match env::var_os(PROFILE_ENV) {
+    Some(path) => Registry::load(Path::new(&path))
+        .unwrap_or_else(|err| panic!("invalid {}: {}", PROFILE_ENV, err)),
+    None => Registry::builtin(),
+});
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("invalid profile: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[cfg(feature = "toml")]
+    #[error("invalid profile: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("unsupported profile format: {0}")]
+    Format(PathBuf),
+
+    #[error("invalid sector profile {name}: {reason}")]
+    Invalid { name: String, reason: &'static str },
+
+    #[error("duplicate sector profile {0}")]
+    Duplicate(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Shape of the replica tree (`tree_r_last`), named after the `SectorShape*`
+/// types of the `seal` crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TreeShape {
+    /// A single oct tree.
+    Base,
+    /// 2 oct trees.
+    Sub2,
+    /// 8 oct trees.
+    Sub8,
+    /// 2 groups of 8 oct trees.
+    Top2,
+}
+
+impl TreeShape {
+    /// Number of base trees the replica tree is split into.
+    pub fn base_tree_count(self) -> u64 {
+        match self {
+            TreeShape::Base => 1,
+            TreeShape::Sub2 => 2,
+            TreeShape::Sub8 => 8,
+            TreeShape::Top2 => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SectorProfile {
+    /// Name of the size on chain, e.g. `sector_size2_kib`.
+    pub name: String,
+    /// Size in bytes.
+    pub sector_size: u64,
+    pub shape: TreeShape,
+    pub layers: usize,
+    pub partitions: u8,
+    pub minimum_challenges: usize,
+    pub window_post_sector_count: usize,
+    /// Digests of the known parent caches of this size, by cache id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parent_cache: BTreeMap<String, String>,
+}
+
+impl SectorProfile {
+    /// Number of nodes in a sector.
+    pub fn nodes(&self) -> u64 {
+        self.sector_size / NODE_SIZE
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason| {
+            Err(Error::Invalid {
+                name: self.name.clone(),
+                reason,
+            })
+        };
+
+        if self.name.is_empty() {
+            return invalid("empty name");
+        }
+        if !self.sector_size.is_power_of_two() || self.sector_size < NODE_SIZE {
+            return invalid("sector size must be a power of two of at least one node");
+        }
+        let base_nodes = self.nodes() / self.shape.base_tree_count();
+        if base_nodes < BASE_ARITY || !is_power_of(base_nodes, BASE_ARITY) {
+            return invalid("tree shape does not fit the sector size");
+        }
+        if self.layers == 0 || self.partitions == 0 {
+            return invalid("layers and partitions must be positive");
+        }
+        if self.minimum_challenges == 0 || self.window_post_sector_count == 0 {
+            return invalid("challenge counts must be positive");
+        }
+
+        Ok(())
+    }
+}
+
+fn is_power_of(mut value: u64, base: u64) -> bool {
+    while value.is_multiple_of(base) {
+        value /= base;
+    }
+    value == 1
+}
+
+#[derive(Deserialize)]
+struct ProfileFile {
+    profiles: Vec<SectorProfile>,
+}
+
+/// A validated set of profiles, ordered by sector size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registry {
+    profiles: Vec<SectorProfile>,
+}
+
+impl Registry {
+    pub fn new(mut profiles: Vec<SectorProfile>) -> Result<Self> {
+        let mut names = HashSet::new();
+        let mut sizes = HashSet::new();
+        for profile in &profiles {
+            profile.validate()?;
+            if !names.insert(&profile.name) || !sizes.insert(profile.sector_size) {
+                return Err(Error::Duplicate(profile.name.clone()));
+            }
+        }
+        profiles.sort_by_key(|profile| profile.sector_size);
+
+        Ok(Registry { profiles })
+    }
+
+    /// The profiles shipped with the crate.
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_PROFILES).expect("invalid built-in sector profiles")
+    }
+
+    pub fn from_json(data: &str) -> Result<Self> {
+        let file: ProfileFile = serde_json::from_str(data)?;
+        Self::new(file.profiles)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(data: &str) -> Result<Self> {
+        let file: ProfileFile = toml::from_str(data)?;
+        Self::new(file.profiles)
+    }
+
+    /// Loads a profile file, its format is picked by the extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&data),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&data),
+            _ => Err(Error::Format(path.to_path_buf())),
+        }
+    }
+
+    pub fn profiles(&self) -> &[SectorProfile] {
+        &self.profiles
+    }
+
+    pub fn get(&self, sector_size: u64) -> Option<&SectorProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.sector_size == sector_size)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&SectorProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// The smallest sector holding `len` bytes.
+    pub fn fit(&self, len: u64) -> Option<&SectorProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.sector_size >= len)
+    }
+}
+
+/// The registry of the process, loaded from `EUENO_SECTOR_PROFILE` if set and
+/// the built-in profiles otherwise.
+///
+/// Panics if the profile file can not be loaded.
+pub fn global() -> &'static Registry {
+    &GLOBAL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, sector_size: u64, shape: TreeShape) -> SectorProfile {
+        SectorProfile {
+            name: name.to_owned(),
+            sector_size,
+            shape,
+            layers: 2,
+            partitions: 1,
+            minimum_challenges: 2,
+            window_post_sector_count: 2,
+            parent_cache: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_builtin() {
+        let registry = Registry::builtin();
+        assert_eq!(registry.profiles().len(), 10);
+
+        let profile = registry.get(1 << 35).unwrap();
+        assert_eq!(profile.name, "sector_size32_gib");
+        assert_eq!(profile.shape, TreeShape::Sub8);
+        assert_eq!((profile.layers, profile.partitions), (11, 10));
+        assert_eq!(profile.minimum_challenges, 176);
+        assert_eq!(profile.parent_cache.len(), 2);
+
+        assert_eq!(
+            registry.by_name("sector_size4_kib").unwrap().sector_size,
+            1 << 12
+        );
+        assert_eq!(registry.fit(1).unwrap().sector_size, 1 << 11);
+        assert_eq!(registry.fit(5000).unwrap().sector_size, 1 << 14);
+        assert!(registry.fit((1 << 36) + 1).is_none());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_from_toml() {
+        let registry = Registry::from_toml(
+            r#"
+            [[profiles]]
+            name = "sector_size128_kib"
+            sector_size = 131072
+            shape = "base"
+            layers = 3
+            partitions = 1
+            minimum_challenges = 4
+            window_post_sector_count = 2
+
+            [profiles.parent_cache]
+            "v28-sdr-parent-00" = "ab"
+            "#,
+        )
+        .unwrap();
+
+        let profile = registry.get(1 << 17).unwrap();
+        assert_eq!(profile.nodes(), 4096);
+        assert_eq!(profile.layers, 3);
+        assert_eq!(profile.parent_cache["v28-sdr-parent-00"], "ab");
+    }
+
+    #[test]
+    fn test_validation() {
+        // 4KiB is 128 nodes, which a single oct tree can not hold.
+        let err = Registry::new(vec![profile("bad", 1 << 12, TreeShape::Base)]).unwrap_err();
+        assert!(matches!(err, Error::Invalid { .. }));
+
+        let err = Registry::new(vec![profile("odd", 3000, TreeShape::Base)]).unwrap_err();
+        assert!(matches!(err, Error::Invalid { .. }));
+
+        let err = Registry::new(vec![
+            profile("a", 1 << 11, TreeShape::Base),
+            profile("b", 1 << 11, TreeShape::Base),
+        ])
+        .unwrap_err();
+        assert!(matches!(err, Error::Duplicate(_)));
+    }
+}
@@ -12,6 +12,8 @@ mod platform;
 mod sha256;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod sha256_intrinsics;
+#[cfg(target_arch = "x86_64")]
+mod sha256_multi;
 mod sha256_utils;
 
-pub use sha256::Sha256;
+pub use sha256::{Sha256, Sha256Multi, MAX_MESSAGES};
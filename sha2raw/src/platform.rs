@@ -1,5 +1,7 @@
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 use crate::sha256_intrinsics;
+#[cfg(target_arch = "x86_64")]
+use crate::sha256_multi;
 use crate::sha256_utils;
 
 #[allow(dead_code)]
@@ -89,3 +91,168 @@ impl Implementation {
         }
     }
 }
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MultiPlatform {
+    /// One message after the other.
+    Serial,
+    #[cfg(target_arch = "x86_64")]
+    Sse41,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+/// Compression of several independent messages at once.
+#[derive(Clone, Copy, Debug)]
+pub struct MultiImplementation {
+    platform: MultiPlatform,
+    /// Used for the messages which do not fill all lanes, and by `Serial`.
+    serial: Implementation,
+}
+
+impl MultiImplementation {
+    pub fn detect() -> Self {
+        let serial = Implementation::detect();
+
+        // With sha-ni, hashing the messages one after the other is faster.
+        #[cfg(target_arch = "x86_64")]
+        {
+            if serial.0 != Platform::Sha {
+                if let Some(avx2_impl) = Self::avx2_if_supported() {
+                    return avx2_impl;
+                }
+                if let Some(sse41_impl) = Self::sse41_if_supported() {
+                    return sse41_impl;
+                }
+            }
+        }
+
+        Self::serial(serial)
+    }
+
+    pub fn serial(serial: Implementation) -> Self {
+        MultiImplementation {
+            platform: MultiPlatform::Serial,
+            serial,
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    pub fn avx2_if_supported() -> Option<Self> {
+        cpufeatures::new!(cpuid_avx2, "avx2");
+
+        if cpuid_avx2::get() {
+            return Some(MultiImplementation {
+                platform: MultiPlatform::Avx2,
+                serial: Implementation::detect(),
+            });
+        }
+
+        None
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    pub fn sse41_if_supported() -> Option<Self> {
+        cpufeatures::new!(cpuid_sse41, "sse4.1");
+
+        if cpuid_sse41::get() {
+            return Some(MultiImplementation {
+                platform: MultiPlatform::Sse41,
+                serial: Implementation::detect(),
+            });
+        }
+
+        None
+    }
+
+    /// Number of messages compressed at once.
+    pub fn lanes(self) -> usize {
+        match self.platform {
+            MultiPlatform::Serial => 1,
+            #[cfg(target_arch = "x86_64")]
+            MultiPlatform::Sse41 => 4,
+            #[cfg(target_arch = "x86_64")]
+            MultiPlatform::Avx2 => 8,
+        }
+    }
+
+    /// Compresses `blocks[i]` into `states[i]` for all messages, which must have the same number
+    /// of blocks.
+    #[inline]
+    pub fn compress256_multi(self, states: &mut [[u32; 8]], blocks: &[&[&[u8]]]) {
+        assert_eq!(states.len(), blocks.len());
+
+        let lanes = self.lanes();
+        for (states, blocks) in states.chunks_mut(lanes).zip(blocks.chunks(lanes)) {
+            if states.len() < lanes {
+                for (state, blocks) in states.iter_mut().zip(blocks) {
+                    self.serial.compress256(state, blocks);
+                }
+                continue;
+            }
+
+            match self.platform {
+                MultiPlatform::Serial => self.serial.compress256(&mut states[0], blocks[0]),
+                #[cfg(target_arch = "x86_64")]
+                MultiPlatform::Sse41 => unsafe { sha256_multi::compress256_x4(states, blocks) },
+                #[cfg(target_arch = "x86_64")]
+                MultiPlatform::Avx2 => unsafe { sha256_multi::compress256_x8(states, blocks) },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::consts::H256;
+
+    fn check_multi(multi: MultiImplementation) {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for messages in 1..=19 {
+            for num_blocks in [2, 4, 14] {
+                let mut input = vec![0u8; 32 * num_blocks * messages];
+                rng.fill_bytes(&mut input);
+                let blocks = input
+                    .chunks(32 * num_blocks)
+                    .map(|message| message.chunks(32).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                let blocks = blocks.iter().map(|blocks| &blocks[..]).collect::<Vec<_>>();
+
+                let mut states = vec![H256; messages];
+                multi.compress256_multi(&mut states, &blocks);
+
+                for (state, blocks) in states.iter().zip(&blocks) {
+                    let mut expected = H256;
+                    sha256_utils::compress256(&mut expected, blocks);
+                    assert_eq!(state, &expected, "{:?}", multi);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress256_multi() {
+        check_multi(MultiImplementation::detect());
+        check_multi(MultiImplementation::serial(Implementation::portable()));
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(sse41_impl) = MultiImplementation::sse41_if_supported() {
+                check_multi(sse41_impl);
+            }
+            if let Some(avx2_impl) = MultiImplementation::avx2_if_supported() {
+                check_multi(avx2_impl);
+            }
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use byteorder::{ByteOrder, BE};
 use lazy_static::lazy_static;
 
-use crate::{consts::H256, platform::Implementation};
+use crate::{
+    consts::H256,
+    platform::{Implementation, MultiImplementation},
+};
 
 lazy_static! {
     static ref IMPL: Implementation = Implementation::detect();
+    static ref MULTI_IMPL: MultiImplementation = MultiImplementation::detect();
 }
 
 #[derive(Clone)]
@@ -81,6 +85,121 @@ impl Sha256 {
 
 opaque_debug::implement!(Sha256);
 
+/// Maximum number of messages hashed at once by a [`Sha256Multi`], the widest SIMD lanes.
+pub const MAX_MESSAGES: usize = 8;
+
+/// Hashes up to [`MAX_MESSAGES`] independent messages of the same length at once, using SIMD
+/// lanes when available. Each message is hashed as by a [`Sha256`].
+#[derive(Clone)]
+pub struct Sha256Multi {
+    len: u64,
+    messages: usize,
+    states: [[u32; 8]; MAX_MESSAGES],
+}
+
+impl Sha256Multi {
+    pub fn new(messages: usize) -> Self {
+        assert!(
+            messages <= MAX_MESSAGES,
+            "at most {} messages are hashed at once",
+            MAX_MESSAGES
+        );
+
+        Sha256Multi {
+            len: 0,
+            messages,
+            states: [H256; MAX_MESSAGES],
+        }
+    }
+
+    /// Number of messages hashed at once by the detected implementation.
+    pub fn lanes() -> usize {
+        MULTI_IMPL.lanes()
+    }
+
+    pub fn messages(&self) -> usize {
+        self.messages
+    }
+
+    /// Digests of any number of messages, hashed [`MAX_MESSAGES`] at a time.
+    pub fn digest(blocks: &[&[&[u8]]]) -> Vec<[u8; 32]> {
+        let mut hashes = Vec::with_capacity(blocks.len());
+        for blocks in blocks.chunks(MAX_MESSAGES) {
+            let mut sha = Sha256Multi::new(blocks.len());
+            sha.input(blocks);
+            hashes.extend_from_slice(&sha.finish()[..blocks.len()]);
+        }
+        hashes
+    }
+
+    /// Inputs `blocks[i]` to the `i`th message.
+    pub fn input(&mut self, blocks: &[&[&[u8]]]) {
+        debug_assert_eq!(blocks.len(), self.messages, "invalid number of messages");
+        debug_assert_eq!(blocks[0].len() % 2, 0, "invalid block length");
+
+        self.len += (blocks[0].len() as u64) << 8;
+
+        MULTI_IMPL.compress256_multi(&mut self.states[..self.messages], blocks);
+    }
+
+    /// Returns the digests of the messages, in the first [`Sha256Multi::messages`] entries.
+    pub fn finish(mut self) -> [[u8; 32]; MAX_MESSAGES] {
+        let mut block0 = [0u8; 32];
+        let mut block1 = [0u8; 32];
+
+        // Append single 1 bit
+        block0[0] = 0b1000_0000;
+
+        // Write L as 64 big endian integer
+        let l = self.len;
+        block1[32 - 8..].copy_from_slice(&l.to_be_bytes()[..]);
+
+        let padding = [&block0[..], &block1[..]];
+        let blocks = [&padding[..]; MAX_MESSAGES];
+        MULTI_IMPL.compress256_multi(&mut self.states[..self.messages], &blocks[..self.messages]);
+
+        self.output()
+    }
+
+    /// Finishes the `i`th message with `block0[i]`, returning the digests as
+    /// [`Sha256Multi::finish`].
+    pub fn finish_with(mut self, block0: &[&[u8]]) -> [[u8; 32]; MAX_MESSAGES] {
+        debug_assert_eq!(block0.len(), self.messages);
+        debug_assert!(block0.iter().all(|block| block.len() == 32));
+
+        let mut block1 = [0u8; 32];
+
+        // Append single 1 bit
+        block1[0] = 0b1000_0000;
+
+        // Write L as 64 big endian integer
+        let l = self.len + 256;
+        block1[32 - 8..].copy_from_slice(&l.to_be_bytes()[..]);
+
+        let mut padded = [[&block1[..]; 2]; MAX_MESSAGES];
+        for (padded, block) in padded.iter_mut().zip(block0) {
+            padded[0] = block;
+        }
+        let mut blocks: [&[&[u8]]; MAX_MESSAGES] = [&[]; MAX_MESSAGES];
+        for (blocks, padded) in blocks.iter_mut().zip(&padded) {
+            *blocks = &padded[..];
+        }
+        MULTI_IMPL.compress256_multi(&mut self.states[..self.messages], &blocks[..self.messages]);
+
+        self.output()
+    }
+
+    fn output(&self) -> [[u8; 32]; MAX_MESSAGES] {
+        let mut out = [[0u8; 32]; MAX_MESSAGES];
+        for (out, state) in out.iter_mut().zip(&self.states[..self.messages]) {
+            BE::write_u32_into(state, out);
+        }
+        out
+    }
+}
+
+opaque_debug::implement!(Sha256Multi);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,6 +219,48 @@ mod tests {
         fuzz(1_000);
     }
 
+    #[test]
+    fn test_multi() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        for messages in 1..=17 {
+            let mut input = vec![0u8; 32 * 7 * messages];
+            rng.fill_bytes(&mut input);
+            let inputs = input.chunks(32 * 7).collect::<Vec<_>>();
+
+            let blocks = inputs
+                .iter()
+                .map(|input| input[..32 * 6].chunks(32).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let blocks = blocks.iter().map(|blocks| &blocks[..]).collect::<Vec<_>>();
+            let last = inputs
+                .iter()
+                .map(|input| &input[32 * 6..])
+                .collect::<Vec<_>>();
+
+            for ((blocks, last), inputs) in blocks
+                .chunks(MAX_MESSAGES)
+                .zip(last.chunks(MAX_MESSAGES))
+                .zip(inputs.chunks(MAX_MESSAGES))
+            {
+                let mut hasher = Sha256Multi::new(blocks.len());
+                hasher.input(blocks);
+                let hashes = hasher.finish_with(last);
+                for (hash, input) in hashes[..blocks.len()].iter().zip(inputs) {
+                    assert_eq!(&hash[..], &Original::digest(input)[..]);
+                }
+            }
+
+            let hashes = Sha256Multi::digest(&blocks);
+            assert_eq!(hashes.len(), messages);
+            for (hash, input) in hashes.iter().zip(&inputs) {
+                assert_eq!(&hash[..], &Original::digest(&input[..32 * 6])[..]);
+            }
+        }
+    }
+
     fn fuzz(n: usize) {
         let rng = &mut XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
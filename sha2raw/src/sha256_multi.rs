@@ -0,0 +1,257 @@
+#![allow(clippy::many_single_char_names)]
+
+//! Multi-buffer SHA-256, compressing the blocks of several independent messages at once, one
+//! message per SIMD lane.
+
+use std::arch::x86_64::{
+    __m128i, __m256i, _mm256_add_epi32, _mm256_and_si256, _mm256_andnot_si256, _mm256_loadu_si256,
+    _mm256_or_si256, _mm256_set1_epi32, _mm256_slli_epi32, _mm256_srli_epi32, _mm256_storeu_si256,
+    _mm256_xor_si256, _mm_add_epi32, _mm_and_si128, _mm_andnot_si128, _mm_loadu_si128,
+    _mm_or_si128, _mm_set1_epi32, _mm_slli_epi32, _mm_srli_epi32, _mm_storeu_si128, _mm_xor_si128,
+};
+
+use byteorder::{ByteOrder, BE};
+
+use crate::consts::{K32, STATE_LEN};
+
+/// Maximum number of lanes of the supported vectors.
+const MAX_LANES: usize = 8;
+
+/// The operations on vectors of `u32`s needed by the compression.
+trait Lanes: Copy {
+    const LANES: usize;
+
+    unsafe fn splat(x: u32) -> Self;
+    /// Loads the first `LANES` words.
+    unsafe fn load(words: &[u32; MAX_LANES]) -> Self;
+    /// Stores into the first `LANES` words.
+    unsafe fn store(self, words: &mut [u32; MAX_LANES]);
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn and(self, other: Self) -> Self;
+    /// `!self & other`
+    unsafe fn andnot(self, other: Self) -> Self;
+    unsafe fn xor(self, other: Self) -> Self;
+    unsafe fn shr<const N: i32>(self) -> Self;
+    /// Rotates right by `R`, where `L` must be `32 - R`.
+    unsafe fn rotr<const R: i32, const L: i32>(self) -> Self;
+}
+
+#[derive(Clone, Copy)]
+struct U32x4(__m128i);
+
+impl Lanes for U32x4 {
+    const LANES: usize = 4;
+
+    #[inline(always)]
+    unsafe fn splat(x: u32) -> Self {
+        U32x4(_mm_set1_epi32(x as i32))
+    }
+
+    #[inline(always)]
+    unsafe fn load(words: &[u32; MAX_LANES]) -> Self {
+        U32x4(_mm_loadu_si128(words.as_ptr() as *const __m128i))
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, words: &mut [u32; MAX_LANES]) {
+        _mm_storeu_si128(words.as_mut_ptr() as *mut __m128i, self.0)
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        U32x4(_mm_add_epi32(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn and(self, other: Self) -> Self {
+        U32x4(_mm_and_si128(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn andnot(self, other: Self) -> Self {
+        U32x4(_mm_andnot_si128(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        U32x4(_mm_xor_si128(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn shr<const N: i32>(self) -> Self {
+        U32x4(_mm_srli_epi32::<N>(self.0))
+    }
+
+    #[inline(always)]
+    unsafe fn rotr<const R: i32, const L: i32>(self) -> Self {
+        U32x4(_mm_or_si128(
+            _mm_srli_epi32::<R>(self.0),
+            _mm_slli_epi32::<L>(self.0),
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct U32x8(__m256i);
+
+impl Lanes for U32x8 {
+    const LANES: usize = 8;
+
+    #[inline(always)]
+    unsafe fn splat(x: u32) -> Self {
+        U32x8(_mm256_set1_epi32(x as i32))
+    }
+
+    #[inline(always)]
+    unsafe fn load(words: &[u32; MAX_LANES]) -> Self {
+        U32x8(_mm256_loadu_si256(words.as_ptr() as *const __m256i))
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, words: &mut [u32; MAX_LANES]) {
+        _mm256_storeu_si256(words.as_mut_ptr() as *mut __m256i, self.0)
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        U32x8(_mm256_add_epi32(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn and(self, other: Self) -> Self {
+        U32x8(_mm256_and_si256(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn andnot(self, other: Self) -> Self {
+        U32x8(_mm256_andnot_si256(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        U32x8(_mm256_xor_si256(self.0, other.0))
+    }
+
+    #[inline(always)]
+    unsafe fn shr<const N: i32>(self) -> Self {
+        U32x8(_mm256_srli_epi32::<N>(self.0))
+    }
+
+    #[inline(always)]
+    unsafe fn rotr<const R: i32, const L: i32>(self) -> Self {
+        U32x8(_mm256_or_si256(
+            _mm256_srli_epi32::<R>(self.0),
+            _mm256_slli_epi32::<L>(self.0),
+        ))
+    }
+}
+
+#[inline(always)]
+unsafe fn big_sigma0<V: Lanes>(a: V) -> V {
+    a.rotr::<2, 30>()
+        .xor(a.rotr::<13, 19>())
+        .xor(a.rotr::<22, 10>())
+}
+
+#[inline(always)]
+unsafe fn big_sigma1<V: Lanes>(e: V) -> V {
+    e.rotr::<6, 26>()
+        .xor(e.rotr::<11, 21>())
+        .xor(e.rotr::<25, 7>())
+}
+
+#[inline(always)]
+unsafe fn small_sigma0<V: Lanes>(w: V) -> V {
+    w.rotr::<7, 25>().xor(w.rotr::<18, 14>()).xor(w.shr::<3>())
+}
+
+#[inline(always)]
+unsafe fn small_sigma1<V: Lanes>(w: V) -> V {
+    w.rotr::<17, 15>()
+        .xor(w.rotr::<19, 13>())
+        .xor(w.shr::<10>())
+}
+
+/// Compresses the blocks of `V::LANES` messages, `blocks[lane]` being the 32 byte halves of the
+/// blocks of the message of `states[lane]`.
+#[inline(always)]
+unsafe fn compress<V: Lanes>(states: &mut [[u32; STATE_LEN]], blocks: &[&[&[u8]]]) {
+    debug_assert_eq!(states.len(), V::LANES);
+    debug_assert_eq!(blocks.len(), V::LANES);
+
+    let num_blocks = blocks[0].len();
+    assert_eq!(num_blocks % 2, 0);
+    assert!(blocks.iter().all(|lane| lane.len() == num_blocks));
+
+    let mut words = [0u32; MAX_LANES];
+    let mut state = [V::splat(0); STATE_LEN];
+    for (i, s) in state.iter_mut().enumerate() {
+        for (word, lane) in words.iter_mut().zip(states.iter()) {
+            *word = lane[i];
+        }
+        *s = V::load(&words);
+    }
+
+    for block in (0..num_blocks).step_by(2) {
+        // Transpose the message words, so each vector holds the same word of all lanes.
+        let mut w = [V::splat(0); 16];
+        for (i, wi) in w.iter_mut().enumerate() {
+            for (word, lane) in words.iter_mut().zip(blocks.iter()) {
+                let half = lane[block + i / 8];
+                *word = BE::read_u32(&half[(i % 8) * 4..]);
+            }
+            *wi = V::load(&words);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (t, k) in K32.iter().enumerate() {
+            if t >= 16 {
+                w[t % 16] = small_sigma1(w[(t - 2) % 16])
+                    .add(w[(t - 7) % 16])
+                    .add(small_sigma0(w[(t - 15) % 16]))
+                    .add(w[t % 16]);
+            }
+
+            let ch = e.and(f).xor(e.andnot(g));
+            let maj = a.and(b).xor(a.and(c)).xor(b.and(c));
+            let t1 = h
+                .add(big_sigma1(e))
+                .add(ch)
+                .add(V::splat(*k))
+                .add(w[t % 16]);
+            let t2 = big_sigma0(a).add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.add(t2);
+        }
+
+        for (s, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.add(x);
+        }
+    }
+
+    for (i, s) in state.iter().enumerate() {
+        s.store(&mut words);
+        for (word, lane) in words.iter().zip(states.iter_mut()) {
+            lane[i] = *word;
+        }
+    }
+}
+
+/// Compresses the blocks of 4 messages, using SSE4.1.
+#[target_feature(enable = "sse4.1")]
+pub unsafe fn compress256_x4(states: &mut [[u32; STATE_LEN]], blocks: &[&[&[u8]]]) {
+    compress::<U32x4>(states, blocks)
+}
+
+/// Compresses the blocks of 8 messages, using AVX2.
+#[target_feature(enable = "avx2")]
+pub unsafe fn compress256_x8(states: &mut [[u32; STATE_LEN]], blocks: &[&[&[u8]]]) {
+    compress::<U32x8>(states, blocks)
+}